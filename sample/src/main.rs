@@ -261,8 +261,11 @@ fn main() {
                                   bounds,
                                   0,
                                   None,
+                                  webrender_traits::TransformStyle::Flat,
+                                  true,
                                   None,
                                   webrender_traits::MixBlendMode::Normal,
+                                  Vec::new(),
                                   Vec::new());
     builder.push_image(
         LayoutRect::new(LayoutPoint::new(0.0, 0.0), LayoutSize::new(100.0, 100.0)),
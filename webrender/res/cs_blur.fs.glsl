@@ -16,6 +16,13 @@ float gauss(float x, float sigma) {
 
 void main(void) {
     vec4 cache_sample = texture(sCacheRGBA8, vUv);
+
+    // A zero radius blur (e.g. a sharp drop shadow) is just a copy.
+    if (vBlurRadius == 0) {
+        oFragColor = cache_sample;
+        return;
+    }
+
     vec4 color = vec4(cache_sample.rgb, 1.0) * (cache_sample.a * gauss(0.0, vSigma));
 
     for (int i=1 ; i < vBlurRadius ; ++i) {
@@ -41,9 +41,9 @@ void main(void) {
                    aPosition.xy);
 
     vec2 texture_size = vec2(textureSize(sCacheRGBA8, 0).xy);
-    vUv.z = src_task.data1.x;
-    vBlurRadius = int(task.data1.y);
-    vSigma = task.data1.y * 0.5;
+    vUv.z = src_task.data1.z;
+    vBlurRadius = int(task.data1.w);
+    vSigma = task.data1.w * 0.5;
 
     switch (cmd.dir) {
         case DIR_HORIZONTAL:
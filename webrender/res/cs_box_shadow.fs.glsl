@@ -67,81 +67,88 @@ float ellipsePoint(float y, float y0, vec2 radii) {
     return radii.x * sqrt(1.0 - bStep * bStep);
 }
 
-// A helper function to compute the value that needs to be subtracted to accommodate the border
-// corners.
+// A helper function to compute the value that needs to be subtracted to accommodate a single
+// border corner. `d` is -1 for corners on the left edge of the box and 1 for corners on the right
+// edge:
 //
-//     "colorCutout"_sigma(x_{0_l}, x_{0_r}, y_0, y_{min}, y_{max}, a, b)
+//     "colorCutout"_sigma(x_0, d, y_0, y_{min}, y_{max}, a, b)
 //          = int_{y_{min}}^{y_{max}}
-//              int_{x_{0_r} + "ellipsePoint"(y, y_0, a, b)}^{x_{0_r} + a} G_sigma(y) G_sigma(x) dx
-//              + int_{x_{0_l} - a}^{x_{0_l} - "ellipsePoint"(y, y_0, a, b)} G_sigma(y) G_sigma(x)
-//                  dx dy
+//              d int_{x_0 + d "ellipsePoint"(y, y_0, a, b)}^{x_0 + d a} G_sigma(y) G_sigma(x) dx dy
 //          = int_{y_{min}}^{y_{max}} 1/2 G_sigma(y)
-//              ("erf"_sigma(x_{0_r} + a) - "erf"_sigma(x_{0_r} + "ellipsePoint"(y, y_0, a, b)) +
-//               "erf"_sigma(x_{0_l} - "ellipsePoint"(y, y_0, a, b)) - "erf"_sigma(x_{0_l} - a))
+//              d ("erf"_sigma(x_0 + d a) - "erf"_sigma(x_0 + d "ellipsePoint"(y, y_0, a, b)))
 //
 // with the outer integral evaluated numerically.
-float colorCutoutGeneral(float x0l,
-                         float x0r,
-                         float y0,
-                         float yMin,
-                         float yMax,
-                         vec2 radii,
-                         float sigma) {
+float colorCutout(float x0,
+                  float d,
+                  float y0,
+                  float yMin,
+                  float yMax,
+                  vec2 radii,
+                  float sigma) {
+    if (radii.x == 0.0 || radii.y == 0.0)
+        return 0.0;
+
     float sum = 0.0;
     for (float y = yMin; y <= yMax; y += 1.0) {
         float xEllipsePoint = ellipsePoint(y, y0, radii);
-        sum += gauss(y, sigma) *
-            (erfSigma(x0r + radii.x, sigma) - erfSigma(x0r + xEllipsePoint, sigma) +
-             erfSigma(x0l - xEllipsePoint, sigma) - erfSigma(x0l - radii.x, sigma));
+        sum += gauss(y, sigma) * d *
+            (erfSigma(x0 + d * radii.x, sigma) - erfSigma(x0 + d * xEllipsePoint, sigma));
     }
     return sum / 2.0;
 }
 
-// The value that needs to be subtracted to accommodate the top border corners.
-float colorCutoutTop(float x0l, float x0r, float y0, vec2 radii, float sigma) {
-    return colorCutoutGeneral(x0l, x0r, y0, y0, y0 + radii.y, radii, sigma);
+// The value that needs to be subtracted to accommodate a corner at the maximum `y` edge of the box.
+float colorCutoutMaxY(float x0, float d, float y0, vec2 radii, float sigma) {
+    return colorCutout(x0, d, y0, y0, y0 + radii.y, radii, sigma);
 }
 
-// The value that needs to be subtracted to accommodate the bottom border corners.
-float colorCutoutBottom(float x0l, float x0r, float y0, vec2 radii, float sigma) {
-    return colorCutoutGeneral(x0l, x0r, y0, y0 - radii.y, y0, radii, sigma);
+// The value that needs to be subtracted to accommodate a corner at the minimum `y` edge of the box.
+float colorCutoutMinY(float x0, float d, float y0, vec2 radii, float sigma) {
+    return colorCutout(x0, d, y0, y0 - radii.y, y0, radii, sigma);
 }
 
 // The blurred color value for the point at `pos` with the top left corner of the box at
-// `p_{0_"rect"}` and the bottom right corner of the box at `p_{1_"rect"}`.
-float color(vec2 pos, vec2 p0Rect, vec2 p1Rect, vec2 radii, float sigma) {
+// `p_{0_"rect"}` and the bottom right corner of the box at `p_{1_"rect"}`. `radiiTop` holds the
+// top left and top right corner radii, `radiiBottom` the bottom left and bottom right ones.
+float color(vec2 pos, vec2 p0Rect, vec2 p1Rect, vec4 radiiTop, vec4 radiiBottom, float sigma) {
     // Compute the vector distances `p_0` and `p_1`.
     vec2 p0 = p0Rect - pos, p1 = p1Rect - pos;
 
     // Compute the basic color `"colorFromRect"_sigma(p_0, p_1)`. This is all we have to do if
     // the box is unrounded.
     float cRect = colorFromRect(p0, p1, sigma);
-    if (radii.x == 0.0 || radii.y == 0.0)
+    if (radiiTop == vec4(0.0) && radiiBottom == vec4(0.0))
         return cRect;
 
-    // Compute the inner corners of the box, taking border radii into account: `x_{0_l}`,
-    // `y_{0_t}`, `x_{0_r}`, and `y_{0_b}`.
-    float x0l = p0.x + radii.x;
-    float y0t = p1.y - radii.y;
-    float x0r = p1.x - radii.x;
-    float y0b = p0.y + radii.y;
+    vec2 tl = radiiTop.xy, tr = radiiTop.zw;
+    vec2 bl = radiiBottom.xy, br = radiiBottom.zw;
 
-    // Compute the final color:
+    // Compute the final color by subtracting the cutout of each corner, using the inner edges
+    // of that corner's ellipse:
     //
-    //     "colorFromRect"_sigma(p_0, p_1) -
-    //          ("colorCutoutTop"_sigma(x_{0_l}, x_{0_r}, y_{0_t}, a, b) +
-    //           "colorCutoutBottom"_sigma(x_{0_l}, x_{0_r}, y_{0_b}, a, b))
-    float cCutoutTop = colorCutoutTop(x0l, x0r, y0t, radii, sigma);
-    float cCutoutBottom = colorCutoutBottom(x0l, x0r, y0b, radii, sigma);
-    return cRect - (cCutoutTop + cCutoutBottom);
+    //     "colorFromRect"_sigma(p_0, p_1) - sum_"corners" "colorCutout"_sigma
+    float cCutout =
+        colorCutoutMinY(p0.x + tl.x, -1.0, p0.y + tl.y, tl, sigma) +
+        colorCutoutMinY(p1.x - tr.x, 1.0, p0.y + tr.y, tr, sigma) +
+        colorCutoutMaxY(p0.x + bl.x, -1.0, p1.y - bl.y, bl, sigma) +
+        colorCutoutMaxY(p1.x - br.x, 1.0, p1.y - br.y, br, sigma);
+    return cRect - cCutout;
 }
 
 void main(void) {
     vec2 pos = vPos.xy;
     vec2 p0Rect = vBoxShadowRect.xy, p1Rect = vBoxShadowRect.zw;
-    vec2 radii = vBorderRadii.xy;
+
+    // If the corners differ, the quadrants past the corner size map
+    // to the right and bottom edges of the box shadow rect instead.
+    if (vMirror == 0.0) {
+        vec2 patchPos = pos - p0Rect + vec2(2.0 * vBlurRadius);
+        vec2 farOffset = p1Rect - p0Rect + vec2(4.0 * vBlurRadius) - 2.0 * vCornerSize;
+        pos += step(vCornerSize, patchPos) * farOffset;
+    }
+
     float sigma = vBlurRadius / 2.0;
-    float value = color(pos, p0Rect, p1Rect, radii, sigma);
+    float value = color(pos, p0Rect, p1Rect, vBorderRadiiTop, vBorderRadiiBottom, sigma);
 
     value = max(value, 0.0);
     oFragColor = dither(vec4(1.0, 1.0, 1.0, vInverted == 1.0 ? 1.0 - value : value));
@@ -4,7 +4,10 @@
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
 varying vec2 vPos;
-flat varying vec2 vBorderRadii;
+flat varying vec4 vBorderRadiiTop;
+flat varying vec4 vBorderRadiiBottom;
 flat varying float vBlurRadius;
 flat varying vec4 vBoxShadowRect;
 flat varying float vInverted;
+flat varying float vMirror;
+flat varying vec2 vCornerSize;
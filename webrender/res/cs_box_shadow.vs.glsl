@@ -13,11 +13,17 @@ void main(void) {
 
     vec2 pos = mix(p0, p1, aPosition.xy);
 
-    vBorderRadii = bs.border_radius_edge_size_blur_radius_inverted.xx;
-    vBlurRadius = bs.border_radius_edge_size_blur_radius_inverted.z;
-    vInverted = bs.border_radius_edge_size_blur_radius_inverted.w;
+    vBorderRadiiTop = bs.border_radii_top;
+    vBorderRadiiBottom = bs.border_radii_bottom;
+    vBlurRadius = bs.edge_size_blur_radius_inverted_mirror.y;
+    vInverted = bs.edge_size_blur_radius_inverted_mirror.z;
+    vMirror = bs.edge_size_blur_radius_inverted_mirror.w;
     vBoxShadowRect = vec4(bs.bs_rect.xy, bs.bs_rect.xy + bs.bs_rect.zw);
 
+    // When the corners differ, the patch holds one corner per quadrant.
+    // This is the logical size of each quadrant.
+    vCornerSize = (task.data0.zw - 2.0) / (2.0 * uDevicePixelRatio);
+
     // The fragment shader expects logical units, beginning at where the
     // blur radius begins.
     // The first path of the equation gets the virtual position in
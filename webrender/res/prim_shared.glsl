@@ -30,8 +30,9 @@
 #define UV_NORMALIZED    uint(0)
 #define UV_PIXEL         uint(1)
 
-#define EXTEND_MODE_CLAMP  0
-#define EXTEND_MODE_REPEAT 1
+#define EXTEND_MODE_CLAMP   0
+#define EXTEND_MODE_REPEAT  1
+#define EXTEND_MODE_REFLECT 2
 
 uniform sampler2DArray sCacheA8;
 uniform sampler2DArray sCacheRGBA8;
@@ -277,8 +278,23 @@ RadialGradient fetch_radial_gradient(int index) {
     return gradient;
 }
 
+struct ConicGradient {
+    vec4 center_angle_extend_mode;
+};
+
+ConicGradient fetch_conic_gradient(int index) {
+    ConicGradient gradient;
+
+    ivec2 uv = get_fetch_uv_2(index);
+
+    gradient.center_angle_extend_mode = texelFetchOffset(sData32, uv, 0, ivec2(0, 0));
+
+    return gradient;
+}
+
 struct Glyph {
-    vec4 offset;
+    vec2 offset;
+    float raster_scale;
 };
 
 Glyph fetch_glyph(int index) {
@@ -286,7 +302,9 @@ Glyph fetch_glyph(int index) {
 
     ivec2 uv = get_fetch_uv_1(index);
 
-    glyph.offset = texelFetchOffset(sData16, uv, 0, ivec2(0, 0));
+    vec4 data = texelFetchOffset(sData16, uv, 0, ivec2(0, 0));
+    glyph.offset = data.xy;
+    glyph.raster_scale = data.z;
 
     return glyph;
 }
@@ -634,6 +652,22 @@ Rectangle fetch_rectangle(int index) {
     return rect;
 }
 
+struct Line {
+    vec4 color;
+    vec4 style_orientation;
+};
+
+Line fetch_line(int index) {
+    Line line;
+
+    ivec2 uv = get_fetch_uv_2(index);
+
+    line.color = texelFetchOffset(sData32, uv, 0, ivec2(0, 0));
+    line.style_orientation = texelFetchOffset(sData32, uv, 0, ivec2(1, 0));
+
+    return line;
+}
+
 struct TextRun {
     vec4 color;
 };
@@ -691,18 +725,23 @@ struct BoxShadow {
     vec4 src_rect;
     vec4 bs_rect;
     vec4 color;
-    vec4 border_radius_edge_size_blur_radius_inverted;
+    // Corner radii as (top-left, top-right) and (bottom-left, bottom-right).
+    vec4 border_radii_top;
+    vec4 border_radii_bottom;
+    vec4 edge_size_blur_radius_inverted_mirror;
 };
 
 BoxShadow fetch_boxshadow(int index) {
     BoxShadow bs;
 
-    ivec2 uv = get_fetch_uv_4(index);
+    ivec2 uv = get_fetch_uv_8(index);
 
-    bs.src_rect = texelFetchOffset(sData64, uv, 0, ivec2(0, 0));
-    bs.bs_rect = texelFetchOffset(sData64, uv, 0, ivec2(1, 0));
-    bs.color = texelFetchOffset(sData64, uv, 0, ivec2(2, 0));
-    bs.border_radius_edge_size_blur_radius_inverted = texelFetchOffset(sData64, uv, 0, ivec2(3, 0));
+    bs.src_rect = texelFetchOffset(sData128, uv, 0, ivec2(0, 0));
+    bs.bs_rect = texelFetchOffset(sData128, uv, 0, ivec2(1, 0));
+    bs.color = texelFetchOffset(sData128, uv, 0, ivec2(2, 0));
+    bs.border_radii_top = texelFetchOffset(sData128, uv, 0, ivec2(3, 0));
+    bs.border_radii_bottom = texelFetchOffset(sData128, uv, 0, ivec2(4, 0));
+    bs.edge_size_blur_radius_inverted_mirror = texelFetchOffset(sData128, uv, 0, ivec2(5, 0));
 
     return bs;
 }
@@ -767,4 +806,42 @@ vec4 dither(vec4 color) {
     float noise = texelFetch(sDither, pos, 0).r * noise_factor;
     return color + vec4(noise, noise, noise, 0);
 }
+
+uniform sampler2D sGradients;
+
+// Look up the color at an offset along a gradient whose table of
+// entry_count entries starts at block gradient_index. Each block holds
+// one entry per pair of texels, in a pair of rows.
+vec4 sample_gradient(float offset,
+                     float gradient_index,
+                     float entry_count,
+                     int extend_mode) {
+    // Saturate, wrap or mirror the offset depending on the extend mode.
+    if (extend_mode == EXTEND_MODE_REPEAT) {
+        offset = fract(offset);
+    } else if (extend_mode == EXTEND_MODE_REFLECT) {
+        offset = 1.0 - abs(mod(offset, 2.0) - 1.0);
+    } else {
+        offset = clamp(offset, 0.0, 1.0);
+    }
+
+    vec2 texture_size = vec2(textureSize(sGradients, 0));
+    float entries_per_block = 0.5 * texture_size.x;
+
+    // Find the entry the offset falls in, and how far through it the offset is.
+    float x = offset * entry_count;
+    float entry = min(floor(x), entry_count - 1.0);
+    float fraction = x - entry;
+    float block = floor(entry / entries_per_block);
+    entry -= block * entries_per_block;
+
+    // The start and end colors of an entry are adjacent texels, so linear
+    // filtering interpolates between them within the entry.
+    x = 2.0 * entry + 0.5 + fraction;
+
+    // Use linear filtering to mix in the low bits (second row of the block)
+    // with the high bits (first row of the block).
+    float y = (gradient_index + block) * 2.0 + 0.5 + 1.0 / 256.0;
+    return texture(sGradients, vec2(x, y) / texture_size);
+}
 #endif //WR_FRAGMENT_SHADER
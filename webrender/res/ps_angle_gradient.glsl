@@ -3,5 +3,6 @@
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
 flat varying float vGradientIndex;
-flat varying float vGradientRepeat;
+flat varying float vGradientEntries;
+flat varying float vGradientExtendMode;
 varying float vOffset;
@@ -29,6 +29,9 @@ void main(void) {
     // V coordinate of gradient row in lookup texture.
     vGradientIndex = float(prim.sub_index);
 
-    // Whether to repeat the gradient instead of clamping.
-    vGradientRepeat = float(int(gradient.extend_mode.x) == EXTEND_MODE_REPEAT);
+    // Number of entries in the gradient's lookup table.
+    vGradientEntries = float(prim.user_data.y);
+
+    // Whether to clamp, repeat or reflect the gradient.
+    vGradientExtendMode = gradient.extend_mode.x;
 }
@@ -51,9 +51,42 @@ vec3 hsvToRgb(vec3 c) {
     }
 }
 
-vec4 Blur(float radius, vec2 direction) {
-    // TODO(gw): Support blur in WR2!
-    return vec4(1.0);
+float gauss(float x, float sigma) {
+    return (1.0 / sqrt(6.283185307179586 * sigma * sigma)) * exp(-(x * x) / (2.0 * sigma * sigma));
+}
+
+// A single pass of a separable gaussian blur, weighted like cs_blur.
+vec4 Blur(float radius, vec2 offset_scale) {
+    vec4 cache_sample = texture(sCacheRGBA8, vUv);
+    int blur_radius = int(radius);
+
+    if (blur_radius == 0) {
+        return cache_sample;
+    }
+
+    float sigma = radius * 0.5;
+    vec4 color = vec4(cache_sample.rgb, 1.0) * (cache_sample.a * gauss(0.0, sigma));
+
+    for (int i=1 ; i < blur_radius ; ++i) {
+        vec2 offset = vec2(float(i)) * offset_scale;
+
+        vec2 st0 = clamp(vUv.xy + offset, vUvBounds.xy, vUvBounds.zw);
+        vec4 color0 = texture(sCacheRGBA8, vec3(st0, vUv.z));
+
+        vec2 st1 = clamp(vUv.xy - offset, vUvBounds.xy, vUvBounds.zw);
+        vec4 color1 = texture(sCacheRGBA8, vec3(st1, vUv.z));
+
+        float weight = gauss(float(i), sigma);
+        color += vec4(color0.rgb * color0.a, color0.a) * weight;
+        color += vec4(color1.rgb * color1.a, color1.a) * weight;
+    }
+
+    if (color.a == 0.0) {
+        discard;
+    }
+
+    color.rgb /= color.a;
+    return color;
 }
 
 vec4 Contrast(vec4 Cs, float amount) {
@@ -99,6 +132,13 @@ vec4 Opacity(vec4 Cs, float amount) {
 }
 
 void main(void) {
+    // Blurs spread color into transparent pixels, so they can't be
+    // skipped like the other filters.
+    if (vOp == 0 || vOp == 10) {
+        oFragColor = Blur(vAmount, vOffsetScale);
+        return;
+    }
+
     vec4 Cs = texture(sCacheRGBA8, vUv);
 
     if (Cs.a == 0.0) {
@@ -106,10 +146,6 @@ void main(void) {
     }
 
     switch (vOp) {
-        case 0:
-            // Gaussian blur is specially handled:
-            oFragColor = Cs;// Blur(vAmount, vec2(0,0));
-            break;
         case 1:
             oFragColor = Contrast(Cs, vAmount);
             break;
@@ -134,5 +170,9 @@ void main(void) {
         case 8:
             oFragColor = Opacity(Cs, vAmount);
             break;
+        case 9:
+            // The source is the blurred content, so only its alpha is used.
+            oFragColor = vec4(vColor.rgb, vColor.a * Cs.a);
+            break;
     }
 }
@@ -3,5 +3,8 @@
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
 varying vec3 vUv;
+flat varying vec4 vUvBounds;
+flat varying vec2 vOffsetScale;
 flat varying float vAmount;
 flat varying int vOp;
+flat varying vec4 vColor;
@@ -20,9 +20,30 @@ void main(void) {
     vec2 st0 = src_task.render_target_origin / texture_size;
     vec2 st1 = (src_task.render_target_origin + src_task.size) / texture_size;
     vUv = vec3(mix(st0, st1, aPosition.xy), src_task.render_target_layer_index);
+    vUvBounds = vec4(st0, st1);
 
     vOp = pi.sub_index;
     vAmount = float(pi.user_data.y) / 65535.0;
 
+    // Blurs step one device pixel at a time along their axis, with the
+    // radius given in layout pixels.
+    switch (vOp) {
+        case 0:
+            vOffsetScale = vec2(1.0 / texture_size.x, 0.0);
+            vAmount *= uDevicePixelRatio;
+            break;
+        case 10:
+            vOffsetScale = vec2(0.0, 1.0 / texture_size.y);
+            vAmount *= uDevicePixelRatio;
+            break;
+        default:
+            vOffsetScale = vec2(0.0);
+            break;
+    }
+
+    // Drop shadows pack an RGBA8 color in place of the amount.
+    ivec4 color = (ivec4(pi.user_data.y) >> ivec4(24, 16, 8, 0)) & 0xff;
+    vColor = vec4(color) / 255.0;
+
     gl_Position = uTransform * vec4(local_pos, pi.z, 1.0);
 }
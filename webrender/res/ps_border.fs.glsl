@@ -428,9 +428,14 @@ void main(void) {
 
     vec2 brightness_mod = vec2(0.7, 1.3);
 
+    // Corners join two sides which may have different styles. Each side owns
+    // the half of the corner on its side of the mix line, the same split that
+    // is used to pick the color. Edges have the same style on both sides.
+    int border_style = distance_from_mix_line < 0.0 ? vBorderStyle : vVerticalBorderStyle;
+
     // Note: we can't pass-through in the following cases,
     // because Angle doesn't support it and fails to compile the shaders.
-    switch (vBorderStyle) {
+    switch (border_style) {
         case BORDER_STYLE_DASHED:
           draw_dashed_or_dotted_border(local_pos, distance_from_mix_line);
           break;
@@ -450,8 +455,7 @@ void main(void) {
           draw_solid_border(distance_from_mix_line, local_pos);
           break;
         case BORDER_STYLE_NONE:
-          draw_solid_border(distance_from_mix_line, local_pos);
-          break;
+          discard;
         case BORDER_STYLE_GROOVE:
           draw_mixed_border(distance_from_mix_line, distance_from_middle, local_pos, brightness_mod.yx);
           break;
@@ -13,7 +13,8 @@ flat varying vec4 vLocalRect; // The rect of the border (x, y, w, h) in local sp
 // for corners, this is the beginning of the corner.
 // For the lines, this is the top left of the line.
 flat varying vec2 vRefPoint;
-flat varying int vBorderStyle;
+flat varying int vBorderStyle;         // The style of the side drawn in vHorizontalColor.
+flat varying int vVerticalBorderStyle; // The style of the side drawn in vVerticalColor.
 flat varying int vBorderPart; // Which part of the border we're drawing.
 
 flat varying vec4 vPieceRect;
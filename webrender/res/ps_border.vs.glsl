@@ -57,6 +57,7 @@ void main(void) {
             segment_rect.p0 = tl_outer;
             segment_rect.size = tl_inner - tl_outer;
             vBorderStyle = int(border.style.x);
+            vVerticalBorderStyle = int(border.style.y);
             vHorizontalColor = border.colors[BORDER_LEFT];
             vVerticalColor = border.colors[BORDER_TOP];
             vRadii = vec4(border.radii[0].xy,
@@ -66,6 +67,7 @@ void main(void) {
             segment_rect.p0 = vec2(tr_inner.x, tr_outer.y);
             segment_rect.size = vec2(tr_outer.x - tr_inner.x, tr_inner.y - tr_outer.y);
             vBorderStyle = int(border.style.y);
+            vVerticalBorderStyle = int(border.style.z);
             vHorizontalColor = border.colors[BORDER_TOP];
             vVerticalColor = border.colors[BORDER_RIGHT];
             vRadii = vec4(border.radii[0].zw,
@@ -74,7 +76,8 @@ void main(void) {
         case PST_BOTTOM_RIGHT:
             segment_rect.p0 = br_inner;
             segment_rect.size = br_outer - br_inner;
-            vBorderStyle = int(border.style.z);
+            vBorderStyle = int(border.style.w);
+            vVerticalBorderStyle = int(border.style.z);
             vHorizontalColor = border.colors[BORDER_BOTTOM];
             vVerticalColor = border.colors[BORDER_RIGHT];
             vRadii = vec4(border.radii[1].xy,
@@ -84,6 +87,7 @@ void main(void) {
             segment_rect.p0 = vec2(bl_outer.x, bl_inner.y);
             segment_rect.size = vec2(bl_inner.x - bl_outer.x, bl_outer.y - bl_inner.y);
             vBorderStyle = int(border.style.w);
+            vVerticalBorderStyle = int(border.style.x);
             vHorizontalColor = border.colors[BORDER_BOTTOM];
             vVerticalColor = border.colors[BORDER_LEFT];
             vRadii = vec4(border.radii[1].zw,
@@ -93,6 +97,7 @@ void main(void) {
             segment_rect.p0 = vec2(tl_outer.x, tl_inner.y);
             segment_rect.size = vec2(border.widths.x, bl_inner.y - tl_inner.y);
             vBorderStyle = int(border.style.x);
+            vVerticalBorderStyle = vBorderStyle;
             vHorizontalColor = border.colors[BORDER_LEFT];
             vVerticalColor = border.colors[BORDER_LEFT];
             vRadii = vec4(0.0);
@@ -101,6 +106,7 @@ void main(void) {
             segment_rect.p0 = vec2(tr_outer.x - border.widths.z, tr_inner.y);
             segment_rect.size = vec2(border.widths.z, br_inner.y - tr_inner.y);
             vBorderStyle = int(border.style.z);
+            vVerticalBorderStyle = vBorderStyle;
             vHorizontalColor = border.colors[BORDER_RIGHT];
             vVerticalColor = border.colors[BORDER_RIGHT];
             vRadii = vec4(0.0);
@@ -109,6 +115,7 @@ void main(void) {
             segment_rect.p0 = vec2(bl_inner.x, bl_outer.y - border.widths.w);
             segment_rect.size = vec2(br_inner.x - bl_inner.x, border.widths.w);
             vBorderStyle = int(border.style.w);
+            vVerticalBorderStyle = vBorderStyle;
             vHorizontalColor = border.colors[BORDER_BOTTOM];
             vVerticalColor = border.colors[BORDER_BOTTOM];
             vRadii = vec4(0.0);
@@ -117,6 +124,7 @@ void main(void) {
             segment_rect.p0 = vec2(tl_inner.x, tl_outer.y);
             segment_rect.size = vec2(tr_inner.x - tl_inner.x, border.widths.y);
             vBorderStyle = int(border.style.y);
+            vVerticalBorderStyle = vBorderStyle;
             vHorizontalColor = border.colors[BORDER_TOP];
             vVerticalColor = border.colors[BORDER_TOP];
             vRadii = vec4(0.0);
@@ -14,6 +14,12 @@ void main(void) {
     // drawing the outer parts of an inset box shadow.
     uv = clamp(uv, vec2(0.0), vec2(1.0));
 
+    // If the corners differ, select the quadrant of the cached
+    // patch that holds the corner nearest to this fragment.
+    if (vMirror == 0.0) {
+        uv = mix(0.5 * uv, vec2(1.0) - 0.5 * uv, step(vMirrorPoint, vUv.xy));
+    }
+
     // Map the unit UV to the actual UV rect in the cache.
     uv = mix(vCacheUvRectCoords.xy, vCacheUvRectCoords.zw, uv);
 
@@ -6,4 +6,5 @@ flat varying vec4 vColor;
 
 varying vec3 vUv;
 flat varying vec2 vMirrorPoint;
+flat varying float vMirror;
 flat varying vec4 vCacheUvRectCoords;
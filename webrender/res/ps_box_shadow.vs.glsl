@@ -15,13 +15,20 @@ void main(void) {
                                  prim.task);
 
     RenderTaskData child_task = fetch_render_task(prim.user_data.x);
-    vUv.z = child_task.data1.x;
+    vUv.z = child_task.data1.z;
 
     // Constant offsets to inset from bilinear filtering border.
     vec2 patch_origin = child_task.data0.xy + vec2(1.0);
     vec2 patch_size_device_pixels = child_task.data0.zw - vec2(2.0);
     vec2 patch_size = patch_size_device_pixels / uDevicePixelRatio;
 
+    // If the corners differ, each corner only covers one quadrant
+    // of the cached patch.
+    vMirror = bs.edge_size_blur_radius_inverted_mirror.w;
+    if (vMirror == 0.0) {
+        patch_size *= 0.5;
+    }
+
     vUv.xy = (vi.local_pos - prim.local_rect.p0) / patch_size;
     vMirrorPoint = 0.5 * prim.local_rect.size / patch_size;
 
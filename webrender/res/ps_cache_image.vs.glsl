@@ -16,7 +16,7 @@ void main(void) {
                                  prim.task);
 
     RenderTaskData child_task = fetch_render_task(prim.user_data.x);
-    vUv.z = child_task.data1.x;
+    vUv.z = child_task.data1.z;
 
     vec2 texture_size = vec2(textureSize(sCacheRGBA8, 0));
     vec2 uv0 = child_task.data0.xy / texture_size;
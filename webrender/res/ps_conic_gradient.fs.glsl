@@ -0,0 +1,19 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+#define TWO_PI  6.28318530718
+
+void main(void) {
+    vec2 pd = vPos - vCenter;
+
+    // Angle of this fragment around the center, measured clockwise
+    // from the top (local y points down), relative to the start angle.
+    float angle = atan(pd.x, -pd.y) - vStartAngle;
+    float x = fract(angle / TWO_PI);
+
+    oFragColor = dither(sample_gradient(x,
+                                        vGradientIndex,
+                                        vGradientEntries,
+                                        int(vGradientExtendMode)));
+}
@@ -0,0 +1,10 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+flat varying float vGradientIndex;
+flat varying float vGradientEntries;
+flat varying float vGradientExtendMode;
+flat varying vec2 vCenter;
+flat varying float vStartAngle;
+varying vec2 vPos;
@@ -0,0 +1,31 @@
+#line 1
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+void main(void) {
+    Primitive prim = load_primitive();
+    ConicGradient gradient = fetch_conic_gradient(prim.prim_index);
+
+    VertexInfo vi = write_vertex(prim.local_rect,
+                                 prim.local_clip_rect,
+                                 prim.z,
+                                 prim.layer,
+                                 prim.task);
+
+    vPos = vi.local_pos;
+
+    // Snap the center to device pixel units, matching the
+    // radial gradient.
+    vCenter = floor(0.5 + gradient.center_angle_extend_mode.xy * uDevicePixelRatio) / uDevicePixelRatio;
+    vStartAngle = gradient.center_angle_extend_mode.z;
+
+    // V coordinate of gradient row in lookup texture.
+    vGradientIndex = float(prim.sub_index);
+
+    // Number of entries in the gradient's lookup table.
+    vGradientEntries = float(prim.user_data.y);
+
+    // Whether to clamp, repeat or reflect the gradient.
+    vGradientExtendMode = gradient.center_angle_extend_mode.w;
+}
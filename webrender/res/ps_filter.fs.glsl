@@ -0,0 +1,88 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+vec4 sample_input(vec3 uv, vec4 uv_task_bounds) {
+    bvec4 inside = lessThanEqual(vec4(uv_task_bounds.xy, uv.xy),
+                                 vec4(uv.xy, uv_task_bounds.zw));
+    if (all(inside)) {
+        return texture(sCacheRGBA8, uv);
+    }
+    return vec4(0.0);
+}
+
+vec4 unpremultiply(vec4 color) {
+    return color.a > 0.0 ? vec4(color.rgb / color.a, color.a) : vec4(0.0);
+}
+
+vec4 premultiply(vec4 color) {
+    return vec4(color.rgb * color.a, color.a);
+}
+
+// Each column of the matrix holds a function type followed by its
+// parameters: 0 is the identity, 1 is linear and 2 is gamma.
+float transfer(float value, vec4 function) {
+    if (function.x == 1.0) {
+        return function.y * value + function.z;
+    }
+    if (function.x == 2.0) {
+        return function.y * pow(value, function.z) + function.w;
+    }
+    return value;
+}
+
+void main(void) {
+    vec4 in1 = sample_input(vUv1, vUvTaskBounds1);
+    vec4 in2 = sample_input(vUv2, vUvTaskBounds2);
+    vec4 result;
+
+    switch (vOp) {
+        case FILTER_COLOR_MATRIX:
+            result = premultiply(clamp(vColorMat * unpremultiply(in1) + vColorVec, 0.0, 1.0));
+            break;
+        case FILTER_COMPONENT_TRANSFER: {
+            vec4 color = unpremultiply(in1);
+            color = vec4(transfer(color.r, vColorMat[0]),
+                         transfer(color.g, vColorMat[1]),
+                         transfer(color.b, vColorMat[2]),
+                         transfer(color.a, vColorMat[3]));
+            result = premultiply(clamp(color, 0.0, 1.0));
+            break;
+        }
+        case FILTER_FLOOD:
+            result = premultiply(vColorVec);
+            break;
+        case FILTER_OFFSET:
+            result = in1;
+            break;
+        case FILTER_COMPOSITE_OVER:
+            result = in1 + in2 * (1.0 - in1.a);
+            break;
+        case FILTER_COMPOSITE_IN:
+            result = in1 * in2.a;
+            break;
+        case FILTER_COMPOSITE_OUT:
+            result = in1 * (1.0 - in2.a);
+            break;
+        case FILTER_COMPOSITE_ATOP:
+            result = in1 * in2.a + in2 * (1.0 - in1.a);
+            break;
+        case FILTER_COMPOSITE_XOR:
+            result = in1 * (1.0 - in2.a) + in2 * (1.0 - in1.a);
+            break;
+        case FILTER_COMPOSITE_ARITHMETIC: {
+            vec4 k = vColorVec;
+            result = clamp(k.x * in1 * in2 + k.y * in1 + k.z * in2 + k.w, 0.0, 1.0);
+            result.rgb = min(result.rgb, vec3(result.a));
+            break;
+        }
+        case FILTER_MERGE:
+            result = in2 + in1 * (1.0 - in2.a);
+            break;
+        default:
+            result = in1;
+            break;
+    }
+
+    oFragColor = result;
+}
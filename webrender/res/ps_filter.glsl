@@ -0,0 +1,24 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+// These must match the ops in filter_graph.rs!
+#define FILTER_COLOR_MATRIX         0
+#define FILTER_COMPONENT_TRANSFER   1
+#define FILTER_FLOOD                2
+#define FILTER_OFFSET               3
+#define FILTER_COMPOSITE_OVER       4
+#define FILTER_COMPOSITE_IN         5
+#define FILTER_COMPOSITE_OUT        6
+#define FILTER_COMPOSITE_ATOP       7
+#define FILTER_COMPOSITE_XOR        8
+#define FILTER_COMPOSITE_ARITHMETIC 9
+#define FILTER_MERGE                10
+
+varying vec3 vUv1;
+varying vec3 vUv2;
+flat varying vec4 vUvTaskBounds1;
+flat varying vec4 vUvTaskBounds2;
+flat varying int vOp;
+flat varying mat4 vColorMat;
+flat varying vec4 vColorVec;
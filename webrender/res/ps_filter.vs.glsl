@@ -0,0 +1,70 @@
+#line 1
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+struct FilterParams {
+    mat4 matrix;
+    vec4 vector;
+};
+
+FilterParams fetch_filter_params(int index) {
+    FilterParams params;
+
+    ivec2 uv0 = get_fetch_uv_2(index + 0);
+    ivec2 uv1 = get_fetch_uv_2(index + 1);
+    ivec2 uv2 = get_fetch_uv_2(index + 2);
+
+    params.matrix[0] = texelFetchOffset(sData32, uv0, 0, ivec2(0, 0));
+    params.matrix[1] = texelFetchOffset(sData32, uv0, 0, ivec2(1, 0));
+    params.matrix[2] = texelFetchOffset(sData32, uv1, 0, ivec2(0, 0));
+    params.matrix[3] = texelFetchOffset(sData32, uv1, 0, ivec2(1, 0));
+    params.vector = texelFetchOffset(sData32, uv2, 0, ivec2(0, 0));
+
+    return params;
+}
+
+// Every task of a filter graph covers the same rect, so an input is
+// sampled at the same position within its task as the one being drawn.
+// Inputs that aren't used get empty bounds, which makes them transparent.
+void write_input(int task_index,
+                 vec2 local_pos,
+                 out vec3 uv,
+                 out vec4 uv_task_bounds) {
+    if (task_index < 0) {
+        uv = vec3(0.0);
+        uv_task_bounds = vec4(1.0, 1.0, 0.0, 0.0);
+        return;
+    }
+
+    AlphaBatchTask src_task = fetch_alpha_batch_task(task_index);
+    vec2 texture_size = vec2(textureSize(sCacheRGBA8, 0));
+    uv = vec3((src_task.render_target_origin + local_pos) / texture_size,
+              src_task.render_target_layer_index);
+    uv_task_bounds = vec4(src_task.render_target_origin,
+                          src_task.render_target_origin + src_task.size) / texture_size.xyxy;
+}
+
+void main(void) {
+    PrimitiveInstance pi = fetch_prim_instance();
+    AlphaBatchTask dest_task = fetch_alpha_batch_task(pi.render_task_index);
+    FilterParams params = fetch_filter_params(pi.specific_prim_index);
+
+    vec2 local_pos = dest_task.size * aPosition.xy;
+
+    vOp = pi.sub_index;
+    vColorMat = params.matrix;
+    vColorVec = params.vector;
+
+    // Offsets are given in layout pixels.
+    vec2 in1_pos = local_pos;
+    if (vOp == FILTER_OFFSET) {
+        in1_pos -= params.vector.xy * uDevicePixelRatio;
+    }
+
+    write_input(pi.user_data.x, in1_pos, vUv1, vUvTaskBounds1);
+    write_input(pi.user_data.y, local_pos, vUv2, vUvTaskBounds2);
+
+    vec2 dest_pos = dest_task.render_target_origin + local_pos;
+    gl_Position = uTransform * vec4(dest_pos, pi.z, 1.0);
+}
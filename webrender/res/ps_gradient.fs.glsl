@@ -13,8 +13,11 @@ void main(void) {
 
     alpha = min(alpha, do_clip());
 
+    float offset = pow(clamp(vOffset, 0.0, 1.0), vHintExponent);
+    vec4 color = mix(vColor0, vColor1, offset);
+
     // TODO(gw): Re-enable the gradient dither once we get the
     //           reftests passing.
-    //oFragColor = dither(vColor * vec4(1.0, 1.0, 1.0, alpha));
-    oFragColor = vColor * vec4(1.0, 1.0, 1.0, alpha);
+    //oFragColor = dither(color * vec4(1.0, 1.0, 1.0, alpha));
+    oFragColor = color * vec4(1.0, 1.0, 1.0, alpha);
 }
@@ -2,7 +2,10 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
-varying vec4 vColor;
+flat varying vec4 vColor0;
+flat varying vec4 vColor1;
+flat varying float vHintExponent;
+varying float vOffset;
 
 #ifdef WR_FEATURE_TRANSFORM
 varying vec3 vLocalPos;
@@ -12,8 +12,7 @@ void main(void) {
 
     RectWithSize segment_rect;
     vec2 axis;
-    vec4 adjusted_color_g0 = g0.color;
-    vec4 adjusted_color_g1 = g1.color;
+    vec2 adjusted_offset;
     if (gradient.start_end_point.y == gradient.start_end_point.w) {
         // Calculate the x coord of the gradient stops
         vec2 g01_x = mix(gradient.start_end_point.xx, gradient.start_end_point.zz,
@@ -29,10 +28,8 @@ void main(void) {
         segment_rect.size = vec2(g01_x_clamped.y - g01_x_clamped.x, prim.local_rect.size.y);
         axis = vec2(1.0, 0.0);
 
-        // Adjust the stop colors by how much they were clamped
-        vec2 adjusted_offset = (g01_x_clamped - g01_x.xx) / (g01_x.y - g01_x.x);
-        adjusted_color_g0 = mix(g0.color, g1.color, adjusted_offset.x);
-        adjusted_color_g1 = mix(g0.color, g1.color, adjusted_offset.y);
+        // Adjust the segment offsets by how much the stops were clamped
+        adjusted_offset = (g01_x_clamped - g01_x.xx) / (g01_x.y - g01_x.x);
     } else {
         // Calculate the y coord of the gradient stops
         vec2 g01_y = mix(gradient.start_end_point.yy, gradient.start_end_point.ww,
@@ -48,10 +45,8 @@ void main(void) {
         segment_rect.size = vec2(prim.local_rect.size.x, g01_y_clamped.y - g01_y_clamped.x);
         axis = vec2(0.0, 1.0);
 
-        // Adjust the stop colors by how much they were clamped
-        vec2 adjusted_offset = (g01_y_clamped - g01_y.xx) / (g01_y.y - g01_y.x);
-        adjusted_color_g0 = mix(g0.color, g1.color, adjusted_offset.x);
-        adjusted_color_g1 = mix(g0.color, g1.color, adjusted_offset.y);
+        // Adjust the segment offsets by how much the stops were clamped
+        adjusted_offset = (g01_y_clamped - g01_y.xx) / (g01_y.y - g01_y.x);
     }
 
 #ifdef WR_FEATURE_TRANSFORM
@@ -76,5 +71,10 @@ void main(void) {
 
     write_clip(vi.screen_pos, prim.clip_area);
 
-    vColor = mix(adjusted_color_g0, adjusted_color_g1, dot(f, axis));
+    // The colors are mixed per fragment, since a hint between the
+    // stops makes the ramp non-linear.
+    vColor0 = g0.color;
+    vColor1 = g1.color;
+    vHintExponent = g0.offset.y;
+    vOffset = mix(adjusted_offset.x, adjusted_offset.y, dot(f, axis));
 }
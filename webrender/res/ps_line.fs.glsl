@@ -0,0 +1,65 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+void main(void) {
+#ifdef WR_FEATURE_TRANSFORM
+    float alpha = 0.0;
+    vec2 local_pos = init_transform_fs(vLocalPos, vLocalRect, alpha);
+#else
+    float alpha = 1.0;
+    vec2 local_pos = vLocalPos;
+#endif
+
+    alpha = min(alpha, do_clip());
+
+    // Rotate the position so that x is along the line and y is across it.
+    vec2 pos = mix(local_pos, local_pos.yx, vAxisSelect) - vLineRect.xy;
+    vec2 size = vLineRect.zw;
+    float thickness = size.y;
+
+    // Distance over which to apply anti-aliasing.
+    float aa_range = 0.5 * length(fwidth(pos));
+
+    switch (vStyle) {
+        case LINE_STYLE_SOLID: {
+            break;
+        }
+        case LINE_STYLE_DASHED: {
+            // Dashes are three times as long as the line is thick,
+            // separated by gaps of the same length.
+            float dash_length = 3.0 * thickness;
+            float pos_in_dash = mod(pos.x, 2.0 * dash_length);
+            alpha = min(alpha, 1.0 - smoothstep(dash_length - aa_range,
+                                                dash_length + aa_range,
+                                                pos_in_dash));
+            break;
+        }
+        case LINE_STYLE_DOTTED: {
+            // Dots are circles with a diameter of the line thickness,
+            // separated by gaps of the same size.
+            float radius = 0.5 * thickness;
+            vec2 dot_center = vec2(radius);
+            vec2 pos_in_dot = vec2(mod(pos.x, 2.0 * thickness), pos.y);
+            float d = distance(pos_in_dot, dot_center) - radius;
+            alpha = min(alpha, 1.0 - smoothstep(-aa_range, aa_range, d));
+            break;
+        }
+        case LINE_STYLE_WAVY: {
+            // A sine wave whose stroke width is a third of the
+            // line thickness, with a wavelength of twice the thickness.
+            float stroke = max(1.0, thickness / 3.0);
+            float amplitude = 0.5 * (thickness - stroke);
+            float phase = pos.x * 3.14159265 / thickness;
+            float center = 0.5 * thickness + amplitude * sin(phase);
+            // Scale the vertical distance by the slope of the curve
+            // so the stroke width stays roughly constant.
+            float slope = amplitude * 3.14159265 / thickness * cos(phase);
+            float d = abs(pos.y - center) / sqrt(1.0 + slope * slope) - 0.5 * stroke;
+            alpha = min(alpha, 1.0 - smoothstep(-aa_range, aa_range, d));
+            break;
+        }
+    }
+
+    oFragColor = vColor * vec4(1.0, 1.0, 1.0, alpha);
+}
@@ -0,0 +1,43 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+void main(void) {
+    Primitive prim = load_primitive();
+    Line line = fetch_line(prim.prim_index);
+    vColor = line.color;
+    vStyle = int(line.style_orientation.x);
+
+    // The fragment shader works in a space where x runs along the
+    // length of the line and y runs across its thickness.
+    switch (int(line.style_orientation.y)) {
+        case LINE_ORIENTATION_HORIZONTAL:
+            vAxisSelect = 0.0;
+            vLineRect = vec4(prim.local_rect.p0, prim.local_rect.size);
+            break;
+        case LINE_ORIENTATION_VERTICAL:
+        default:
+            vAxisSelect = 1.0;
+            vLineRect = vec4(prim.local_rect.p0.yx, prim.local_rect.size.yx);
+            break;
+    }
+
+#ifdef WR_FEATURE_TRANSFORM
+    TransformVertexInfo vi = write_transform_vertex(prim.local_rect,
+                                                    prim.local_clip_rect,
+                                                    prim.z,
+                                                    prim.layer,
+                                                    prim.task);
+    vLocalRect = vi.clipped_local_rect;
+    vLocalPos = vi.local_pos;
+#else
+    VertexInfo vi = write_vertex(prim.local_rect,
+                                 prim.local_clip_rect,
+                                 prim.z,
+                                 prim.layer,
+                                 prim.task);
+    vLocalPos = vi.local_pos;
+#endif
+
+    write_clip(vi.screen_pos, prim.clip_area);
+}
@@ -36,6 +36,9 @@ void main(void) {
     // V coordinate of gradient row in lookup texture.
     vGradientIndex = float(prim.sub_index);
 
-    // Whether to repeat the gradient instead of clamping.
-    vGradientRepeat = float(int(gradient.start_end_radius_ratio_xy_extend_mode.w) == EXTEND_MODE_REPEAT);
+    // Number of entries in the gradient's lookup table.
+    vGradientEntries = float(prim.user_data.y);
+
+    // Whether to clamp, repeat or reflect the gradient.
+    vGradientExtendMode = gradient.start_end_radius_ratio_xy_extend_mode.w;
 }
@@ -0,0 +1,53 @@
+#line 1
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+struct SplitGeometry {
+    vec2 points[4];
+};
+
+SplitGeometry fetch_split_geometry(int index) {
+    SplitGeometry geometry;
+
+    ivec2 uv = get_fetch_uv_2(index);
+
+    vec4 data0 = texelFetchOffset(sData32, uv, 0, ivec2(0, 0));
+    vec4 data1 = texelFetchOffset(sData32, uv, 0, ivec2(1, 0));
+
+    geometry.points[0] = data0.xy;
+    geometry.points[1] = data0.zw;
+    geometry.points[2] = data1.xy;
+    geometry.points[3] = data1.zw;
+
+    return geometry;
+}
+
+void main(void) {
+    PrimitiveInstance pi = fetch_prim_instance();
+    SplitGeometry geometry = fetch_split_geometry(pi.specific_prim_index);
+    AlphaBatchTask dest_task = fetch_alpha_batch_task(pi.render_task_index);
+    AlphaBatchTask src_task = fetch_alpha_batch_task(pi.user_data.x);
+
+    // The polygon is convex, so the two triangles of the unit quad
+    // cover it exactly.
+    vec2 device_pos = mix(mix(geometry.points[0], geometry.points[1], aPosition.x),
+                          mix(geometry.points[3], geometry.points[2], aPosition.x),
+                          aPosition.y);
+
+    vec2 dest_pos = device_pos -
+                    dest_task.screen_space_origin +
+                    dest_task.render_target_origin;
+
+    // The plane was drawn into the source task with its transform applied,
+    // so the polygon samples the same device pixels from it.
+    vec2 texture_size = vec2(textureSize(sCacheRGBA8, 0));
+    vec2 src_pos = device_pos -
+                   src_task.screen_space_origin +
+                   src_task.render_target_origin;
+    vUv = vec3(src_pos / texture_size, src_task.render_target_layer_index);
+    vUvTaskBounds = vec4(src_task.render_target_origin,
+                         src_task.render_target_origin + src_task.size) / texture_size.xyxy;
+
+    gl_Position = uTransform * vec4(dest_pos, pi.z, 1.0);
+}
@@ -9,8 +9,11 @@ void main(void) {
     Glyph glyph = fetch_glyph(prim.sub_index);
     ResourceRect res = fetch_resource_rect(prim.user_data.x);
 
-    RectWithSize local_rect = RectWithSize(glyph.offset.xy,
-                                           (res.uv_rect.zw - res.uv_rect.xy) / uDevicePixelRatio);
+    // The glyph was rasterized at the device pixel ratio scaled by
+    // the zoom of the scroll frame it is in.
+    RectWithSize local_rect = RectWithSize(glyph.offset,
+                                           (res.uv_rect.zw - res.uv_rect.xy) /
+                                           (uDevicePixelRatio * glyph.raster_scale));
 
 #ifdef WR_FEATURE_TRANSFORM
     TransformVertexInfo vi = write_transform_vertex(local_rect,
@@ -14,6 +14,7 @@ pub trait BorderSideHelpers {
                     scale_factor_1: f32,
                     black_color_0: f32,
                     black_color_1: f32) -> ColorF;
+    fn is_visible(&self) -> bool;
 }
 
 impl BorderSideHelpers for BorderSide {
@@ -37,7 +38,17 @@ impl BorderSideHelpers for BorderSide {
                     ColorF::new(black_color_1, black_color_1, black_color_1, self.color.a)
                 }
             }
+            // Hidden and none sides are transparent, so that corners shared
+            // with a visible side only fade out on the hidden half.
+            BorderStyle::None | BorderStyle::Hidden => ColorF::new(0.0, 0.0, 0.0, 0.0),
             _ => self.color,
         }
     }
+
+    fn is_visible(&self) -> bool {
+        match self.style {
+            BorderStyle::None | BorderStyle::Hidden => false,
+            _ => true,
+        }
+    }
 }
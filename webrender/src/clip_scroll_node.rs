@@ -3,24 +3,55 @@
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
 use euclid::Point3D;
+use frame_builder::ScrollbarStyle;
 use geometry::ray_intersects_rect;
 use mask_cache::{ClipSource, MaskCacheInfo, RegionMode};
 use prim_store::GpuBlock32;
 use renderer::VertexDataStore;
+use scroll_animation::{MIN_FLING_VELOCITY, ScrollAnimation};
 use spring::{DAMPING, STIFFNESS, Spring};
 use tiling::PackedLayerIndex;
-use util::TransformedRect;
+use util::{TransformedRect, lerp};
 use webrender_traits::{ClipRegion, LayerPixel, LayerPoint, LayerRect, LayerSize};
-use webrender_traits::{LayerToScrollTransform, LayerToWorldTransform, PipelineId};
+use webrender_traits::{LayerToScrollTransform, LayerToWorldTransform, LayerTransform};
+use webrender_traits::{LayoutTransform, OverscrollBehavior, PipelineId, PropertyBindingId};
+use webrender_traits::ScrollLinkedValue;
 use webrender_traits::{ScrollEventPhase, ScrollLayerId, ScrollLayerRect, ScrollLocation};
-use webrender_traits::{WorldPoint, WorldPoint4D};
+use webrender_traits::{ScrollSnapAlign, ScrollSnapArea, ScrollSnapType, ScrollbarAxis};
+use webrender_traits::{WorldPoint, WorldPoint4D, ZoomLimits};
 
+/// Whether scroll frames bounce when their `OverscrollBehavior` leaves it to
+/// the platform.
 #[cfg(target_os = "macos")]
 const CAN_OVERSCROLL: bool = true;
 
 #[cfg(not(target_os = "macos"))]
 const CAN_OVERSCROLL: bool = false;
 
+/// The distance scrolled by `ScrollLocation::Line`, in layer pixels.
+const LINE_HEIGHT: f32 = 40.0;
+
+/// The fraction of the viewport scrolled by the page variants of `ScrollLocation`.
+/// Some of the previous page stays in view, so the reader doesn't lose their place.
+const PAGE_FRACTION: f32 = 0.875;
+
+/// If a gesture ends more than this long after its last movement, in
+/// nanoseconds, the finger was resting and the gesture doesn't fling.
+const FLING_RELEASE_TIMEOUT: u64 = 100 * 1000 * 1000;
+
+/// How close a proximity snap point has to be for the viewport to snap to it,
+/// as a fraction of the viewport size along each axis.
+const SNAP_PROXIMITY_FRACTION: f32 = 0.3;
+
+/// Scrollbar thumbs never get shorter than this, however long the content is.
+const MIN_SCROLLBAR_THUMB_LENGTH: f32 = 20.0;
+
+/// How long scrollbars stay fully visible after the last scroll, in nanoseconds.
+const SCROLLBAR_FADE_DELAY: u64 = 1000 * 1000 * 1000;
+
+/// How long scrollbars take to fade out, in nanoseconds.
+const SCROLLBAR_FADE_DURATION: u64 = 300 * 1000 * 1000;
+
 #[derive(Clone, Debug)]
 pub struct ClipInfo {
     /// The ClipSource for this node, which is used to generate mask_cache_info.
@@ -64,6 +95,39 @@ impl ClipInfo {
     }
 
 }
+/// The snap areas of a scroll frame, in the coordinate system of its
+/// reference frame.
+#[derive(Clone, Debug)]
+pub struct ScrollSnapPoints {
+    pub snap_type: ScrollSnapType,
+    pub areas: Vec<ScrollSnapArea>,
+}
+
+/// The transform of a reference frame whose stacking context transform is a
+/// property binding, which may be bound to a scroll offset at any time without
+/// the scene being rebuilt. While it is, the transform is
+/// `origin * value * perspective`, where the value comes from the scroll-linked
+/// binding. Otherwise the reference frame keeps the transform it was created with.
+#[derive(Clone, Debug)]
+pub struct ScrollLinkedTransform {
+    pub origin: LayerToScrollTransform,
+    pub binding_id: PropertyBindingId,
+    pub perspective: LayoutTransform,
+    /// The transform evaluated for the current frame, if the binding follows
+    /// a scroll offset.
+    pub current: Option<LayerToScrollTransform>,
+}
+
+impl ScrollLinkedTransform {
+    pub fn transform_at(&self,
+                        value: &ScrollLinkedValue<LayoutTransform>,
+                        position: f32)
+                        -> LayerToScrollTransform {
+        let value = value.value_at(position).unwrap_or_else(LayoutTransform::identity);
+        self.origin.pre_mul(&value).pre_mul(&self.perspective)
+    }
+}
+
 #[derive(Clone, Debug)]
 pub enum NodeType {
     /// Transform for this layer, relative to parent reference frame. A reference
@@ -113,6 +177,22 @@ pub struct ClipScrollNode {
 
     /// Whether or not this node is a reference frame.
     pub node_type: NodeType,
+
+    /// Where this node settles when a scroll gesture or fling ends, if anywhere.
+    pub scroll_snap: Option<ScrollSnapPoints>,
+
+    /// What happens when a scroll runs past the edges of this node.
+    pub overscroll: OverscrollBehavior,
+
+    /// How far the content of this node can be zoomed, or None if it can't be.
+    pub zoom_limits: Option<ZoomLimits>,
+
+    /// The scale text inside this node is rasterized at, relative to the device
+    /// pixel ratio. This is the settled zoom of this node and all its ancestors.
+    pub raster_scale: f32,
+
+    /// For reference frames, the scroll offset their transform follows, if any.
+    pub scroll_linked_transform: Option<ScrollLinkedTransform>,
 }
 
 impl ClipScrollNode {
@@ -120,7 +200,10 @@ impl ClipScrollNode {
                parent_id: ScrollLayerId,
                local_viewport_rect: &LayerRect,
                content_size: LayerSize,
-               clip_info: ClipInfo)
+               clip_info: ClipInfo,
+               scroll_snap: Option<ScrollSnapPoints>,
+               overscroll: OverscrollBehavior,
+               zoom_limits: Option<ZoomLimits>)
                -> ClipScrollNode {
         ClipScrollNode {
             scrolling: ScrollingState::new(),
@@ -134,6 +217,11 @@ impl ClipScrollNode {
             children: Vec::new(),
             pipeline_id: pipeline_id,
             node_type: NodeType::Clip(clip_info),
+            scroll_snap: scroll_snap,
+            overscroll: overscroll,
+            zoom_limits: zoom_limits,
+            raster_scale: 1.0,
+            scroll_linked_transform: None,
         }
     }
 
@@ -155,6 +243,11 @@ impl ClipScrollNode {
             children: Vec::new(),
             pipeline_id: pipeline_id,
             node_type: NodeType::ReferenceFrame(*local_transform),
+            scroll_snap: None,
+            overscroll: OverscrollBehavior::default(),
+            zoom_limits: None,
+            raster_scale: 1.0,
+            scroll_linked_transform: None,
         }
     }
 
@@ -164,6 +257,51 @@ impl ClipScrollNode {
 
     pub fn finalize(&mut self, scrolling: &ScrollingState) {
         self.scrolling = *scrolling;
+
+        // The spring parameters come from the latest display list, even when
+        // the rest of the scrolling state carries over from the previous one.
+        let (stiffness, damping) = match self.overscroll.spring {
+            Some(spring) => (spring.stiffness, spring.damping),
+            None => (STIFFNESS, DAMPING),
+        };
+        self.scrolling.spring.set_parameters(stiffness, damping);
+
+        // So do the zoom limits, which may no longer allow the current zoom.
+        let (zoom, raster_zoom) = match self.zoom_limits {
+            Some(limits) => (limits.clamp(self.scrolling.zoom), limits.clamp(self.scrolling.raster_zoom)),
+            None => (1.0, 1.0),
+        };
+        self.scrolling.zoom = zoom;
+        self.scrolling.raster_zoom = raster_zoom;
+    }
+
+    /// The transform of a reference frame relative to its parent reference
+    /// frame, including any scroll-linked transform, or the identity for
+    /// other nodes.
+    pub fn local_transform(&self) -> LayerToScrollTransform {
+        match self.node_type {
+            NodeType::ReferenceFrame(transform) => {
+                match self.scroll_linked_transform {
+                    Some(ScrollLinkedTransform { current: Some(current), .. }) => current,
+                    _ => transform,
+                }
+            }
+            NodeType::Clip(_) => LayerToScrollTransform::identity(),
+        }
+    }
+
+    pub fn is_zoomable(&self) -> bool {
+        self.zoom_limits.is_some()
+    }
+
+    /// Whether this node can ever move its content, either because the content
+    /// is larger than the viewport or because it can be zoomed in.
+    pub fn is_scrollable(&self) -> bool {
+        self.scrollable_width() > 0.0 || self.scrollable_height() > 0.0 || self.is_zoomable()
+    }
+
+    pub fn can_overscroll(&self) -> bool {
+        self.overscroll.bounce.unwrap_or(CAN_OVERSCROLL)
     }
 
     pub fn overscroll_amount(&self) -> LayerSize {
@@ -197,6 +335,7 @@ impl ClipScrollNode {
             NodeType::Clip(_) => {}
         };
 
+        self.scrolling.animation = None;
 
         let scrollable_height = self.scrollable_height();
         let scrollable_width = self.scrollable_width();
@@ -216,15 +355,52 @@ impl ClipScrollNode {
         return true;
     }
 
+    pub fn smooth_scroll_to_origin(&mut self, origin: &LayerPoint) -> bool {
+        match self.node_type {
+            NodeType::ReferenceFrame(_) => {
+                warn!("Tried to scroll a reference frame.");
+                return false;
+            }
+            NodeType::Clip(_) => {}
+        };
+
+        self.smooth_scroll_to(LayerPoint::new(-origin.x, -origin.y))
+    }
+
+    /// Start a smooth scroll to the given offset, clamped to the scrollable area.
+    /// Returns false if the node is already at, or on its way to, that offset.
+    fn smooth_scroll_to(&mut self, offset: LayerPoint) -> bool {
+        let target = self.clamp_offset(offset);
+        let target = LayerPoint::new(target.x.round(), target.y.round());
+        if target == self.scroll_target() {
+            return false;
+        }
+
+        self.scrolling.animation = Some(ScrollAnimation::smooth(self.scrolling.offset, target));
+        self.scrolling.bouncing_back = false;
+        self.scrolling.started_bouncing_back = false;
+        true
+    }
+
+    /// The offset this node is heading to. Relative scrolls that arrive during a
+    /// smooth scroll accumulate onto its target rather than the current offset.
+    fn scroll_target(&self) -> LayerPoint {
+        self.scrolling.animation
+                      .and_then(|animation| animation.target())
+                      .unwrap_or(self.scrolling.offset)
+    }
+
+    fn clamp_offset(&self, offset: LayerPoint) -> LayerPoint {
+        LayerPoint::new(offset.x.max(-self.scrollable_width()).min(0.0),
+                        offset.y.max(-self.scrollable_height()).min(0.0))
+    }
+
     pub fn update_transform(&mut self,
                             parent_reference_frame_transform: &LayerToWorldTransform,
                             parent_combined_viewport_rect: &ScrollLayerRect,
                             parent_accumulated_scroll_offset: LayerPoint) {
 
-        let local_transform = match self.node_type {
-            NodeType::ReferenceFrame(transform) => transform,
-            NodeType::Clip(_) => LayerToScrollTransform::identity(),
-        };
+        let local_transform = self.local_transform();
 
         let inv_transform = match local_transform.inverse() {
             Some(transform) => transform,
@@ -274,47 +450,165 @@ impl ClipScrollNode {
             self.world_viewport_transform.pre_translated(self.scrolling.offset.x,
                                                          self.scrolling.offset.y,
                                                          0.0);
+
+        // Zoomed content is scaled about the origin of the viewport, so that
+        // the top left of the content stays put when the offset is zero.
+        if self.scrolling.zoom != 1.0 {
+            let zoom = self.scrolling.zoom;
+            let origin = self.local_viewport_rect.origin;
+            let zoom_transform = LayerTransform::row_major(zoom, 0.0, 0.0, 0.0,
+                                                           0.0, zoom, 0.0, 0.0,
+                                                           0.0, 0.0, 1.0, 0.0,
+                                                           origin.x * (1.0 - zoom),
+                                                           origin.y * (1.0 - zoom),
+                                                           0.0,
+                                                           1.0);
+            self.world_content_transform = self.world_content_transform.pre_mul(&zoom_transform);
+        }
+    }
+
+    /// Move a rect from the space of `local_viewport_rect` into the space of the
+    /// content of this node, taking out the scroll offset and the zoom.
+    pub fn content_space_rect(&self, rect: &LayerRect) -> LayerRect {
+        let origin = self.local_viewport_rect.origin;
+        let offset = self.scrolling.offset;
+        let zoom = self.scrolling.zoom;
+        LayerRect::new(LayerPoint::new(origin.x + (rect.origin.x - offset.x - origin.x) / zoom,
+                                       origin.y + (rect.origin.y - offset.y - origin.y) / zoom),
+                       LayerSize::new(rect.size.width / zoom, rect.size.height / zoom))
+    }
+
+    /// Scale a rect in the space of the content of this node by the zoom, leaving
+    /// out the scroll offset.
+    fn zoomed_rect(&self, rect: &LayerRect) -> LayerRect {
+        let origin = self.local_viewport_rect.origin;
+        let zoom = self.scrolling.zoom;
+        LayerRect::new(LayerPoint::new(origin.x + (rect.origin.x - origin.x) * zoom,
+                                       origin.y + (rect.origin.y - origin.y) * zoom),
+                       LayerSize::new(rect.size.width * zoom, rect.size.height * zoom))
+    }
+
+    /// The size of the content once zoomed, which is what the viewport scrolls over.
+    pub fn zoomed_content_size(&self) -> LayerSize {
+        LayerSize::new(self.content_size.width * self.scrolling.zoom,
+                       self.content_size.height * self.scrolling.zoom)
     }
 
     pub fn scrollable_height(&self) -> f32 {
-        self.content_size.height - self.local_viewport_rect.size.height
+        self.zoomed_content_size().height - self.local_viewport_rect.size.height
     }
 
     pub fn scrollable_width(&self) -> f32 {
-        self.content_size.width - self.local_viewport_rect.size.width
+        self.zoomed_content_size().width - self.local_viewport_rect.size.width
+    }
+
+    /// Zoom the content of this node to `zoom`, clamped to its limits, keeping
+    /// the content under `focal_point` in place. `focal_point` is in the same
+    /// space as `local_viewport_rect`. Text is rasterized at the new zoom once
+    /// the gesture ends. Returns true if anything changed.
+    pub fn zoom(&mut self, zoom: f32, focal_point: LayerPoint, phase: ScrollEventPhase) -> bool {
+        let limits = match self.zoom_limits {
+            Some(limits) => limits,
+            None => {
+                warn!("Tried to zoom a node which can't be zoomed.");
+                return false;
+            }
+        };
+
+        let old_zoom = self.scrolling.zoom;
+        let old_offset = self.scrolling.offset;
+        let new_zoom = limits.clamp(zoom);
+
+        // Solve for the offset which puts the content point under the focal
+        // point before the zoom back under it afterwards.
+        let origin = self.local_viewport_rect.origin;
+        let scale = new_zoom / old_zoom;
+        let focal_x = focal_point.x - origin.x;
+        let focal_y = focal_point.y - origin.y;
+        let offset = LayerPoint::new(focal_x - (focal_x - old_offset.x) * scale,
+                                     focal_y - (focal_y - old_offset.y) * scale);
+
+        self.scrolling.zoom = new_zoom;
+        self.scrolling.offset = self.clamp_offset(offset);
+        self.scrolling.animation = None;
+        self.scrolling.bouncing_back = false;
+        self.scrolling.started_bouncing_back = false;
+
+        let old_raster_zoom = self.scrolling.raster_zoom;
+        if phase == ScrollEventPhase::End {
+            self.scrolling.raster_zoom = new_zoom;
+        }
+
+        new_zoom != old_zoom ||
+            self.scrolling.offset != old_offset ||
+            self.scrolling.raster_zoom != old_raster_zoom
     }
 
-    pub fn scroll(&mut self, scroll_location: ScrollLocation, phase: ScrollEventPhase) -> bool {
+    /// Scroll this node in response to an input event that happened at
+    /// `event_time`, in nanoseconds.
+    pub fn scroll(&mut self,
+                  scroll_location: ScrollLocation,
+                  phase: ScrollEventPhase,
+                  event_time: u64)
+                  -> bool {
         if self.scrolling.started_bouncing_back && phase == ScrollEventPhase::Move(false) {
             return false;
         }
 
+        let target = self.scroll_target();
         let mut delta = match scroll_location {
             ScrollLocation::Delta(delta) => delta,
             ScrollLocation::Start => {
-                if self.scrolling.offset.y.round() >= 0.0 {
-                    // Nothing to do on this layer.
-                    return false;
-                }
-
-                self.scrolling.offset.y = 0.0;
-                return true;
+                return self.smooth_scroll_to(LayerPoint::new(target.x, 0.0));
             },
             ScrollLocation::End => {
-                let end_pos = self.local_viewport_rect.size.height - self.content_size.height;
-
-                if self.scrolling.offset.y.round() <= end_pos {
-                    // Nothing to do on this layer.
-                    return false;
-                }
-
-                self.scrolling.offset.y = end_pos;
-                return true;
+                let end_pos = -self.scrollable_height();
+                return self.smooth_scroll_to(LayerPoint::new(target.x, end_pos));
+            }
+            ScrollLocation::HorizontalStart => {
+                return self.smooth_scroll_to(LayerPoint::new(0.0, target.y));
+            }
+            ScrollLocation::HorizontalEnd => {
+                let end_pos = -self.scrollable_width();
+                return self.smooth_scroll_to(LayerPoint::new(end_pos, target.y));
+            }
+            ScrollLocation::PageUp => {
+                let page_height = self.local_viewport_rect.size.height * PAGE_FRACTION;
+                return self.smooth_scroll_to(LayerPoint::new(target.x, target.y + page_height));
+            }
+            ScrollLocation::PageDown => {
+                let page_height = self.local_viewport_rect.size.height * PAGE_FRACTION;
+                return self.smooth_scroll_to(LayerPoint::new(target.x, target.y - page_height));
+            }
+            ScrollLocation::PageLeft => {
+                let page_width = self.local_viewport_rect.size.width * PAGE_FRACTION;
+                return self.smooth_scroll_to(LayerPoint::new(target.x + page_width, target.y));
+            }
+            ScrollLocation::PageRight => {
+                let page_width = self.local_viewport_rect.size.width * PAGE_FRACTION;
+                return self.smooth_scroll_to(LayerPoint::new(target.x - page_width, target.y));
+            }
+            ScrollLocation::Page(pages) => {
+                let page_height = self.local_viewport_rect.size.height * PAGE_FRACTION;
+                return self.smooth_scroll_to(LayerPoint::new(target.x,
+                                                             target.y - pages * page_height));
+            }
+            ScrollLocation::Line(lines) => {
+                return self.smooth_scroll_to(LayerPoint::new(target.x,
+                                                             target.y - lines * LINE_HEIGHT));
+            }
+            ScrollLocation::Position(origin) => {
+                return self.smooth_scroll_to(LayerPoint::new(-origin.x, -origin.y));
             }
         };
 
+        // Scrolling directly takes over from any animation in progress.
+        self.scrolling.animation = None;
+        self.update_gesture_velocity(delta, phase, event_time);
+
         let overscroll_amount = self.overscroll_amount();
-        let overscrolling = CAN_OVERSCROLL && (overscroll_amount.width != 0.0 ||
+        let can_overscroll = self.can_overscroll();
+        let overscrolling = can_overscroll && (overscroll_amount.width != 0.0 ||
                                                overscroll_amount.height != 0.0);
         if overscrolling {
             if overscroll_amount.width != 0.0 {
@@ -332,7 +626,7 @@ impl ClipScrollNode {
 
         if scrollable_width > 0. {
             self.scrolling.offset.x = self.scrolling.offset.x + delta.x;
-            if is_unscrollable || !CAN_OVERSCROLL {
+            if is_unscrollable || !can_overscroll {
                 self.scrolling.offset.x =
                     self.scrolling.offset.x.min(0.0).max(-scrollable_width).round();
             }
@@ -340,7 +634,7 @@ impl ClipScrollNode {
 
         if scrollable_height > 0. {
             self.scrolling.offset.y = self.scrolling.offset.y + delta.y;
-            if is_unscrollable || !CAN_OVERSCROLL {
+            if is_unscrollable || !can_overscroll {
                 self.scrolling.offset.y =
                     self.scrolling.offset.y.min(0.0).max(-scrollable_height).round();
             }
@@ -354,12 +648,295 @@ impl ClipScrollNode {
             self.scrolling.bouncing_back = true
         }
 
-        if CAN_OVERSCROLL {
+        if can_overscroll {
             self.stretch_overscroll_spring();
         }
 
+        let started_fling = phase == ScrollEventPhase::End &&
+                            !self.scrolling.started_bouncing_back &&
+                            self.start_fling(event_time);
+
         self.scrolling.offset != original_layer_scroll_offset ||
-            self.scrolling.started_bouncing_back
+            self.scrolling.started_bouncing_back ||
+            started_fling
+    }
+
+    fn update_gesture_velocity(&mut self, delta: LayerPoint, phase: ScrollEventPhase, time: u64) {
+        match phase {
+            ScrollEventPhase::Start => {
+                self.scrolling.velocity = LayerPoint::zero();
+                self.scrolling.last_scroll_event_time = Some(time);
+            }
+            ScrollEventPhase::Move(true) => {
+                if let Some(last_time) = self.scrolling.last_scroll_event_time {
+                    let dt = time.saturating_sub(last_time) as f32 / 1.0e9;
+                    if dt <= 0.0 {
+                        return;
+                    }
+
+                    // Average with the previous estimate to smooth out jitter in
+                    // the timing of events.
+                    let velocity = self.scrolling.velocity;
+                    self.scrolling.velocity = LayerPoint::new((velocity.x + delta.x / dt) * 0.5,
+                                                              (velocity.y + delta.y / dt) * 0.5);
+                }
+                self.scrolling.last_scroll_event_time = Some(time);
+            }
+            ScrollEventPhase::Move(false) => {
+                // The platform is generating momentum events for this gesture
+                // already, so it mustn't fling again when it ends.
+                self.scrolling.velocity = LayerPoint::zero();
+                self.scrolling.last_scroll_event_time = None;
+            }
+            ScrollEventPhase::End => {}
+        }
+    }
+
+    /// Start a fling with the velocity of the gesture that just ended. Returns
+    /// false if the gesture was released too slowly, or after resting.
+    fn start_fling(&mut self, time: u64) -> bool {
+        let velocity = self.scrolling.velocity;
+        let released_while_moving = match self.scrolling.last_scroll_event_time {
+            Some(last_time) => time.saturating_sub(last_time) < FLING_RELEASE_TIMEOUT,
+            None => false,
+        };
+
+        self.scrolling.velocity = LayerPoint::zero();
+        self.scrolling.last_scroll_event_time = None;
+
+        if !released_while_moving ||
+           (velocity.x.abs() < MIN_FLING_VELOCITY && velocity.y.abs() < MIN_FLING_VELOCITY) {
+            return false;
+        }
+
+        self.scrolling.animation = Some(ScrollAnimation::fling(self.scrolling.offset, velocity));
+        true
+    }
+
+    /// Advance the smooth scroll or fling of this node, if any, to the given
+    /// frame time. The offsets on the way are snapped to device pixels, so that
+    /// text keeps its subpixel antialiasing and isn't rasterized again for
+    /// every frame of the animation.
+    pub fn tick_scroll_animation(&mut self, time: u64, device_pixel_ratio: f32) {
+        let mut animation = match self.scrolling.animation {
+            Some(animation) => animation,
+            None => return,
+        };
+
+        let (offset, finished) = animation.tick(time);
+
+        // Flings stop once they run into the edge of the scrollable area.
+        let clamped_offset = self.clamp_offset(offset);
+        if finished || clamped_offset != offset {
+            self.scrolling.offset = LayerPoint::new(clamped_offset.x.round(),
+                                                    clamped_offset.y.round());
+            self.scrolling.animation = None;
+        } else {
+            self.scrolling.offset =
+                LayerPoint::new((offset.x * device_pixel_ratio).round() / device_pixel_ratio,
+                                (offset.y * device_pixel_ratio).round() / device_pixel_ratio);
+            self.scrolling.animation = Some(animation);
+        }
+    }
+
+    pub fn is_animating_scroll(&self) -> bool {
+        self.scrolling.animation.is_some()
+    }
+
+    /// Spring onto the nearest snap point, if this node has one in reach.
+    /// Returns true if the node started moving.
+    pub fn settle_on_snap_point(&mut self) -> bool {
+        if self.scrolling.bouncing_back || self.is_animating_scroll() {
+            return false;
+        }
+
+        let target = match self.snap_target() {
+            Some(target) if target != self.scrolling.offset => target,
+            _ => return false,
+        };
+
+        self.scrolling.spring.coords(self.scrolling.offset, self.scrolling.offset, target);
+        self.scrolling.bouncing_back = true;
+        true
+    }
+
+    fn snap_target(&self) -> Option<LayerPoint> {
+        let scroll_snap = match self.scroll_snap {
+            Some(ref scroll_snap) => scroll_snap,
+            None => return None,
+        };
+
+        let offset = self.scrolling.offset;
+        let viewport = &self.local_viewport_rect;
+        let distance_to = |target: &LayerPoint| {
+            (target.x - offset.x).abs() + (target.y - offset.y).abs()
+        };
+
+        let mut nearest: Option<LayerPoint> = None;
+        for area in &scroll_snap.areas {
+            let area = ScrollSnapArea {
+                rect: self.zoomed_rect(&area.rect),
+                align: area.align,
+            };
+            let target = self.clamp_offset(snap_offset(&area, viewport));
+            let target = LayerPoint::new(target.x.round(), target.y.round());
+            nearest = match nearest {
+                Some(nearest) if distance_to(&nearest) <= distance_to(&target) => Some(nearest),
+                _ => Some(target),
+            };
+        }
+
+        match (nearest, scroll_snap.snap_type) {
+            (Some(target), ScrollSnapType::Proximity) => {
+                let in_reach =
+                    (target.x - offset.x).abs() <= viewport.size.width * SNAP_PROXIMITY_FRACTION &&
+                    (target.y - offset.y).abs() <= viewport.size.height * SNAP_PROXIMITY_FRACTION;
+                if in_reach { Some(target) } else { None }
+            }
+            (nearest, _) => nearest,
+        }
+    }
+
+    /// Show the scrollbars if this node moved since the last call, and fade them
+    /// out once it has been still for a while. `time` is the frame time, if this
+    /// frame was generated with one; the fade only advances on those frames.
+    pub fn update_scrollbar_fade(&mut self, time: Option<u64>) {
+        if self.scrolling.offset != self.scrolling.scrollbar_offset {
+            self.scrolling.scrollbar_offset = self.scrolling.offset;
+            self.scrolling.scrollbar_opacity = 1.0;
+            self.scrolling.scrollbar_fade_start = None;
+        }
+
+        let time = match time {
+            Some(time) if self.is_showing_scrollbars() => time,
+            _ => return,
+        };
+
+        let start = match self.scrolling.scrollbar_fade_start {
+            Some(start) => start,
+            None => {
+                self.scrolling.scrollbar_fade_start = Some(time);
+                time
+            }
+        };
+        let elapsed = time.saturating_sub(start);
+        self.scrolling.scrollbar_opacity = if elapsed <= SCROLLBAR_FADE_DELAY {
+            1.0
+        } else {
+            let progress = (elapsed - SCROLLBAR_FADE_DELAY) as f32 / SCROLLBAR_FADE_DURATION as f32;
+            (1.0 - progress).max(0.0)
+        };
+    }
+
+    pub fn is_showing_scrollbars(&self) -> bool {
+        self.scrolling.scrollbar_opacity > 0.0
+    }
+
+    /// The start of the track the scrollbar thumb on `axis` moves along, the
+    /// length of that track and the length of the thumb, or None if this node
+    /// can't scroll along that axis.
+    fn scrollbar_track(&self, axis: ScrollbarAxis, style: &ScrollbarStyle) -> Option<(f32, f32, f32)> {
+        let viewport = &self.local_viewport_rect;
+        let content_size = self.zoomed_content_size();
+        let (start, viewport_length, content_length, has_other_scrollbar) = match axis {
+            ScrollbarAxis::Vertical => (viewport.origin.y,
+                                        viewport.size.height,
+                                        content_size.height,
+                                        self.scrollable_width() > 0.0),
+            ScrollbarAxis::Horizontal => (viewport.origin.x,
+                                          viewport.size.width,
+                                          content_size.width,
+                                          self.scrollable_height() > 0.0),
+        };
+
+        if content_length <= viewport_length {
+            return None;
+        }
+
+        // Leave the corner free for the other scrollbar, so the thumbs never overlap.
+        let corner = if has_other_scrollbar { style.width + style.margin } else { 0.0 };
+        let track_length = viewport_length - 2.0 * style.margin - corner;
+        if track_length <= 0.0 {
+            return None;
+        }
+
+        let thumb_length = (track_length * viewport_length / content_length)
+            .max(MIN_SCROLLBAR_THUMB_LENGTH)
+            .min(track_length);
+        Some((start + style.margin, track_length, thumb_length))
+    }
+
+    /// The rect of the scrollbar thumb on `axis`, in the same space as
+    /// `local_viewport_rect`, or None if this node can't scroll along that axis.
+    pub fn scrollbar_thumb_rect(&self, axis: ScrollbarAxis, style: &ScrollbarStyle) -> Option<LayerRect> {
+        let (track_start, track_length, thumb_length) = match self.scrollbar_track(axis, style) {
+            Some(track) => track,
+            None => return None,
+        };
+
+        let viewport = &self.local_viewport_rect;
+        let track_end = track_start + track_length - thumb_length;
+        let rect = match axis {
+            ScrollbarAxis::Vertical => {
+                let fraction = (-self.scrolling.offset.y / self.scrollable_height()).max(0.0).min(1.0);
+                LayerRect::new(LayerPoint::new(viewport.max_x() - style.margin - style.width,
+                                               lerp(track_start, track_end, fraction)),
+                               LayerSize::new(style.width, thumb_length))
+            }
+            ScrollbarAxis::Horizontal => {
+                let fraction = (-self.scrolling.offset.x / self.scrollable_width()).max(0.0).min(1.0);
+                LayerRect::new(LayerPoint::new(lerp(track_start, track_end, fraction),
+                                               viewport.max_y() - style.margin - style.width),
+                               LayerSize::new(thumb_length, style.width))
+            }
+        };
+        Some(rect)
+    }
+
+    /// Scroll by dragging the scrollbar thumb on `axis` by `delta` along its
+    /// track. Returns true if the node moved.
+    pub fn drag_scrollbar_thumb(&mut self,
+                                axis: ScrollbarAxis,
+                                delta: f32,
+                                style: &ScrollbarStyle)
+                                -> bool {
+        let travel = match self.scrollbar_track(axis, style) {
+            Some((_, track_length, thumb_length)) => track_length - thumb_length,
+            None => return false,
+        };
+        if travel <= 0.0 {
+            return false;
+        }
+
+        let origin = LayerPoint::new(-self.scrolling.offset.x, -self.scrolling.offset.y);
+        let origin = match axis {
+            ScrollbarAxis::Vertical => {
+                LayerPoint::new(origin.x, origin.y + delta * self.scrollable_height() / travel)
+            }
+            ScrollbarAxis::Horizontal => {
+                LayerPoint::new(origin.x + delta * self.scrollable_width() / travel, origin.y)
+            }
+        };
+        self.set_scroll_origin(&origin)
+    }
+
+    /// The scrollbar thumb under `cursor`, if it is currently visible.
+    pub fn ray_intersects_scrollbar_thumb(&self,
+                                          cursor: &WorldPoint,
+                                          style: &ScrollbarStyle)
+                                          -> Option<ScrollbarAxis> {
+        if !self.is_showing_scrollbars() {
+            return None;
+        }
+
+        for &axis in &[ScrollbarAxis::Vertical, ScrollbarAxis::Horizontal] {
+            if let Some(thumb_rect) = self.scrollbar_thumb_rect(axis, style) {
+                if self.ray_intersects_viewport_rect(cursor, &thumb_rect) {
+                    return Some(axis);
+                }
+            }
+        }
+        None
     }
 
     pub fn stretch_overscroll_spring(&mut self) {
@@ -370,6 +947,10 @@ impl ClipScrollNode {
     }
 
     pub fn tick_scrolling_bounce_animation(&mut self) {
+        if !self.scrolling.bouncing_back {
+            return;
+        }
+
         let finished = self.scrolling.spring.animate();
         self.scrolling.offset = self.scrolling.spring.current();
         if finished {
@@ -378,6 +959,15 @@ impl ClipScrollNode {
     }
 
     pub fn ray_intersects_node(&self, cursor: &WorldPoint) -> bool {
+        if self.scrollable_width() <= 0. && self.scrollable_height() <= 0. {
+            return false;
+        }
+        self.ray_intersects_viewport_rect(cursor, &self.local_viewport_rect)
+    }
+
+    /// Whether a ray along the z axis through `cursor` hits `rect`, which is in
+    /// the same space as `local_viewport_rect`.
+    fn ray_intersects_viewport_rect(&self, cursor: &WorldPoint, rect: &LayerRect) -> bool {
         let inv = self.world_viewport_transform.inverse().unwrap();
         let z0 = -10000.0;
         let z1 =  10000.0;
@@ -391,10 +981,27 @@ impl ClipScrollNode {
                               p1.y / p1.w,
                               p1.z / p1.w);
 
-        if self.scrollable_width() <= 0. && self.scrollable_height() <= 0. {
-            return false;
+        ray_intersects_rect(p0, p1, rect.to_untyped())
+    }
+}
+
+/// The scroll offset that lines `area` up with the viewport.
+fn snap_offset(area: &ScrollSnapArea, viewport: &LayerRect) -> LayerPoint {
+    match area.align {
+        ScrollSnapAlign::Start => {
+            LayerPoint::new(viewport.origin.x - area.rect.origin.x,
+                            viewport.origin.y - area.rect.origin.y)
+        }
+        ScrollSnapAlign::Center => {
+            LayerPoint::new((viewport.min_x() + viewport.max_x() -
+                             area.rect.min_x() - area.rect.max_x()) * 0.5,
+                            (viewport.min_y() + viewport.max_y() -
+                             area.rect.min_y() - area.rect.max_y()) * 0.5)
+        }
+        ScrollSnapAlign::End => {
+            LayerPoint::new(viewport.max_x() - area.rect.max_x(),
+                            viewport.max_y() - area.rect.max_y())
         }
-        ray_intersects_rect(p0, p1, self.local_viewport_rect.to_untyped())
     }
 }
 
@@ -404,7 +1011,39 @@ pub struct ScrollingState {
     pub spring: Spring,
     pub started_bouncing_back: bool,
     pub bouncing_back: bool,
-    pub should_handoff_scroll: bool
+    pub should_handoff_scroll: bool,
+
+    /// The smooth scroll or fling currently moving this node, if any.
+    pub animation: Option<ScrollAnimation>,
+
+    /// The estimated velocity of the current scroll gesture, in layer pixels per
+    /// second. A fling starts with this velocity when the gesture ends.
+    pub velocity: LayerPoint,
+
+    /// When the last movement of the current scroll gesture arrived, in nanoseconds.
+    pub last_scroll_event_time: Option<u64>,
+
+    /// The offset the scrollbars were last updated for, used to notice when
+    /// this node moves.
+    pub scrollbar_offset: LayerPoint,
+
+    /// The frame time the scrollbars have been still since, or None if no
+    /// frame time has been seen since they were last shown.
+    pub scrollbar_fade_start: Option<u64>,
+
+    /// The opacity of the scrollbars of this node, from 0 (hidden) to 1.
+    pub scrollbar_opacity: f32,
+
+    /// The offset last reported to the embedder, used to tell it only about
+    /// the nodes which moved.
+    pub reported_offset: LayerPoint,
+
+    /// The zoom of the content of this node, about the origin of its viewport.
+    pub zoom: f32,
+
+    /// The zoom text is rasterized at, which catches up with `zoom` when a
+    /// zoom gesture ends.
+    pub raster_zoom: f32,
 }
 
 impl ScrollingState {
@@ -414,8 +1053,385 @@ impl ScrollingState {
             spring: Spring::at(LayerPoint::zero(), STIFFNESS, DAMPING),
             started_bouncing_back: false,
             bouncing_back: false,
-            should_handoff_scroll: false
+            should_handoff_scroll: false,
+            animation: None,
+            velocity: LayerPoint::zero(),
+            last_scroll_event_time: None,
+            scrollbar_offset: LayerPoint::zero(),
+            scrollbar_fade_start: None,
+            scrollbar_opacity: 0.0,
+            reported_offset: LayerPoint::zero(),
+            zoom: 1.0,
+            raster_zoom: 1.0,
         }
     }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::{ClipInfo, ClipScrollNode, FLING_RELEASE_TIMEOUT, SNAP_PROXIMITY_FRACTION};
+    use super::{SCROLLBAR_FADE_DELAY, SCROLLBAR_FADE_DURATION, ScrollSnapPoints, ScrollingState};
+    use super::snap_offset;
+    use frame_builder::ScrollbarStyle;
+    use renderer::VertexDataStore;
+    use tiling::PackedLayerIndex;
+    use webrender_traits::{ClipRegion, ColorF, LayerPoint, LayerRect, LayerSize};
+    use webrender_traits::{OverscrollBehavior, PipelineId, ScrollEventPhase, ScrollLayerId};
+    use webrender_traits::{ScrollLocation, ScrollSnapAlign, ScrollSnapArea, ScrollSnapType};
+    use webrender_traits::{ScrollbarAxis, WorldPoint};
+
+    const MS: u64 = 1000 * 1000;
+
+    /// A scroll frame with a 100x100 viewport at the origin, over content of
+    /// the given size, which doesn't bounce.
+    fn scroll_frame(content_size: LayerSize) -> ClipScrollNode {
+        let pipeline_id = PipelineId(0, 0);
+        let viewport = LayerRect::new(LayerPoint::zero(), LayerSize::new(100.0, 100.0));
+        let clip_info = ClipInfo::new(&ClipRegion::simple(&viewport),
+                                      &mut VertexDataStore::new(),
+                                      PackedLayerIndex(0));
+        let overscroll = OverscrollBehavior {
+            bounce: Some(false),
+            ..OverscrollBehavior::default()
+        };
+        let mut node = ClipScrollNode::new(pipeline_id,
+                                           ScrollLayerId::root_reference_frame(pipeline_id),
+                                           &viewport,
+                                           content_size,
+                                           clip_info,
+                                           None,
+                                           overscroll,
+                                           None);
+        node.finalize(&ScrollingState::new());
+        node
+    }
+
+    /// A 100 pixel tall scroll frame over 1000 pixels of content, with snap
+    /// areas 50 pixels tall at the given positions.
+    fn snapping_scroll_frame(snap_type: ScrollSnapType,
+                             areas: &[(f32, ScrollSnapAlign)])
+                             -> ClipScrollNode {
+        let mut node = scroll_frame(LayerSize::new(100.0, 1000.0));
+        node.scroll_snap = Some(ScrollSnapPoints {
+            snap_type: snap_type,
+            areas: areas.iter().map(|&(y, align)| snap_area(y, align)).collect(),
+        });
+        node
+    }
+
+    fn snap_area(y: f32, align: ScrollSnapAlign) -> ScrollSnapArea {
+        ScrollSnapArea {
+            rect: LayerRect::new(LayerPoint::new(0.0, y), LayerSize::new(100.0, 50.0)),
+            align: align,
+        }
+    }
+
+    fn scrollbar_style() -> ScrollbarStyle {
+        ScrollbarStyle {
+            color: ColorF::new(0.0, 0.0, 0.0, 1.0),
+            width: 10.0,
+            radius: 0.0,
+            margin: 5.0,
+        }
+    }
+
+    fn rect(x: f32, y: f32, width: f32, height: f32) -> LayerRect {
+        LayerRect::new(LayerPoint::new(x, y), LayerSize::new(width, height))
+    }
+
+    fn scroll_by(node: &mut ClipScrollNode, dy: f32, phase: ScrollEventPhase, time: u64) -> bool {
+        node.scroll(ScrollLocation::Delta(LayerPoint::new(0.0, dy)), phase, time)
+    }
+
+    /// Scroll down by 20 pixels in two moves `interval` apart, then release.
+    fn swipe(node: &mut ClipScrollNode, interval: u64) -> bool {
+        scroll_by(node, 0.0, ScrollEventPhase::Start, 0);
+        scroll_by(node, -10.0, ScrollEventPhase::Move(true), interval);
+        scroll_by(node, -10.0, ScrollEventPhase::Move(true), 2 * interval);
+        scroll_by(node, 0.0, ScrollEventPhase::End, 2 * interval + MS)
+    }
+
+    #[test]
+    fn gestures_released_while_moving_fling() {
+        let mut node = scroll_frame(LayerSize::new(100.0, 10000.0));
+        assert!(swipe(&mut node, 10 * MS));
+        assert!(node.is_animating_scroll());
+        assert_eq!(node.scrolling.offset, LayerPoint::new(0.0, -20.0));
+
+        // The fling carries on in the direction of the gesture, and slows down.
+        node.tick_scroll_animation(40 * MS, 1.0);
+        assert_eq!(node.scrolling.offset, LayerPoint::new(0.0, -20.0));
+        node.tick_scroll_animation(56 * MS, 1.0);
+        let first_step = -20.0 - node.scrolling.offset.y;
+        node.tick_scroll_animation(72 * MS, 1.0);
+        let second_step = -20.0 - first_step - node.scrolling.offset.y;
+        assert!(first_step > 0.0);
+        assert!(second_step > 0.0 && second_step < first_step);
+    }
+
+    #[test]
+    fn fling_velocity_comes_from_event_times() {
+        // The same deltas spread over a longer time make a slower fling.
+        let mut fast = scroll_frame(LayerSize::new(100.0, 10000.0));
+        let mut slow = scroll_frame(LayerSize::new(100.0, 10000.0));
+        assert!(swipe(&mut fast, 10 * MS));
+        assert!(swipe(&mut slow, 40 * MS));
+        for node in &mut [&mut fast, &mut slow] {
+            node.tick_scroll_animation(1000 * MS, 1.0);
+            node.tick_scroll_animation(1016 * MS, 1.0);
+        }
+        assert!(fast.scrolling.offset.y < slow.scrolling.offset.y);
+        assert!(slow.scrolling.offset.y < -20.0);
+    }
+
+    #[test]
+    fn gestures_released_after_resting_do_not_fling() {
+        let mut node = scroll_frame(LayerSize::new(100.0, 10000.0));
+        scroll_by(&mut node, 0.0, ScrollEventPhase::Start, 0);
+        scroll_by(&mut node, -10.0, ScrollEventPhase::Move(true), 10 * MS);
+        scroll_by(&mut node, -10.0, ScrollEventPhase::Move(true), 20 * MS);
+        assert!(!scroll_by(&mut node, 0.0, ScrollEventPhase::End, 20 * MS + FLING_RELEASE_TIMEOUT));
+        assert!(!node.is_animating_scroll());
+    }
+
+    #[test]
+    fn platform_momentum_does_not_fling() {
+        let mut node = scroll_frame(LayerSize::new(100.0, 10000.0));
+        scroll_by(&mut node, 0.0, ScrollEventPhase::Start, 0);
+        scroll_by(&mut node, -10.0, ScrollEventPhase::Move(true), 10 * MS);
+        scroll_by(&mut node, -10.0, ScrollEventPhase::Move(false), 20 * MS);
+        scroll_by(&mut node, 0.0, ScrollEventPhase::End, 30 * MS);
+        assert!(!node.is_animating_scroll());
+    }
+
+    #[test]
+    fn flings_stop_at_the_edge() {
+        let mut node = scroll_frame(LayerSize::new(100.0, 150.0));
+        assert!(swipe(&mut node, 10 * MS));
+
+        let mut time = 40 * MS;
+        while node.is_animating_scroll() {
+            node.tick_scroll_animation(time, 1.0);
+            time += 16 * MS;
+            assert!(time < 10000 * MS);
+        }
+        assert_eq!(node.scrolling.offset, LayerPoint::new(0.0, -50.0));
+    }
+
+    #[test]
+    fn smooth_scrolls_reach_their_target() {
+        let mut node = scroll_frame(LayerSize::new(100.0, 1000.0));
+        assert!(node.scroll(ScrollLocation::Line(1.0), ScrollEventPhase::Start, 0));
+
+        // A second line arriving mid-scroll adds onto the target.
+        node.tick_scroll_animation(100 * MS, 1.0);
+        node.tick_scroll_animation(200 * MS, 1.0);
+        let offset = node.scrolling.offset;
+        assert!(offset.y < 0.0 && offset.y > -40.0);
+        assert!(node.scroll(ScrollLocation::Line(1.0), ScrollEventPhase::Start, 200 * MS));
+        assert_eq!(node.scrolling.offset, offset);
+
+        node.tick_scroll_animation(300 * MS, 1.0);
+        node.tick_scroll_animation(1000 * MS, 1.0);
+        assert!(!node.is_animating_scroll());
+        assert_eq!(node.scrolling.offset, LayerPoint::new(0.0, -80.0));
+    }
+
+    #[test]
+    fn animated_offsets_are_snapped_to_device_pixels() {
+        let mut node = scroll_frame(LayerSize::new(100.0, 1000.0));
+        assert!(node.scroll(ScrollLocation::Line(1.0), ScrollEventPhase::Start, 0));
+        for frame in 0..16 {
+            node.tick_scroll_animation(frame * 16 * MS, 2.0);
+            let y = node.scrolling.offset.y * 2.0;
+            assert_eq!(y, y.round());
+        }
+
+        // Flings keep their fractional progress between frames, so they travel
+        // as far as they would have without snapping.
+        let mut node = scroll_frame(LayerSize::new(100.0, 10000.0));
+        assert!(swipe(&mut node, 10 * MS));
+        let mut exact = node.scrolling.animation.unwrap();
+        let mut exact_offset = node.scrolling.offset;
+        let mut time = 40 * MS;
+        while node.is_animating_scroll() {
+            node.tick_scroll_animation(time, 1.0);
+            exact_offset = exact.tick(time).0;
+            let y = node.scrolling.offset.y;
+            assert_eq!(y, y.round());
+            time += 16 * MS;
+            assert!(time < 10000 * MS);
+        }
+        assert!((node.scrolling.offset.y - exact_offset.y).abs() <= 0.5);
+    }
+
+    #[test]
+    fn page_scrolls_move_by_most_of_the_viewport() {
+        let mut node = scroll_frame(LayerSize::new(1000.0, 1000.0));
+        let settle = |node: &mut ClipScrollNode| {
+            node.tick_scroll_animation(0, 1.0);
+            node.tick_scroll_animation(1000 * MS, 1.0);
+            node.scrolling.offset
+        };
+
+        assert!(node.scroll(ScrollLocation::Page(2.0), ScrollEventPhase::Start, 0));
+        assert_eq!(settle(&mut node), LayerPoint::new(0.0, -175.0));
+        assert!(node.scroll(ScrollLocation::PageUp, ScrollEventPhase::Start, 0));
+        assert_eq!(settle(&mut node), LayerPoint::new(0.0, -88.0));
+        assert!(node.scroll(ScrollLocation::PageDown, ScrollEventPhase::Start, 0));
+        assert_eq!(settle(&mut node), LayerPoint::new(0.0, -176.0));
+        assert!(node.scroll(ScrollLocation::Page(-3.0), ScrollEventPhase::Start, 0));
+        assert_eq!(settle(&mut node), LayerPoint::new(0.0, 0.0));
+        assert!(node.scroll(ScrollLocation::PageRight, ScrollEventPhase::Start, 0));
+        assert_eq!(settle(&mut node), LayerPoint::new(-88.0, 0.0));
+    }
+
+    #[test]
+    fn snap_offsets_line_up_each_alignment() {
+        let viewport = LayerRect::new(LayerPoint::new(10.0, 20.0), LayerSize::new(100.0, 100.0));
+        let area = ScrollSnapArea {
+            rect: LayerRect::new(LayerPoint::new(10.0, 320.0), LayerSize::new(60.0, 50.0)),
+            align: ScrollSnapAlign::Start,
+        };
+        assert_eq!(snap_offset(&area, &viewport), LayerPoint::new(0.0, -300.0));
+
+        let area = ScrollSnapArea { align: ScrollSnapAlign::Center, ..area };
+        assert_eq!(snap_offset(&area, &viewport), LayerPoint::new(20.0, -275.0));
+
+        let area = ScrollSnapArea { align: ScrollSnapAlign::End, ..area };
+        assert_eq!(snap_offset(&area, &viewport), LayerPoint::new(40.0, -250.0));
+    }
+
+    #[test]
+    fn mandatory_snapping_picks_the_nearest_area_however_far() {
+        let mut node = snapping_scroll_frame(ScrollSnapType::Mandatory,
+                                             &[(0.0, ScrollSnapAlign::Start),
+                                               (500.0, ScrollSnapAlign::Start)]);
+        node.scrolling.offset = LayerPoint::new(0.0, -200.0);
+        assert_eq!(node.snap_target(), Some(LayerPoint::new(0.0, 0.0)));
+
+        node.scrolling.offset = LayerPoint::new(0.0, -300.0);
+        assert_eq!(node.snap_target(), Some(LayerPoint::new(0.0, -500.0)));
+    }
+
+    #[test]
+    fn proximity_snapping_only_reaches_nearby_areas() {
+        let mut node = snapping_scroll_frame(ScrollSnapType::Proximity,
+                                             &[(400.0, ScrollSnapAlign::Start)]);
+        let reach = 100.0 * SNAP_PROXIMITY_FRACTION;
+
+        node.scrolling.offset = LayerPoint::new(0.0, -400.0 + reach - 1.0);
+        assert_eq!(node.snap_target(), Some(LayerPoint::new(0.0, -400.0)));
+
+        node.scrolling.offset = LayerPoint::new(0.0, -400.0 - reach + 1.0);
+        assert_eq!(node.snap_target(), Some(LayerPoint::new(0.0, -400.0)));
+
+        node.scrolling.offset = LayerPoint::new(0.0, -400.0 + reach + 1.0);
+        assert_eq!(node.snap_target(), None);
+    }
+
+    #[test]
+    fn snap_targets_stay_in_the_scrollable_area() {
+        let mut node = snapping_scroll_frame(ScrollSnapType::Mandatory,
+                                             &[(950.0, ScrollSnapAlign::Start),
+                                               (0.0, ScrollSnapAlign::End)]);
+        node.scrolling.offset = LayerPoint::new(0.0, -850.0);
+        assert_eq!(node.snap_target(), Some(LayerPoint::new(0.0, -900.0)));
+
+        node.scrolling.offset = LayerPoint::new(0.0, -10.0);
+        assert_eq!(node.snap_target(), Some(LayerPoint::new(0.0, 0.0)));
+    }
+
+    #[test]
+    fn settling_springs_onto_the_snap_point() {
+        let mut node = snapping_scroll_frame(ScrollSnapType::Mandatory,
+                                             &[(200.0, ScrollSnapAlign::Center)]);
+        node.scrolling.offset = LayerPoint::new(0.0, -150.0);
+        assert!(node.settle_on_snap_point());
+        // Already on the way there.
+        assert!(!node.settle_on_snap_point());
+
+        let mut ticks = 0;
+        while node.scrolling.bouncing_back {
+            node.tick_scrolling_bounce_animation();
+            ticks += 1;
+            assert!(ticks < 1000);
+        }
+        assert_eq!(node.scrolling.offset, LayerPoint::new(0.0, -175.0));
+        assert!(!node.settle_on_snap_point());
+    }
+
+    #[test]
+    fn scrollbar_thumbs_follow_the_scroll_offset() {
+        let style = scrollbar_style();
+        let mut node = scroll_frame(LayerSize::new(100.0, 1000.0));
+
+        // The thumb would be 9 pixels long, so it gets the minimum length.
+        assert_eq!(node.scrollbar_thumb_rect(ScrollbarAxis::Vertical, &style),
+                   Some(rect(85.0, 5.0, 10.0, 20.0)));
+        assert_eq!(node.scrollbar_thumb_rect(ScrollbarAxis::Horizontal, &style), None);
+
+        node.scrolling.offset = LayerPoint::new(0.0, -900.0);
+        assert_eq!(node.scrollbar_thumb_rect(ScrollbarAxis::Vertical, &style),
+                   Some(rect(85.0, 75.0, 10.0, 20.0)));
+
+        // Bouncing past the end doesn't take the thumb out of its track.
+        node.scrolling.offset = LayerPoint::new(0.0, -950.0);
+        assert_eq!(node.scrollbar_thumb_rect(ScrollbarAxis::Vertical, &style),
+                   Some(rect(85.0, 75.0, 10.0, 20.0)));
+    }
+
+    #[test]
+    fn scrollbar_thumbs_leave_the_corner_free() {
+        let style = scrollbar_style();
+        let node = scroll_frame(LayerSize::new(200.0, 400.0));
+
+        // Each track is 75 pixels long. The vertical thumb would be a quarter
+        // of that, which is below the minimum length.
+        assert_eq!(node.scrollbar_thumb_rect(ScrollbarAxis::Vertical, &style),
+                   Some(rect(85.0, 5.0, 10.0, 20.0)));
+        assert_eq!(node.scrollbar_thumb_rect(ScrollbarAxis::Horizontal, &style),
+                   Some(rect(5.0, 85.0, 37.5, 10.0)));
+    }
+
+    #[test]
+    fn dragging_scrollbar_thumbs_scrolls_in_proportion() {
+        let style = scrollbar_style();
+        let mut node = scroll_frame(LayerSize::new(100.0, 1000.0));
+
+        // The thumb travels 70 pixels over 900 pixels of scrolling.
+        assert!(node.drag_scrollbar_thumb(ScrollbarAxis::Vertical, 35.0, &style));
+        assert_eq!(node.scrolling.offset, LayerPoint::new(0.0, -450.0));
+        assert_eq!(node.scrollbar_thumb_rect(ScrollbarAxis::Vertical, &style),
+                   Some(rect(85.0, 40.0, 10.0, 20.0)));
+
+        assert!(node.drag_scrollbar_thumb(ScrollbarAxis::Vertical, 1000.0, &style));
+        assert_eq!(node.scrolling.offset, LayerPoint::new(0.0, -900.0));
+        assert!(!node.drag_scrollbar_thumb(ScrollbarAxis::Vertical, 10.0, &style));
+        assert!(!node.drag_scrollbar_thumb(ScrollbarAxis::Horizontal, 10.0, &style));
+    }
+
+    #[test]
+    fn only_visible_scrollbar_thumbs_are_hit() {
+        let style = scrollbar_style();
+        let mut node = scroll_frame(LayerSize::new(100.0, 1000.0));
+        let on_thumb = WorldPoint::new(90.0, 50.0);
+
+        // Scrollbars are hidden until the node scrolls.
+        node.scrolling.offset = LayerPoint::new(0.0, -450.0);
+        assert_eq!(node.ray_intersects_scrollbar_thumb(&on_thumb, &style), None);
+
+        node.update_scrollbar_fade(Some(0));
+        assert_eq!(node.ray_intersects_scrollbar_thumb(&on_thumb, &style),
+                   Some(ScrollbarAxis::Vertical));
+        assert_eq!(node.ray_intersects_scrollbar_thumb(&WorldPoint::new(90.0, 10.0), &style),
+                   None);
+        assert_eq!(node.ray_intersects_scrollbar_thumb(&WorldPoint::new(50.0, 50.0), &style),
+                   None);
+
+        node.update_scrollbar_fade(Some(SCROLLBAR_FADE_DELAY + SCROLLBAR_FADE_DURATION));
+        assert!(!node.is_showing_scrollbars());
+        assert_eq!(node.ray_intersects_scrollbar_thumb(&on_thumb, &style), None);
+    }
+}
@@ -2,13 +2,17 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
-use clip_scroll_node::{ClipScrollNode, NodeType, ScrollingState};
+use clip_scroll_node::{ClipScrollNode, NodeType, ScrollLinkedTransform, ScrollingState};
 use fnv::FnvHasher;
+use frame_builder::ScrollbarStyle;
+use scene::SceneProperties;
 use std::collections::{HashMap, HashSet};
 use std::hash::BuildHasherDefault;
 use webrender_traits::{LayerPoint, LayerRect, LayerToScrollTransform, LayerToWorldTransform};
-use webrender_traits::{PipelineId, ScrollEventPhase, ScrollLayerId, ScrollLayerRect};
-use webrender_traits::{ScrollLayerState, ScrollLocation, WorldPoint, as_scroll_parent_rect};
+use webrender_traits::{PipelineId, ScrollBehavior, ScrollEventPhase, ScrollLayerId, ScrollLayerRect};
+use webrender_traits::{ScrollChangePhase, ScrollLayerState, ScrollLocation, ScrollOffsetChange};
+use webrender_traits::{ScrollbarAxis, ScrollbarHitTestResult};
+use webrender_traits::{WorldPoint, as_scroll_parent_rect};
 
 pub type ScrollStates = HashMap<ScrollLayerId, ScrollingState, BuildHasherDefault<FnvHasher>>;
 
@@ -77,6 +81,17 @@ impl ClipScrollTree {
         nodes_bouncing_back
     }
 
+    pub fn collect_nodes_animating_scroll(&self)
+                                          -> HashSet<ScrollLayerId, BuildHasherDefault<FnvHasher>> {
+        let mut nodes_animating_scroll = HashSet::with_hasher(Default::default());
+        for (scroll_layer_id, node) in self.nodes.iter() {
+            if node.is_animating_scroll() || node.is_showing_scrollbars() {
+                nodes_animating_scroll.insert(*scroll_layer_id);
+            }
+        }
+        nodes_animating_scroll
+    }
+
     fn find_scrolling_node_at_point_in_node(&self,
                                             cursor: &WorldPoint,
                                             scroll_layer_id: ScrollLayerId)
@@ -106,6 +121,119 @@ impl ClipScrollTree {
             .unwrap_or(self.topmost_scroll_layer_id())
     }
 
+    fn find_scrollbar_at_point_in_node(&self,
+                                       cursor: &WorldPoint,
+                                       scroll_layer_id: ScrollLayerId,
+                                       style: &ScrollbarStyle)
+                                       -> Option<ScrollbarHitTestResult> {
+        self.nodes.get(&scroll_layer_id).and_then(|node| {
+            for child_layer_id in node.children.iter().rev() {
+                if let Some(result) =
+                    self.find_scrollbar_at_point_in_node(cursor, *child_layer_id, style) {
+                    return Some(result);
+                }
+            }
+
+            node.ray_intersects_scrollbar_thumb(cursor, style).map(|axis| {
+                ScrollbarHitTestResult {
+                    scroll_layer_id: scroll_layer_id,
+                    axis: axis,
+                }
+            })
+        })
+    }
+
+    /// Finds the visible scrollbar thumb under `cursor`, preferring the thumbs
+    /// of the innermost scroll frames.
+    pub fn hit_test_scrollbar(&self,
+                              cursor: &WorldPoint,
+                              style: &ScrollbarStyle)
+                              -> Option<ScrollbarHitTestResult> {
+        if self.nodes.is_empty() {
+            return None;
+        }
+        self.find_scrollbar_at_point_in_node(cursor, self.root_reference_frame_id(), style)
+    }
+
+    /// Returns true if the node moved.
+    pub fn drag_scrollbar_thumb(&mut self,
+                                id: ScrollLayerId,
+                                axis: ScrollbarAxis,
+                                delta: f32,
+                                style: &ScrollbarStyle)
+                                -> bool {
+        match self.nodes.get_mut(&id) {
+            Some(node) => node.drag_scrollbar_thumb(axis, delta, style),
+            None => false,
+        }
+    }
+
+    /// Zoom the content of the given node about `focal_point`, which is in world
+    /// space. Returns true if the node changed.
+    pub fn zoom_node(&mut self,
+                     id: ScrollLayerId,
+                     zoom: f32,
+                     focal_point: &WorldPoint,
+                     phase: ScrollEventPhase)
+                     -> bool {
+        let node = match self.nodes.get_mut(&id) {
+            Some(node) => node,
+            None => return false,
+        };
+
+        let focal_point = match node.world_viewport_transform.inverse() {
+            Some(inverse) => inverse.transform_point(focal_point),
+            None => return false,
+        };
+        node.zoom(zoom, focal_point, phase)
+    }
+
+    /// The reference frame that the content of a pipeline is placed in, which is
+    /// the one whose parent is in another pipeline, or the root.
+    pub fn pipeline_reference_frame_id(&self, pipeline_id: PipelineId) -> Option<ScrollLayerId> {
+        for (id, node) in &self.nodes {
+            if node.pipeline_id != pipeline_id {
+                continue;
+            }
+
+            match node.node_type {
+                NodeType::ReferenceFrame(..) => {}
+                NodeType::Clip(_) => continue,
+            }
+
+            let is_pipeline_root = match node.parent {
+                Some(parent_id) => self.nodes[&parent_id].pipeline_id != pipeline_id,
+                None => true,
+            };
+            if is_pipeline_root {
+                return Some(*id);
+            }
+        }
+        None
+    }
+
+    /// Zoom the whole of a pipeline about `focal_point`, which is in world space.
+    /// Returns true if the pipeline changed.
+    pub fn zoom_pipeline(&mut self,
+                         pipeline_id: PipelineId,
+                         zoom: f32,
+                         focal_point: &WorldPoint,
+                         phase: ScrollEventPhase)
+                         -> bool {
+        match self.pipeline_reference_frame_id(pipeline_id) {
+            Some(id) => self.zoom_node(id, zoom, focal_point, phase),
+            None => false,
+        }
+    }
+
+    /// Show the scrollbars of the nodes that moved and fade out the rest. See
+    /// `ClipScrollNode::update_scrollbar_fade`.
+    pub fn update_scrollbar_fades(&mut self, frame_time: Option<u64>) {
+        for (_, node) in &mut self.nodes {
+            node.update_scrollbar_fade(frame_time);
+        }
+    }
+
     pub fn get_scroll_node_state(&self) -> Vec<ScrollLayerState> {
         let mut result = vec![];
         for (id, node) in self.nodes.iter() {
@@ -118,6 +246,24 @@ impl ClipScrollTree {
         result
     }
 
+    /// The nodes which moved since the last call, attributed to `phase`.
+    pub fn take_scroll_offset_changes(&mut self, phase: ScrollChangePhase) -> Vec<ScrollOffsetChange> {
+        let mut changes = vec![];
+        for (id, node) in self.nodes.iter_mut() {
+            if node.scrolling.offset == node.scrolling.reported_offset {
+                continue;
+            }
+
+            node.scrolling.reported_offset = node.scrolling.offset;
+            changes.push(ScrollOffsetChange {
+                state: ScrollLayerState { id: *id, scroll_offset: node.scrolling.offset },
+                external_id: id.external_id(),
+                phase: phase,
+            });
+        }
+        changes
+    }
+
     pub fn drain(&mut self) -> ScrollStates {
         self.current_reference_frame_id = 1;
 
@@ -132,7 +278,11 @@ impl ClipScrollTree {
         scroll_states
     }
 
-    pub fn scroll_nodes(&mut self, origin: LayerPoint, id: ScrollLayerId) -> bool {
+    pub fn scroll_nodes(&mut self,
+                        origin: LayerPoint,
+                        id: ScrollLayerId,
+                        behavior: ScrollBehavior)
+                        -> bool {
         if id.is_reference_frame() {
             warn!("Tried to scroll a reference frame.");
             return false;
@@ -145,7 +295,10 @@ impl ClipScrollTree {
 
         let origin = LayerPoint::new(origin.x.max(0.0), origin.y.max(0.0));
         if let Some(node) = self.nodes.get_mut(&id) {
-            return node.set_scroll_origin(&origin);
+            return match behavior {
+                ScrollBehavior::Instant => node.set_scroll_origin(&origin),
+                ScrollBehavior::Smooth => node.smooth_scroll_to_origin(&origin),
+            };
         }
 
         self.pending_scroll_offsets.insert(id, origin);
@@ -155,7 +308,8 @@ impl ClipScrollTree {
     pub fn scroll(&mut self,
                   scroll_location: ScrollLocation,
                   cursor: WorldPoint,
-                  phase: ScrollEventPhase)
+                  phase: ScrollEventPhase,
+                  event_time: u64)
                   -> bool {
         if self.nodes.is_empty() {
             return false;
@@ -182,10 +336,10 @@ impl ClipScrollTree {
             (_, _, None) => return false,
         };
 
-        let topmost_scroll_layer_id = self.topmost_scroll_layer_id();
-        let non_root_overscroll = if scroll_layer_id != topmost_scroll_layer_id {
+        let chaining_parent_id = self.scroll_chaining_parent(scroll_layer_id);
+        let non_root_overscroll = if chaining_parent_id.is_some() {
             // true if the current node is overscrolling,
-            // and it is not the root scroll node.
+            // and it has a parent to hand the scroll on to.
             let child_node = self.nodes.get(&scroll_layer_id).unwrap();
             let overscroll_amount = child_node.overscroll_amount();
             overscroll_amount.width != 0.0 || overscroll_amount.height != 0.0
@@ -216,13 +370,91 @@ impl ClipScrollTree {
             },
         };
 
-        let scroll_layer_id = if switch_node {
-            topmost_scroll_layer_id
-        } else {
-            scroll_layer_id
+        let scroll_layer_id = match chaining_parent_id {
+            Some(parent_id) if switch_node => parent_id,
+            _ => scroll_layer_id,
+        };
+
+        match scroll_location {
+            ScrollLocation::Delta(_) => {
+                let node = self.nodes.get_mut(&scroll_layer_id).unwrap();
+                let scrolled = node.scroll(scroll_location, phase, event_time);
+
+                // Once the gesture is over, and unless it turned into a fling or
+                // an overscroll bounce, come to rest on a snap point.
+                let settling = phase == ScrollEventPhase::End && node.settle_on_snap_point();
+                scrolled || settling
+            }
+            _ => self.scroll_node_or_ancestors(scroll_layer_id, scroll_location, phase, event_time),
+        }
+    }
+
+    /// The node which gets the part of a scroll that a node can't take. This is
+    /// the topmost scroll layer, unless the node chains scrolls to its nearest
+    /// scrolling ancestor or keeps them. None if there is nowhere to go.
+    fn scroll_chaining_parent(&self, scroll_layer_id: ScrollLayerId) -> Option<ScrollLayerId> {
+        let (scroll_chaining, parent) = match self.nodes.get(&scroll_layer_id) {
+            Some(node) => (node.overscroll.scroll_chaining, node.parent),
+            None => return None,
         };
 
-        self.nodes.get_mut(&scroll_layer_id).unwrap().scroll(scroll_location, phase)
+        let mut current_id = match scroll_chaining {
+            Some(true) => parent,
+            Some(false) => return None,
+            None => {
+                let topmost_scroll_layer_id = self.topmost_scroll_layer_id();
+                if scroll_layer_id == topmost_scroll_layer_id {
+                    return None;
+                }
+                return Some(topmost_scroll_layer_id);
+            }
+        };
+
+        while let Some(id) = current_id {
+            let node = match self.nodes.get(&id) {
+                Some(node) => node,
+                None => return None,
+            };
+
+            if !id.is_reference_frame() &&
+               (node.scrollable_width() > 0.0 || node.scrollable_height() > 0.0) {
+                return Some(id);
+            }
+
+            current_id = node.parent;
+        }
+
+        None
+    }
+
+    /// Scroll a node to a page, line or edge. If the node can't move any further
+    /// in that direction, its scrolling ancestors are tried in turn, so that
+    /// keyboard navigation always moves something when it can.
+    fn scroll_node_or_ancestors(&mut self,
+                                scroll_layer_id: ScrollLayerId,
+                                scroll_location: ScrollLocation,
+                                phase: ScrollEventPhase,
+                                event_time: u64)
+                                -> bool {
+        let mut current_id = Some(scroll_layer_id);
+        while let Some(id) = current_id {
+            let node = match self.nodes.get_mut(&id) {
+                Some(node) => node,
+                None => return false,
+            };
+
+            if !id.is_reference_frame() && node.scroll(scroll_location, phase, event_time) {
+                return true;
+            }
+
+            if node.overscroll.scroll_chaining == Some(false) {
+                return false;
+            }
+
+            current_id = node.parent;
+        }
+
+        false
     }
 
     pub fn update_all_node_transforms(&mut self, pan: LayerPoint) {
@@ -235,39 +467,58 @@ impl ClipScrollTree {
         self.update_node_transform(root_reference_frame_id,
                                    &LayerToWorldTransform::create_translation(pan.x, pan.y, 0.0),
                                    &as_scroll_parent_rect(&root_viewport),
-                                   LayerPoint::zero());
+                                   LayerPoint::zero(),
+                                   1.0);
     }
 
     fn update_node_transform(&mut self,
                              layer_id: ScrollLayerId,
                              parent_reference_frame_transform: &LayerToWorldTransform,
                              parent_viewport_rect: &ScrollLayerRect,
-                             parent_accumulated_scroll_offset: LayerPoint) {
+                             parent_accumulated_scroll_offset: LayerPoint,
+                             parent_raster_scale: f32) {
         // TODO(gw): This is an ugly borrow check workaround to clone these.
         //           Restructure this to avoid the clones!
-        let (reference_frame_transform, viewport_rect, accumulated_scroll_offset, node_children) = {
+        let (reference_frame_transform, viewport_rect, accumulated_scroll_offset, raster_scale,
+             node_children) = {
             match self.nodes.get_mut(&layer_id) {
                 Some(node) => {
                     node.update_transform(parent_reference_frame_transform,
                                           parent_viewport_rect,
                                           parent_accumulated_scroll_offset);
+                    node.raster_scale = parent_raster_scale * node.scrolling.raster_zoom;
 
                     // The transformation we are passing is the transformation of the parent
                     // reference frame and the offset is the accumulated offset of all the nodes
                     // between us and the parent reference frame. If we are a reference frame,
                     // we need to reset both these values.
-                    let (transform, offset) = match node.node_type {
+                    let (transform, offset, viewport_rect) = match node.node_type {
+                        NodeType::ReferenceFrame(..) if node.is_zoomable() =>
+                            (node.world_content_transform,
+                             LayerPoint::zero(),
+                             node.content_space_rect(&node.combined_local_viewport_rect)),
                         NodeType::ReferenceFrame(..) =>
-                            (node.world_viewport_transform, LayerPoint::zero()),
+                            (node.world_viewport_transform,
+                             LayerPoint::zero(),
+                             node.combined_local_viewport_rect),
+                        // A zoom can't be accumulated as an offset, so the children of a
+                        // zoomable node are positioned relative to its content, much like
+                        // the children of a reference frame.
+                        NodeType::Clip(_) if node.is_zoomable() =>
+                            (node.world_content_transform,
+                             LayerPoint::zero(),
+                             node.content_space_rect(&node.combined_local_viewport_rect)),
                         NodeType::Clip(_) => {
                             (*parent_reference_frame_transform,
-                             parent_accumulated_scroll_offset + node.scrolling.offset)
+                             parent_accumulated_scroll_offset + node.scrolling.offset,
+                             node.combined_local_viewport_rect)
                         }
                     };
 
                     (transform,
-                     as_scroll_parent_rect(&node.combined_local_viewport_rect),
+                     as_scroll_parent_rect(&viewport_rect),
                      offset,
+                     node.raster_scale,
                      node.children.clone())
                 }
                 None => return,
@@ -278,7 +529,52 @@ impl ClipScrollTree {
             self.update_node_transform(child_layer_id,
                                        &reference_frame_transform,
                                        &viewport_rect,
-                                       accumulated_scroll_offset);
+                                       accumulated_scroll_offset,
+                                       raster_scale);
+        }
+    }
+
+    /// How far the given node is scrolled along `axis`, or zero if there is no
+    /// such node.
+    pub fn scroll_position(&self, id: &ScrollLayerId, axis: ScrollbarAxis) -> f32 {
+        match self.nodes.get(id) {
+            Some(node) => match axis {
+                ScrollbarAxis::Horizontal => -node.scrolling.offset.x,
+                ScrollbarAxis::Vertical => -node.scrolling.offset.y,
+            },
+            None => 0.0,
+        }
+    }
+
+    pub fn set_scroll_linked_transform(&mut self,
+                                       id: ScrollLayerId,
+                                       transform: ScrollLinkedTransform) {
+        if let Some(node) = self.nodes.get_mut(&id) {
+            node.scroll_linked_transform = Some(transform);
+        }
+    }
+
+    /// Evaluate the transforms of the reference frames which follow a scroll
+    /// offset, so that they move in step with the current offsets. This must
+    /// be called before the node transforms are updated for a new frame.
+    pub fn update_scroll_linked_transforms(&mut self, properties: &SceneProperties) {
+        let mut transforms = vec![];
+        for (id, node) in &self.nodes {
+            if let Some(ref linked) = node.scroll_linked_transform {
+                let current = properties.scroll_linked_transform(linked.binding_id).map(|value| {
+                    let position = self.scroll_position(&value.scroll_layer_id, value.axis);
+                    linked.transform_at(value, position)
+                });
+                transforms.push((*id, current));
+            }
+        }
+
+        for (id, current) in transforms {
+            if let Some(node) = self.nodes.get_mut(&id) {
+                if let Some(ref mut linked) = node.scroll_linked_transform {
+                    linked.current = current;
+                }
+            }
         }
     }
 
@@ -288,6 +584,21 @@ impl ClipScrollTree {
         }
     }
 
+    pub fn tick_scroll_animations(&mut self, frame_time: u64, device_pixel_ratio: f32) {
+        for (_, node) in &mut self.nodes {
+            if !node.is_animating_scroll() {
+                continue;
+            }
+
+            node.tick_scroll_animation(frame_time, device_pixel_ratio);
+
+            // Flings and smooth scrolls come to rest on a snap point too.
+            if !node.is_animating_scroll() {
+                node.settle_on_snap_point();
+            }
+        }
+    }
+
     pub fn finalize_and_apply_pending_scroll_offsets(&mut self, old_states: ScrollStates) {
         // TODO(gw): These are all independent - can be run through thread pool if it shows up
         // in the profile!
@@ -347,3 +658,308 @@ impl ClipScrollTree {
     }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use clip_scroll_node::{ClipInfo, ClipScrollNode, ScrollLinkedTransform};
+    use renderer::VertexDataStore;
+    use scene::SceneProperties;
+    use std::collections::HashMap;
+    use super::ClipScrollTree;
+    use tiling::PackedLayerIndex;
+    use webrender_traits::{ClipRegion, LayerPoint, LayerRect, LayerSize, LayerToScrollTransform};
+    use webrender_traits::{LayoutTransform, OverscrollBehavior, PipelineId, PropertyBindingKey};
+    use webrender_traits::{ScrollBehavior, ScrollChangePhase, ScrollEventPhase, ScrollLayerId};
+    use webrender_traits::{ScrollLinkedProperties, ScrollLinkedValue, ScrollLocation, ScrollbarAxis};
+    use webrender_traits::{WorldPoint, ZoomLimits};
+
+    const MS: u64 = 1000 * 1000;
+
+    /// A 100x100 root scroll layer over 1000 pixels of content, with a
+    /// scroll frame over 500 pixels inside it, and an inner scroll frame over
+    /// 200 pixels inside that, which bounces.
+    fn nested_scroll_frames(inner_scroll_chaining: Option<bool>) -> ClipScrollTree {
+        let pipeline_id = PipelineId(0, 0);
+        let viewport = LayerRect::new(LayerPoint::zero(), LayerSize::new(100.0, 100.0));
+        let mut tree = ClipScrollTree::new();
+        let root_reference_frame_id = tree.add_reference_frame(&viewport,
+                                                               &LayerToScrollTransform::identity(),
+                                                               pipeline_id,
+                                                               None);
+
+        let root_id = ScrollLayerId::root_scroll_layer(pipeline_id);
+        let middle_id = ScrollLayerId::new(1, pipeline_id);
+        let inner_id = ScrollLayerId::new(2, pipeline_id);
+        let frames = [(root_id, root_reference_frame_id, 1000.0),
+                      (middle_id, root_id, 500.0),
+                      (inner_id, middle_id, 200.0)];
+        for &(id, parent_id, content_height) in &frames {
+            let clip_info = ClipInfo::new(&ClipRegion::simple(&viewport),
+                                          &mut VertexDataStore::new(),
+                                          PackedLayerIndex(0));
+            let is_inner = id == inner_id;
+            let overscroll = OverscrollBehavior {
+                bounce: Some(is_inner),
+                scroll_chaining: if is_inner { inner_scroll_chaining } else { None },
+                spring: None,
+            };
+            let node = ClipScrollNode::new(pipeline_id,
+                                           parent_id,
+                                           &viewport,
+                                           LayerSize::new(100.0, content_height),
+                                           clip_info,
+                                           None,
+                                           overscroll,
+                                           None);
+            tree.add_node(node, id);
+        }
+
+        tree.topmost_scroll_layer_id = root_id;
+        tree.finalize_and_apply_pending_scroll_offsets(HashMap::with_hasher(Default::default()));
+        tree.update_all_node_transforms(LayerPoint::zero());
+        tree
+    }
+
+    fn offset(tree: &ClipScrollTree, id: ScrollLayerId) -> LayerPoint {
+        tree.nodes[&id].scrolling.offset
+    }
+
+    /// Scroll the node under the middle of the viewport to a page, line or edge,
+    /// and run the smooth scroll that starts to completion.
+    fn scroll_to(tree: &mut ClipScrollTree, scroll_location: ScrollLocation, time: u64) -> bool {
+        let cursor = WorldPoint::new(50.0, 50.0);
+        let scrolled = tree.scroll(scroll_location, cursor, ScrollEventPhase::Start, time);
+        tree.tick_scroll_animations(time, 1.0);
+        tree.tick_scroll_animations(time + 1000 * MS, 1.0);
+        scrolled
+    }
+
+    fn gesture(tree: &mut ClipScrollTree, dy: f32, start_time: u64) {
+        let cursor = WorldPoint::new(50.0, 50.0);
+        let delta = |dy| ScrollLocation::Delta(LayerPoint::new(0.0, dy));
+        tree.scroll(delta(0.0), cursor, ScrollEventPhase::Start, start_time);
+        tree.scroll(delta(dy), cursor, ScrollEventPhase::Move(true), start_time + 200 * MS);
+        tree.scroll(delta(0.0), cursor, ScrollEventPhase::End, start_time + 400 * MS);
+    }
+
+    #[test]
+    fn overscrolled_frames_bounce_back() {
+        let mut tree = nested_scroll_frames(Some(true));
+        let inner_id = ScrollLayerId::new(2, PipelineId(0, 0));
+
+        gesture(&mut tree, -150.0, 0);
+        assert_eq!(offset(&tree, inner_id), LayerPoint::new(0.0, -150.0));
+        assert!(tree.collect_nodes_bouncing_back().contains(&inner_id));
+
+        let mut ticks = 0;
+        while !tree.collect_nodes_bouncing_back().is_empty() {
+            tree.tick_scrolling_bounce_animations();
+            ticks += 1;
+            assert!(ticks < 1000);
+        }
+        assert_eq!(offset(&tree, inner_id), LayerPoint::new(0.0, -100.0));
+    }
+
+    #[test]
+    fn scrolls_from_overscrolled_frames_go_to_the_topmost_scroll_layer_by_default() {
+        let mut tree = nested_scroll_frames(None);
+        let pipeline_id = PipelineId(0, 0);
+
+        gesture(&mut tree, -150.0, 0);
+        gesture(&mut tree, -50.0, 1000 * MS);
+        assert_eq!(offset(&tree, ScrollLayerId::new(2, pipeline_id)), LayerPoint::new(0.0, -150.0));
+        assert_eq!(offset(&tree, ScrollLayerId::new(1, pipeline_id)), LayerPoint::zero());
+        assert_eq!(offset(&tree, ScrollLayerId::root_scroll_layer(pipeline_id)), LayerPoint::new(0.0, -50.0));
+    }
+
+    #[test]
+    fn scrolls_from_overscrolled_frames_chain_to_the_nearest_scrolling_ancestor() {
+        let mut tree = nested_scroll_frames(Some(true));
+        let pipeline_id = PipelineId(0, 0);
+
+        gesture(&mut tree, -150.0, 0);
+        gesture(&mut tree, -50.0, 1000 * MS);
+        assert_eq!(offset(&tree, ScrollLayerId::new(2, pipeline_id)), LayerPoint::new(0.0, -150.0));
+        assert_eq!(offset(&tree, ScrollLayerId::new(1, pipeline_id)), LayerPoint::new(0.0, -50.0));
+        assert_eq!(offset(&tree, ScrollLayerId::root_scroll_layer(pipeline_id)), LayerPoint::zero());
+    }
+
+    #[test]
+    fn frames_without_scroll_chaining_keep_their_scrolls() {
+        let mut tree = nested_scroll_frames(Some(false));
+        let pipeline_id = PipelineId(0, 0);
+
+        gesture(&mut tree, -150.0, 0);
+        gesture(&mut tree, -50.0, 1000 * MS);
+        assert_eq!(offset(&tree, ScrollLayerId::new(1, pipeline_id)), LayerPoint::zero());
+        assert_eq!(offset(&tree, ScrollLayerId::root_scroll_layer(pipeline_id)), LayerPoint::zero());
+    }
+
+    #[test]
+    fn keyboard_scrolls_fall_back_to_ancestors_at_the_edge() {
+        let mut tree = nested_scroll_frames(Some(true));
+        let pipeline_id = PipelineId(0, 0);
+        let root_id = ScrollLayerId::root_scroll_layer(pipeline_id);
+        let middle_id = ScrollLayerId::new(1, pipeline_id);
+        let inner_id = ScrollLayerId::new(2, pipeline_id);
+
+        assert!(scroll_to(&mut tree, ScrollLocation::End, 0));
+        assert_eq!(offset(&tree, inner_id), LayerPoint::new(0.0, -100.0));
+        assert_eq!(offset(&tree, middle_id), LayerPoint::zero());
+
+        // The inner frame is at its end, so the next scrolling ancestor moves.
+        assert!(scroll_to(&mut tree, ScrollLocation::End, 2000 * MS));
+        assert_eq!(offset(&tree, inner_id), LayerPoint::new(0.0, -100.0));
+        assert_eq!(offset(&tree, middle_id), LayerPoint::new(0.0, -400.0));
+        assert_eq!(offset(&tree, root_id), LayerPoint::zero());
+
+        assert!(scroll_to(&mut tree, ScrollLocation::End, 4000 * MS));
+        assert_eq!(offset(&tree, root_id), LayerPoint::new(0.0, -900.0));
+        assert!(!scroll_to(&mut tree, ScrollLocation::End, 6000 * MS));
+
+        // Without scroll chaining, the inner frame keeps the scrolls it can't take.
+        let mut tree = nested_scroll_frames(Some(false));
+        assert!(scroll_to(&mut tree, ScrollLocation::End, 0));
+        assert!(!scroll_to(&mut tree, ScrollLocation::End, 2000 * MS));
+        assert_eq!(offset(&tree, middle_id), LayerPoint::zero());
+    }
+
+    #[test]
+    fn horizontal_edge_scrolls_move_the_nearest_horizontally_scrolling_frame() {
+        let mut tree = nested_scroll_frames(Some(true));
+        let pipeline_id = PipelineId(0, 0);
+        let middle_id = ScrollLayerId::new(1, pipeline_id);
+        let inner_id = ScrollLayerId::new(2, pipeline_id);
+        tree.nodes.get_mut(&middle_id).unwrap().content_size = LayerSize::new(300.0, 500.0);
+        tree.scroll_nodes(LayerPoint::new(0.0, 20.0), middle_id, ScrollBehavior::Instant);
+
+        // Only the middle frame scrolls horizontally, and it keeps its vertical offset.
+        assert!(scroll_to(&mut tree, ScrollLocation::HorizontalEnd, 0));
+        assert_eq!(offset(&tree, inner_id), LayerPoint::zero());
+        assert_eq!(offset(&tree, middle_id), LayerPoint::new(-200.0, -20.0));
+        assert!(!scroll_to(&mut tree, ScrollLocation::HorizontalEnd, 2000 * MS));
+
+        assert!(scroll_to(&mut tree, ScrollLocation::HorizontalStart, 4000 * MS));
+        assert_eq!(offset(&tree, middle_id), LayerPoint::new(0.0, -20.0));
+        assert!(!scroll_to(&mut tree, ScrollLocation::HorizontalStart, 6000 * MS));
+        assert_eq!(offset(&tree, ScrollLayerId::root_scroll_layer(pipeline_id)), LayerPoint::zero());
+    }
+
+    #[test]
+    fn scroll_offset_changes_are_reported_once() {
+        let mut tree = nested_scroll_frames(Some(true));
+        let middle_id = ScrollLayerId::new(1, PipelineId(0, 0));
+        assert!(tree.take_scroll_offset_changes(ScrollChangePhase::Animation).is_empty());
+
+        tree.scroll_nodes(LayerPoint::new(0.0, 50.0), middle_id, ScrollBehavior::Instant);
+        let changes = tree.take_scroll_offset_changes(ScrollChangePhase::Direct);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].state.id, middle_id);
+        assert_eq!(changes[0].state.scroll_offset, LayerPoint::new(0.0, -50.0));
+        assert_eq!(changes[0].external_id, Some(1));
+        assert_eq!(changes[0].phase, ScrollChangePhase::Direct);
+
+        assert!(tree.take_scroll_offset_changes(ScrollChangePhase::Direct).is_empty());
+
+        // A node which moves away and back again between two calls didn't move.
+        tree.scroll_nodes(LayerPoint::new(0.0, 100.0), middle_id, ScrollBehavior::Instant);
+        tree.scroll_nodes(LayerPoint::new(0.0, 50.0), middle_id, ScrollBehavior::Instant);
+        assert!(tree.take_scroll_offset_changes(ScrollChangePhase::Direct).is_empty());
+    }
+
+    #[test]
+    fn scroll_offset_changes_are_attributed_to_their_phase() {
+        let mut tree = nested_scroll_frames(Some(true));
+        let inner_id = ScrollLayerId::new(2, PipelineId(0, 0));
+        let cursor = WorldPoint::new(50.0, 50.0);
+        let delta = |dy| ScrollLocation::Delta(LayerPoint::new(0.0, dy));
+
+        for &(dy, phase) in &[(0.0, ScrollEventPhase::Start),
+                              (-150.0, ScrollEventPhase::Move(true)),
+                              (0.0, ScrollEventPhase::End)] {
+            tree.scroll(delta(dy), cursor, phase, 0);
+            let changes = tree.take_scroll_offset_changes(ScrollChangePhase::Gesture(phase));
+            if phase == ScrollEventPhase::Move(true) {
+                assert_eq!(changes.len(), 1);
+                assert_eq!(changes[0].state.id, inner_id);
+                assert_eq!(changes[0].phase, ScrollChangePhase::Gesture(phase));
+            } else {
+                assert!(changes.is_empty());
+            }
+        }
+
+        // The bounce back moves the node when frames are generated.
+        tree.tick_scrolling_bounce_animations();
+        let changes = tree.take_scroll_offset_changes(ScrollChangePhase::Animation);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].state.id, inner_id);
+        assert_eq!(changes[0].external_id, Some(2));
+        assert_eq!(changes[0].phase, ScrollChangePhase::Animation);
+    }
+
+    #[test]
+    fn pipelines_are_only_zoomable_with_limits() {
+        let mut tree = nested_scroll_frames(Some(true));
+        let pipeline_id = PipelineId(0, 0);
+        let root_reference_frame_id = tree.root_reference_frame_id();
+        assert_eq!(tree.pipeline_reference_frame_id(pipeline_id), Some(root_reference_frame_id));
+
+        let focal_point = WorldPoint::zero();
+        assert!(!tree.zoom_pipeline(pipeline_id, 2.0, &focal_point, ScrollEventPhase::End));
+
+        tree.nodes.get_mut(&root_reference_frame_id).unwrap().zoom_limits = Some(ZoomLimits::default());
+        assert!(tree.zoom_pipeline(pipeline_id, 2.0, &focal_point, ScrollEventPhase::End));
+        tree.update_all_node_transforms(LayerPoint::zero());
+
+        // Everything in the pipeline is scaled, and text is rasterized to match.
+        let inner = &tree.nodes[&ScrollLayerId::new(2, pipeline_id)];
+        let point = inner.world_viewport_transform.transform_point(&LayerPoint::new(10.0, 10.0));
+        assert_eq!(point, WorldPoint::new(20.0, 20.0));
+        assert_eq!(inner.raster_scale, 2.0);
+    }
+
+    #[test]
+    fn scroll_linked_transforms_leave_the_base_transform_alone() {
+        let mut tree = nested_scroll_frames(Some(true));
+        let pipeline_id = PipelineId(0, 0);
+        let root_id = ScrollLayerId::root_scroll_layer(pipeline_id);
+        let key = PropertyBindingKey::new(1);
+
+        // A reference frame inside the root scroll layer, offset by 10 pixels.
+        let rect = LayerRect::new(LayerPoint::zero(), LayerSize::new(100.0, 100.0));
+        let base = LayerToScrollTransform::create_translation(10.0, 0.0, 0.0);
+        let id = tree.add_reference_frame(&rect, &base, pipeline_id, Some(root_id));
+        tree.set_scroll_linked_transform(id, ScrollLinkedTransform {
+            origin: base,
+            binding_id: key.id,
+            perspective: LayoutTransform::identity(),
+            current: None,
+        });
+
+        // Moves down by as much as the root scroll layer is scrolled, up to 100 pixels.
+        let mut properties = SceneProperties::new();
+        let linked = ScrollLinkedValue::linear(key,
+                                               root_id,
+                                               ScrollbarAxis::Vertical,
+                                               (0.0, LayoutTransform::identity()),
+                                               (100.0, LayoutTransform::create_translation(0.0, 100.0, 0.0)));
+        properties.set_scroll_linked_properties(ScrollLinkedProperties {
+            transforms: vec![linked],
+            floats: vec![],
+        });
+
+        tree.scroll_nodes(LayerPoint::new(0.0, 50.0), root_id, ScrollBehavior::Instant);
+        tree.update_scroll_linked_transforms(&properties);
+        let expected = LayerToScrollTransform::create_translation(10.0, 50.0, 0.0);
+        assert_eq!(tree.nodes[&id].local_transform(), expected);
+
+        // Once the binding no longer follows the scroll offset, the reference
+        // frame goes back to the transform it was created with.
+        properties.set_scroll_linked_properties(ScrollLinkedProperties {
+            transforms: vec![],
+            floats: vec![],
+        });
+        tree.update_scroll_linked_transforms(&properties);
+        assert_eq!(tree.nodes[&id].local_transform(), base);
+    }
+}
@@ -1685,13 +1685,14 @@ impl Device {
                                    width: gl::GLint,
                                    height: gl::GLint,
                                    format: gl::GLuint,
+                                   type_: gl::GLuint,
                                    data: &[u8]) {
         self.gl.tex_sub_image_2d(target,
                                   0,
                                   x0, y0,
                                   width, height,
                                   format,
-                                  gl::UNSIGNED_BYTE,
+                                  type_,
                                   data);
     }
 
@@ -1707,7 +1708,8 @@ impl Device {
 
         let mut expanded_data = Vec::new();
 
-        let (gl_format, bpp, data) = match self.textures.get(&texture_id).unwrap().format {
+        let format = self.textures.get(&texture_id).unwrap().format;
+        let (gl_format, bpp, data) = match format {
             ImageFormat::A8 => {
                 if cfg!(any(target_arch="arm", target_arch="aarch64")) {
                     for byte in data {
@@ -1723,7 +1725,8 @@ impl Device {
             }
             ImageFormat::RGB8 => (gl::RGB, 3, data),
             ImageFormat::RGBA8 => (get_gl_format_bgra(self.gl()), 4, data),
-            ImageFormat::Invalid | ImageFormat::RGBAF32 => unreachable!(),
+            ImageFormat::RGBAF32 => (gl::RGBA, 16, data),
+            ImageFormat::Invalid => unreachable!(),
         };
 
         let row_length = match stride {
@@ -1749,6 +1752,7 @@ impl Device {
                                          width as gl::GLint,
                                          height as gl::GLint,
                                          gl_format,
+                                         gl_type_for_texture_format(format),
                                          data);
 
         // Reset row length to 0, otherwise the stride would apply to all texture uploads.
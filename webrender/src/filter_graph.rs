@@ -0,0 +1,470 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use fnv::FnvHasher;
+use gpu_store::GpuStoreAddress;
+use prim_store::GpuBlock32;
+use render_task::{AlphaRenderItem, RenderTask, RenderTaskId, RenderTaskIndex};
+use render_task::RenderTaskLocation;
+use renderer::VertexDataStore;
+use std::cmp;
+use std::collections::HashMap;
+use std::hash::BuildHasherDefault;
+use tiling::StackingContextIndex;
+use webrender_traits::{CompositeOperator, DeviceIntRect, FilterInput, FilterPrimitive};
+use webrender_traits::{FilterPrimitiveKind, FilterResultName, TransferFunction};
+
+const FILTER_NODE_GPU_BLOCKS: usize = 3;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum FilterNodeInput {
+    /// The contents of the stacking context.
+    Source,
+    /// The output of an earlier node.
+    Node(usize),
+}
+
+impl FilterNodeInput {
+    // The values of a graph are numbered with the source first, followed
+    // by the output of each node.
+    fn value_index(&self) -> usize {
+        match *self {
+            FilterNodeInput::Source => 0,
+            FilterNodeInput::Node(index) => index + 1,
+        }
+    }
+}
+
+/// Why the primitives of a filter graph don't make up a valid graph.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum FilterGraphError {
+    /// The primitive at the given index reads a result no primitive has.
+    UnknownResult(usize, FilterResultName),
+    /// The primitive at the given index reads a result that only later
+    /// primitives have.
+    ForwardReference(usize, FilterResultName),
+}
+
+#[derive(Debug, Clone)]
+pub struct FilterNode {
+    kind: FilterPrimitiveKind,
+    inputs: Vec<FilterNodeInput>,
+    gpu_address: GpuStoreAddress,
+}
+
+impl FilterNode {
+    fn new(kind: FilterPrimitiveKind,
+           inputs: Vec<FilterNodeInput>) -> FilterNode {
+        debug_assert_eq!(inputs.len(), input_count(&kind));
+        FilterNode {
+            kind: kind,
+            inputs: inputs,
+            gpu_address: GpuStoreAddress(0),
+        }
+    }
+
+    // This must match the FILTER_ values in ps_filter.glsl!
+    fn op(&self) -> i32 {
+        match self.kind {
+            FilterPrimitiveKind::ColorMatrix(..) => 0,
+            FilterPrimitiveKind::ComponentTransfer(..) => 1,
+            FilterPrimitiveKind::Flood(..) => 2,
+            FilterPrimitiveKind::Offset(..) => 3,
+            FilterPrimitiveKind::Composite(CompositeOperator::Over) => 4,
+            FilterPrimitiveKind::Composite(CompositeOperator::In) => 5,
+            FilterPrimitiveKind::Composite(CompositeOperator::Out) => 6,
+            FilterPrimitiveKind::Composite(CompositeOperator::Atop) => 7,
+            FilterPrimitiveKind::Composite(CompositeOperator::Xor) => 8,
+            FilterPrimitiveKind::Composite(CompositeOperator::Arithmetic(..)) => 9,
+            FilterPrimitiveKind::Merge => 10,
+        }
+    }
+
+    // The parameters of a node are a 4x4 matrix, stored by column,
+    // followed by a vector.
+    fn gpu_data(&self) -> [f32; FILTER_NODE_GPU_BLOCKS * 8] {
+        let mut data = [0.0; FILTER_NODE_GPU_BLOCKS * 8];
+
+        match self.kind {
+            FilterPrimitiveKind::ColorMatrix(ref matrix) => {
+                for row in 0..4 {
+                    for column in 0..4 {
+                        data[column * 4 + row] = matrix[row * 5 + column];
+                    }
+                    data[16 + row] = matrix[row * 5 + 4];
+                }
+            }
+            FilterPrimitiveKind::ComponentTransfer(ref functions) => {
+                // Each column holds the function type and its parameters.
+                for (channel, function) in functions.iter().enumerate() {
+                    let params = match *function {
+                        TransferFunction::Identity => [0.0, 0.0, 0.0, 0.0],
+                        TransferFunction::Linear(slope, intercept) => {
+                            [1.0, slope, intercept, 0.0]
+                        }
+                        TransferFunction::Gamma(amplitude, exponent, offset) => {
+                            [2.0, amplitude, exponent, offset]
+                        }
+                    };
+                    data[channel * 4..channel * 4 + 4].copy_from_slice(&params);
+                }
+            }
+            FilterPrimitiveKind::Flood(color) => {
+                data[16..20].copy_from_slice(&[color.r, color.g, color.b, color.a]);
+            }
+            FilterPrimitiveKind::Offset(offset) => {
+                data[16] = offset.x;
+                data[17] = offset.y;
+            }
+            FilterPrimitiveKind::Composite(CompositeOperator::Arithmetic(k1, k2, k3, k4)) => {
+                data[16..20].copy_from_slice(&[k1, k2, k3, k4]);
+            }
+            FilterPrimitiveKind::Composite(..) |
+            FilterPrimitiveKind::Merge => {}
+        }
+
+        data
+    }
+}
+
+/// A chain of filter primitives applied to the contents of a stacking
+/// context. The output of the last node is the result of the graph.
+#[derive(Debug, Clone)]
+pub struct FilterGraph {
+    nodes: Vec<FilterNode>,
+}
+
+impl FilterGraph {
+    /// Builds the graph of `primitives`, which is invalid if any of them
+    /// reads a result that no earlier primitive has. Only the inputs that a
+    /// primitive actually uses are checked.
+    pub fn new(primitives: &[FilterPrimitive]) -> Result<FilterGraph, FilterGraphError> {
+        // The index of the latest primitive with each name so far.
+        let mut results: HashMap<FilterResultName, usize, BuildHasherDefault<FnvHasher>> =
+            HashMap::with_hasher(Default::default());
+        let mut nodes = Vec::with_capacity(primitives.len());
+
+        for (index, primitive) in primitives.iter().enumerate() {
+            let mut inputs = Vec::new();
+            for input in [primitive.in1, primitive.in2].iter().take(input_count(&primitive.kind)) {
+                match resolve_input(*input, index, &results, primitives) {
+                    Ok(input) => inputs.push(input),
+                    Err(error) => return Err(error),
+                }
+            }
+            nodes.push(FilterNode::new(primitive.kind, inputs));
+
+            if let Some(name) = primitive.result {
+                results.insert(name, index);
+            }
+        }
+
+        Ok(FilterGraph {
+            nodes: nodes,
+        })
+    }
+
+    pub fn color_matrix(matrix: [f32; 20]) -> FilterGraph {
+        FilterGraph {
+            nodes: vec![
+                FilterNode::new(FilterPrimitiveKind::ColorMatrix(matrix),
+                                vec![FilterNodeInput::Source]),
+            ],
+        }
+    }
+
+    pub fn write_gpu_data(&mut self, gpu_data32: &mut VertexDataStore<GpuBlock32>) {
+        for node in &mut self.nodes {
+            let data = node.gpu_data();
+            let address = gpu_data32.alloc(FILTER_NODE_GPU_BLOCKS);
+            let blocks = gpu_data32.get_slice_mut(address, FILTER_NODE_GPU_BLOCKS);
+            for (block, chunk) in blocks.iter_mut().zip(data.chunks(8)) {
+                let mut values = [0.0; 8];
+                values.copy_from_slice(chunk);
+                *block = GpuBlock32::from(values);
+            }
+            node.gpu_address = address;
+        }
+    }
+
+    /// Builds the render tasks that run the graph over `source`, which
+    /// draws the contents of the stacking context, and returns the task
+    /// holding the output of the last node. Every task covers `rect`.
+    ///
+    /// Each node is drawn in the pass after its last input is ready. Tasks
+    /// can only read from the pass directly before them, so inputs that are
+    /// ready earlier are copied forward a pass at a time until needed.
+    pub fn build_render_task(&self,
+                             source: RenderTask,
+                             stacking_context_index: StackingContextIndex,
+                             rect: DeviceIntRect,
+                             next_task_index: &mut RenderTaskIndex,
+                             next_z: &mut i32) -> RenderTask {
+        let output = match self.nodes.len() {
+            0 => return source,
+            count => count - 1,
+        };
+        let value_count = self.nodes.len() + 1;
+
+        // Only the nodes that contribute to the output are drawn.
+        let mut used = vec![false; value_count];
+        used[output + 1] = true;
+        for (index, node) in self.nodes.iter().enumerate().rev() {
+            if used[index + 1] {
+                for input in &node.inputs {
+                    used[input.value_index()] = true;
+                }
+            }
+        }
+
+        // The pass each value is ready in, counting up from the source, and
+        // the last pass that reads it.
+        let mut heights = vec![0; value_count];
+        for (index, node) in self.nodes.iter().enumerate() {
+            let input_height = node.inputs
+                                   .iter()
+                                   .map(|input| heights[input.value_index()])
+                                   .max()
+                                   .unwrap_or(0);
+            heights[index + 1] = input_height + 1;
+        }
+        let mut last_reads = heights.clone();
+        for (index, node) in self.nodes.iter().enumerate() {
+            if used[index + 1] {
+                for input in &node.inputs {
+                    let value = input.value_index();
+                    last_reads[value] = cmp::max(last_reads[value], heights[index + 1]);
+                }
+            }
+        }
+
+        // Every task is created up front, along with the slot of the task
+        // that owns it in the task tree. The versions of a value are its own
+        // task followed by its copies, one per pass.
+        let mut slots: Vec<FilterTaskSlot> = Vec::new();
+        let mut versions: Vec<Vec<usize>> = vec![Vec::new(); value_count];
+
+        slots.push(FilterTaskSlot::new(source, 0));
+        versions[0].push(0);
+        push_copies(&mut slots,
+                    &mut versions[0],
+                    last_reads[0],
+                    stacking_context_index,
+                    rect,
+                    next_task_index,
+                    next_z);
+
+        for (index, node) in self.nodes.iter().enumerate() {
+            if !used[index + 1] {
+                continue;
+            }
+
+            let height = heights[index + 1];
+            let slot = slots.len();
+            let mut input_ids = [None, None];
+            for (input_index, input) in node.inputs.iter().enumerate() {
+                let value = input.value_index();
+                let version = versions[value][height - 1 - heights[value]];
+                input_ids[input_index] = Some(slots[version].task.as_ref().unwrap().id);
+                if slots[version].owner.is_none() && height == last_reads[value] {
+                    slots[version].owner = Some(slot);
+                }
+            }
+
+            let mut task = RenderTask::new_alpha_batch(*next_task_index,
+                                                       rect.origin,
+                                                       true,
+                                                       RenderTaskLocation::Dynamic(None, rect.size));
+            next_task_index.0 += 1;
+            let item = AlphaRenderItem::Filter(stacking_context_index,
+                                               node.op(),
+                                               node.gpu_address,
+                                               input_ids,
+                                               *next_z);
+            *next_z += 1;
+            task.as_alpha_batch().alpha_items.push(item);
+
+            slots.push(FilterTaskSlot::new(task, height));
+            versions[index + 1].push(slot);
+            push_copies(&mut slots,
+                        &mut versions[index + 1],
+                        last_reads[index + 1],
+                        stacking_context_index,
+                        rect,
+                        next_task_index,
+                        next_z);
+        }
+
+        // Move each task into its owner, lowest passes first, so that every
+        // task is complete before its owner is moved.
+        let mut order: Vec<usize> = (0..slots.len()).collect();
+        order.sort_by_key(|slot| slots[*slot].height);
+        for slot in order {
+            if let Some(owner) = slots[slot].owner {
+                let task = slots[slot].task.take().unwrap();
+                slots[owner].task.as_mut().unwrap().children.push(task);
+            }
+        }
+
+        let output_slot = versions[output + 1][0];
+        slots[output_slot].task.take().unwrap()
+    }
+}
+
+struct FilterTaskSlot {
+    task: Option<RenderTask>,
+    height: usize,
+    owner: Option<usize>,
+}
+
+impl FilterTaskSlot {
+    fn new(task: RenderTask, height: usize) -> FilterTaskSlot {
+        FilterTaskSlot {
+            task: Some(task),
+            height: height,
+            owner: None,
+        }
+    }
+}
+
+// Copy the latest version of a value forward until the pass before
+// `last_read`, each copy owning the version it reads.
+fn push_copies(slots: &mut Vec<FilterTaskSlot>,
+               versions: &mut Vec<usize>,
+               last_read: usize,
+               stacking_context_index: StackingContextIndex,
+               rect: DeviceIntRect,
+               next_task_index: &mut RenderTaskIndex,
+               next_z: &mut i32) {
+    loop {
+        let previous = *versions.last().unwrap();
+        let height = slots[previous].height + 1;
+        if height >= last_read {
+            break;
+        }
+
+        let src_task_id = slots[previous].task.as_ref().unwrap().id;
+        let task = RenderTask::new_alpha_batch_copy(*next_task_index,
+                                                    stacking_context_index,
+                                                    src_task_id,
+                                                    rect,
+                                                    *next_z);
+        next_task_index.0 += 1;
+        *next_z += 1;
+
+        let slot = slots.len();
+        slots[previous].owner = Some(slot);
+        slots.push(FilterTaskSlot::new(task, height));
+        versions.push(slot);
+    }
+}
+
+// The number of inputs that a primitive of the given kind reads.
+fn input_count(kind: &FilterPrimitiveKind) -> usize {
+    match *kind {
+        FilterPrimitiveKind::Flood(..) => 0,
+        FilterPrimitiveKind::ColorMatrix(..) |
+        FilterPrimitiveKind::ComponentTransfer(..) |
+        FilterPrimitiveKind::Offset(..) => 1,
+        FilterPrimitiveKind::Composite(..) |
+        FilterPrimitiveKind::Merge => 2,
+    }
+}
+
+// Resolve an input of the primitive at `index`, given the results named by
+// the primitives before it.
+fn resolve_input(input: FilterInput,
+                 index: usize,
+                 results: &HashMap<FilterResultName, usize, BuildHasherDefault<FnvHasher>>,
+                 primitives: &[FilterPrimitive])
+                 -> Result<FilterNodeInput, FilterGraphError> {
+    match input {
+        FilterInput::SourceGraphic => Ok(FilterNodeInput::Source),
+        FilterInput::Previous if index == 0 => Ok(FilterNodeInput::Source),
+        FilterInput::Previous => Ok(FilterNodeInput::Node(index - 1)),
+        FilterInput::Result(name) => {
+            if let Some(node) = results.get(&name) {
+                return Ok(FilterNodeInput::Node(*node));
+            }
+            if primitives[index..].iter().any(|primitive| primitive.result == Some(name)) {
+                Err(FilterGraphError::ForwardReference(index, name))
+            } else {
+                Err(FilterGraphError::UnknownResult(index, name))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FilterGraph, FilterGraphError, FilterNodeInput};
+    use webrender_traits::{ColorF, FilterInput, FilterPrimitive, FilterPrimitiveKind};
+    use webrender_traits::{FilterResultName, LayoutPoint};
+
+    fn offset(in1: FilterInput, result: Option<u32>) -> FilterPrimitive {
+        FilterPrimitive {
+            kind: FilterPrimitiveKind::Offset(LayoutPoint::new(1.0, 1.0)),
+            in1: in1,
+            in2: FilterInput::Previous,
+            result: result.map(FilterResultName),
+        }
+    }
+
+    fn result(name: u32) -> FilterInput {
+        FilterInput::Result(FilterResultName(name))
+    }
+
+    #[test]
+    fn inputs_resolve_to_the_latest_primitive_with_the_name() {
+        let graph = FilterGraph::new(&[
+            offset(FilterInput::Previous, Some(0)),
+            offset(FilterInput::SourceGraphic, Some(0)),
+            offset(result(0), None),
+            offset(FilterInput::Previous, None),
+        ]).unwrap();
+
+        assert_eq!(graph.nodes[0].inputs, vec![FilterNodeInput::Source]);
+        assert_eq!(graph.nodes[1].inputs, vec![FilterNodeInput::Source]);
+        assert_eq!(graph.nodes[2].inputs, vec![FilterNodeInput::Node(1)]);
+        assert_eq!(graph.nodes[3].inputs, vec![FilterNodeInput::Node(2)]);
+    }
+
+    #[test]
+    fn unknown_results_are_rejected() {
+        let error = FilterGraph::new(&[
+            offset(FilterInput::Previous, Some(0)),
+            offset(result(1), None),
+        ]).unwrap_err();
+
+        assert_eq!(error, FilterGraphError::UnknownResult(1, FilterResultName(1)));
+    }
+
+    #[test]
+    fn forward_references_are_rejected() {
+        let error = FilterGraph::new(&[
+            offset(result(0), None),
+            offset(FilterInput::Previous, Some(0)),
+        ]).unwrap_err();
+        assert_eq!(error, FilterGraphError::ForwardReference(0, FilterResultName(0)));
+
+        // A primitive can't read its own result either.
+        let error = FilterGraph::new(&[
+            offset(result(0), Some(0)),
+        ]).unwrap_err();
+        assert_eq!(error, FilterGraphError::ForwardReference(0, FilterResultName(0)));
+    }
+
+    #[test]
+    fn unused_inputs_are_not_checked() {
+        let flood = FilterPrimitive {
+            kind: FilterPrimitiveKind::Flood(ColorF::new(0.0, 1.0, 0.0, 1.0)),
+            in1: result(7),
+            in2: result(8),
+            result: None,
+        };
+        let graph = FilterGraph::new(&[flood]).unwrap();
+
+        assert!(graph.nodes[0].inputs.is_empty());
+    }
+}
@@ -4,36 +4,47 @@
 
 use app_units::Au;
 use euclid::rect::rect;
+use filter_graph::FilterGraph;
 use fnv::FnvHasher;
 use internal_types::{ANGLE_FLOAT_TO_FIXED, AxisDirection};
 use internal_types::{LowLevelFilterOp};
 use internal_types::{RendererFrame};
 use frame_builder::{FrameBuilder, FrameBuilderConfig};
+use mask_store::{ClipMaskStore, ClipMaskStoreContents, new_clip_mask_store};
+use picture_cache::{PictureCache, PictureCacheContents, new_picture_cache};
+use clip_scroll_node::{ScrollLinkedTransform, ScrollSnapPoints};
 use clip_scroll_tree::{ClipScrollTree, ScrollStates};
 use profiler::TextureCacheProfileCounters;
 use resource_cache::ResourceCache;
 use scene::{Scene, SceneProperties};
 use std::collections::HashMap;
 use std::hash::BuildHasherDefault;
-use tiling::{AuxiliaryListsMap, CompositeOps, PrimitiveFlags};
+use tiling::{AuxiliaryListsMap, CompositeOps, PrimitiveFlags, ScrollLinkedOpacities};
 use util::subtract_rect;
-use webrender_traits::{AuxiliaryLists, ClipDisplayItem, ClipRegion, ColorF, DeviceUintRect};
+use webrender_traits::{AuxiliaryLists, BoxShadow, ClipDisplayItem, ClipRegion};
+use webrender_traits::DeviceUintRect;
 use webrender_traits::{DeviceUintSize, DisplayItem, Epoch, FilterOp, ImageDisplayItem, LayerPoint};
 use webrender_traits::{LayerRect, LayerSize, LayerToScrollTransform, LayoutRect, LayoutTransform};
-use webrender_traits::{MixBlendMode, PipelineId, ScrollEventPhase, ScrollLayerId};
-use webrender_traits::{ScrollLayerState, ScrollLocation, ScrollPolicy, SpecificDisplayItem};
+use webrender_traits::{MixBlendMode, OverscrollBehavior, PipelineId, PropertyBinding, ScrollBehavior};
+use webrender_traits::{ScrollChangePhase, ScrollEventPhase, ScrollLayerId, ScrollOffsetChange};
+use webrender_traits::{ScrollLayerState, ScrollLocation, ScrollPolicy, ScrollSnapArea};
+use webrender_traits::{ScrollbarAxis, ScrollbarHitTestResult};
+use webrender_traits::SpecificDisplayItem;
 use webrender_traits::{StackingContext, TileOffset, WorldPoint};
 
 #[derive(Copy, Clone, PartialEq, PartialOrd, Debug)]
 pub struct FrameId(pub u32);
 
-static DEFAULT_SCROLLBAR_COLOR: ColorF = ColorF { r: 0.3, g: 0.3, b: 0.3, a: 0.6 };
-
 struct FlattenContext<'a> {
     scene: &'a Scene,
     builder: &'a mut FrameBuilder,
     resource_cache: &'a mut ResourceCache,
     replacements: Vec<(ScrollLayerId, ScrollLayerId)>,
+
+    /// The scroll frames that still need scrollbars. Each stacking context adds
+    /// the scrollbars of the frames defined inside it once its items are done,
+    /// so they are drawn above the content they scroll.
+    scroll_frames: Vec<ScrollLayerId>,
 }
 
 impl<'a> FlattenContext<'a> {
@@ -46,6 +57,7 @@ impl<'a> FlattenContext<'a> {
             builder: builder,
             resource_cache: resource_cache,
             replacements: Vec::new(),
+            scroll_frames: Vec::new(),
         }
     }
 
@@ -65,6 +77,12 @@ pub struct Frame {
     id: FrameId,
     frame_builder_config: FrameBuilderConfig,
     frame_builder: Option<FrameBuilder>,
+    picture_cache: PictureCache,
+    mask_store: ClipMaskStore,
+
+    /// The frame time given to the last `tick_scroll_animations`, if no frame
+    /// has been built since. Scrollbars fade out in step with it.
+    frame_time: Option<u64>,
 }
 
 trait DisplayListHelpers {
@@ -86,7 +104,12 @@ trait StackingContextHelpers {
     fn mix_blend_mode_for_compositing(&self) -> Option<MixBlendMode>;
     fn filter_ops_for_compositing(&self,
                                   auxiliary_lists: &AuxiliaryLists,
-                                  properties: &SceneProperties) -> Vec<LowLevelFilterOp>;
+                                  properties: &SceneProperties)
+                                  -> (Vec<LowLevelFilterOp>, Vec<FilterGraph>, ScrollLinkedOpacities);
+    fn backdrop_filter_ops_for_compositing(&self,
+                                           auxiliary_lists: &AuxiliaryLists,
+                                           properties: &SceneProperties)
+                                           -> Vec<LowLevelFilterOp>;
 }
 
 impl StackingContextHelpers for StackingContext {
@@ -99,56 +122,132 @@ impl StackingContextHelpers for StackingContext {
 
     fn filter_ops_for_compositing(&self,
                                   auxiliary_lists: &AuxiliaryLists,
-                                  properties: &SceneProperties) -> Vec<LowLevelFilterOp> {
-        let mut filters = vec![];
-        for filter in auxiliary_lists.filters(&self.filters) {
-            match *filter {
-                FilterOp::Blur(radius) => {
-                    filters.push(LowLevelFilterOp::Blur(
-                        radius,
-                        AxisDirection::Horizontal));
-                    filters.push(LowLevelFilterOp::Blur(
-                        radius,
-                        AxisDirection::Vertical));
-                }
-                FilterOp::Brightness(amount) => {
-                    filters.push(
-                            LowLevelFilterOp::Brightness(Au::from_f32_px(amount)));
-                }
-                FilterOp::Contrast(amount) => {
-                    filters.push(
-                            LowLevelFilterOp::Contrast(Au::from_f32_px(amount)));
-                }
-                FilterOp::Grayscale(amount) => {
-                    filters.push(
-                            LowLevelFilterOp::Grayscale(Au::from_f32_px(amount)));
-                }
-                FilterOp::HueRotate(angle) => {
-                    filters.push(
-                            LowLevelFilterOp::HueRotate(f32::round(
-                                    angle * ANGLE_FLOAT_TO_FIXED) as i32));
-                }
-                FilterOp::Invert(amount) => {
-                    filters.push(
-                            LowLevelFilterOp::Invert(Au::from_f32_px(amount)));
+                                  properties: &SceneProperties)
+                                  -> (Vec<LowLevelFilterOp>, Vec<FilterGraph>, ScrollLinkedOpacities) {
+        low_level_filter_ops(auxiliary_lists.filters(&self.filters), auxiliary_lists, properties)
+    }
+
+    fn backdrop_filter_ops_for_compositing(&self,
+                                           auxiliary_lists: &AuxiliaryLists,
+                                           properties: &SceneProperties)
+                                           -> Vec<LowLevelFilterOp> {
+        // Filter graphs aren't supported for backdrops. They are dropped before
+        // lowering, so that an invalid graph doesn't hide the stacking context.
+        let backdrop_filters: Vec<FilterOp> = auxiliary_lists.filters(&self.backdrop_filters)
+                                                             .iter()
+                                                             .filter(|filter| {
+            match **filter {
+                FilterOp::Graph(..) => {
+                    warn!("Unsupported backdrop filter {:?}.", filter);
+                    false
                 }
-                FilterOp::Opacity(ref value) => {
-                    let amount = properties.resolve_float(value, 1.0);
-                    filters.push(
-                            LowLevelFilterOp::Opacity(Au::from_f32_px(amount)));
+                _ => true,
+            }
+        }).cloned().collect();
+
+        // Scroll-linked opacities aren't supported for backdrops, and stay opaque.
+        let (filters, _, _) = low_level_filter_ops(&backdrop_filters,
+                                                   auxiliary_lists,
+                                                   properties);
+
+        // Backdrops are filtered in place, so only filters that read a
+        // single pixel or a single axis of pixels are supported.
+        filters.into_iter().filter(|filter| {
+            match *filter {
+                LowLevelFilterOp::DropShadow(..) |
+                LowLevelFilterOp::Graph(..) => {
+                    warn!("Unsupported backdrop filter {:?}.", filter);
+                    false
                 }
-                FilterOp::Saturate(amount) => {
-                    filters.push(
-                            LowLevelFilterOp::Saturate(Au::from_f32_px(amount)));
+                _ => true,
+            }
+        }).collect()
+    }
+}
+
+fn low_level_filter_ops(filter_ops: &[FilterOp],
+                        auxiliary_lists: &AuxiliaryLists,
+                        properties: &SceneProperties)
+                        -> (Vec<LowLevelFilterOp>, Vec<FilterGraph>, ScrollLinkedOpacities) {
+    let mut filters = vec![];
+    let mut filter_graphs = vec![];
+    let mut scroll_linked_opacities = vec![];
+    for filter in filter_ops {
+        match *filter {
+            FilterOp::Blur(radius) => {
+                filters.push(LowLevelFilterOp::Blur(
+                    radius,
+                    AxisDirection::Horizontal));
+                filters.push(LowLevelFilterOp::Blur(
+                    radius,
+                    AxisDirection::Vertical));
+            }
+            FilterOp::Brightness(amount) => {
+                filters.push(
+                        LowLevelFilterOp::Brightness(Au::from_f32_px(amount)));
+            }
+            FilterOp::Contrast(amount) => {
+                filters.push(
+                        LowLevelFilterOp::Contrast(Au::from_f32_px(amount)));
+            }
+            FilterOp::Grayscale(amount) => {
+                filters.push(
+                        LowLevelFilterOp::Grayscale(Au::from_f32_px(amount)));
+            }
+            FilterOp::HueRotate(angle) => {
+                filters.push(
+                        LowLevelFilterOp::HueRotate(f32::round(
+                                angle * ANGLE_FLOAT_TO_FIXED) as i32));
+            }
+            FilterOp::Invert(amount) => {
+                filters.push(
+                        LowLevelFilterOp::Invert(Au::from_f32_px(amount)));
+            }
+            FilterOp::Opacity(ref value) => {
+                let amount = properties.resolve_float(value, 1.0);
+                if let PropertyBinding::Binding(ref key) = *value {
+                    scroll_linked_opacities.push((filters.len(), key.id, amount));
                 }
-                FilterOp::Sepia(amount) => {
-                    filters.push(
-                            LowLevelFilterOp::Sepia(Au::from_f32_px(amount)));
+                filters.push(
+                        LowLevelFilterOp::Opacity(Au::from_f32_px(amount)));
+            }
+            FilterOp::Saturate(amount) => {
+                filters.push(
+                        LowLevelFilterOp::Saturate(Au::from_f32_px(amount)));
+            }
+            FilterOp::Sepia(amount) => {
+                filters.push(
+                        LowLevelFilterOp::Sepia(Au::from_f32_px(amount)));
+            }
+            FilterOp::DropShadow(offset, blur_radius, color) => {
+                filters.push(
+                        LowLevelFilterOp::DropShadow(Au::from_f32_px(offset.x),
+                                                     Au::from_f32_px(offset.y),
+                                                     Au::from_f32_px(blur_radius),
+                                                     color.into()));
+            }
+            FilterOp::ColorMatrix(matrix) => {
+                filters.push(LowLevelFilterOp::Graph(filter_graphs.len()));
+                filter_graphs.push(FilterGraph::color_matrix(matrix));
+            }
+            FilterOp::Graph(ref primitives) => {
+                let primitives = auxiliary_lists.filter_primitives(primitives);
+                match FilterGraph::new(primitives) {
+                    Ok(filter_graph) => {
+                        filters.push(LowLevelFilterOp::Graph(filter_graphs.len()));
+                        filter_graphs.push(filter_graph);
+                    }
+                    Err(error) => {
+                        // Like an SVG filter in error, an invalid graph keeps the
+                        // stacking context from being drawn at all.
+                        warn!("Invalid filter graph: {:?}", error);
+                        filters.push(LowLevelFilterOp::Opacity(Au(0)));
+                    }
                 }
             }
         }
-        filters
     }
+    (filters, filter_graphs, scroll_linked_opacities)
 }
 
 struct DisplayListTraversal<'a> {
@@ -223,14 +322,19 @@ fn clip_intersection(original_rect: &LayerRect,
 }
 
 impl Frame {
-    pub fn new(config: FrameBuilderConfig) -> Frame {
+    pub fn new(config: FrameBuilderConfig,
+               picture_cache_contents: PictureCacheContents,
+               mask_store_contents: ClipMaskStoreContents) -> Frame {
         Frame {
             pipeline_epoch_map: HashMap::with_hasher(Default::default()),
             pipeline_auxiliary_lists: HashMap::with_hasher(Default::default()),
             clip_scroll_tree: ClipScrollTree::new(),
             id: FrameId(0),
             frame_builder: None,
+            picture_cache: new_picture_cache(picture_cache_contents),
+            mask_store: new_clip_mask_store(mask_store_contents),
             frame_builder_config: config,
+            frame_time: None,
         }
     }
 
@@ -247,28 +351,85 @@ impl Frame {
         self.clip_scroll_tree.get_scroll_node_state()
     }
 
+    pub fn take_scroll_offset_changes(&mut self, phase: ScrollChangePhase) -> Vec<ScrollOffsetChange> {
+        self.clip_scroll_tree.take_scroll_offset_changes(phase)
+    }
+
     /// Returns true if any nodes actually changed position or false otherwise.
-    pub fn scroll_nodes(&mut self, origin: LayerPoint, id: ScrollLayerId) -> bool {
-        self.clip_scroll_tree.scroll_nodes(origin, id)
+    pub fn scroll_nodes(&mut self,
+                        origin: LayerPoint,
+                        id: ScrollLayerId,
+                        behavior: ScrollBehavior)
+                        -> bool {
+        self.clip_scroll_tree.scroll_nodes(origin, id, behavior)
     }
 
     /// Returns true if any nodes actually changed position or false otherwise.
+    /// `event_time` is when the input event happened, in nanoseconds.
     pub fn scroll(&mut self,
                   scroll_location: ScrollLocation,
                   cursor: WorldPoint,
-                  phase: ScrollEventPhase)
+                  phase: ScrollEventPhase,
+                  event_time: u64)
                   -> bool {
-        self.clip_scroll_tree.scroll(scroll_location, cursor, phase,)
+        self.clip_scroll_tree.scroll(scroll_location, cursor, phase, event_time)
     }
 
     pub fn tick_scrolling_bounce_animations(&mut self) {
         self.clip_scroll_tree.tick_scrolling_bounce_animations();
     }
 
+    /// Advance smooth scrolls and flings to the given frame time, in nanoseconds.
+    pub fn tick_scroll_animations(&mut self, frame_time: u64, device_pixel_ratio: f32) {
+        self.clip_scroll_tree.tick_scroll_animations(frame_time, device_pixel_ratio);
+        self.frame_time = Some(frame_time);
+    }
+
+    pub fn hit_test_scrollbar(&self, cursor: WorldPoint) -> Option<ScrollbarHitTestResult> {
+        if !self.frame_builder_config.enable_scrollbars {
+            return None;
+        }
+        self.clip_scroll_tree.hit_test_scrollbar(&cursor, &self.frame_builder_config.scrollbar_style)
+    }
+
+    /// Returns true if the node actually moved or false otherwise.
+    pub fn drag_scrollbar_thumb(&mut self,
+                                id: ScrollLayerId,
+                                axis: ScrollbarAxis,
+                                delta: f32)
+                                -> bool {
+        self.clip_scroll_tree.drag_scrollbar_thumb(id,
+                                                   axis,
+                                                   delta,
+                                                   &self.frame_builder_config.scrollbar_style)
+    }
+
+    pub fn zoom_scroll_layer(&mut self,
+                             id: ScrollLayerId,
+                             zoom: f32,
+                             focal_point: WorldPoint,
+                             phase: ScrollEventPhase)
+                             -> bool {
+        self.clip_scroll_tree.zoom_node(id, zoom, &focal_point, phase)
+    }
+
+    pub fn zoom_pipeline(&mut self,
+                         pipeline_id: PipelineId,
+                         zoom: f32,
+                         focal_point: WorldPoint,
+                         phase: ScrollEventPhase)
+                         -> bool {
+        self.clip_scroll_tree.zoom_pipeline(pipeline_id, zoom, &focal_point, phase)
+    }
+
     pub fn discard_frame_state_for_pipeline(&mut self, pipeline_id: PipelineId) {
         self.clip_scroll_tree.discard_frame_state_for_pipeline(pipeline_id);
     }
 
+    pub fn discard_pictures_for_pipeline(&mut self, pipeline_id: PipelineId) {
+        self.picture_cache.discard_pipeline(pipeline_id);
+    }
+
     pub fn create(&mut self,
                   scene: &Scene,
                   resource_cache: &mut ResourceCache,
@@ -319,6 +480,7 @@ impl Frame {
 
         let mut frame_builder = FrameBuilder::new(window_size,
                                                   background_color,
+                                                  device_pixel_ratio,
                                                   self.frame_builder_config);
 
         {
@@ -327,6 +489,9 @@ impl Frame {
             let scroll_layer_id = context.builder.push_root(root_pipeline_id,
                                                             &root_pipeline.viewport_size,
                                                             &root_bounds.size,
+                                                            scene.pipeline_zoom_limits
+                                                                 .get(&root_pipeline_id)
+                                                                 .cloned(),
                                                             &mut self.clip_scroll_tree);
 
             context.builder.setup_viewport_offset(window_size,
@@ -357,14 +522,37 @@ impl Frame {
                         reference_frame_relative_offset: LayerPoint,
                         clip: &ClipRegion) {
         let clip_rect = clip.main.translate(&reference_frame_relative_offset);
+        let scroll_snap = item.scroll_snap.map(|scroll_snap| {
+            let auxiliary_lists = self.pipeline_auxiliary_lists
+                                      .get(&pipeline_id)
+                                      .expect("No auxiliary lists?!");
+            let areas = auxiliary_lists.scroll_snap_areas(&scroll_snap.areas).iter().map(|area| {
+                ScrollSnapArea {
+                    rect: area.rect.translate(&reference_frame_relative_offset),
+                    align: area.align,
+                }
+            }).collect();
+
+            ScrollSnapPoints {
+                snap_type: scroll_snap.snap_type,
+                areas: areas,
+            }
+        });
+
         context.builder.add_clip_scroll_node(item.id,
                                              parent_id,
                                              pipeline_id,
                                              &clip_rect,
                                              &item.content_size,
                                              clip,
+                                             scroll_snap,
+                                             item.overscroll,
+                                             item.zoom_limits,
                                              &mut self.clip_scroll_tree);
 
+        if self.frame_builder_config.enable_scrollbars {
+            context.scroll_frames.push(item.id);
+        }
     }
 
     fn flatten_stacking_context<'a>(&mut self,
@@ -386,9 +574,16 @@ impl Frame {
             let auxiliary_lists = self.pipeline_auxiliary_lists
                                       .get(&pipeline_id)
                                       .expect("No auxiliary lists?!");
-            CompositeOps::new(
-                stacking_context.filter_ops_for_compositing(auxiliary_lists, &context.scene.properties),
-                stacking_context.mix_blend_mode_for_compositing())
+            let (filters, filter_graphs, scroll_linked_opacities) =
+                stacking_context.filter_ops_for_compositing(auxiliary_lists, &context.scene.properties);
+            let backdrop_filters =
+                stacking_context.backdrop_filter_ops_for_compositing(auxiliary_lists,
+                                                                     &context.scene.properties);
+            CompositeOps::new(filters,
+                              filter_graphs,
+                              scroll_linked_opacities,
+                              backdrop_filters,
+                              stacking_context.mix_blend_mode_for_compositing())
         };
 
         if composition_operations.will_make_invisible() {
@@ -413,13 +608,12 @@ impl Frame {
             let transform = context.scene.properties.resolve_layout_transform(transform);
             let perspective =
                 stacking_context.perspective.unwrap_or_else(LayoutTransform::identity);
-            let transform =
+            let origin =
                 LayerToScrollTransform::create_translation(reference_frame_relative_offset.x,
                                                            reference_frame_relative_offset.y,
                                                            0.0)
-                                        .pre_translated(bounds.origin.x, bounds.origin.y, 0.0)
-                                        .pre_mul(&transform)
-                                        .pre_mul(&perspective);
+                                        .pre_translated(bounds.origin.x, bounds.origin.y, 0.0);
+            let transform = origin.pre_mul(&transform).pre_mul(&perspective);
 
             let reference_frame_bounds = LayerRect::new(LayerPoint::zero(), bounds.size);
             scroll_layer_id = context.builder.push_reference_frame(Some(scroll_layer_id),
@@ -427,6 +621,17 @@ impl Frame {
                                                                    &reference_frame_bounds,
                                                                    &transform,
                                                                    &mut self.clip_scroll_tree);
+            // Any bound transform may follow a scroll offset later on, without
+            // the scene being flattened again.
+            if let Some(PropertyBinding::Binding(ref key)) = stacking_context.transform {
+                let linked = ScrollLinkedTransform {
+                    origin: origin,
+                    binding_id: key.id,
+                    perspective: perspective,
+                    current: None,
+                };
+                self.clip_scroll_tree.set_scroll_linked_transform(scroll_layer_id, linked);
+            }
             context.replacements.push((context_scroll_layer_id, scroll_layer_id));
             reference_frame_relative_offset = LayerPoint::zero();
         } else {
@@ -439,6 +644,9 @@ impl Frame {
         context.builder.push_stacking_context(&reference_frame_relative_offset,
                                               pipeline_id,
                                               level == 0,
+                                              stacking_context.transform_style,
+                                              stacking_context.backface_visible,
+                                              &bounds.size,
                                               composition_operations);
 
         // For the root pipeline, there's no need to add a full screen rectangle
@@ -458,20 +666,36 @@ impl Frame {
             }
         }
 
+        let scroll_frames_start = context.scroll_frames.len();
+        if level == 0 && self.frame_builder_config.enable_scrollbars {
+            context.scroll_frames.push(ScrollLayerId::root_scroll_layer(pipeline_id));
+        }
+
         self.flatten_items(traversal,
                            pipeline_id,
                            context,
                            reference_frame_relative_offset,
                            level);
 
-        if level == 0 && self.frame_builder_config.enable_scrollbars {
-            let scrollbar_rect = LayerRect::new(LayerPoint::zero(), LayerSize::new(10.0, 70.0));
-            context.builder.add_solid_rectangle(
-                scroll_layer_id,
-                &scrollbar_rect,
-                &ClipRegion::simple(&scrollbar_rect),
-                &DEFAULT_SCROLLBAR_COLOR,
-                PrimitiveFlags::Scrollbar(self.clip_scroll_tree.topmost_scroll_layer_id(), 4.0));
+        // The thumbs are positioned every frame by FrameBuilder::update_scroll_bars.
+        // Frames which can't move never show them, so they don't need any.
+        let scrollbar_style = self.frame_builder_config.scrollbar_style;
+        for scroll_frame_id in context.scroll_frames.split_off(scroll_frames_start) {
+            let is_scrollable = self.clip_scroll_tree
+                                    .nodes
+                                    .get(&scroll_frame_id)
+                                    .map_or(false, |node| node.is_scrollable());
+            if !is_scrollable {
+                continue;
+            }
+
+            for &axis in &[ScrollbarAxis::Vertical, ScrollbarAxis::Horizontal] {
+                context.builder.add_solid_rectangle(scroll_frame_id,
+                                                    &LayerRect::zero(),
+                                                    &ClipRegion::simple(&LayerRect::zero()),
+                                                    &scrollbar_style.color,
+                                                    PrimitiveFlags::Scrollbar(scroll_frame_id, axis));
+            }
         }
 
         if stacking_context.scroll_policy == ScrollPolicy::Fixed {
@@ -527,6 +751,9 @@ impl Frame {
                                                  &iframe_rect,
                                                  &transform,
                                                  &mut self.clip_scroll_tree);
+        if let Some(node) = self.clip_scroll_tree.nodes.get_mut(&iframe_reference_frame_id) {
+            node.zoom_limits = context.scene.pipeline_zoom_limits.get(&pipeline_id).cloned();
+        }
 
         let iframe_scroll_layer_id = ScrollLayerId::root_scroll_layer(pipeline_id);
         context.builder.add_clip_scroll_node(
@@ -536,6 +763,9 @@ impl Frame {
             &LayerRect::new(LayerPoint::zero(), iframe_rect.size),
             &iframe_stacking_context_bounds.size,
             &ClipRegion::simple(&iframe_stacking_context_bounds),
+            None,
+            OverscrollBehavior::default(),
+            None,
             &mut self.clip_scroll_tree);
 
         let mut traversal = DisplayListTraversal::new_skipping_first(display_list);
@@ -601,6 +831,9 @@ impl Frame {
                                                   info.color_space);
                 }
                 SpecificDisplayItem::Text(ref text_info) => {
+                    let auxiliary_lists = self.pipeline_auxiliary_lists
+                                              .get(&pipeline_id)
+                                              .expect("No auxiliary lists?!");
                     context.builder.add_text(scroll_layer_id,
                                              item.rect,
                                              &item.clip,
@@ -609,6 +842,7 @@ impl Frame {
                                              text_info.blur_radius,
                                              &text_info.color,
                                              text_info.glyphs,
+                                             auxiliary_lists.glyph_instances(&text_info.glyphs),
                                              text_info.glyph_options);
                 }
                 SpecificDisplayItem::Rectangle(ref info) => {
@@ -641,6 +875,14 @@ impl Frame {
                                                             PrimitiveFlags::None);
                     }
                 }
+                SpecificDisplayItem::Line(ref info) => {
+                    context.builder.add_line(scroll_layer_id,
+                                             &item.rect,
+                                             &item.clip,
+                                             &info.color,
+                                             info.style,
+                                             info.orientation);
+                }
                 SpecificDisplayItem::Gradient(ref info) => {
                     context.builder.add_gradient(scroll_layer_id,
                                                  item.rect,
@@ -648,7 +890,8 @@ impl Frame {
                                                  info.gradient.start_point,
                                                  info.gradient.end_point,
                                                  info.gradient.stops,
-                                                 info.gradient.extend_mode);
+                                                 info.gradient.extend_mode,
+                                                 info.gradient.interpolation);
                 }
                 SpecificDisplayItem::RadialGradient(ref info) => {
                     context.builder.add_radial_gradient(scroll_layer_id,
@@ -660,18 +903,42 @@ impl Frame {
                                                         info.gradient.end_radius,
                                                         info.gradient.ratio_xy,
                                                         info.gradient.stops,
-                                                        info.gradient.extend_mode);
+                                                        info.gradient.extend_mode,
+                                                        info.gradient.interpolation);
+                }
+                SpecificDisplayItem::ConicGradient(ref info) => {
+                    context.builder.add_conic_gradient(scroll_layer_id,
+                                                       item.rect,
+                                                       &item.clip,
+                                                       info.gradient.center,
+                                                       info.gradient.angle,
+                                                       info.gradient.stops,
+                                                       info.gradient.extend_mode,
+                                                       info.gradient.interpolation);
                 }
                 SpecificDisplayItem::BoxShadow(ref box_shadow_info) => {
-                    context.builder.add_box_shadow(scroll_layer_id,
-                                                   &box_shadow_info.box_bounds,
-                                                   &item.clip,
-                                                   &box_shadow_info.offset,
-                                                   &box_shadow_info.color,
-                                                   box_shadow_info.blur_radius,
-                                                   box_shadow_info.spread_radius,
-                                                   box_shadow_info.border_radius,
-                                                   box_shadow_info.clip_mode);
+                    let shadow = BoxShadow {
+                        offset: box_shadow_info.offset,
+                        color: box_shadow_info.color,
+                        blur_radius: box_shadow_info.blur_radius,
+                        spread_radius: box_shadow_info.spread_radius,
+                        clip_mode: box_shadow_info.clip_mode,
+                    };
+                    context.builder.add_box_shadows(scroll_layer_id,
+                                                    &box_shadow_info.box_bounds,
+                                                    &item.clip,
+                                                    box_shadow_info.border_radius,
+                                                    &[shadow]);
+                }
+                SpecificDisplayItem::BoxShadowList(ref info) => {
+                    let auxiliary_lists = self.pipeline_auxiliary_lists
+                                              .get(&pipeline_id)
+                                              .expect("No auxiliary lists?!");
+                    context.builder.add_box_shadows(scroll_layer_id,
+                                                    &info.box_bounds,
+                                                    &item.clip,
+                                                    info.border_radius,
+                                                    auxiliary_lists.box_shadows(&info.shadows));
                 }
                 SpecificDisplayItem::Border(ref info) => {
                     context.builder.add_border(scroll_layer_id,
@@ -704,6 +971,12 @@ impl Frame {
                                       reference_frame_relative_offset,
                                       &item.clip);
                 }
+                SpecificDisplayItem::PushTextShadow(ref info) => {
+                    context.builder.push_text_shadow(info.shadow);
+                }
+                SpecificDisplayItem::PopTextShadow => {
+                    context.builder.pop_text_shadow();
+                }
                 SpecificDisplayItem::PopStackingContext => return,
             }
         }
@@ -979,13 +1252,19 @@ impl Frame {
     pub fn build(&mut self,
                  resource_cache: &mut ResourceCache,
                  auxiliary_lists_map: &AuxiliaryListsMap,
+                 properties: &SceneProperties,
                  device_pixel_ratio: f32,
                  pan: LayerPoint,
                  texture_cache_profile: &mut TextureCacheProfileCounters)
                  -> RendererFrame {
+        if self.frame_builder_config.enable_scrollbars {
+            self.clip_scroll_tree.update_scrollbar_fades(self.frame_time.take());
+        }
+        self.clip_scroll_tree.update_scroll_linked_transforms(properties);
         self.clip_scroll_tree.update_all_node_transforms(pan);
         let frame = self.build_frame(resource_cache,
                                      auxiliary_lists_map,
+                                     properties,
                                      device_pixel_ratio,
                                      texture_cache_profile);
         resource_cache.expire_old_resources(self.id);
@@ -995,6 +1274,7 @@ impl Frame {
     fn build_frame(&mut self,
                    resource_cache: &mut ResourceCache,
                    auxiliary_lists_map: &AuxiliaryListsMap,
+                   properties: &SceneProperties,
                    device_pixel_ratio: f32,
                    texture_cache_profile: &mut TextureCacheProfileCounters)
                    -> RendererFrame {
@@ -1004,12 +1284,20 @@ impl Frame {
                           self.id,
                           &mut self.clip_scroll_tree,
                           auxiliary_lists_map,
+                          &self.pipeline_epoch_map,
+                          properties,
+                          &mut self.picture_cache,
+                          &mut self.mask_store,
                           device_pixel_ratio,
                           texture_cache_profile)
         );
         self.frame_builder = frame_builder;
 
         let nodes_bouncing_back = self.clip_scroll_tree.collect_nodes_bouncing_back();
-        RendererFrame::new(self.pipeline_epoch_map.clone(), nodes_bouncing_back, frame)
+        let nodes_animating_scroll = self.clip_scroll_tree.collect_nodes_animating_scroll();
+        RendererFrame::new(self.pipeline_epoch_map.clone(),
+                           nodes_bouncing_back,
+                           nodes_animating_scroll,
+                           frame)
     }
 }
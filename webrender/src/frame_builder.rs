@@ -4,36 +4,56 @@
 
 use app_units::Au;
 use batch_builder::BorderSideHelpers;
+use fnv::FnvHasher;
 use frame::FrameId;
 use gpu_store::GpuStoreAddress;
-use internal_types::{HardwareCompositeOp, SourceTexture};
+use internal_types::{HardwareCompositeOp, LowLevelFilterOp, SourceTexture};
 use mask_cache::{ClipMode, ClipSource, MaskCacheInfo, RegionMode};
+use mask_store::{ClipMaskKey, ClipMaskStore, ClipMaskStoreOp};
+use persistent_target::PersistentTargetOp;
+use picture_cache::{PICTURE_CACHE_SIZE, PictureCache, PictureCacheOp, PictureId, PictureKey};
+use plane_split::{BspSplitter, Polygon};
 use prim_store::{BorderPrimitiveCpu, BorderPrimitiveGpu, BoxShadowPrimitiveGpu};
+use prim_store::{ConicGradientPrimitiveCpu, ConicGradientPrimitiveGpu, GpuBlock32};
 use prim_store::{GradientPrimitiveCpu, GradientPrimitiveGpu, ImagePrimitiveCpu, ImagePrimitiveGpu};
-use prim_store::{ImagePrimitiveKind, PrimitiveContainer, PrimitiveGeometry, PrimitiveIndex};
-use prim_store::{PrimitiveStore, RadialGradientPrimitiveCpu, RadialGradientPrimitiveGpu};
-use prim_store::{RectanglePrimitive, TextRunPrimitiveCpu, TextRunPrimitiveGpu};
-use prim_store::{TexelRect, YuvImagePrimitiveCpu, YuvImagePrimitiveGpu};
+use prim_store::{ImagePrimitiveKind, LinePrimitive, PrimitiveContainer, PrimitiveGeometry};
+use prim_store::{PrimitiveIndex, PrimitiveKind, PrimitiveStore, RadialGradientPrimitiveCpu};
+use prim_store::{RadialGradientPrimitiveGpu, RectanglePrimitive, SplitGeometry, TextRunPrimitiveCpu};
+use prim_store::{TextRunPrimitiveGpu, TexelRect, YuvImagePrimitiveCpu, YuvImagePrimitiveGpu};
 use profiler::{FrameProfileCounters, TextureCacheProfileCounters};
 use render_task::{AlphaRenderItem, MaskCacheKey, MaskResult, RenderTask, RenderTaskIndex};
-use render_task::RenderTaskLocation;
+use render_task::{RenderTaskKind, RenderTaskLocation};
 use resource_cache::ResourceCache;
-use clip_scroll_node::{ClipInfo, ClipScrollNode, NodeType};
+use scene::SceneProperties;
+use clip_scroll_node::{ClipInfo, ClipScrollNode, NodeType, ScrollSnapPoints};
 use clip_scroll_tree::ClipScrollTree;
 use std::{cmp, f32, i32, mem, usize};
+use std::collections::HashMap;
+use std::hash::{BuildHasherDefault, Hash, Hasher};
 use euclid::SideOffsets2D;
 use tiling::StackingContextIndex;
 use tiling::{AuxiliaryListsMap, ClipScrollGroup, ClipScrollGroupIndex, CompositeOps, Frame};
-use tiling::{PackedLayer, PackedLayerIndex, PrimitiveFlags, PrimitiveRunCmd, RenderPass};
+use tiling::{PackedLayer, PackedLayerIndex, PipelineEpochMap, PrimitiveFlags, PrimitiveRunCmd, RenderPass};
 use tiling::{RenderTargetContext, RenderTaskCollection, ScrollbarPrimitive, StackingContext};
 use util::{self, pack_as_float, rect_from_points_f, subtract_rect};
-use util::{RectHelpers, TransformedRectKind};
-use webrender_traits::{BorderDetails, BorderDisplayItem, BorderSide, BorderStyle};
-use webrender_traits::{BoxShadowClipMode, ClipRegion, ColorF, DeviceIntPoint, DeviceIntRect};
+use util::{MatrixHelpers, RectHelpers, TransformedRectKind};
+use webrender_traits::{BorderDetails, BorderDisplayItem, BorderRadius, BorderStyle};
+use webrender_traits::{BoxShadow, BoxShadowClipMode, ClipRegion, ColorF, ComplexClipRegion};
+use webrender_traits::{DeviceIntPoint, DeviceIntRect};
+use webrender_traits::{device_length, DevicePoint};
 use webrender_traits::{DeviceIntSize, DeviceUintRect, DeviceUintSize, ExtendMode, FontKey};
-use webrender_traits::{FontRenderMode, GlyphOptions, ImageKey, ImageRendering, ItemRange};
-use webrender_traits::{LayerPoint, LayerRect, LayerSize, LayerToScrollTransform, PipelineId};
-use webrender_traits::{RepeatMode, ScrollLayerId, TileOffset, WebGLContextId, YuvColorSpace};
+use webrender_traits::{FontRenderMode, GlyphInstance, GlyphOptions, ImageKey, ImageRendering};
+use webrender_traits::{GradientInterpolation, ItemRange};
+use webrender_traits::{LayerPoint, LayerRect, LayerSize, LayerToScrollTransform, LayerToWorldTransform};
+use webrender_traits::LineOrientation;
+use webrender_traits::{LineStyle, OverscrollBehavior, PipelineId};
+use webrender_traits::{RepeatMode, ScrollLayerId, TextShadow, TileOffset, TransformStyle};
+use webrender_traits::WebGLContextId;
+use webrender_traits::{YuvColorSpace, ZoomLimits};
+
+/// The smallest size of the render targets used for cached render tasks.
+/// The targets are grown to the framebuffer size if that is larger.
+const MIN_CACHE_TARGET_SIZE: u32 = 1024;
 
 #[derive(Debug, Clone)]
 struct ImageBorderSegment {
@@ -83,20 +103,248 @@ impl ImageBorderSegment {
     }
 }
 
+/// The parameters of a text item, kept around so that the item can be
+/// drawn again for each text shadow that applies to it.
+#[derive(Debug, Clone, Copy)]
+struct TextRunInfo {
+    scroll_layer_id: ScrollLayerId,
+    rect: LayerRect,
+    clip_region: ClipRegion,
+    font_key: FontKey,
+    size: Au,
+    blur_radius: Au,
+    color: ColorF,
+    glyph_range: ItemRange,
+    glyph_options: Option<GlyphOptions>,
+}
+
+/// A primitive that has been built, but not added to the primitive store yet.
+#[derive(Debug)]
+struct PendingPrimitive {
+    scroll_layer_id: ScrollLayerId,
+    rect: LayerRect,
+    clip_region: ClipRegion,
+    extra_clip: Option<ClipSource>,
+    container: PrimitiveContainer,
+}
+
+/// An item added while a text shadow is in effect. Text runs are kept
+/// as-is, since they are drawn again for every shadow that applies to them.
+#[derive(Debug)]
+enum DeferredItem {
+    TextRun(TextRunInfo, Vec<GlyphInstance>),
+    Primitive(PendingPrimitive),
+}
+
+/// A text shadow that is in effect, and the items added to its stacking
+/// context since. The items are held back until the shadow is flushed,
+/// so that the shadow can be drawn underneath all of them.
+struct TextShadowScope {
+    shadow: TextShadow,
+    stacking_context_index: StackingContextIndex,
+    /// The shadows of text shadows nested inside this one, which are drawn
+    /// underneath the shadows of this one.
+    shadows: Vec<PendingPrimitive>,
+    items: Vec<DeferredItem>,
+}
+
+/// Splits a run of glyphs into ranges whose (conservative) bounds don't
+/// exceed `max_extent` in either direction. Every range contains at
+/// least one glyph.
+fn split_text_run(glyphs: &[GlyphInstance],
+                  glyph_size: f32,
+                  max_extent: f32,
+                  ranges: &mut Vec<(usize, usize)>) {
+    let mut start = 0;
+    let mut bounds: Option<LayerRect> = None;
+
+    for (index, glyph) in glyphs.iter().enumerate() {
+        let glyph_rect = LayerRect::new(LayerPoint::new(glyph.point.x, glyph.point.y),
+                                        LayerSize::zero()).inflate(glyph_size, glyph_size);
+        let run_rect = match bounds {
+            Some(bounds) => bounds.union(&glyph_rect),
+            None => glyph_rect,
+        };
+
+        if index > start &&
+           (run_rect.size.width > max_extent || run_rect.size.height > max_extent) {
+            ranges.push((start, index));
+            start = index;
+            bounds = Some(glyph_rect);
+        } else {
+            bounds = Some(run_rect);
+        }
+    }
+
+    if start < glyphs.len() {
+        ranges.push((start, glyphs.len()));
+    }
+}
+
+/// The signature of a stacking context that may be drawn from the picture
+/// cache, built up while walking its contents. Positions are hashed relative
+/// to the origin of its bounding rect, so that it can move by whole device
+/// pixels without being drawn again.
+struct PictureSignature {
+    stacking_context_index: StackingContextIndex,
+    picture_id: PictureId,
+    origin: DeviceIntPoint,
+    /// The first primitive hashed into the signature. Primitive and stacking
+    /// context indices are hashed relative to the picture's own, which stay
+    /// the same when the scene is rebuilt for a change in another pipeline.
+    first_prim_index: Option<PrimitiveIndex>,
+    hasher: FnvHasher,
+    is_cacheable: bool,
+}
+
+fn hash_f32<H: Hasher>(value: f32, state: &mut H) {
+    unsafe { mem::transmute::<f32, u32>(value) }.hash(state);
+}
+
+fn hash_device_rect<H: Hasher>(rect: &DeviceIntRect, origin: DeviceIntPoint, state: &mut H) {
+    (rect.origin.x - origin.x, rect.origin.y - origin.y).hash(state);
+    (rect.size.width, rect.size.height).hash(state);
+}
+
+/// Whether a text run can be drawn with subpixel AA. Subpixel AA blends each
+/// color channel of a glyph separately with what is already drawn beneath
+/// it, so it needs an opaque destination. Every pixel of the framebuffer
+/// starts out as the frame's background color, so a run drawn straight into
+/// the framebuffer sits on an opaque background exactly when that color is
+/// opaque. Runs inside a stacking context that is drawn into an intermediate
+/// surface are drawn over transparent pixels instead. The glyphs must also
+/// land on the device pixel grid they were rasterized for.
+fn allows_subpixel_aa<'a, I>(background_color: Option<ColorF>,
+                             stacking_contexts: I,
+                             transform: &LayerToWorldTransform,
+                             device_pixel_ratio: f32)
+                             -> bool
+                             where I: IntoIterator<Item=&'a StackingContext> {
+    background_color.map_or(false, |color| color.a == 1.0) &&
+    stacking_contexts.into_iter().all(|stacking_context| {
+        stacking_context.composite_ops.count() == 0 &&
+        !stacking_context.should_isolate &&
+        !stacking_context.is_3d_plane
+    }) &&
+    transform.is_2d_integer_translation(device_pixel_ratio)
+}
+
+fn hash_packed_layer<H: Hasher>(layer: &PackedLayer,
+                                origin: DeviceIntPoint,
+                                device_pixel_ratio: f32,
+                                state: &mut H) {
+    // Translating the picture to the origin moves each row of the
+    // transform by its w component.
+    let x = origin.x as f32 / device_pixel_ratio;
+    let y = origin.y as f32 / device_pixel_ratio;
+    let m = &layer.transform;
+    for &(m1, m2, m3, m4) in &[(m.m11, m.m12, m.m13, m.m14),
+                               (m.m21, m.m22, m.m23, m.m24),
+                               (m.m31, m.m32, m.m33, m.m34),
+                               (m.m41, m.m42, m.m43, m.m44)] {
+        hash_f32(m1 - m4 * x, state);
+        hash_f32(m2 - m4 * y, state);
+        hash_f32(m3, state);
+        hash_f32(m4, state);
+    }
+
+    let clip_rect = &layer.local_clip_rect;
+    hash_f32(clip_rect.origin.x, state);
+    hash_f32(clip_rect.origin.y, state);
+    hash_f32(clip_rect.size.width, state);
+    hash_f32(clip_rect.size.height, state);
+}
+
+/// Hash the display list epochs of the pipelines of a node and its ancestors,
+/// which between them decide the clips and offsets baked into the node. The
+/// ids of the nodes are left out, since reference frames are numbered across
+/// the whole scene, and so change whenever another pipeline does.
+fn hash_pipeline_epochs<H: Hasher>(clip_scroll_tree: &ClipScrollTree,
+                                   id: ScrollLayerId,
+                                   pipeline_epoch_map: &PipelineEpochMap,
+                                   state: &mut H) {
+    let mut current_id = Some(id);
+    while let Some(id) = current_id {
+        pipeline_epoch_map.get(&id.pipeline_id()).hash(state);
+        current_id = clip_scroll_tree.nodes.get(&id).and_then(|node| node.parent);
+    }
+}
+
+/// The key of the clip mask shared by everything inside a masking clip. The
+/// clip data only changes with the display lists the clips come from, so
+/// their epochs and the positions of the clips are all there is to hash.
+/// Masks with image clips aren't stored, since the images may change without
+/// a new display list.
+fn clip_mask_key(clip_scroll_tree: &ClipScrollTree,
+                 clip_id: ScrollLayerId,
+                 pipeline_epoch_map: &PipelineEpochMap,
+                 task_rect: &DeviceIntRect,
+                 clip_stack: &[(PackedLayerIndex, MaskCacheInfo)],
+                 packed_layers: &[PackedLayer],
+                 device_pixel_ratio: f32)
+                 -> Option<ClipMaskKey> {
+    if clip_stack.iter().any(|&(_, ref info)| info.image.is_some()) {
+        return None;
+    }
+
+    let mut hasher = FnvHasher::default();
+    hash_pipeline_epochs(clip_scroll_tree, clip_id, pipeline_epoch_map, &mut hasher);
+    hash_device_rect(task_rect, DeviceIntPoint::zero(), &mut hasher);
+    hash_f32(device_pixel_ratio, &mut hasher);
+    for &(packed_layer_index, ref info) in clip_stack {
+        hash_packed_layer(&packed_layers[packed_layer_index.0],
+                          DeviceIntPoint::zero(),
+                          device_pixel_ratio,
+                          &mut hasher);
+        info.clip_range.item_count.hash(&mut hasher);
+        info.effective_clip_count.hash(&mut hasher);
+    }
+
+    Some(ClipMaskKey(hasher.finish()))
+}
+
+/// The look of the overlay scrollbars drawn when `enable_scrollbars` is set.
+/// Lengths are in layer pixels.
+#[derive(Clone, Copy, Debug)]
+pub struct ScrollbarStyle {
+    /// The color of the thumb while it is fully visible.
+    pub color: ColorF,
+    /// The thickness of the thumb.
+    pub width: f32,
+    /// The corner radius of the thumb.
+    pub radius: f32,
+    /// The gap between the thumb and the edges of the scroll frame.
+    pub margin: f32,
+}
+
+impl Default for ScrollbarStyle {
+    fn default() -> ScrollbarStyle {
+        ScrollbarStyle {
+            color: ColorF::new(0.3, 0.3, 0.3, 0.6),
+            width: 10.0,
+            radius: 4.0,
+            margin: 8.0,
+        }
+    }
+}
+
 #[derive(Clone, Copy)]
 pub struct FrameBuilderConfig {
     pub enable_scrollbars: bool,
+    pub scrollbar_style: ScrollbarStyle,
     pub enable_subpixel_aa: bool,
     pub debug: bool,
 }
 
 impl FrameBuilderConfig {
     pub fn new(enable_scrollbars: bool,
+               scrollbar_style: ScrollbarStyle,
                enable_subpixel_aa: bool,
                debug: bool)
                -> FrameBuilderConfig {
         FrameBuilderConfig {
             enable_scrollbars: enable_scrollbars,
+            scrollbar_style: scrollbar_style,
             enable_subpixel_aa: enable_subpixel_aa,
             debug: debug,
         }
@@ -106,6 +354,7 @@ impl FrameBuilderConfig {
 pub struct FrameBuilder {
     screen_size: DeviceUintSize,
     background_color: Option<ColorF>,
+    device_pixel_ratio: f32,
     prim_store: PrimitiveStore,
     cmds: Vec<PrimitiveRunCmd>,
     config: FrameBuilderConfig,
@@ -123,15 +372,25 @@ pub struct FrameBuilder {
     /// A stack of stacking contexts used for creating ClipScrollGroups as
     /// primitives are added to the frame.
     stacking_context_stack: Vec<StackingContextIndex>,
+
+    /// A stack of the text shadows currently in effect, each holding the
+    /// text items that will be drawn on top of it once it is popped.
+    text_shadow_stack: Vec<TextShadowScope>,
+
+    /// The blocks of split plane geometry in the GPU store. This is rebuilt
+    /// on every frame, so the blocks are freed at the start of the next build.
+    split_geometry_blocks: Vec<(GpuStoreAddress, usize)>,
 }
 
 impl FrameBuilder {
     pub fn new(screen_size: DeviceUintSize,
                background_color: Option<ColorF>,
+               device_pixel_ratio: f32,
                config: FrameBuilderConfig) -> FrameBuilder {
         FrameBuilder {
             screen_size: screen_size,
             background_color: background_color,
+            device_pixel_ratio: device_pixel_ratio,
             stacking_context_store: Vec::new(),
             clip_scroll_group_store: Vec::new(),
             prim_store: PrimitiveStore::new(),
@@ -141,17 +400,65 @@ impl FrameBuilder {
             config: config,
             reference_frame_stack: Vec::new(),
             stacking_context_stack: Vec::new(),
+            text_shadow_stack: Vec::new(),
+            split_geometry_blocks: Vec::new(),
         }
     }
 
+    /// Adds a primitive to the current stacking context, unless a text
+    /// shadow is in effect for it, in which case the primitive is deferred
+    /// until the shadow is flushed.
     fn add_primitive(&mut self,
                      scroll_layer_id: ScrollLayerId,
                      rect: &LayerRect,
                      clip_region: &ClipRegion,
                      extra_clip: Option<ClipSource>,
                      container: PrimitiveContainer)
-                     -> PrimitiveIndex {
+                     -> Option<PrimitiveIndex> {
         let stacking_context_index = *self.stacking_context_stack.last().unwrap();
+        if let Some(scope) = self.text_shadow_stack.last_mut() {
+            if scope.stacking_context_index == stacking_context_index {
+                scope.items.push(DeferredItem::Primitive(PendingPrimitive {
+                    scroll_layer_id: scroll_layer_id,
+                    rect: *rect,
+                    clip_region: *clip_region,
+                    extra_clip: extra_clip,
+                    container: container,
+                }));
+                return None;
+            }
+        }
+
+        Some(self.create_primitive(stacking_context_index,
+                                   scroll_layer_id,
+                                   rect,
+                                   clip_region,
+                                   extra_clip,
+                                   container))
+    }
+
+    fn add_pending_primitive(&mut self,
+                             stacking_context_index: StackingContextIndex,
+                             prim: PendingPrimitive) {
+        self.create_primitive(stacking_context_index,
+                              prim.scroll_layer_id,
+                              &prim.rect,
+                              &prim.clip_region,
+                              prim.extra_clip,
+                              prim.container);
+    }
+
+    fn create_primitive(&mut self,
+                        stacking_context_index: StackingContextIndex,
+                        scroll_layer_id: ScrollLayerId,
+                        rect: &LayerRect,
+                        clip_region: &ClipRegion,
+                        extra_clip: Option<ClipSource>,
+                        container: PrimitiveContainer)
+                        -> PrimitiveIndex {
+        // Primitive runs are recorded in order, so primitives can only be
+        // added to the stacking context that is currently open.
+        debug_assert_eq!(self.stacking_context_stack.last(), Some(&stacking_context_index));
         if !self.stacking_context_store[stacking_context_index.0]
                 .has_clip_scroll_group(scroll_layer_id) {
             let group_index = self.create_clip_scroll_group(stacking_context_index,
@@ -219,28 +526,55 @@ impl FrameBuilder {
                                  reference_frame_offset: &LayerPoint,
                                  pipeline_id: PipelineId,
                                  is_page_root: bool,
-                                 composite_ops: CompositeOps) {
+                                 transform_style: TransformStyle,
+                                 backface_visible: bool,
+                                 size: &LayerSize,
+                                 mut composite_ops: CompositeOps) {
+        // The contents of the new stacking context are drawn on top of the
+        // items added to the current one so far, and their text shadows.
+        self.flush_text_shadows();
+
+        let stacking_context_index = StackingContextIndex(self.stacking_context_store.len());
+        let mut is_3d_plane = false;
+
+        for filter_graph in &mut composite_ops.filter_graphs {
+            filter_graph.write_gpu_data(&mut self.prim_store.gpu_data32);
+        }
+
         if let Some(parent_index) = self.stacking_context_stack.last() {
-            let parent_is_root = self.stacking_context_store[parent_index.0].is_page_root;
+            let parent = &mut self.stacking_context_store[parent_index.0];
 
-            if composite_ops.mix_blend_mode.is_some() && !parent_is_root {
+            if composite_ops.mix_blend_mode.is_some() && !parent.is_page_root {
                 // the parent stacking context of a stacking context with mix-blend-mode
                 // must be drawn with a transparent background, unless the parent stacking context
                 // is the root of the page
-                self.stacking_context_store[parent_index.0].should_isolate = true;
+                parent.should_isolate = true;
+            }
+
+            if parent.transform_style == TransformStyle::Preserve3D {
+                parent.planes.push(stacking_context_index);
+                is_3d_plane = true;
             }
         }
 
-        let stacking_context_index = StackingContextIndex(self.stacking_context_store.len());
-        self.stacking_context_store.push(StackingContext::new(pipeline_id,
-                                                              *reference_frame_offset,
-                                                              is_page_root,
-                                                              composite_ops));
+        let reference_frame_id = self.current_reference_frame_id();
+        let local_bounds = LayerRect::new(*reference_frame_offset, *size);
+        let mut stacking_context = StackingContext::new(pipeline_id,
+                                                        *reference_frame_offset,
+                                                        reference_frame_id,
+                                                        local_bounds,
+                                                        is_page_root,
+                                                        transform_style,
+                                                        backface_visible,
+                                                        composite_ops);
+        stacking_context.is_3d_plane = is_3d_plane;
+        self.stacking_context_store.push(stacking_context);
         self.cmds.push(PrimitiveRunCmd::PushStackingContext(stacking_context_index));
         self.stacking_context_stack.push(stacking_context_index);
     }
 
     pub fn pop_stacking_context(&mut self) {
+        self.flush_text_shadows();
         self.cmds.push(PrimitiveRunCmd::PopStackingContext);
         self.stacking_context_stack.pop();
     }
@@ -298,11 +632,16 @@ impl FrameBuilder {
                      pipeline_id: PipelineId,
                      viewport_size: &LayerSize,
                      content_size: &LayerSize,
+                     zoom_limits: Option<ZoomLimits>,
                      clip_scroll_tree: &mut ClipScrollTree)
                      -> ScrollLayerId {
         let viewport_rect = LayerRect::new(LayerPoint::zero(), *viewport_size);
         let identity = &LayerToScrollTransform::identity();
-        self.push_reference_frame(None, pipeline_id, &viewport_rect, identity, clip_scroll_tree);
+        let root_id =
+            self.push_reference_frame(None, pipeline_id, &viewport_rect, identity, clip_scroll_tree);
+        if let Some(root_node) = clip_scroll_tree.nodes.get_mut(&root_id) {
+            root_node.zoom_limits = zoom_limits;
+        }
 
         let topmost_scroll_layer_id = ScrollLayerId::root_scroll_layer(pipeline_id);
         clip_scroll_tree.topmost_scroll_layer_id = topmost_scroll_layer_id;
@@ -312,6 +651,9 @@ impl FrameBuilder {
                                    &viewport_rect,
                                    content_size,
                                    &ClipRegion::simple(&viewport_rect),
+                                   None,
+                                   OverscrollBehavior::default(),
+                                   None,
                                    clip_scroll_tree);
         topmost_scroll_layer_id
     }
@@ -323,6 +665,9 @@ impl FrameBuilder {
                                 local_viewport_rect: &LayerRect,
                                 content_size: &LayerSize,
                                 clip_region: &ClipRegion,
+                                scroll_snap: Option<ScrollSnapPoints>,
+                                overscroll: OverscrollBehavior,
+                                zoom_limits: Option<ZoomLimits>,
                                 clip_scroll_tree: &mut ClipScrollTree) {
         let clip_info = ClipInfo::new(clip_region,
                                       &mut self.prim_store.gpu_data32,
@@ -331,7 +676,10 @@ impl FrameBuilder {
                                        parent_id,
                                        local_viewport_rect,
                                        *content_size,
-                                       clip_info);
+                                       clip_info,
+                                       scroll_snap,
+                                       overscroll,
+                                       zoom_limits);
 
         clip_scroll_tree.add_node(node, new_node_id);
         self.packed_layers.push(PackedLayer::empty());
@@ -355,42 +703,55 @@ impl FrameBuilder {
             color: *color,
         };
 
-        let prim_index = self.add_primitive(scroll_layer_id,
-                                            rect,
-                                            clip_region,
-                                            None,
-                                            PrimitiveContainer::Rectangle(prim));
-
         match flags {
-            PrimitiveFlags::None => {}
-            PrimitiveFlags::Scrollbar(scroll_layer_id, border_radius) => {
+            PrimitiveFlags::None => {
+                self.add_primitive(scroll_layer_id,
+                                   rect,
+                                   clip_region,
+                                   None,
+                                   PrimitiveContainer::Rectangle(prim));
+            }
+            PrimitiveFlags::Scrollbar(scrollbar_layer_id, axis) => {
+                // Scrollbars are overlays, so text shadows never apply to them.
+                let stacking_context_index = *self.stacking_context_stack.last().unwrap();
+                let prim_index = self.create_primitive(stacking_context_index,
+                                                       scroll_layer_id,
+                                                       rect,
+                                                       clip_region,
+                                                       None,
+                                                       PrimitiveContainer::Rectangle(prim));
                 self.scrollbar_prims.push(ScrollbarPrimitive {
                     prim_index: prim_index,
-                    scroll_layer_id: scroll_layer_id,
-                    border_radius: border_radius,
+                    scroll_layer_id: scrollbar_layer_id,
+                    axis: axis,
                 });
             }
         }
     }
 
-    pub fn supported_style(&mut self, border: &BorderSide) -> bool {
-        match border.style {
-            BorderStyle::Solid |
-            BorderStyle::None |
-            BorderStyle::Dotted |
-            BorderStyle::Dashed |
-            BorderStyle::Inset |
-            BorderStyle::Ridge |
-            BorderStyle::Groove |
-            BorderStyle::Outset |
-            BorderStyle::Double => {
-                return true;
-            }
-            _ => {
-                println!("TODO: Other border styles {:?}", border.style);
-                return false;
-            }
+    pub fn add_line(&mut self,
+                    scroll_layer_id: ScrollLayerId,
+                    rect: &LayerRect,
+                    clip_region: &ClipRegion,
+                    color: &ColorF,
+                    style: LineStyle,
+                    orientation: LineOrientation) {
+        if color.a == 0.0 {
+            return;
         }
+
+        let prim = LinePrimitive {
+            color: *color,
+            style: style as u32 as f32,
+            orientation: orientation as u32 as f32,
+            padding: [0.0; 2],
+        };
+
+        self.add_primitive(scroll_layer_id,
+                           rect,
+                           clip_region,
+                           None,
+                           PrimitiveContainer::Line(prim));
     }
 
     pub fn add_border(&mut self,
@@ -548,9 +909,10 @@ impl FrameBuilder {
                 let top = &border.top;
                 let bottom = &border.bottom;
 
-                if !self.supported_style(left) || !self.supported_style(right) ||
-                   !self.supported_style(top) || !self.supported_style(bottom) {
-                    println!("Unsupported border style, not rendering border");
+                // Sides with a none or hidden style are never drawn. If that's
+                // true of all four sides, there is nothing left to render.
+                if !left.is_visible() && !top.is_visible() &&
+                   !right.is_visible() && !bottom.is_visible() {
                     return;
                 }
 
@@ -668,7 +1030,8 @@ impl FrameBuilder {
                                       border.gradient.start_point,
                                       border.gradient.end_point,
                                       border.gradient.stops,
-                                      border.gradient.extend_mode);
+                                      border.gradient.extend_mode,
+                                      border.gradient.interpolation);
                 }
             }
             BorderDetails::RadialGradient(ref border) => {
@@ -682,7 +1045,20 @@ impl FrameBuilder {
                                              border.gradient.end_radius,
                                              border.gradient.ratio_xy,
                                              border.gradient.stops,
-                                             border.gradient.extend_mode);
+                                             border.gradient.extend_mode,
+                                             border.gradient.interpolation);
+                }
+            }
+            BorderDetails::ConicGradient(ref border) => {
+                for segment in create_segments(border.outset) {
+                    self.add_conic_gradient(scroll_layer_id,
+                                            segment,
+                                            clip_region,
+                                            border.gradient.center,
+                                            border.gradient.angle,
+                                            border.gradient.stops,
+                                            border.gradient.extend_mode,
+                                            border.gradient.interpolation);
                 }
             }
         }
@@ -695,9 +1071,12 @@ impl FrameBuilder {
                         start_point: LayerPoint,
                         end_point: LayerPoint,
                         stops: ItemRange,
-                        extend_mode: ExtendMode) {
-        // Fast path for clamped, axis-aligned gradients:
+                        extend_mode: ExtendMode,
+                        interpolation: GradientInterpolation) {
+        // Fast path for clamped, axis-aligned gradients. The stops are
+        // mixed linearly in the shader, so linear light needs the table.
         let aligned = extend_mode == ExtendMode::Clamp &&
+                      interpolation != GradientInterpolation::LinearLight &&
                       (start_point.x == end_point.x ||
                        start_point.y == end_point.y);
         // Try to ensure that if the gradient is specified in reverse, then so long as the stops
@@ -714,7 +1093,9 @@ impl FrameBuilder {
         let gradient_cpu = GradientPrimitiveCpu {
             stops_range: stops,
             extend_mode: extend_mode,
+            interpolation: interpolation,
             reverse_stops: reverse_stops,
+            entry_count: 0,
             cache_dirty: true,
         };
 
@@ -757,10 +1138,13 @@ impl FrameBuilder {
                                end_radius: f32,
                                ratio_xy: f32,
                                stops: ItemRange,
-                               extend_mode: ExtendMode) {
+                               extend_mode: ExtendMode,
+                               interpolation: GradientInterpolation) {
         let radial_gradient_cpu = RadialGradientPrimitiveCpu {
             stops_range: stops,
             extend_mode: extend_mode,
+            interpolation: interpolation,
+            entry_count: 0,
             cache_dirty: true,
         };
 
@@ -780,6 +1164,37 @@ impl FrameBuilder {
                            PrimitiveContainer::RadialGradient(radial_gradient_cpu, radial_gradient_gpu));
     }
 
+    pub fn add_conic_gradient(&mut self,
+                              scroll_layer_id: ScrollLayerId,
+                              rect: LayerRect,
+                              clip_region: &ClipRegion,
+                              center: LayerPoint,
+                              angle: f32,
+                              stops: ItemRange,
+                              extend_mode: ExtendMode,
+                              interpolation: GradientInterpolation) {
+        let conic_gradient_cpu = ConicGradientPrimitiveCpu {
+            stops_range: stops,
+            extend_mode: extend_mode,
+            interpolation: interpolation,
+            entry_count: 0,
+            cache_dirty: true,
+        };
+
+        let conic_gradient_gpu = ConicGradientPrimitiveGpu {
+            center: center,
+            angle: angle,
+            extend_mode: pack_as_float(extend_mode as u32),
+            padding: [0.0; 4],
+        };
+
+        self.add_primitive(scroll_layer_id,
+                           &rect,
+                           clip_region,
+                           None,
+                           PrimitiveContainer::ConicGradient(conic_gradient_cpu, conic_gradient_gpu));
+    }
+
     pub fn add_text(&mut self,
                     scroll_layer_id: ScrollLayerId,
                     rect: LayerRect,
@@ -789,53 +1204,206 @@ impl FrameBuilder {
                     blur_radius: Au,
                     color: &ColorF,
                     glyph_range: ItemRange,
+                    glyphs: &[GlyphInstance],
                     glyph_options: Option<GlyphOptions>) {
-        if color.a == 0.0 {
+        if size.0 <= 0 {
             return
         }
 
-        if size.0 <= 0 {
+        let text_run = TextRunInfo {
+            scroll_layer_id: scroll_layer_id,
+            rect: rect,
+            clip_region: *clip_region,
+            font_key: font_key,
+            size: size,
+            blur_radius: blur_radius,
+            color: *color,
+            glyph_range: glyph_range,
+            glyph_options: glyph_options,
+        };
+
+        // If a text shadow is in effect for the current stacking context,
+        // defer the text until the shadow is flushed, so that the shadow
+        // can be drawn underneath it.
+        let stacking_context_index = *self.stacking_context_stack.last().unwrap();
+        if let Some(scope) = self.text_shadow_stack.last_mut() {
+            if scope.stacking_context_index == stacking_context_index {
+                scope.items.push(DeferredItem::TextRun(text_run, glyphs.to_vec()));
+                return;
+            }
+        }
+
+        let mut prims = Vec::new();
+        self.build_text_runs(&text_run, glyphs, LayerPoint::zero(), color, blur_radius, &mut prims);
+        for prim in prims {
+            self.add_pending_primitive(stacking_context_index, prim);
+        }
+    }
+
+    pub fn push_text_shadow(&mut self, shadow: TextShadow) {
+        let stacking_context_index = *self.stacking_context_stack.last().unwrap();
+        self.text_shadow_stack.push(TextShadowScope {
+            shadow: shadow,
+            stacking_context_index: stacking_context_index,
+            shadows: Vec::new(),
+            items: Vec::new(),
+        });
+    }
+
+    pub fn pop_text_shadow(&mut self) {
+        assert!(!self.text_shadow_stack.is_empty(), "Unbalanced text shadow stack!");
+        let scope_index = self.text_shadow_stack.len() - 1;
+        self.flush_text_shadow(scope_index);
+        self.text_shadow_stack.pop();
+    }
+
+    /// Flushes the text shadows in effect for the current stacking context,
+    /// innermost first. The shadows stay in effect for the items added
+    /// after this.
+    fn flush_text_shadows(&mut self) {
+        let stacking_context_index = match self.stacking_context_stack.last() {
+            Some(index) => *index,
+            None => return,
+        };
+
+        for scope_index in (0..self.text_shadow_stack.len()).rev() {
+            if self.text_shadow_stack[scope_index].stacking_context_index == stacking_context_index {
+                self.flush_text_shadow(scope_index);
+            }
+        }
+    }
+
+    /// Draws the shadows of the text deferred by the text shadow at
+    /// `scope_index`, then all of its deferred items on top, in the order
+    /// they were added. If an enclosing text shadow applies to the same
+    /// stacking context, everything is handed over to it instead, so that
+    /// the text picks up that shadow too.
+    fn flush_text_shadow(&mut self, scope_index: usize) {
+        let (shadow, stacking_context_index, mut shadows, items) = {
+            let scope = &mut self.text_shadow_stack[scope_index];
+            (scope.shadow,
+             scope.stacking_context_index,
+             mem::replace(&mut scope.shadows, Vec::new()),
+             mem::replace(&mut scope.items, Vec::new()))
+        };
+
+        // Shadows of nested text shadows go first, so that the outermost
+        // shadow ends up on top.
+        let blur_radius = Au::from_f32_px(shadow.blur_radius);
+        for item in &items {
+            if let DeferredItem::TextRun(ref text_run, ref glyphs) = *item {
+                self.build_text_runs(text_run,
+                                     glyphs,
+                                     shadow.offset,
+                                     &shadow.color,
+                                     blur_radius,
+                                     &mut shadows);
+            }
+        }
+
+        if scope_index > 0 &&
+           self.text_shadow_stack[scope_index - 1].stacking_context_index == stacking_context_index {
+            let parent = &mut self.text_shadow_stack[scope_index - 1];
+            parent.shadows.extend(shadows);
+            parent.items.extend(items);
+            return;
+        }
+
+        for prim in shadows {
+            self.add_pending_primitive(stacking_context_index, prim);
+        }
+
+        for item in items {
+            match item {
+                DeferredItem::TextRun(text_run, glyphs) => {
+                    let mut prims = Vec::new();
+                    self.build_text_runs(&text_run,
+                                         &glyphs,
+                                         LayerPoint::zero(),
+                                         &text_run.color,
+                                         text_run.blur_radius,
+                                         &mut prims);
+                    for prim in prims {
+                        self.add_pending_primitive(stacking_context_index, prim);
+                    }
+                }
+                DeferredItem::Primitive(prim) => {
+                    self.add_pending_primitive(stacking_context_index, prim);
+                }
+            }
+        }
+    }
+
+    fn build_text_runs(&self,
+                       text_run: &TextRunInfo,
+                       glyphs: &[GlyphInstance],
+                       offset: LayerPoint,
+                       color: &ColorF,
+                       blur_radius: Au,
+                       prims: &mut Vec<PendingPrimitive>) {
+        if color.a == 0.0 {
             return
         }
 
-        let (render_mode, glyphs_per_run) = if blur_radius == Au(0) {
-            // TODO(gw): Use a proper algorithm to select
-            // whether this item should be rendered with
-            // subpixel AA!
+        let blur_px = blur_radius.to_f32_px();
+        let mut sub_ranges = Vec::new();
+        let render_mode = if blur_radius == Au(0) {
+            // Subpixel AA is only requested here. Whether it is actually
+            // used is decided each frame, once the transform of the layer
+            // and the surface the run is drawn into are known.
             let render_mode = if self.config.enable_subpixel_aa {
                 FontRenderMode::Subpixel
             } else {
                 FontRenderMode::Alpha
             };
 
-            (render_mode, 8)
+            let glyphs_per_run = 8;
+            let mut start = 0;
+            while start < glyphs.len() {
+                let end = cmp::min(start + glyphs_per_run, glyphs.len());
+                sub_ranges.push((start, end));
+                start = end;
+            }
+
+            render_mode
         } else {
-            // TODO(gw): Support breaking up text shadow when
-            // the size of the text run exceeds the dimensions
-            // of the render target texture.
-            (FontRenderMode::Alpha, glyph_range.length)
+            // A blurred run is drawn into a render target before being
+            // blurred, so break it up into pieces that are guaranteed to fit.
+            // Each piece is inflated by the blur radius, which makes the blurred
+            // edges of neighbouring pieces overlap and blend together.
+            let max_extent = MIN_CACHE_TARGET_SIZE as f32 / self.device_pixel_ratio -
+                             4.0 * blur_px;
+            split_text_run(glyphs,
+                           text_run.size.to_f32_px(),
+                           max_extent,
+                           &mut sub_ranges);
+
+            // Text runs drawn to blur never get drawn with subpixel AA.
+            FontRenderMode::Alpha
         };
 
-        let text_run_count = (glyph_range.length + glyphs_per_run - 1) / glyphs_per_run;
-        for run_index in 0..text_run_count {
-            let start = run_index * glyphs_per_run;
-            let end = cmp::min(start + glyphs_per_run, glyph_range.length);
+        let rect = text_run.rect.translate(&offset).inflate(blur_px, blur_px);
+
+        for (start, end) in sub_ranges {
             let sub_range = ItemRange {
-                start: glyph_range.start + start,
+                start: text_run.glyph_range.start + start,
                 length: end - start,
             };
 
             let prim_cpu = TextRunPrimitiveCpu {
-                font_key: font_key,
-                logical_font_size: size,
+                font_key: text_run.font_key,
+                logical_font_size: text_run.size,
                 blur_radius: blur_radius,
                 glyph_range: sub_range,
+                offset: offset,
                 cache_dirty: true,
                 glyph_instances: Vec::new(),
                 color_texture_id: SourceTexture::Invalid,
                 color: *color,
+                normal_render_mode: render_mode,
                 render_mode: render_mode,
-                glyph_options: glyph_options,
+                raster_scale: 1.0,
+                glyph_options: text_run.glyph_options,
                 resource_address: GpuStoreAddress(0),
             };
 
@@ -843,153 +1411,223 @@ impl FrameBuilder {
                 color: *color,
             };
 
-            self.add_primitive(scroll_layer_id,
-                               &rect,
-                               clip_region,
-                               None,
-                               PrimitiveContainer::TextRun(prim_cpu, prim_gpu));
+            prims.push(PendingPrimitive {
+                scroll_layer_id: text_run.scroll_layer_id,
+                rect: rect,
+                clip_region: text_run.clip_region,
+                extra_clip: None,
+                container: PrimitiveContainer::TextRun(prim_cpu, prim_gpu),
+            });
         }
     }
 
-    pub fn add_box_shadow(&mut self,
-                          scroll_layer_id: ScrollLayerId,
-                          box_bounds: &LayerRect,
-                          clip_region: &ClipRegion,
-                          box_offset: &LayerPoint,
-                          color: &ColorF,
-                          blur_radius: f32,
-                          spread_radius: f32,
-                          border_radius: f32,
-                          clip_mode: BoxShadowClipMode) {
-        if color.a == 0.0 {
-            return
-        }
-
-        // Fast path.
-        if blur_radius == 0.0 && spread_radius == 0.0 && clip_mode == BoxShadowClipMode::None {
-            self.add_solid_rectangle(scroll_layer_id,
-                                     &box_bounds,
-                                     clip_region,
-                                     color,
-                                     PrimitiveFlags::None);
-            return;
-        }
-
-        // The local space box shadow rect. It is the element rect
-        // translated by the box shadow offset and inflated by the
-        // box shadow spread.
-        let inflate_amount = match clip_mode {
-            BoxShadowClipMode::Outset | BoxShadowClipMode::None => spread_radius,
-            BoxShadowClipMode::Inset => -spread_radius,
+    pub fn add_box_shadows(&mut self,
+                           scroll_layer_id: ScrollLayerId,
+                           box_bounds: &LayerRect,
+                           clip_region: &ClipRegion,
+                           border_radius: BorderRadius,
+                           shadows: &[BoxShadow]) {
+        // The cached corner patch needs to cover the largest corner
+        // radius, in either axis.
+        let corner_radii = [border_radius.top_left,
+                            border_radius.top_right,
+                            border_radius.bottom_left,
+                            border_radius.bottom_right];
+        let max_border_radius = corner_radii.iter().fold(0.0f32, |max, size| {
+            max.max(size.width).max(size.height)
+        });
+
+        // When all four corners match, a single corner is cached
+        // and mirrored over the primitive. Otherwise, each corner
+        // gets its own quadrant of the cached patch.
+        let mirror = match border_radius.is_uniform_size() {
+            Some(..) => 1.0,
+            None => 0.0,
         };
 
-        let bs_rect = box_bounds.translate(box_offset)
-                                .inflate(inflate_amount, inflate_amount);
-
-        // Get the outer rectangle, based on the blur radius.
-        let outside_edge_size = 2.0 * blur_radius;
-        let inside_edge_size = outside_edge_size.max(border_radius);
-        let edge_size = outside_edge_size + inside_edge_size;
-        let outer_rect = bs_rect.inflate(outside_edge_size, outside_edge_size);
-
-        // Box shadows are often used for things like text underline and other
-        // simple primitives, so we want to draw these simple cases with the
-        // solid rectangle shader wherever possible, to avoid invoking the
-        // expensive box-shadow shader.
-        enum BoxShadowKind {
-            Simple(Vec<LayerRect>),     // Can be drawn via simple rectangles only
-            Shadow(Vec<LayerRect>),     // Requires the full box-shadow code path
-        }
+        // Shadows that need the full box-shadow code path are collected
+        // into a single primitive, so that they share cache tasks and are
+        // drawn in one batch. The batch is flushed whenever a shadow that
+        // can be drawn with simple rectangles needs to go on top of it.
+        let mut batch = Vec::new();
 
-        let shadow_kind = match clip_mode {
-            BoxShadowClipMode::Outset | BoxShadowClipMode::None => {
-                // If a border radius is set, we need to draw inside
-                // the original box in order to draw where the border
-                // corners are. A clip-out mask applied below will
-                // ensure that we don't draw on the box itself.
-                let inner_box_bounds = box_bounds.inflate(-border_radius,
-                                                          -border_radius);
-                // For outset shadows, subtracting the element rectangle
-                // from the outer rectangle gives the rectangles we need
-                // to draw. In the simple case (no blur radius), we can
-                // just draw these as solid colors.
-                let mut rects = Vec::new();
-                subtract_rect(&outer_rect, &inner_box_bounds, &mut rects);
-                if edge_size == 0.0 {
-                    BoxShadowKind::Simple(rects)
-                } else {
-                    BoxShadowKind::Shadow(rects)
-                }
+        // Earlier shadows are drawn on top, so walk the list back to front.
+        for shadow in shadows.iter().rev() {
+            if shadow.color.a == 0.0 {
+                continue;
             }
-            BoxShadowClipMode::Inset => {
-                // For inset shadows, in the simple case (no blur) we
-                // can draw the shadow area by subtracting the box
-                // shadow rect from the element rect (since inset box
-                // shadows never extend past the element rect). However,
-                // in the case of an inset box shadow with blur, we
-                // currently just draw the box shadow over the entire
-                // rect. The opaque parts of the shadow (past the outside
-                // edge of the box-shadow) are handled by the shadow
-                // shader.
-                // TODO(gw): We should be able to optimize the complex
-                //           inset shadow case to touch fewer pixels. We
-                //           can probably calculate the inner rect that
-                //           can't be affected, and subtract that from
-                //           the element rect?
-                let mut rects = Vec::new();
-                if edge_size == 0.0 {
-                    subtract_rect(box_bounds, &bs_rect, &mut rects);
-                    BoxShadowKind::Simple(rects)
-                } else {
-                    rects.push(*box_bounds);
-                    BoxShadowKind::Shadow(rects)
-                }
+
+            // Fast path.
+            if shadow.blur_radius == 0.0 &&
+               shadow.spread_radius == 0.0 &&
+               shadow.clip_mode == BoxShadowClipMode::None {
+                self.add_box_shadow_batch(scroll_layer_id,
+                                          box_bounds,
+                                          clip_region,
+                                          border_radius,
+                                          max_border_radius,
+                                          mem::replace(&mut batch, Vec::new()));
+                self.add_solid_rectangle(scroll_layer_id,
+                                         &box_bounds,
+                                         clip_region,
+                                         &shadow.color,
+                                         PrimitiveFlags::None);
+                continue;
             }
-        };
 
-        match shadow_kind {
-            BoxShadowKind::Simple(rects) => {
-                for rect in &rects {
-                    self.add_solid_rectangle(scroll_layer_id,
-                                             rect,
-                                             clip_region,
-                                             color,
-                                             PrimitiveFlags::None)
-                }
+            // The local space box shadow rect. It is the element rect
+            // translated by the box shadow offset and inflated by the
+            // box shadow spread.
+            let inflate_amount = match shadow.clip_mode {
+                BoxShadowClipMode::Outset | BoxShadowClipMode::None => shadow.spread_radius,
+                BoxShadowClipMode::Inset => -shadow.spread_radius,
+            };
+
+            let bs_rect = box_bounds.translate(&shadow.offset)
+                                    .inflate(inflate_amount, inflate_amount);
+
+            // Get the outer rectangle, based on the blur radius.
+            let outside_edge_size = 2.0 * shadow.blur_radius;
+            let inside_edge_size = outside_edge_size.max(max_border_radius);
+            let edge_size = outside_edge_size + inside_edge_size;
+            let outer_rect = bs_rect.inflate(outside_edge_size, outside_edge_size);
+
+            // Box shadows are often used for things like text underline and other
+            // simple primitives, so we want to draw these simple cases with the
+            // solid rectangle shader wherever possible, to avoid invoking the
+            // expensive box-shadow shader.
+            enum BoxShadowKind {
+                Simple(Vec<LayerRect>),     // Can be drawn via simple rectangles only
+                Shadow(Vec<LayerRect>),     // Requires the full box-shadow code path
             }
-            BoxShadowKind::Shadow(rects) => {
-                let inverted = match clip_mode {
-                    BoxShadowClipMode::Outset | BoxShadowClipMode::None => 0.0,
-                    BoxShadowClipMode::Inset => 1.0,
-                };
 
-                // If we have a border radius, we'll need to apply
-                // a clip-out mask.
-                let extra_clip = if border_radius > 0.0 {
-                    Some(ClipSource::Complex(*box_bounds,
-                                             border_radius,
-                                             ClipMode::ClipOut))
-                } else {
-                    None
-                };
+            let shadow_kind = match shadow.clip_mode {
+                BoxShadowClipMode::Outset | BoxShadowClipMode::None => {
+                    // If a border radius is set, we need to draw inside
+                    // the original box in order to draw where the border
+                    // corners are. A clip-out mask applied below will
+                    // ensure that we don't draw on the box itself.
+                    let inner_box_bounds = ComplexClipRegion::new(*box_bounds, border_radius)
+                                               .get_inner_rect()
+                                               .unwrap_or(LayerRect::zero());
+                    // For outset shadows, subtracting the element rectangle
+                    // from the outer rectangle gives the rectangles we need
+                    // to draw. In the simple case (no blur radius), we can
+                    // just draw these as solid colors.
+                    let mut rects = Vec::new();
+                    subtract_rect(&outer_rect, &inner_box_bounds, &mut rects);
+                    if edge_size == 0.0 {
+                        BoxShadowKind::Simple(rects)
+                    } else {
+                        BoxShadowKind::Shadow(rects)
+                    }
+                }
+                BoxShadowClipMode::Inset => {
+                    // For inset shadows, in the simple case (no blur) we
+                    // can draw the shadow area by subtracting the box
+                    // shadow rect from the element rect (since inset box
+                    // shadows never extend past the element rect). However,
+                    // in the case of an inset box shadow with blur, we
+                    // currently just draw the box shadow over the entire
+                    // rect. The opaque parts of the shadow (past the outside
+                    // edge of the box-shadow) are handled by the shadow
+                    // shader.
+                    // TODO(gw): We should be able to optimize the complex
+                    //           inset shadow case to touch fewer pixels. We
+                    //           can probably calculate the inner rect that
+                    //           can't be affected, and subtract that from
+                    //           the element rect?
+                    let mut rects = Vec::new();
+                    if edge_size == 0.0 {
+                        subtract_rect(box_bounds, &bs_rect, &mut rects);
+                        BoxShadowKind::Simple(rects)
+                    } else {
+                        rects.push(*box_bounds);
+                        BoxShadowKind::Shadow(rects)
+                    }
+                }
+            };
 
-                let prim_gpu = BoxShadowPrimitiveGpu {
-                    src_rect: *box_bounds,
-                    bs_rect: bs_rect,
-                    color: *color,
-                    blur_radius: blur_radius,
-                    border_radius: border_radius,
-                    edge_size: edge_size,
-                    inverted: inverted,
-                };
+            match shadow_kind {
+                BoxShadowKind::Simple(rects) => {
+                    self.add_box_shadow_batch(scroll_layer_id,
+                                              box_bounds,
+                                              clip_region,
+                                              border_radius,
+                                              max_border_radius,
+                                              mem::replace(&mut batch, Vec::new()));
+                    for rect in &rects {
+                        self.add_solid_rectangle(scroll_layer_id,
+                                                 rect,
+                                                 clip_region,
+                                                 &shadow.color,
+                                                 PrimitiveFlags::None)
+                    }
+                }
+                BoxShadowKind::Shadow(rects) => {
+                    let inverted = match shadow.clip_mode {
+                        BoxShadowClipMode::Outset | BoxShadowClipMode::None => 0.0,
+                        BoxShadowClipMode::Inset => 1.0,
+                    };
 
-                self.add_primitive(scroll_layer_id,
-                                   &outer_rect,
-                                   clip_region,
-                                   extra_clip,
-                                   PrimitiveContainer::BoxShadow(prim_gpu, rects));
+                    let prim_gpu = BoxShadowPrimitiveGpu {
+                        src_rect: *box_bounds,
+                        bs_rect: bs_rect,
+                        color: shadow.color,
+                        border_radii: corner_radii,
+                        edge_size: edge_size,
+                        blur_radius: shadow.blur_radius,
+                        inverted: inverted,
+                        mirror: mirror,
+                        padding: [0.0; 8],
+                    };
+
+                    batch.push((prim_gpu, outer_rect, rects));
+                }
             }
         }
+
+        self.add_box_shadow_batch(scroll_layer_id,
+                                  box_bounds,
+                                  clip_region,
+                                  border_radius,
+                                  max_border_radius,
+                                  batch);
+    }
+
+    fn add_box_shadow_batch(&mut self,
+                            scroll_layer_id: ScrollLayerId,
+                            box_bounds: &LayerRect,
+                            clip_region: &ClipRegion,
+                            border_radius: BorderRadius,
+                            max_border_radius: f32,
+                            shadows: Vec<(BoxShadowPrimitiveGpu, LayerRect, Vec<LayerRect>)>) {
+        let local_rect = match shadows.split_first() {
+            Some((&(_, first_rect, _), rest)) => {
+                rest.iter().fold(first_rect, |rect, &(_, outer_rect, _)| rect.union(&outer_rect))
+            }
+            None => return,
+        };
+
+        // If we have a border radius, we'll need to apply
+        // a clip-out mask.
+        let extra_clip = if max_border_radius > 0.0 {
+            Some(ClipSource::Complex(*box_bounds,
+                                     border_radius,
+                                     ClipMode::ClipOut))
+        } else {
+            None
+        };
+
+        let shadows = shadows.into_iter()
+                             .map(|(prim_gpu, _, rects)| (prim_gpu, rects))
+                             .collect();
+
+        self.add_primitive(scroll_layer_id,
+                           &local_rect,
+                           clip_region,
+                           extra_clip,
+                           PrimitiveContainer::BoxShadow(shadows));
     }
 
     pub fn add_webgl_rectangle(&mut self,
@@ -1002,6 +1640,7 @@ impl FrameBuilder {
             color_texture_id: SourceTexture::Invalid,
             resource_address: GpuStoreAddress(0),
             sub_rect: None,
+            raster_scale: 1.0,
         };
 
         let prim_gpu = ImagePrimitiveGpu {
@@ -1034,6 +1673,7 @@ impl FrameBuilder {
             color_texture_id: SourceTexture::Invalid,
             resource_address: GpuStoreAddress(0),
             sub_rect: sub_rect,
+            raster_scale: 1.0,
         };
 
         let prim_gpu = ImagePrimitiveGpu {
@@ -1078,7 +1718,9 @@ impl FrameBuilder {
                                                 screen_rect: &DeviceIntRect,
                                                 clip_scroll_tree: &mut ClipScrollTree,
                                                 auxiliary_lists_map: &AuxiliaryListsMap,
+                                                pipeline_epoch_map: &PipelineEpochMap,
                                                 resource_cache: &mut ResourceCache,
+                                                mask_store: &mut ClipMaskStore,
                                                 profile_counters: &mut FrameProfileCounters,
                                                 device_pixel_ratio: f32) {
         profile_scope!("cull");
@@ -1086,57 +1728,387 @@ impl FrameBuilder {
                                                            screen_rect,
                                                            clip_scroll_tree,
                                                            auxiliary_lists_map,
+                                                           pipeline_epoch_map,
                                                            resource_cache,
+                                                           mask_store,
                                                            profile_counters,
                                                            device_pixel_ratio);
     }
 
     fn update_scroll_bars(&mut self, clip_scroll_tree: &ClipScrollTree) {
-        let distance_from_edge = 8.0;
+        let style = self.config.scrollbar_style;
+        let radius = style.radius.min(style.width * 0.5);
 
         for scrollbar_prim in &self.scrollbar_prims {
-            let mut geom = (*self.prim_store.gpu_geometry.get(GpuStoreAddress(scrollbar_prim.prim_index.0 as i32))).clone();
+            let geom_address = GpuStoreAddress(scrollbar_prim.prim_index.0 as i32);
+            let mut geom = (*self.prim_store.gpu_geometry.get(geom_address)).clone();
             let clip_scroll_node = &clip_scroll_tree.nodes[&scrollbar_prim.scroll_layer_id];
 
-            let scrollable_distance = clip_scroll_node.scrollable_height();
+            let thumb_rect = match clip_scroll_node.scrollbar_thumb_rect(scrollbar_prim.axis, &style) {
+                Some(thumb_rect) if clip_scroll_node.is_showing_scrollbars() => thumb_rect,
+                _ => {
+                    geom.local_clip_rect.size = LayerSize::zero();
+                    *self.prim_store.gpu_geometry.get_mut(geom_address) = geom;
+                    continue;
+                }
+            };
 
-            if scrollable_distance <= 0.0 {
-                geom.local_clip_rect.size = LayerSize::zero();
-                *self.prim_store.gpu_geometry.get_mut(GpuStoreAddress(scrollbar_prim.prim_index.0 as i32)) = geom;
-                continue;
+            // The thumb belongs to the scroll frame itself, so take the scroll
+            // offset and zoom back out to keep it still while the content moves.
+            geom.local_rect = clip_scroll_node.content_space_rect(&thumb_rect);
+            geom.local_clip_rect = geom.local_rect;
+
+            let clip_source = if radius > 0.0 {
+                Some(ClipSource::Complex(geom.local_rect,
+                                         BorderRadius::uniform(radius),
+                                         ClipMode::Clip))
+            } else {
+                None
+            };
+            self.prim_store.set_clip_source(scrollbar_prim.prim_index, clip_source);
+
+            let opacity = clip_scroll_node.scrolling.scrollbar_opacity;
+            let color = ColorF::new(style.color.r, style.color.g, style.color.b, style.color.a * opacity);
+            self.prim_store.set_rectangle_color(scrollbar_prim.prim_index, color);
+            *self.prim_store.gpu_geometry.get_mut(geom_address) = geom;
+        }
+    }
+
+    /// Evaluate the opacity filters which follow a scroll offset, so they keep
+    /// up with asynchronous scrolling without the scene being rebuilt. Bound
+    /// opacities which don't follow one keep the amount they were flattened with.
+    fn update_scroll_linked_opacities(&mut self,
+                                      clip_scroll_tree: &ClipScrollTree,
+                                      properties: &SceneProperties) {
+        for stacking_context in &mut self.stacking_context_store {
+            let composite_ops = &mut stacking_context.composite_ops;
+            for &(filter_index, binding_id, amount) in &composite_ops.scroll_linked_opacities {
+                let amount = match properties.scroll_linked_float(binding_id) {
+                    Some(linked) => {
+                        let position = clip_scroll_tree.scroll_position(&linked.scroll_layer_id,
+                                                                        linked.axis);
+                        linked.value_at(position).unwrap_or(1.0)
+                    }
+                    None => amount,
+                };
+                composite_ops.filters[filter_index] = LowLevelFilterOp::Opacity(Au::from_f32_px(amount));
             }
+        }
+    }
 
-            let f = -clip_scroll_node.scrolling.offset.y / scrollable_distance;
+    /// Sorts the planes of each 3D rendering context from back to front,
+    /// splitting any planes that intersect each other.
+    fn build_split_planes(&mut self,
+                          clip_scroll_tree: &ClipScrollTree,
+                          device_pixel_ratio: f32) {
+        profile_scope!("build_split_planes");
 
-            let min_y = clip_scroll_node.local_viewport_rect.origin.y -
-                        clip_scroll_node.scrolling.offset.y +
-                        distance_from_edge;
+        for (address, count) in self.split_geometry_blocks.drain(..) {
+            self.prim_store.gpu_data32.free(address, count);
+        }
 
-            let max_y = clip_scroll_node.local_viewport_rect.origin.y +
-                        clip_scroll_node.local_viewport_rect.size.height -
-                        clip_scroll_node.scrolling.offset.y -
-                        geom.local_rect.size.height -
-                        distance_from_edge;
+        for stacking_context_index in 0..self.stacking_context_store.len() {
+            let polygons = {
+                let stacking_context = &self.stacking_context_store[stacking_context_index];
+                if !stacking_context.is_visible || stacking_context.planes.is_empty() {
+                    continue;
+                }
 
-            geom.local_rect.origin.x = clip_scroll_node.local_viewport_rect.origin.x +
-                                       clip_scroll_node.local_viewport_rect.size.width -
-                                       geom.local_rect.size.width -
-                                       distance_from_edge;
+                let mut splitter = BspSplitter::new();
+                for (anchor, plane_index) in stacking_context.planes.iter().enumerate() {
+                    let plane = &self.stacking_context_store[plane_index.0];
+                    if !plane.is_visible {
+                        continue;
+                    }
 
-            geom.local_rect.origin.y = util::lerp(min_y, max_y, f);
-            geom.local_clip_rect = geom.local_rect;
+                    let node = &clip_scroll_tree.nodes[&plane.reference_frame_id];
+                    if let Some(polygon) = Polygon::from_transformed_rect(&plane.local_bounds,
+                                                                          &node.world_content_transform,
+                                                                          anchor) {
+                        splitter.add(polygon);
+                    }
+                }
 
-            let clip_source = if scrollbar_prim.border_radius > 0.0 {
-                Some(ClipSource::Complex(geom.local_rect, scrollbar_prim.border_radius, ClipMode::Clip))
-            } else {
-                None
+                splitter.sort()
             };
-            self.prim_store.set_clip_source(scrollbar_prim.prim_index, clip_source);
-            *self.prim_store.gpu_geometry.get_mut(GpuStoreAddress(scrollbar_prim.prim_index.0 as i32)) = geom;
+
+            let block_address = self.prim_store.gpu_data32.alloc(polygons.len());
+            self.split_geometry_blocks.push((block_address, polygons.len()));
+
+            let mut split_polygons = Vec::with_capacity(polygons.len());
+            for (index, polygon) in polygons.into_iter().enumerate() {
+                let mut geometry = SplitGeometry {
+                    points: [DevicePoint::zero(); 4],
+                };
+                for (device_point, point) in geometry.points.iter_mut().zip(polygon.points.iter()) {
+                    *device_point = DevicePoint::new(point.x * device_pixel_ratio,
+                                                     point.y * device_pixel_ratio);
+                }
+
+                let address = block_address + index;
+                *self.prim_store.gpu_data32.get_mut(address) = GpuBlock32::from(geometry);
+
+                let plane_index = self.stacking_context_store[stacking_context_index].planes[polygon.anchor];
+                split_polygons.push((plane_index, address));
+            }
+
+            self.stacking_context_store[stacking_context_index].split_polygons = split_polygons;
+        }
+    }
+
+    /// Whether the contents of a stacking context can be kept in the picture
+    /// cache. Only stacking contexts that are drawn into a surface of their
+    /// own are cached, and only if that surface covers everything they draw
+    /// and doesn't depend on what is drawn beneath them.
+    fn is_cacheable_picture(&self, stacking_context: &StackingContext) -> bool {
+        let composite_ops = &stacking_context.composite_ops;
+        let rect = &stacking_context.bounding_rect;
+
+        let has_offset_filter = composite_ops.filters.iter().any(|filter| {
+            match *filter {
+                LowLevelFilterOp::DropShadow(..) |
+                LowLevelFilterOp::Graph(..) => true,
+                _ => false,
+            }
+        });
+
+        stacking_context.is_visible &&
+        !stacking_context.is_3d_plane &&
+        stacking_context.transform_style == TransformStyle::Flat &&
+        (stacking_context.should_isolate || !composite_ops.filters.is_empty()) &&
+        composite_ops.mix_blend_mode.is_none() &&
+        composite_ops.backdrop_filters.is_empty() &&
+        !has_offset_filter &&
+        rect.size.width > 0 && rect.size.height > 0 &&
+        (rect.size.width as u32) <= PICTURE_CACHE_SIZE &&
+        (rect.size.height as u32) <= PICTURE_CACHE_SIZE
+    }
+
+    /// Whether a primitive can change without anything in its signature
+    /// changing. Scrollbars are updated in place every frame, and external
+    /// images and WebGL canvases are updated without bumping an epoch.
+    fn is_cacheable_primitive(&self,
+                              prim_index: PrimitiveIndex,
+                              resource_cache: &ResourceCache) -> bool {
+        if self.scrollbar_prims.iter().any(|scrollbar| scrollbar.prim_index == prim_index) {
+            return false;
+        }
+
+        let metadata = self.prim_store.get_metadata(prim_index);
+        match metadata.prim_kind {
+            PrimitiveKind::Image => {
+                match self.prim_store.cpu_images[metadata.cpu_prim_index.0].kind {
+                    ImagePrimitiveKind::Image(image_key, ..) => {
+                        resource_cache.get_image_properties(image_key).external_image.is_none()
+                    }
+                    ImagePrimitiveKind::WebGL(..) => false,
+                }
+            }
+            PrimitiveKind::YuvImage => {
+                let image_cpu = &self.prim_store.cpu_yuv_images[metadata.cpu_prim_index.0];
+                image_cpu.yuv_key.iter().all(|image_key| {
+                    resource_cache.get_image_properties(*image_key).external_image.is_none()
+                })
+            }
+            _ => true,
+        }
+    }
+
+    fn hash_primitive<H: Hasher>(&self,
+                                 prim_index: PrimitiveIndex,
+                                 first_prim_index: PrimitiveIndex,
+                                 bounding_rect: &DeviceIntRect,
+                                 origin: DeviceIntPoint,
+                                 resource_cache: &ResourceCache,
+                                 state: &mut H) {
+        let metadata = self.prim_store.get_metadata(prim_index);
+        (prim_index.0 - first_prim_index.0).hash(state);
+        hash_device_rect(bounding_rect, origin, state);
+
+        match metadata.prim_kind {
+            PrimitiveKind::TextRun => {
+                let text = &self.prim_store.cpu_text_runs[metadata.cpu_prim_index.0];
+                hash_f32(text.raster_scale, state);
+                text.render_mode.hash(state);
+            }
+            PrimitiveKind::Image => {
+                let image_cpu = &self.prim_store.cpu_images[metadata.cpu_prim_index.0];
+                if let ImagePrimitiveKind::Image(image_key, ..) = image_cpu.kind {
+                    resource_cache.get_image_properties(image_key).epoch.hash(state);
+                }
+            }
+            PrimitiveKind::YuvImage => {
+                let image_cpu = &self.prim_store.cpu_yuv_images[metadata.cpu_prim_index.0];
+                for image_key in &image_cpu.yuv_key {
+                    resource_cache.get_image_properties(*image_key).epoch.hash(state);
+                }
+            }
+            _ => {}
+        }
+
+        metadata.clip_task.is_some().hash(state);
+        if let Some(ref clip_task) = metadata.clip_task {
+            if let RenderTaskKind::CacheMask(ref mask_task) = clip_task.kind {
+                hash_device_rect(&mask_task.actual_rect, origin, state);
+                for &(packed_layer_index, ref clip_info) in &mask_task.clips {
+                    hash_packed_layer(&self.packed_layers[packed_layer_index.0],
+                                      origin,
+                                      self.device_pixel_ratio,
+                                      state);
+                    if let Some((ref image_mask, _)) = clip_info.image {
+                        resource_cache.get_image_properties(image_mask.image).epoch.hash(state);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Decide which stacking contexts are drawn from the picture cache this
+    /// frame, and which are stored in it, indexed by stacking context.
+    fn build_picture_cache_ops(&self,
+                               clip_scroll_tree: &ClipScrollTree,
+                               pipeline_epoch_map: &PipelineEpochMap,
+                               picture_cache: &mut PictureCache,
+                               resource_cache: &ResourceCache)
+                               -> Vec<Option<PictureCacheOp>> {
+        profile_scope!("build_picture_cache_ops");
+
+        let mut picture_ops = vec![None; self.stacking_context_store.len()];
+        let mut sc_stack = Vec::new();
+        let mut signatures: Vec<PictureSignature> = Vec::new();
+        let mut pipeline_sc_counts: HashMap<PipelineId, usize, BuildHasherDefault<FnvHasher>> =
+            HashMap::with_hasher(Default::default());
+
+        for cmd in &self.cmds {
+            match *cmd {
+                PrimitiveRunCmd::PushStackingContext(stacking_context_index) => {
+                    let stacking_context = &self.stacking_context_store[stacking_context_index.0];
+                    sc_stack.push(stacking_context_index);
+
+                    let pipeline_sc_index = {
+                        let count = pipeline_sc_counts.entry(stacking_context.pipeline_id).or_insert(0);
+                        *count += 1;
+                        *count - 1
+                    };
+
+                    for signature in &mut signatures {
+                        let state = &mut signature.hasher;
+                        (stacking_context_index.0 - signature.stacking_context_index.0).hash(state);
+                        stacking_context.is_visible.hash(state);
+                        hash_device_rect(&stacking_context.bounding_rect, signature.origin, state);
+                        stacking_context.composite_ops.filters.hash(state);
+                        stacking_context.composite_ops.backdrop_filters.hash(state);
+                        stacking_context.composite_ops.mix_blend_mode.hash(state);
+                    }
+
+                    if !self.is_cacheable_picture(stacking_context) {
+                        continue;
+                    }
+
+                    // Trailing opacity filters are applied when the picture is
+                    // composited, so they are left out of the signature.
+                    let filter_count = stacking_context.composite_ops.picture_filter_count();
+                    let size = stacking_context.bounding_rect.size;
+                    let mut hasher = FnvHasher::default();
+                    (size.width, size.height).hash(&mut hasher);
+                    hash_f32(self.device_pixel_ratio, &mut hasher);
+                    stacking_context.composite_ops.filters[..filter_count].hash(&mut hasher);
+
+                    signatures.push(PictureSignature {
+                        stacking_context_index: stacking_context_index,
+                        picture_id: PictureId {
+                            pipeline_id: stacking_context.pipeline_id,
+                            epoch: pipeline_epoch_map[&stacking_context.pipeline_id],
+                            index: pipeline_sc_index,
+                        },
+                        origin: stacking_context.bounding_rect.origin,
+                        first_prim_index: None,
+                        hasher: hasher,
+                        is_cacheable: true,
+                    });
+                }
+                PrimitiveRunCmd::PopStackingContext => {
+                    let stacking_context_index = sc_stack.pop().unwrap();
+                    let is_picture = signatures.last().map_or(false, |signature| {
+                        signature.stacking_context_index == stacking_context_index
+                    });
+                    if !is_picture {
+                        continue;
+                    }
+
+                    let signature = signatures.pop().unwrap();
+                    if signature.is_cacheable {
+                        let stacking_context = &self.stacking_context_store[stacking_context_index.0];
+                        picture_ops[stacking_context_index.0] =
+                            picture_cache.request(signature.picture_id,
+                                                  stacking_context.pipeline_id,
+                                                  PictureKey(signature.hasher.finish()),
+                                                  stacking_context.bounding_rect.size);
+                    }
+                }
+                PrimitiveRunCmd::PrimitiveRun(first_prim_index, prim_count, scroll_layer_id) => {
+                    let stacking_context_index = *sc_stack.last().unwrap();
+                    let stacking_context = &self.stacking_context_store[stacking_context_index.0];
+
+                    if signatures.is_empty() || !stacking_context.is_visible {
+                        continue;
+                    }
+
+                    let group_index = stacking_context.clip_scroll_group(scroll_layer_id);
+                    let group = &self.clip_scroll_group_store[group_index.0];
+                    if !group.is_visible() {
+                        continue;
+                    }
+
+                    // The primitives of a run come from the display list of
+                    // its clip or scroll node's pipeline, and are positioned
+                    // by those of the node's ancestors.
+                    let packed_layer = &self.packed_layers[group.packed_layer_index.0];
+                    for signature in &mut signatures {
+                        hash_pipeline_epochs(clip_scroll_tree,
+                                             scroll_layer_id,
+                                             pipeline_epoch_map,
+                                             &mut signature.hasher);
+                        hash_packed_layer(packed_layer,
+                                          signature.origin,
+                                          self.device_pixel_ratio,
+                                          &mut signature.hasher);
+                    }
+
+                    for i in 0..prim_count {
+                        let prim_index = PrimitiveIndex(first_prim_index.0 + i);
+                        let bounding_rect = match self.prim_store.cpu_bounding_rects[prim_index.0] {
+                            Some(ref bounding_rect) => bounding_rect,
+                            None => continue,
+                        };
+
+                        let is_cacheable = self.is_cacheable_primitive(prim_index, resource_cache);
+                        for signature in &mut signatures {
+                            let first_prim_index = match signature.first_prim_index {
+                                Some(first_prim_index) => first_prim_index,
+                                None => {
+                                    signature.first_prim_index = Some(prim_index);
+                                    prim_index
+                                }
+                            };
+                            signature.is_cacheable = signature.is_cacheable && is_cacheable;
+                            self.hash_primitive(prim_index,
+                                                first_prim_index,
+                                                bounding_rect,
+                                                signature.origin,
+                                                resource_cache,
+                                                &mut signature.hasher);
+                        }
+                    }
+                }
+            }
         }
+
+        picture_ops
     }
 
-    fn build_render_task(&self) -> (RenderTask, usize) {
+    fn build_render_task(&self,
+                         picture_ops: &[Option<PictureCacheOp>]) -> (RenderTask, usize) {
         profile_scope!("build_render_task");
 
         let mut next_z = 0;
@@ -1150,20 +2122,119 @@ impl FrameBuilder {
         next_task_index.0 += 1;
         let mut alpha_task_stack = Vec::new();
 
+        // The surfaces of the planes of any 3D rendering contexts being built.
+        // Each preserve-3d stacking context records where its own planes begin.
+        let mut plane_tasks = Vec::new();
+        let mut plane_task_starts = Vec::new();
+
+        // The depth of the stacking context being drawn from the picture
+        // cache, while its contents are skipped.
+        let mut cached_picture_depth = None;
+
         for cmd in &self.cmds {
             match *cmd {
                 PrimitiveRunCmd::PushStackingContext(stacking_context_index) => {
                     let stacking_context = &self.stacking_context_store[stacking_context_index.0];
                     sc_stack.push(stacking_context_index);
 
-                    if !stacking_context.is_visible {
+                    if !stacking_context.is_visible || cached_picture_depth.is_some() {
                         continue;
                     }
 
                     let stacking_context_rect = &stacking_context.bounding_rect;
                     let composite_count = stacking_context.composite_ops.count();
 
-                    if composite_count == 0 && stacking_context.should_isolate {
+                    // Backdrop filters are applied in place to the task the
+                    // stacking context is drawn over, before any of its content.
+                    for (filter_index, filter) in stacking_context.composite_ops
+                                                                  .backdrop_filters
+                                                                  .iter()
+                                                                  .enumerate() {
+                        let readback_task = RenderTask::new_backdrop_readback(stacking_context_index,
+                                                                              filter_index,
+                                                                              *stacking_context_rect);
+                        let item = AlphaRenderItem::BackdropFilter(stacking_context_index,
+                                                                   readback_task.id,
+                                                                   *filter,
+                                                                   next_z);
+                        next_z += 1;
+                        current_task.as_alpha_batch().alpha_items.push(item);
+                        current_task.children.push(readback_task);
+                    }
+
+                    if stacking_context.transform_style == TransformStyle::Preserve3D {
+                        plane_task_starts.push(plane_tasks.len());
+                    }
+
+                    // Planes are drawn into their own surface, after any composite
+                    // operations have been applied, so they can be split later.
+                    if stacking_context.is_3d_plane {
+                        let location = RenderTaskLocation::Dynamic(None, stacking_context_rect.size);
+                        let new_task = RenderTask::new_alpha_batch(next_task_index,
+                                                                   stacking_context_rect.origin,
+                                                                   true,
+                                                                   location);
+                        next_task_index.0 += 1;
+                        let prev_task = mem::replace(&mut current_task, new_task);
+                        alpha_task_stack.push(prev_task);
+                    }
+
+                    // A cached stacking context is drawn into a surface of its own,
+                    // which any opacity filters after the cached ones are applied
+                    // to, or which is composited directly if there are none.
+                    if let Some(picture_op) = picture_ops[stacking_context_index.0] {
+                        let filter_count = stacking_context.composite_ops.picture_filter_count();
+                        let surface_count = cmp::max(composite_count - filter_count, 1);
+
+                        for _ in 0..surface_count - 1 {
+                            let location = RenderTaskLocation::Dynamic(None, stacking_context_rect.size);
+                            let new_task = RenderTask::new_alpha_batch(next_task_index,
+                                                                       stacking_context_rect.origin,
+                                                                       stacking_context.should_isolate,
+                                                                       location);
+                            next_task_index.0 += 1;
+                            let prev_task = mem::replace(&mut current_task, new_task);
+                            alpha_task_stack.push(prev_task);
+                        }
+
+                        let picture_task = match picture_op {
+                            PersistentTargetOp::Store(cache_rect, key) => {
+                                let location = RenderTaskLocation::Dynamic(None, stacking_context_rect.size);
+                                let mut task = RenderTask::new_alpha_batch(next_task_index,
+                                                                           stacking_context_rect.origin,
+                                                                           true,
+                                                                           location);
+                                task.as_alpha_batch().picture_store = Some((cache_rect, key));
+                                task
+                            }
+                            PersistentTargetOp::Reuse(cache_rect) => {
+                                cached_picture_depth = Some(sc_stack.len());
+                                RenderTask::new_cached_picture(next_task_index,
+                                                               *stacking_context_rect,
+                                                               cache_rect)
+                            }
+                        };
+                        next_task_index.0 += 1;
+                        let prev_task = mem::replace(&mut current_task, picture_task);
+                        alpha_task_stack.push(prev_task);
+
+                        if cached_picture_depth.is_none() {
+                            for _ in 0..filter_count {
+                                let location = RenderTaskLocation::Dynamic(None, stacking_context_rect.size);
+                                let new_task = RenderTask::new_alpha_batch(next_task_index,
+                                                                           stacking_context_rect.origin,
+                                                                           stacking_context.should_isolate,
+                                                                           location);
+                                next_task_index.0 += 1;
+                                let prev_task = mem::replace(&mut current_task, new_task);
+                                alpha_task_stack.push(prev_task);
+                            }
+                        }
+                        continue;
+                    }
+
+                    if composite_count == 0 && stacking_context.should_isolate &&
+                       !stacking_context.is_3d_plane {
                         let location = RenderTaskLocation::Dynamic(None, stacking_context_rect.size);
                         let new_task = RenderTask::new_alpha_batch(next_task_index,
                                                                    stacking_context_rect.origin,
@@ -1189,13 +2260,67 @@ impl FrameBuilder {
                     let stacking_context_index = sc_stack.pop().unwrap();
                     let stacking_context = &self.stacking_context_store[stacking_context_index.0];
 
+                    if let Some(depth) = cached_picture_depth {
+                        if sc_stack.len() >= depth {
+                            continue;
+                        }
+                        cached_picture_depth = None;
+                    }
+
                     if !stacking_context.is_visible {
                         continue;
                     }
 
                     let composite_count = stacking_context.composite_ops.count();
 
-                    if composite_count == 0 && stacking_context.should_isolate {
+                    // The filters kept in the picture cache are only applied when the
+                    // picture is stored, before its surface is composited.
+                    let picture_op = picture_ops[stacking_context_index.0];
+                    let picture_filter_count = match picture_op {
+                        Some(..) => stacking_context.composite_ops.picture_filter_count(),
+                        None => 0,
+                    };
+                    let (picture_filters, filters) =
+                        stacking_context.composite_ops.filters.split_at(picture_filter_count);
+
+                    if stacking_context.transform_style == TransformStyle::Preserve3D {
+                        let start = plane_task_starts.pop().unwrap();
+                        let planes: Vec<(StackingContextIndex, RenderTask)> =
+                            plane_tasks.drain(start..).collect();
+
+                        for &(plane_index, geometry_address) in &stacking_context.split_polygons {
+                            let plane_task = planes.iter().find(|&&(index, _)| index == plane_index);
+                            if let Some(&(_, ref plane_task)) = plane_task {
+                                let item = AlphaRenderItem::SplitComposite(plane_index,
+                                                                           plane_task.id,
+                                                                           geometry_address,
+                                                                           next_z);
+                                next_z += 1;
+                                current_task.as_alpha_batch().alpha_items.push(item);
+                            }
+                        }
+
+                        current_task.children.extend(planes.into_iter().map(|(_, task)| task));
+                    }
+
+                    if let Some(PersistentTargetOp::Store(..)) = picture_op {
+                        for filter in picture_filters {
+                            current_task = self.add_filter_task(stacking_context_index,
+                                                                *filter,
+                                                                current_task,
+                                                                &mut alpha_task_stack,
+                                                                &mut next_task_index,
+                                                                &mut next_z);
+                        }
+                    }
+
+                    let needs_composite = match picture_op {
+                        Some(..) => filters.is_empty(),
+                        None => composite_count == 0 && stacking_context.should_isolate &&
+                                !stacking_context.is_3d_plane,
+                    };
+
+                    if needs_composite {
                         let mut prev_task = alpha_task_stack.pop().unwrap();
                         let item = AlphaRenderItem::HardwareComposite(stacking_context_index,
                                                                       current_task.id,
@@ -1207,16 +2332,13 @@ impl FrameBuilder {
                         current_task = prev_task;
                     }
 
-                    for filter in &stacking_context.composite_ops.filters {
-                        let mut prev_task = alpha_task_stack.pop().unwrap();
-                        let item = AlphaRenderItem::Blend(stacking_context_index,
-                                                          current_task.id,
-                                                          *filter,
-                                                          next_z);
-                        next_z += 1;
-                        prev_task.as_alpha_batch().alpha_items.push(item);
-                        prev_task.children.push(current_task);
-                        current_task = prev_task;
+                    for filter in filters {
+                        current_task = self.add_filter_task(stacking_context_index,
+                                                            *filter,
+                                                            current_task,
+                                                            &mut alpha_task_stack,
+                                                            &mut next_task_index,
+                                                            &mut next_z);
                     }
                     if let Some(mix_blend_mode) = stacking_context.composite_ops.mix_blend_mode {
                         let readback_task =
@@ -1235,12 +2357,20 @@ impl FrameBuilder {
                         prev_task.children.push(readback_task);
                         current_task = prev_task;
                     }
+
+                    // The surface of a plane is composited by the parent once all
+                    // of its planes have been sorted.
+                    if stacking_context.is_3d_plane {
+                        let prev_task = alpha_task_stack.pop().unwrap();
+                        let plane_task = mem::replace(&mut current_task, prev_task);
+                        plane_tasks.push((stacking_context_index, plane_task));
+                    }
                 }
                 PrimitiveRunCmd::PrimitiveRun(first_prim_index, prim_count, scroll_layer_id) => {
                     let stacking_context_index = *sc_stack.last().unwrap();
                     let stacking_context = &self.stacking_context_store[stacking_context_index.0];
 
-                    if !stacking_context.is_visible {
+                    if !stacking_context.is_visible || cached_picture_depth.is_some() {
                         continue;
                     }
 
@@ -1262,6 +2392,11 @@ impl FrameBuilder {
                             if let Some(ref render_task) = prim_metadata.render_task {
                                 current_task.children.push(render_task.clone());
                             }
+                            if prim_metadata.prim_kind == PrimitiveKind::BoxShadow {
+                                let box_shadow_index = prim_metadata.cpu_prim_index.0;
+                                let box_shadow_cpu = &self.prim_store.cpu_box_shadows[box_shadow_index];
+                                current_task.children.extend(box_shadow_cpu.render_tasks.iter().cloned());
+                            }
                             if let Some(ref clip_task) = prim_metadata.clip_task {
                                 current_task.children.push(clip_task.clone());
                             }
@@ -1288,11 +2423,105 @@ impl FrameBuilder {
         (current_task, next_task_index.0)
     }
 
+    /// Apply one of a stacking context's filters to `current_task`, drawing
+    /// the result into the task that was pushed for the filter, which is
+    /// returned.
+    fn add_filter_task(&self,
+                       stacking_context_index: StackingContextIndex,
+                       filter: LowLevelFilterOp,
+                       current_task: RenderTask,
+                       alpha_task_stack: &mut Vec<RenderTask>,
+                       next_task_index: &mut RenderTaskIndex,
+                       next_z: &mut i32) -> RenderTask {
+        let stacking_context = &self.stacking_context_store[stacking_context_index.0];
+        let mut prev_task = alpha_task_stack.pop().unwrap();
+
+        if let LowLevelFilterOp::DropShadow(offset_x, offset_y, blur_radius, _) = filter {
+            // The shadow is blurred over two passes, while the content
+            // is copied forward over the same passes so that both can be
+            // composited by the parent, the shadow first.
+            let content_rect = stacking_context.bounding_rect;
+            let copy_task = RenderTask::new_alpha_batch_copy(*next_task_index,
+                                                             stacking_context_index,
+                                                             current_task.id,
+                                                             content_rect,
+                                                             *next_z);
+            next_task_index.0 += 1;
+            *next_z += 1;
+            let mut content_task = RenderTask::new_alpha_batch_copy(*next_task_index,
+                                                                    stacking_context_index,
+                                                                    copy_task.id,
+                                                                    content_rect,
+                                                                    *next_z);
+            next_task_index.0 += 1;
+            *next_z += 1;
+            content_task.children.push(copy_task);
+
+            let shadow_origin = DeviceIntPoint::new(
+                content_rect.origin.x +
+                    (offset_x.to_f32_px() * self.device_pixel_ratio).round() as i32,
+                content_rect.origin.y +
+                    (offset_y.to_f32_px() * self.device_pixel_ratio).round() as i32);
+            let blur_radius = device_length(blur_radius.to_f32_px(),
+                                            self.device_pixel_ratio);
+            let shadow_task = RenderTask::new_alpha_batch_blur(current_task,
+                                                               blur_radius,
+                                                               shadow_origin,
+                                                               next_task_index);
+
+            let shadow_item = AlphaRenderItem::Blend(stacking_context_index,
+                                                     shadow_task.id,
+                                                     filter,
+                                                     *next_z);
+            *next_z += 1;
+            let content_item = AlphaRenderItem::HardwareComposite(stacking_context_index,
+                                                                  content_task.id,
+                                                                  HardwareCompositeOp::PremultipliedAlpha,
+                                                                  *next_z);
+            *next_z += 1;
+            prev_task.as_alpha_batch().alpha_items.push(shadow_item);
+            prev_task.as_alpha_batch().alpha_items.push(content_item);
+            prev_task.children.push(shadow_task);
+            prev_task.children.push(content_task);
+            return prev_task;
+        }
+
+        if let LowLevelFilterOp::Graph(graph_index) = filter {
+            let filter_graph = &stacking_context.composite_ops.filter_graphs[graph_index];
+            let output_task = filter_graph.build_render_task(current_task,
+                                                             stacking_context_index,
+                                                             stacking_context.bounding_rect,
+                                                             next_task_index,
+                                                             next_z);
+            let item = AlphaRenderItem::HardwareComposite(stacking_context_index,
+                                                          output_task.id,
+                                                          HardwareCompositeOp::PremultipliedAlpha,
+                                                          *next_z);
+            *next_z += 1;
+            prev_task.as_alpha_batch().alpha_items.push(item);
+            prev_task.children.push(output_task);
+            return prev_task;
+        }
+
+        let item = AlphaRenderItem::Blend(stacking_context_index,
+                                          current_task.id,
+                                          filter,
+                                          *next_z);
+        *next_z += 1;
+        prev_task.as_alpha_batch().alpha_items.push(item);
+        prev_task.children.push(current_task);
+        prev_task
+    }
+
     pub fn build(&mut self,
                  resource_cache: &mut ResourceCache,
                  frame_id: FrameId,
                  clip_scroll_tree: &mut ClipScrollTree,
                  auxiliary_lists_map: &AuxiliaryListsMap,
+                 pipeline_epoch_map: &PipelineEpochMap,
+                 properties: &SceneProperties,
+                 picture_cache: &mut PictureCache,
+                 mask_store: &mut ClipMaskStore,
                  device_pixel_ratio: f32,
                  texture_cache_profile: &mut TextureCacheProfileCounters)
                  -> Frame {
@@ -1312,19 +2541,33 @@ impl FrameBuilder {
         // has to be at least as large as the framebuffer size. This ensures that it will
         // always be able to allocate the worst case render task (such as a clip mask that
         // covers the entire screen).
-        let cache_size = DeviceUintSize::new(cmp::max(1024, screen_rect.size.width as u32),
-                                             cmp::max(1024, screen_rect.size.height as u32));
+        let cache_size = DeviceUintSize::new(cmp::max(MIN_CACHE_TARGET_SIZE,
+                                                      screen_rect.size.width as u32),
+                                             cmp::max(MIN_CACHE_TARGET_SIZE,
+                                                      screen_rect.size.height as u32));
 
         self.update_scroll_bars(clip_scroll_tree);
+        self.update_scroll_linked_opacities(clip_scroll_tree, properties);
 
         self.build_layer_screen_rects_and_cull_layers(&screen_rect,
                                                       clip_scroll_tree,
                                                       auxiliary_lists_map,
+                                                      pipeline_epoch_map,
                                                       resource_cache,
+                                                      mask_store,
                                                       &mut profile_counters,
                                                       device_pixel_ratio);
+        mask_store.end_frame();
+
+        self.build_split_planes(clip_scroll_tree, device_pixel_ratio);
 
-        let (main_render_task, static_render_task_count) = self.build_render_task();
+        let picture_ops = self.build_picture_cache_ops(clip_scroll_tree,
+                                                       pipeline_epoch_map,
+                                                       picture_cache,
+                                                       resource_cache);
+        picture_cache.end_frame();
+
+        let (main_render_task, static_render_task_count) = self.build_render_task(&picture_ops);
         let mut render_tasks = RenderTaskCollection::new(static_render_task_count);
 
         let mut required_pass_count = 0;
@@ -1399,19 +2642,27 @@ struct LayerRectCalculationAndCullingPass<'a> {
     screen_rect: &'a DeviceIntRect,
     clip_scroll_tree: &'a mut ClipScrollTree,
     auxiliary_lists_map: &'a AuxiliaryListsMap,
+    pipeline_epoch_map: &'a PipelineEpochMap,
     resource_cache: &'a mut ResourceCache,
+    mask_store: &'a mut ClipMaskStore,
     profile_counters: &'a mut FrameProfileCounters,
     device_pixel_ratio: f32,
     stacking_context_stack: Vec<StackingContextIndex>,
 
+    /// How the clip masks shared inside masking clips use the clip mask
+    /// store this frame, requested for the first primitive of each clip.
+    mask_store_ops: HashMap<ScrollLayerId, Option<ClipMaskStoreOp>, BuildHasherDefault<FnvHasher>>,
+
     /// A cached clip info stack, which should handle the most common situation,
     /// which is that we are using the same clip info stack that we were using
     /// previously.
     current_clip_stack: Vec<(PackedLayerIndex, MaskCacheInfo)>,
 
     /// Information about the cached clip stack, which is used to avoid having
-    /// to recalculate it for every primitive.
-    current_clip_info: Option<(ScrollLayerId, Option<DeviceIntRect>)>
+    /// to recalculate it for every primitive. This is the node the stack was
+    /// last looked up for, and the innermost masking clip of that stack along
+    /// with its device bounds, if there is one.
+    current_clip_info: Option<(ScrollLayerId, Option<(ScrollLayerId, DeviceIntRect)>)>
 }
 
 impl<'a> LayerRectCalculationAndCullingPass<'a> {
@@ -1419,7 +2670,9 @@ impl<'a> LayerRectCalculationAndCullingPass<'a> {
                       screen_rect: &'a DeviceIntRect,
                       clip_scroll_tree: &'a mut ClipScrollTree,
                       auxiliary_lists_map: &'a AuxiliaryListsMap,
+                      pipeline_epoch_map: &'a PipelineEpochMap,
                       resource_cache: &'a mut ResourceCache,
+                      mask_store: &'a mut ClipMaskStore,
                       profile_counters: &'a mut FrameProfileCounters,
                       device_pixel_ratio: f32) {
 
@@ -1428,10 +2681,13 @@ impl<'a> LayerRectCalculationAndCullingPass<'a> {
             screen_rect: screen_rect,
             clip_scroll_tree: clip_scroll_tree,
             auxiliary_lists_map: auxiliary_lists_map,
+            pipeline_epoch_map: pipeline_epoch_map,
             resource_cache: resource_cache,
+            mask_store: mask_store,
             profile_counters: profile_counters,
             device_pixel_ratio: device_pixel_ratio,
             stacking_context_stack: Vec::new(),
+            mask_store_ops: HashMap::with_hasher(Default::default()),
             current_clip_stack: Vec::new(),
             current_clip_info: None,
         };
@@ -1503,7 +2759,7 @@ impl<'a> LayerRectCalculationAndCullingPass<'a> {
                 if let Some(mask) = clip_source.image_mask() {
                     // We don't add the image mask for resolution, because
                     // layer masks are resolved later.
-                    self.resource_cache.request_image(mask.image, ImageRendering::Auto, None);
+                    self.resource_cache.request_image(mask.image, ImageRendering::Auto, None, 1.0);
                 }
             }
         }
@@ -1533,9 +2789,8 @@ impl<'a> LayerRectCalculationAndCullingPass<'a> {
             // Here we move the viewport rectangle into the coordinate system
             // of the stacking context content.
             let viewport_rect =
-                &node.combined_local_viewport_rect
-                     .translate(&-stacking_context.reference_frame_offset)
-                     .translate(&-node.scrolling.offset);
+                &node.content_space_rect(&node.combined_local_viewport_rect)
+                     .translate(&-stacking_context.reference_frame_offset);
             group.xf_rect = packed_layer.set_rect(viewport_rect,
                                                   self.screen_rect,
                                                   self.device_pixel_ratio);
@@ -1544,15 +2799,23 @@ impl<'a> LayerRectCalculationAndCullingPass<'a> {
 
     fn compute_stacking_context_visibility(&mut self) {
         for context_index in 0..self.frame_builder.stacking_context_store.len() {
-            let is_visible = {
+            let (is_visible, is_backface_hidden) = {
                 // We don't take into account visibility of children here, so we must
-                // do that later.
+                // do that later. The same goes for the back face of ancestors, which
+                // is handled when the stacking context is pushed.
                 let stacking_context = &self.frame_builder.stacking_context_store[context_index];
-                stacking_context.clip_scroll_groups.iter().any(|group_index| {
+                let is_backface_hidden = !stacking_context.backface_visible && {
+                    let node = &self.clip_scroll_tree.nodes[&stacking_context.reference_frame_id];
+                    !node.world_content_transform.is_front_face_visible()
+                };
+                let is_visible = stacking_context.clip_scroll_groups.iter().any(|group_index| {
                     self.frame_builder.clip_scroll_group_store[group_index.0].is_visible()
-                })
+                });
+                (is_visible, is_backface_hidden)
             };
-            self.frame_builder.stacking_context_store[context_index].is_visible = is_visible;
+            let stacking_context = &mut self.frame_builder.stacking_context_store[context_index];
+            stacking_context.is_visible = is_visible && !is_backface_hidden;
+            stacking_context.is_backface_hidden = is_backface_hidden;
         }
     }
 
@@ -1562,9 +2825,30 @@ impl<'a> LayerRectCalculationAndCullingPass<'a> {
         let (bounding_rect, is_visible) = {
             let stacking_context =
                 &mut self.frame_builder.stacking_context_store[stacking_context_index.0];
+
+            // Drop shadows are drawn outside of the content, so make room for
+            // them in the surface that the content is drawn into.
+            if !util::rect_is_empty(&stacking_context.bounding_rect) {
+                for filter in &stacking_context.composite_ops.filters {
+                    if let LowLevelFilterOp::DropShadow(offset_x, offset_y, blur_radius, _) = *filter {
+                        let extent = blur_radius.to_f32_px() +
+                                     offset_x.to_f32_px().abs().max(offset_y.to_f32_px().abs());
+                        let extent = (extent * self.device_pixel_ratio).ceil() as i32;
+                        stacking_context.bounding_rect = stacking_context.bounding_rect
+                                                                         .inflate(extent, extent);
+                    }
+                }
+            }
+
             stacking_context.bounding_rect = stacking_context.bounding_rect
                                                              .intersection(self.screen_rect)
                                                              .unwrap_or(DeviceIntRect::zero());
+
+            // Visible children must not make a stacking context that was culled
+            // for facing away from the viewer visible again.
+            if stacking_context.is_backface_hidden {
+                stacking_context.is_visible = false;
+            }
             (stacking_context.bounding_rect.clone(), stacking_context.is_visible)
         };
 
@@ -1579,6 +2863,9 @@ impl<'a> LayerRectCalculationAndCullingPass<'a> {
     }
 
     fn handle_push_stacking_context(&mut self, stacking_context_index: StackingContextIndex) {
+        let parent_is_backface_hidden = self.stacking_context_stack.last().map_or(false, |index| {
+            self.frame_builder.stacking_context_store[index.0].is_backface_hidden
+        });
         self.stacking_context_stack.push(stacking_context_index);
 
         // Reset bounding rect to zero. We will calculate it as we collect primitives
@@ -1589,21 +2876,53 @@ impl<'a> LayerRectCalculationAndCullingPass<'a> {
         let stacking_context = &mut self.frame_builder
                                         .stacking_context_store[stacking_context_index.0];
         stacking_context.bounding_rect = DeviceIntRect::zero();
+
+        // Everything inside a culled stacking context is culled along with it.
+        if parent_is_backface_hidden {
+            stacking_context.is_backface_hidden = true;
+            stacking_context.is_visible = false;
+        }
     }
 
-    fn rebuild_clip_info_stack_if_necessary(&mut self, id: ScrollLayerId) -> Option<DeviceIntRect> {
-        if let Some((current_scroll_id, bounding_rect)) = self.current_clip_info {
-            if current_scroll_id == id {
-                return bounding_rect;
+    /// Find the nearest masking clip at or above the given node. The clip stack
+    /// of a node is made up of this clip and its masking ancestors, so every node
+    /// below the same masking clip, such as the content of a scroll frame with a
+    /// rounded viewport, shares a clip stack.
+    fn innermost_masking_clip(&self, id: ScrollLayerId) -> Option<ScrollLayerId> {
+        let mut current_id = Some(id);
+        while let Some(id) = current_id {
+            let node = &self.clip_scroll_tree.nodes[&id];
+            match node.node_type {
+                NodeType::Clip(ref clip) if clip.is_masking() => return Some(id),
+                _ => current_id = node.parent,
+            }
+        }
+        None
+    }
+
+    fn rebuild_clip_info_stack_if_necessary(&mut self,
+                                            id: ScrollLayerId)
+                                            -> Option<(ScrollLayerId, DeviceIntRect)> {
+        let current_clip = match self.current_clip_info {
+            Some((current_scroll_id, clip)) if current_scroll_id == id => return clip,
+            Some((_, clip)) => clip,
+            None => None,
+        };
+
+        // Moving between nodes below the same masking clip, as when scrolling
+        // content inside a clipped scroll frame, keeps the stack we have.
+        let innermost_clip_id = self.innermost_masking_clip(id);
+        if let Some((current_clip_id, _)) = current_clip {
+            if innermost_clip_id == Some(current_clip_id) {
+                self.current_clip_info = Some((id, current_clip));
+                return current_clip;
             }
         }
 
-        // TODO(mrobinson): If we notice that this process is expensive, we can special-case
-        // more common situations, such as moving from a child or a parent.
         self.current_clip_stack.clear();
-        let mut bounding_rect = None;
+        let mut innermost_clip = None;
 
-        let mut current_id = Some(id);
+        let mut current_id = innermost_clip_id;
         while let Some(id) = current_id {
             let node = &self.clip_scroll_tree.nodes.get(&id).unwrap();
             current_id = node.parent;
@@ -1613,18 +2932,18 @@ impl<'a> LayerRectCalculationAndCullingPass<'a> {
                 _ => continue,
             };
 
-            if bounding_rect.is_none() {
-                bounding_rect =
-                    Some(clip_info.xf_rect.as_ref().map_or_else(DeviceIntRect::zero,
-                                                                |x| x.bounding_rect))
+            if innermost_clip.is_none() {
+                let bounding_rect = clip_info.xf_rect.as_ref().map_or_else(DeviceIntRect::zero,
+                                                                           |x| x.bounding_rect);
+                innermost_clip = Some((id, bounding_rect));
             }
             self.current_clip_stack.push((clip_info.packed_layer_index,
                                           clip_info.mask_cache_info.clone().unwrap()))
         }
         self.current_clip_stack.reverse();
 
-        self.current_clip_info = Some((id, bounding_rect));
-        bounding_rect
+        self.current_clip_info = Some((id, innermost_clip));
+        innermost_clip
     }
 
     fn handle_primitive_run(&mut self,
@@ -1645,11 +2964,22 @@ impl<'a> LayerRectCalculationAndCullingPass<'a> {
             (clip_scroll_group.packed_layer_index, stacking_context.pipeline_id)
         };
 
-        let node_clip_bounds = self.rebuild_clip_info_stack_if_necessary(scroll_layer_id);
-        if node_clip_bounds.map_or(false, |bounds| bounds.is_empty()) {
+        let node_clip = self.rebuild_clip_info_stack_if_necessary(scroll_layer_id);
+        if node_clip.map_or(false, |(_, bounds)| bounds.is_empty()) {
             return;
         }
 
+        let allow_subpixel_aa = {
+            let stacking_context_store = &self.frame_builder.stacking_context_store;
+            allows_subpixel_aa(self.frame_builder.background_color,
+                               self.stacking_context_stack.iter().map(|index| &stacking_context_store[index.0]),
+                               &self.frame_builder.packed_layers[packed_layer_index.0].transform,
+                               self.device_pixel_ratio)
+        };
+
+        // Text is rasterized at the settled zoom of the scroll frames it is in.
+        let raster_scale = self.clip_scroll_tree.nodes[&scroll_layer_id].raster_scale;
+
         let stacking_context =
             &mut self.frame_builder.stacking_context_store[stacking_context_index.0];
         let packed_layer = &self.frame_builder.packed_layers[packed_layer_index.0];
@@ -1667,6 +2997,8 @@ impl<'a> LayerRectCalculationAndCullingPass<'a> {
                                                                          self.resource_cache,
                                                                          &packed_layer.transform,
                                                                          self.device_pixel_ratio,
+                                                                         raster_scale,
+                                                                         allow_subpixel_aa,
                                                                          auxiliary_lists) {
                     self.frame_builder.prim_store.build_bounding_rect(prim_index,
                                                                       self.screen_rect,
@@ -1697,13 +3029,17 @@ impl<'a> LayerRectCalculationAndCullingPass<'a> {
                 // Try to create a mask if we may need to.
                 if !self.current_clip_stack.is_empty() {
                     // If the primitive doesn't have a specific clip, key the task ID off the
-                    // stacking context. This means that two primitives which are only clipped
-                    // by the stacking context stack can share clip masks during render task
+                    // innermost masking clip. This means that all the primitives which are
+                    // only clipped by the same clips, including those in any scroll frames
+                    // nested inside them, share one clip mask per frame during render task
                     // assignment to targets.
-                    let node_clip_bounds = node_clip_bounds.unwrap_or_else(DeviceIntRect::zero);
                     let (mask_key, mask_rect) = match prim_clip_info {
                         Some(..) => (MaskCacheKey::Primitive(prim_index), prim_bounding_rect),
-                        None => (MaskCacheKey::ScrollLayer(scroll_layer_id), node_clip_bounds)
+                        None => {
+                            let (clip_id, clip_bounds) =
+                                node_clip.unwrap_or((scroll_layer_id, DeviceIntRect::zero()));
+                            (MaskCacheKey::ScrollLayer(clip_id), clip_bounds)
+                        }
                     };
                     let mask_opt =
                         RenderTask::new_mask(mask_rect, mask_key, &self.current_clip_stack);
@@ -1713,7 +3049,41 @@ impl<'a> LayerRectCalculationAndCullingPass<'a> {
                             self.frame_builder.prim_store.cpu_bounding_rects[prim_index.0] = None;
                             visible = false;
                         }
-                        MaskResult::Inside(task) => prim_metadata.clip_task = Some(task),
+                        MaskResult::Inside(mut task) => {
+                            // Shared masks are kept in the clip mask store, so that
+                            // scrolling inside the clip doesn't render them again.
+                            let requested_op = match mask_key {
+                                MaskCacheKey::ScrollLayer(clip_id) => {
+                                    Some((clip_id, self.mask_store_ops.get(&clip_id).cloned()))
+                                }
+                                MaskCacheKey::Primitive(..) => None,
+                            };
+                            if let RenderTaskKind::CacheMask(ref mut mask) = task.kind {
+                                mask.store_op = match requested_op {
+                                    Some((_, Some(store_op))) => store_op,
+                                    Some((clip_id, None)) => {
+                                        let key = clip_mask_key(self.clip_scroll_tree,
+                                                                clip_id,
+                                                                self.pipeline_epoch_map,
+                                                                &mask.actual_rect,
+                                                                &self.current_clip_stack,
+                                                                &self.frame_builder.packed_layers,
+                                                                self.device_pixel_ratio);
+                                        let store_op = match key {
+                                            Some(key) => self.mask_store.request(clip_id,
+                                                                                 clip_id.pipeline_id(),
+                                                                                 key,
+                                                                                 mask.actual_rect.size),
+                                            None => None,
+                                        };
+                                        self.mask_store_ops.insert(clip_id, store_op);
+                                        store_op
+                                    }
+                                    None => None,
+                                };
+                            }
+                            prim_metadata.clip_task = Some(task)
+                        }
                     }
                 }
 
@@ -1728,3 +3098,175 @@ impl<'a> LayerRectCalculationAndCullingPass<'a> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use app_units::Au;
+    use clip_scroll_tree::ClipScrollTree;
+    use internal_types::LowLevelFilterOp;
+    use persistent_target::PersistentTargetOp;
+    use picture_cache::new_picture_cache;
+    use resource_cache::ResourceCache;
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+    use super::{FrameBuilder, FrameBuilderConfig, ScrollbarStyle, allows_subpixel_aa};
+    use texture_cache::TextureCache;
+    use threadpool::ThreadPool;
+    use tiling::{CompositeOps, PipelineEpochMap, PrimitiveFlags, StackingContext};
+    use util::TransformedRect;
+    use webrender_traits::{ClipRegion, ColorF, DeviceIntPoint, DeviceIntRect, DeviceIntSize};
+    use webrender_traits::{DeviceUintSize, Epoch, LayerPoint, LayerRect, LayerSize};
+    use webrender_traits::{LayerToWorldTransform, PipelineId, ScrollLayerId, TransformStyle};
+
+    fn stacking_context(composite_ops: CompositeOps) -> StackingContext {
+        let pipeline_id = PipelineId(0, 0);
+        StackingContext::new(pipeline_id,
+                             LayerPoint::zero(),
+                             ScrollLayerId::root_reference_frame(pipeline_id),
+                             LayerRect::zero(),
+                             true,
+                             TransformStyle::Flat,
+                             true,
+                             composite_ops)
+    }
+
+    fn no_composite_ops() -> CompositeOps {
+        CompositeOps::new(Vec::new(), Vec::new(), Vec::new(), Vec::new(), None)
+    }
+
+    #[test]
+    fn subpixel_aa_is_used_on_an_opaque_background() {
+        let root = stacking_context(no_composite_ops());
+        let transform = LayerToWorldTransform::create_translation(10.0, 20.0, 0.0);
+        assert!(allows_subpixel_aa(Some(ColorF::new(1.0, 1.0, 1.0, 1.0)), &[root], &transform, 1.0));
+    }
+
+    #[test]
+    fn subpixel_aa_needs_an_opaque_background_color() {
+        let root = stacking_context(no_composite_ops());
+        let transform = LayerToWorldTransform::identity();
+        assert!(!allows_subpixel_aa(None, &[root], &transform, 1.0));
+
+        let root = stacking_context(no_composite_ops());
+        let translucent = ColorF::new(1.0, 1.0, 1.0, 0.5);
+        assert!(!allows_subpixel_aa(Some(translucent), &[root], &transform, 1.0));
+    }
+
+    #[test]
+    fn subpixel_aa_falls_back_inside_isolated_and_filtered_contexts() {
+        let white = Some(ColorF::new(1.0, 1.0, 1.0, 1.0));
+        let transform = LayerToWorldTransform::identity();
+
+        let filters = vec![LowLevelFilterOp::Opacity(Au::from_f32_px(0.5))];
+        let filtered = stacking_context(CompositeOps::new(filters, Vec::new(), Vec::new(), Vec::new(), None));
+        assert!(!allows_subpixel_aa(white, &[stacking_context(no_composite_ops()), filtered], &transform, 1.0));
+
+        let mut isolated = stacking_context(no_composite_ops());
+        isolated.should_isolate = true;
+        assert!(!allows_subpixel_aa(white, &[stacking_context(no_composite_ops()), isolated], &transform, 1.0));
+    }
+
+    #[test]
+    fn subpixel_aa_falls_back_on_fractional_translations() {
+        let white = Some(ColorF::new(1.0, 1.0, 1.0, 1.0));
+        let root = stacking_context(no_composite_ops());
+        let transform = LayerToWorldTransform::create_translation(10.5, 20.0, 0.0);
+        assert!(!allows_subpixel_aa(white, &[root], &transform, 1.0));
+
+        // Half a CSS pixel is a whole device pixel at twice the density.
+        let root = stacking_context(no_composite_ops());
+        assert!(allows_subpixel_aa(white, &[root], &transform, 2.0));
+    }
+
+    /// Builds a page with a rect in a stacking context of the given opacity,
+    /// which is the second stacking context of the scene.
+    fn picture_frame_builder(opacity: f32) -> FrameBuilder {
+        let pipeline_id = PipelineId(0, 0);
+        let config = FrameBuilderConfig::new(false, ScrollbarStyle::default(), false, false);
+        let mut builder = FrameBuilder::new(DeviceUintSize::new(400, 400), None, 1.0, config);
+        let reference_frame_id = ScrollLayerId::root_reference_frame(pipeline_id);
+        builder.reference_frame_stack.push(reference_frame_id);
+
+        let size = LayerSize::new(100.0, 100.0);
+        let rect = LayerRect::new(LayerPoint::zero(), size);
+        let filters = vec![LowLevelFilterOp::Opacity(Au::from_f32_px(opacity))];
+        builder.push_stacking_context(&LayerPoint::zero(),
+                                      pipeline_id,
+                                      true,
+                                      TransformStyle::Flat,
+                                      true,
+                                      &size,
+                                      no_composite_ops());
+        builder.push_stacking_context(&LayerPoint::zero(),
+                                      pipeline_id,
+                                      false,
+                                      TransformStyle::Flat,
+                                      true,
+                                      &size,
+                                      CompositeOps::new(filters, Vec::new(), Vec::new(), Vec::new(), None));
+        builder.add_solid_rectangle(reference_frame_id,
+                                    &rect,
+                                    &ClipRegion::simple(&rect),
+                                    &ColorF::new(0.0, 0.0, 1.0, 1.0),
+                                    PrimitiveFlags::None);
+        builder.pop_stacking_context();
+        builder.pop_stacking_context();
+
+        // Stand in for the culling pass, which needs a whole frame to run.
+        let bounding_rect = DeviceIntRect::new(DeviceIntPoint::zero(), DeviceIntSize::new(100, 100));
+        for stacking_context in &mut builder.stacking_context_store {
+            stacking_context.is_visible = true;
+            stacking_context.bounding_rect = bounding_rect;
+        }
+        for group in &mut builder.clip_scroll_group_store {
+            group.xf_rect = Some(TransformedRect::new(&rect, &LayerToWorldTransform::identity(), 1.0));
+        }
+        for prim_bounding_rect in &mut builder.prim_store.cpu_bounding_rects {
+            *prim_bounding_rect = Some(bounding_rect);
+        }
+
+        builder
+    }
+
+    #[test]
+    fn pictures_are_reused_after_a_property_only_scene_rebuild() {
+        let contents = Arc::new(Mutex::new(Vec::new()));
+        let mut picture_cache = new_picture_cache(contents.clone());
+        let workers = Arc::new(Mutex::new(ThreadPool::new(1)));
+        let resource_cache = ResourceCache::new(TextureCache::new(2048), workers, None, true, 1.0, 1.0);
+        let clip_scroll_tree = ClipScrollTree::new();
+        let mut pipeline_epoch_map: PipelineEpochMap = HashMap::with_hasher(Default::default());
+        pipeline_epoch_map.insert(PipelineId(0, 0), Epoch(1));
+
+        // Every frame builds the scene again, with a new opacity value, as a
+        // GenerateFrame with new property bindings does.
+        let ops = picture_frame_builder(0.5).build_picture_cache_ops(&clip_scroll_tree,
+                                                                     &pipeline_epoch_map,
+                                                                     &mut picture_cache,
+                                                                     &resource_cache);
+        picture_cache.end_frame();
+        assert!(ops[1].is_none());
+
+        let ops = picture_frame_builder(0.6).build_picture_cache_ops(&clip_scroll_tree,
+                                                                     &pipeline_epoch_map,
+                                                                     &mut picture_cache,
+                                                                     &resource_cache);
+        picture_cache.end_frame();
+        let rect = match ops[1] {
+            Some(PersistentTargetOp::Store(rect, key)) => {
+                contents.lock().unwrap().push((rect, key));
+                rect
+            }
+            op => panic!("expected a store, got {:?}", op),
+        };
+
+        let ops = picture_frame_builder(0.7).build_picture_cache_ops(&clip_scroll_tree,
+                                                                     &pipeline_epoch_map,
+                                                                     &mut picture_cache,
+                                                                     &resource_cache);
+        match ops[1] {
+            Some(PersistentTargetOp::Reuse(stored_rect)) => assert_eq!(stored_rect, rect),
+            op => panic!("expected a reuse, got {:?}", op),
+        }
+    }
+}
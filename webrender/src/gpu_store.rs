@@ -3,9 +3,10 @@
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
 use device::TextureFilter;
+use std::cmp;
 use std::marker::PhantomData;
 use std::mem;
-use std::ops::Add;
+use std::ops::{Add, Range};
 use webrender_traits::ImageFormat;
 
 #[derive(Debug, Copy, Clone, Eq, Hash, PartialEq)]
@@ -57,15 +58,89 @@ pub trait GpuStoreLayout {
     fn rows_per_item<T>() -> usize {
         Self::texels_per_item::<T>() / Self::texture_width::<T>()
     }
+
+    /// The number of items in the smallest run that covers whole texture rows.
+    fn items_per_upload<T>() -> usize {
+        cmp::max(Self::items_per_row::<T>(), 1)
+    }
+
+    /// The number of texture rows taken by `count` items. The count must be
+    /// a multiple of `items_per_upload()`.
+    fn rows_for_items<T>(count: usize) -> usize {
+        let items_per_row = Self::items_per_row::<T>();
+        if items_per_row != 0 {
+            count / items_per_row
+        } else {
+            count * Self::rows_per_item::<T>()
+        }
+    }
+}
+
+/// Sorts a list of item ranges, widens them to whole texture rows and merges
+/// any that overlap. Ranges are clamped to `item_count`, which must itself
+/// cover whole rows.
+pub fn merge_dirty_ranges<T, L: GpuStoreLayout>(mut ranges: Vec<Range<usize>>,
+                                                item_count: usize) -> Vec<Range<usize>> {
+    let alignment = L::items_per_upload::<T>();
+    ranges.sort_by_key(|range| range.start);
+
+    let mut merged: Vec<Range<usize>> = Vec::new();
+    for range in ranges {
+        let start = range.start - range.start % alignment;
+        let end = cmp::min(round_up(range.end, alignment), item_count);
+        if start >= end {
+            continue;
+        }
+        if let Some(last) = merged.last_mut() {
+            if start <= last.end {
+                last.end = cmp::max(last.end, end);
+                continue;
+            }
+        }
+        merged.push(start..end);
+    }
+
+    merged
+}
+
+fn round_up(value: usize, alignment: usize) -> usize {
+    (value + alignment - 1) / alignment * alignment
+}
+
+/// A run of items to copy into the GPU copy of a store.
+pub struct GpuStoreBlock<T> {
+    pub address: GpuStoreAddress,
+    pub data: Vec<T>,
+}
+
+/// The changes made to a `GpuStore` since the previous frame was built.
+pub struct GpuStoreUpdates<T> {
+    /// The size of the store, padded to a whole number of texture rows.
+    pub item_count: usize,
+    /// The runs of items that changed. Each one starts and ends on a
+    /// texture row boundary.
+    pub blocks: Vec<GpuStoreBlock<T>>,
 }
 
 /// A CPU-side buffer storing content to be uploaded to the GPU.
+///
+/// Addresses stay the same for as long as the store lives, and the renderer
+/// keeps its own copy of the data. Only the items written since the last
+/// `build()` are sent to it, and blocks released with `free()` are handed
+/// out again by later allocations.
+///
+/// Known limitation: the stores belong to the `FrameBuilder`, and a new one
+/// is created for every new display list or property update, so all of
+/// their contents are uploaded again after a scene change, however little
+/// of it changed.
 pub struct GpuStore<T, L> {
     data: Vec<T>,
     layout: PhantomData<L>,
     // TODO(gw): Could store this intrusively inside
     // the data array free slots.
-    //free_list: Vec<GpuStoreAddress>,
+    free_list: Vec<(GpuStoreAddress, usize)>,
+    /// The item ranges written since the last build.
+    dirty_ranges: Vec<Range<usize>>,
 }
 
 impl<T: Clone + Default, L: GpuStoreLayout> GpuStore<T, L> {
@@ -73,45 +148,106 @@ impl<T: Clone + Default, L: GpuStoreLayout> GpuStore<T, L> {
         GpuStore {
             data: Vec::new(),
             layout: PhantomData,
-            //free_list: Vec::new(),
+            free_list: Vec::new(),
+            dirty_ranges: Vec::new(),
         }
     }
 
     pub fn push<E>(&mut self, data: E) -> GpuStoreAddress where T: From<E> {
         let address = GpuStoreAddress(self.data.len() as i32);
         self.data.push(T::from(data));
+        self.mark_dirty(address, 1);
         address
     }
 
-    // TODO(gw): Change this to do incremental updates, which means
-    // there is no need to copy all this data during every scroll!
-    pub fn build(&self) -> Vec<T> {
-        let items_per_row = L::items_per_row::<T>();
-
-        let mut items = self.data.clone();
-
-        // Extend the data array to be a multiple of the row size.
-        // This ensures memory safety when the array is passed to
-        // OpenGL to upload to the GPU.
-        if items_per_row != 0 {
-            while items_per_row != 0 && items.len() % items_per_row != 0 {
-                items.push(T::default());
+    /// Returns the items that changed since the previous call, so that the
+    /// renderer can patch its copy of the store.
+    pub fn build(&mut self) -> GpuStoreUpdates<T> {
+        // Pad the store to a multiple of the row size. This ensures memory
+        // safety when the data is passed to OpenGL to upload to the GPU.
+        let item_count = round_up(self.data.len(), L::items_per_upload::<T>());
+
+        let dirty_ranges = mem::replace(&mut self.dirty_ranges, Vec::new());
+        let blocks = merge_dirty_ranges::<T, L>(dirty_ranges, item_count).into_iter().map(|range| {
+            let end = cmp::min(range.end, self.data.len());
+            let mut data = self.data[range.start..end].to_vec();
+            data.resize(range.end - range.start, T::default());
+            GpuStoreBlock {
+                address: GpuStoreAddress(range.start as i32),
+                data: data,
             }
-        }
+        }).collect();
 
-        items
+        GpuStoreUpdates {
+            item_count: item_count,
+            blocks: blocks,
+        }
     }
 
+    /// Allocates `count` default items, reusing the lowest freed block that is
+    /// large enough before growing the store.
     pub fn alloc(&mut self, count: usize) -> GpuStoreAddress {
+        let free_index = self.free_list.iter().position(|&(_, size)| size >= count);
+        if count > 0 {
+            if let Some(index) = free_index {
+                let (address, size) = self.free_list[index];
+                if size == count {
+                    self.free_list.remove(index);
+                } else {
+                    self.free_list[index] = (address + count, size - count);
+                }
+
+                for item in self.get_slice_mut(address, count) {
+                    *item = T::default();
+                }
+                return address;
+            }
+        }
+
         let address = self.get_next_address();
 
         for _ in 0..count {
             self.data.push(T::default());
         }
+        self.mark_dirty(address, count);
 
         address
     }
 
+    /// Releases a block returned by `alloc()`, so that a later allocation can
+    /// reuse it. The address must not be used again by the caller.
+    ///
+    /// The free list is kept sorted by address, and the block is merged with
+    /// any free neighbours, so that blocks freed one at a time can be reused
+    /// by a larger allocation.
+    pub fn free(&mut self, address: GpuStoreAddress, count: usize) {
+        if count == 0 {
+            return;
+        }
+
+        let index = self.free_list
+                            .iter()
+                            .position(|&(free_address, _)| free_address.0 > address.0)
+                            .unwrap_or(self.free_list.len());
+        self.free_list.insert(index, (address, count));
+
+        if index + 1 < self.free_list.len() {
+            let (next_address, next_count) = self.free_list[index + 1];
+            if address + count == next_address {
+                self.free_list[index].1 += next_count;
+                self.free_list.remove(index + 1);
+            }
+        }
+
+        if index > 0 {
+            let (previous_address, previous_count) = self.free_list[index - 1];
+            if previous_address + previous_count == address {
+                self.free_list[index - 1].1 += self.free_list[index].1;
+                self.free_list.remove(index);
+            }
+        }
+    }
+
     pub fn get_next_address(&self) -> GpuStoreAddress {
         GpuStoreAddress(self.data.len() as i32)
     }
@@ -120,27 +256,139 @@ impl<T: Clone + Default, L: GpuStoreLayout> GpuStore<T, L> {
         &self.data[address.0 as usize]
     }
 
+    /// Writes an item, only marking it dirty if its value changed, so that
+    /// data which is resolved again every frame isn't uploaded every frame.
+    pub fn set(&mut self, address: GpuStoreAddress, value: T) where T: PartialEq {
+        let index = address.0 as usize;
+        if self.data[index] != value {
+            self.data[index] = value;
+            self.mark_dirty(address, 1);
+        }
+    }
+
     pub fn get_mut(&mut self, address: GpuStoreAddress) -> &mut T {
+        self.mark_dirty(address, 1);
         &mut self.data[address.0 as usize]
     }
 
     pub fn get_slice_mut(&mut self,
                          address: GpuStoreAddress,
                          count: usize) -> &mut [T] {
+        self.mark_dirty(address, count);
         let offset = address.0 as usize;
         &mut self.data[offset..offset + count]
     }
 
-    // TODO(gw): Implement incremental updates of
-    // GPU backed data, and support freelist for removing
-    // dynamic items.
+    fn mark_dirty(&mut self, address: GpuStoreAddress, count: usize) {
+        let start = address.0 as usize;
+        let end = start + count;
 
-    /*
-    pub fn free(&mut self, address: GpuStoreAddress) {
+        // Consecutive writes usually touch neighbouring items, so extend the
+        // last range where possible to keep the list short.
+        if let Some(last) = self.dirty_ranges.last_mut() {
+            if start >= last.start && start <= last.end {
+                last.end = cmp::max(last.end, end);
+                return;
+            }
+        }
+        self.dirty_ranges.push(start..end);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use device::TextureFilter;
+    use super::{GpuStore, GpuStoreAddress, GpuStoreLayout, merge_dirty_ranges};
+    use webrender_traits::ImageFormat;
+
+    type Item = [f32; 4];
+
+    /// Four single-texel items to a row.
+    struct TestLayout {}
+
+    impl GpuStoreLayout for TestLayout {
+        fn image_format() -> ImageFormat {
+            ImageFormat::RGBAF32
+        }
+
+        fn texture_width<T>() -> usize {
+            4
+        }
 
+        fn texture_filter() -> TextureFilter {
+            TextureFilter::Nearest
+        }
+    }
+
+    #[test]
+    fn dirty_ranges_are_widened_to_rows_and_merged() {
+        let ranges = vec![13..14, 1..2, 2..3, 5..6];
+        assert_eq!(merge_dirty_ranges::<Item, TestLayout>(ranges, 16), vec![0..8, 12..16]);
+    }
+
+    #[test]
+    fn dirty_ranges_are_clamped_to_the_store() {
+        let ranges = vec![14..20, 16..18];
+        assert_eq!(merge_dirty_ranges::<Item, TestLayout>(ranges, 16), vec![12..16]);
+    }
+
+    #[test]
+    fn freed_blocks_are_reused() {
+        let mut store: GpuStore<Item, TestLayout> = GpuStore::new();
+        let first = store.alloc(4);
+        store.alloc(4);
+        store.free(first, 4);
+
+        assert_eq!(store.alloc(2), GpuStoreAddress(0));
+        assert_eq!(store.alloc(2), GpuStoreAddress(2));
+        assert_eq!(store.alloc(1), GpuStoreAddress(8));
     }
 
-    pub fn update(&mut self, address: GpuStoreAddress, data: T) {
+    #[test]
+    fn freed_neighbours_are_coalesced() {
+        let mut store: GpuStore<Item, TestLayout> = GpuStore::new();
+        let first = store.alloc(4);
+        let second = store.alloc(4);
+        let third = store.alloc(4);
 
-    }*/
+        store.free(first, 4);
+        store.free(third, 4);
+        store.free(second, 4);
+
+        assert_eq!(store.alloc(12), GpuStoreAddress(0));
+        assert_eq!(store.get_next_address(), GpuStoreAddress(12));
+    }
+
+    #[test]
+    fn build_only_returns_the_rows_written_since_the_last_build() {
+        let mut store: GpuStore<Item, TestLayout> = GpuStore::new();
+        let address = store.alloc(10);
+        let updates = store.build();
+        assert_eq!(updates.item_count, 12);
+        assert_eq!(updates.blocks.len(), 1);
+        assert_eq!(updates.blocks[0].data.len(), 12);
+
+        store.get_mut(address + 5usize)[0] = 1.0;
+        let updates = store.build();
+        assert_eq!(updates.blocks.len(), 1);
+        assert_eq!(updates.blocks[0].address, GpuStoreAddress(4));
+        assert_eq!(updates.blocks[0].data.len(), 4);
+        assert_eq!(updates.blocks[0].data[1][0], 1.0);
+    }
+
+    #[test]
+    fn setting_an_unchanged_item_does_not_dirty_it() {
+        let mut store: GpuStore<Item, TestLayout> = GpuStore::new();
+        let address = store.alloc(4);
+        store.set(address + 1usize, [1.0, 0.0, 0.0, 0.0]);
+        assert_eq!(store.build().blocks.len(), 1);
+
+        store.set(address + 1usize, [1.0, 0.0, 0.0, 0.0]);
+        assert!(store.build().blocks.is_empty());
+
+        store.set(address + 1usize, [2.0, 0.0, 0.0, 0.0]);
+        let updates = store.build();
+        assert_eq!(updates.blocks.len(), 1);
+        assert_eq!(updates.blocks[0].data[1][0], 2.0);
+    }
 }
@@ -15,7 +15,7 @@ use std::path::PathBuf;
 use std::sync::Arc;
 use tiling;
 use renderer::BlendMode;
-use webrender_traits::{Epoch, ColorF, PipelineId};
+use webrender_traits::{Epoch, ColorF, ColorU, PipelineId};
 use webrender_traits::{ImageFormat, NativeFontHandle};
 use webrender_traits::{ExternalImageData, ExternalImageId, ScrollLayerId};
 use webrender_traits::{ImageData};
@@ -331,6 +331,9 @@ pub struct RendererFrame {
     pub pipeline_epoch_map: HashMap<PipelineId, Epoch, BuildHasherDefault<FnvHasher>>,
     /// The layers that are currently affected by the over-scrolling animation.
     pub layers_bouncing_back: HashSet<ScrollLayerId, BuildHasherDefault<FnvHasher>>,
+    /// The layers that are currently moved by a smooth scroll or fling, or
+    /// whose scrollbars are fading out.
+    pub layers_animating_scroll: HashSet<ScrollLayerId, BuildHasherDefault<FnvHasher>>,
 
     pub frame: Option<tiling::Frame>,
 }
@@ -338,11 +341,13 @@ pub struct RendererFrame {
 impl RendererFrame {
     pub fn new(pipeline_epoch_map: HashMap<PipelineId, Epoch, BuildHasherDefault<FnvHasher>>,
                layers_bouncing_back: HashSet<ScrollLayerId, BuildHasherDefault<FnvHasher>>,
+               layers_animating_scroll: HashSet<ScrollLayerId, BuildHasherDefault<FnvHasher>>,
                frame: Option<tiling::Frame>)
                -> RendererFrame {
         RendererFrame {
             pipeline_epoch_map: pipeline_epoch_map,
             layers_bouncing_back: layers_bouncing_back,
+            layers_animating_scroll: layers_animating_scroll,
             frame: frame,
         }
     }
@@ -383,6 +388,10 @@ pub enum LowLevelFilterOp {
     Opacity(Au),
     Saturate(Au),
     Sepia(Au),
+    /// Offset (x, y), blur radius and color.
+    DropShadow(Au, Au, Au, ColorU),
+    /// Index into `CompositeOps::filter_graphs`.
+    Graph(usize),
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
@@ -58,6 +58,7 @@ mod debug_colors;
 mod debug_font_data;
 mod debug_render;
 mod device;
+mod filter_graph;
 mod frame;
 mod frame_builder;
 mod freelist;
@@ -65,6 +66,10 @@ mod geometry;
 mod gpu_store;
 mod internal_types;
 mod mask_cache;
+mod mask_store;
+mod persistent_target;
+mod picture_cache;
+mod plane_split;
 mod prim_store;
 mod profiler;
 mod record;
@@ -72,6 +77,7 @@ mod render_backend;
 mod render_task;
 mod resource_cache;
 mod scene;
+mod scroll_animation;
 mod spring;
 mod texture_cache;
 mod tiling;
@@ -139,8 +145,8 @@ extern crate offscreen_gl_context;
 extern crate byteorder;
 extern crate threadpool;
 
-#[cfg(any(target_os="macos", target_os="windows"))]
 extern crate gamma_lut;
 
+pub use frame_builder::ScrollbarStyle;
 pub use renderer::{ExternalImage, ExternalImageSource, ExternalImageHandler};
 pub use renderer::{Renderer, RendererOptions};
@@ -29,7 +29,7 @@ pub enum RegionMode {
 
 #[derive(Clone, Debug)]
 pub enum ClipSource {
-    Complex(LayerRect, f32, ClipMode),
+    Complex(LayerRect, BorderRadius, ClipMode),
     // The RegionMode here specifies whether to consider the rect
     // from the clip region as part of the mask. This is true
     // for clip/scroll nodes, but false for primitives, where
@@ -49,7 +49,7 @@ impl ClipSource {
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
 pub struct ClipAddressRange {
     pub start: GpuStoreAddress,
-    item_count: usize,
+    pub item_count: usize,
 }
 
 /// Represents a local rect and a device space
@@ -159,6 +159,21 @@ impl MaskCacheInfo {
         })
     }
 
+    /// Releases the GPU store data allocated by `new()`, once this info is no
+    /// longer in use.
+    pub fn free(&self, clip_store: &mut VertexDataStore<GpuBlock32>) {
+        if let Some((_, address)) = self.image {
+            clip_store.free(address, MASK_DATA_GPU_SIZE);
+        }
+        clip_store.free(self.clip_range.start, CLIP_DATA_GPU_SIZE * self.clip_range.item_count);
+    }
+
+    /// Forget the cached bounds, so the next `update()` re-uploads the clip
+    /// data. Used when the clip sources change but keep the same allocation.
+    pub fn invalidate(&mut self) {
+        self.bounds = None;
+    }
+
     pub fn update(&mut self,
                   sources: &[ClipSource],
                   transform: &LayerToWorldTransform,
@@ -180,7 +195,7 @@ impl MaskCacheInfo {
 
             for source in sources {
                 match source {
-                    &ClipSource::Complex(rect, radius, mode) => {
+                    &ClipSource::Complex(rect, radii, mode) => {
                         // Once we encounter a clip-out, we just assume the worst
                         // case clip mask size, for now.
                         if mode == ClipMode::ClipOut {
@@ -191,11 +206,11 @@ impl MaskCacheInfo {
                         self.effective_clip_count += 1;
 
                         let slice = clip_store.get_slice_mut(address, CLIP_DATA_GPU_SIZE);
-                        let data = ClipData::uniform(rect, radius, mode);
+                        let region = ComplexClipRegion::new(rect, radii);
+                        let data = ClipData::complex(&region, mode);
                         PrimitiveStore::populate_clip_data(slice, data);
                         local_rect = local_rect.and_then(|r| r.intersection(&rect));
-                        local_inner = ComplexClipRegion::new(rect, BorderRadius::uniform(radius))
-                                                        .get_inner_rect();
+                        local_inner = region.get_inner_rect();
                     }
                     &ClipSource::Region(ref region, region_mode) => {
                         local_rect = local_rect.and_then(|r| r.intersection(&region.main));
@@ -0,0 +1,63 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use persistent_target::{PersistentTarget, PersistentTargetContents, PersistentTargetOp};
+use webrender_traits::ScrollLayerId;
+
+/// The width and height of the render target that shared clip masks are
+/// kept in between frames.
+pub const CLIP_MASK_STORE_SIZE: u32 = 2048;
+
+/// A signature of everything that affects the contents of the clip mask
+/// shared by the primitives inside a masking clip.
+#[derive(Debug, Copy, Clone, Eq, Hash, PartialEq)]
+pub struct ClipMaskKey(pub u64);
+
+pub type ClipMaskStoreContents = PersistentTargetContents<ClipMaskKey>;
+
+pub type ClipMaskStoreOp = PersistentTargetOp<ClipMaskKey>;
+
+/// Allocates space in the clip mask store for the masks shared by the
+/// primitives of masking clips, so that frames which only scroll inside a
+/// clip don't have to render its mask again.
+pub type ClipMaskStore = PersistentTarget<ScrollLayerId, ClipMaskKey>;
+
+pub fn new_clip_mask_store(contents: ClipMaskStoreContents) -> ClipMaskStore {
+    // Masks are stored right away, since scrolling inside a clip keeps its
+    // mask from the first frame on.
+    ClipMaskStore::new(contents, CLIP_MASK_STORE_SIZE, true)
+}
+
+#[cfg(test)]
+mod tests {
+    use persistent_target::PersistentTargetOp;
+    use std::sync::{Arc, Mutex};
+    use super::{ClipMaskKey, new_clip_mask_store};
+    use webrender_traits::{DeviceIntSize, PipelineId, ScrollLayerId};
+
+    #[test]
+    fn masks_are_stored_on_their_first_frame() {
+        let contents = Arc::new(Mutex::new(Vec::new()));
+        let mut store = new_clip_mask_store(contents.clone());
+        let pipeline_id = PipelineId(0, 0);
+        let clip_id = ScrollLayerId::new(1, pipeline_id);
+        let key = ClipMaskKey(1);
+        let size = DeviceIntSize::new(100, 100);
+
+        let rect = match store.request(clip_id, pipeline_id, key, size) {
+            Some(PersistentTargetOp::Store(rect, stored_key)) => {
+                assert_eq!(stored_key, key);
+                rect
+            }
+            op => panic!("expected a store, got {:?}", op),
+        };
+        store.end_frame();
+
+        contents.lock().unwrap().push((rect, key));
+        match store.request(clip_id, pipeline_id, key, size) {
+            Some(PersistentTargetOp::Reuse(stored_rect)) => assert_eq!(stored_rect, rect),
+            op => panic!("expected a reuse, got {:?}", op),
+        }
+    }
+}
@@ -0,0 +1,277 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Render targets whose contents are kept between frames.
+//!
+//! The backend allocates space in a persistent target for things which are
+//! expensive to render and tend to stay the same, and identifies what it
+//! wants to keep there with a key. The renderer copies what it draws into
+//! that space, and records which key each rect holds, so later frames can
+//! copy from the target instead of rendering again.
+
+use fnv::FnvHasher;
+use internal_types::CacheTextureId;
+use std::collections::HashMap;
+use std::hash::{BuildHasherDefault, Hash};
+use std::sync::{Arc, Mutex};
+use texture_cache::TexturePage;
+use webrender_traits::{DeviceIntPoint, DeviceIntRect, DeviceIntSize, DeviceUintRect};
+use webrender_traits::{DeviceUintSize, PipelineId};
+
+/// The rects of a persistent target that the renderer has drawn, and the keys
+/// of what they hold. Only the renderer adds to this, once it has actually
+/// drawn a frame that stores something, since the frames built by the backend
+/// may be skipped.
+pub type PersistentTargetContents<K> = Arc<Mutex<Vec<(DeviceIntRect, K)>>>;
+
+#[derive(Debug, Copy, Clone)]
+pub enum PersistentTargetOp<K> {
+    /// Render as usual, and copy the result into the given rect of the
+    /// persistent target.
+    Store(DeviceIntRect, K),
+    /// Copy from the given rect of the persistent target, instead of
+    /// rendering.
+    Reuse(DeviceIntRect),
+}
+
+struct PersistentTargetEntry<K> {
+    pipeline_id: PipelineId,
+    key: K,
+    rect: Option<DeviceUintRect>,
+    last_frame: usize,
+}
+
+/// Allocates space in a persistent target, for contents identified by `Id`
+/// whose rendering is described by a key of type `K`.
+pub struct PersistentTarget<Id, K> {
+    allocator: TexturePage,
+    entries: HashMap<Id, PersistentTargetEntry<K>, BuildHasherDefault<FnvHasher>>,
+    contents: PersistentTargetContents<K>,
+    /// Whether new contents are stored the first frame they are requested,
+    /// rather than once their key has stayed the same for two frames in a row.
+    store_new_contents: bool,
+    frame_index: usize,
+}
+
+impl<Id: Copy + Eq + Hash, K: Copy + PartialEq> PersistentTarget<Id, K> {
+    pub fn new(contents: PersistentTargetContents<K>,
+               size: u32,
+               store_new_contents: bool)
+               -> PersistentTarget<Id, K> {
+        PersistentTarget {
+            allocator: TexturePage::new(CacheTextureId(0), DeviceUintSize::new(size, size)),
+            entries: HashMap::with_hasher(Default::default()),
+            contents: contents,
+            store_new_contents: store_new_contents,
+            frame_index: 0,
+        }
+    }
+
+    /// Free the space used by a pipeline right away, rather than waiting for
+    /// the end of a frame that doesn't request it.
+    pub fn discard_pipeline(&mut self, pipeline_id: PipelineId) {
+        let discarded: Vec<Id> = self.entries
+                                     .iter()
+                                     .filter(|&(_, entry)| entry.pipeline_id == pipeline_id)
+                                     .map(|(id, _)| *id)
+                                     .collect();
+        for id in discarded {
+            self.remove_entry(id);
+        }
+    }
+
+    /// Decide whether the contents with the given id are stored this frame,
+    /// copied from the target, or neither, if they have just changed or there
+    /// is no room for them.
+    pub fn request(&mut self,
+                   id: Id,
+                   pipeline_id: PipelineId,
+                   key: K,
+                   size: DeviceIntSize)
+                   -> Option<PersistentTargetOp<K>> {
+        let frame_index = self.frame_index;
+        let previous_key = self.entries.get(&id).map(|entry| entry.key);
+
+        if previous_key != Some(key) {
+            self.remove_entry(id);
+            self.entries.insert(id, PersistentTargetEntry {
+                pipeline_id: pipeline_id,
+                key: key,
+                rect: None,
+                last_frame: frame_index,
+            });
+            if !self.store_new_contents {
+                return None;
+            }
+        }
+
+        let rect = {
+            let entry = self.entries.get_mut(&id).unwrap();
+            entry.last_frame = frame_index;
+            entry.rect
+        };
+
+        if let Some(rect) = rect {
+            let rect = DeviceIntRect::new(DeviceIntPoint::new(rect.origin.x as i32,
+                                                              rect.origin.y as i32),
+                                          size);
+            let is_stored = self.contents.lock().unwrap().contains(&(rect, key));
+            return Some(if is_stored {
+                PersistentTargetOp::Reuse(rect)
+            } else {
+                PersistentTargetOp::Store(rect, key)
+            });
+        }
+
+        let size = DeviceUintSize::new(size.width as u32, size.height as u32);
+        let mut origin = self.allocator.allocate(&size);
+        if origin.is_none() && self.allocator.coalesce() {
+            origin = self.allocator.allocate(&size);
+        }
+        let origin = match origin {
+            Some(origin) => origin,
+            None => return None,
+        };
+
+        let rect = DeviceUintRect::new(origin, size);
+        self.entries.get_mut(&id).unwrap().rect = Some(rect);
+
+        // Anything the renderer stored in this space before is about to be
+        // overwritten, even if it is drawing an older frame that reuses it.
+        self.forget_contents(&rect);
+
+        let rect = DeviceIntRect::new(DeviceIntPoint::new(origin.x as i32, origin.y as i32),
+                                      DeviceIntSize::new(size.width as i32, size.height as i32));
+        Some(PersistentTargetOp::Store(rect, key))
+    }
+
+    /// Free the space of the contents that weren't requested this frame.
+    pub fn end_frame(&mut self) {
+        let frame_index = self.frame_index;
+        let unused: Vec<Id> = self.entries
+                                  .iter()
+                                  .filter(|&(_, entry)| entry.last_frame != frame_index)
+                                  .map(|(id, _)| *id)
+                                  .collect();
+        for id in unused {
+            self.remove_entry(id);
+        }
+
+        self.frame_index += 1;
+    }
+
+    /// Free the space of an entry, and forget what the renderer stored in it.
+    fn remove_entry(&mut self, id: Id) {
+        let rect = self.entries
+                       .remove(&id)
+                       .and_then(|entry| entry.rect);
+        if let Some(rect) = rect {
+            self.allocator.free(&rect);
+            self.forget_contents(&rect);
+        }
+    }
+
+    fn forget_contents(&mut self, rect: &DeviceUintRect) {
+        let rect = DeviceIntRect::new(DeviceIntPoint::new(rect.origin.x as i32, rect.origin.y as i32),
+                                      DeviceIntSize::new(rect.size.width as i32,
+                                                         rect.size.height as i32));
+        self.contents.lock().unwrap().retain(|&(stored_rect, _)| !stored_rect.intersects(&rect));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PersistentTarget, PersistentTargetOp};
+    use std::sync::{Arc, Mutex};
+    use webrender_traits::{DeviceIntSize, PipelineId};
+
+    const SIZE: u32 = 1024;
+
+    #[test]
+    fn contents_are_only_reused_once_the_renderer_has_stored_them() {
+        let contents = Arc::new(Mutex::new(Vec::new()));
+        let mut target: PersistentTarget<usize, u64> = PersistentTarget::new(contents.clone(), SIZE, true);
+        let pipeline_id = PipelineId(0, 0);
+        let size = DeviceIntSize::new(100, 100);
+
+        let rect = match target.request(1, pipeline_id, 1, size) {
+            Some(PersistentTargetOp::Store(rect, 1)) => rect,
+            op => panic!("expected a store, got {:?}", op),
+        };
+        target.end_frame();
+
+        // The renderer hasn't drawn the previous frame yet.
+        match target.request(1, pipeline_id, 1, size) {
+            Some(PersistentTargetOp::Store(stored_rect, 1)) => assert_eq!(stored_rect, rect),
+            op => panic!("expected a store, got {:?}", op),
+        }
+        target.end_frame();
+
+        contents.lock().unwrap().push((rect, 1));
+        match target.request(1, pipeline_id, 1, size) {
+            Some(PersistentTargetOp::Reuse(stored_rect)) => assert_eq!(stored_rect, rect),
+            op => panic!("expected a reuse, got {:?}", op),
+        }
+    }
+
+    #[test]
+    fn new_contents_can_wait_for_a_second_frame() {
+        let contents = Arc::new(Mutex::new(Vec::new()));
+        let mut target: PersistentTarget<usize, u64> = PersistentTarget::new(contents, SIZE, false);
+        let pipeline_id = PipelineId(0, 0);
+        let size = DeviceIntSize::new(100, 100);
+
+        assert!(target.request(1, pipeline_id, 1, size).is_none());
+        target.end_frame();
+        assert!(target.request(1, pipeline_id, 2, size).is_none());
+        target.end_frame();
+        match target.request(1, pipeline_id, 2, size) {
+            Some(PersistentTargetOp::Store(_, 2)) => {}
+            op => panic!("expected a store, got {:?}", op),
+        }
+    }
+
+    #[test]
+    fn changed_unused_and_discarded_contents_are_forgotten() {
+        let contents = Arc::new(Mutex::new(Vec::new()));
+        let mut target: PersistentTarget<usize, u64> = PersistentTarget::new(contents.clone(), SIZE, true);
+        let first_pipeline_id = PipelineId(0, 0);
+        let second_pipeline_id = PipelineId(0, 1);
+        let whole = DeviceIntSize::new(SIZE as i32, SIZE as i32);
+        let half = DeviceIntSize::new(SIZE as i32, SIZE as i32 / 2);
+
+        // A new key takes over the space, and the old contents are forgotten.
+        let rect = match target.request(1, first_pipeline_id, 1, whole) {
+            Some(PersistentTargetOp::Store(rect, _)) => rect,
+            op => panic!("expected a store, got {:?}", op),
+        };
+        contents.lock().unwrap().push((rect, 1));
+        target.end_frame();
+        assert!(target.request(1, first_pipeline_id, 2, whole).is_some());
+        assert!(contents.lock().unwrap().is_empty());
+        target.end_frame();
+
+        // Contents which aren't requested for a frame free their space.
+        target.end_frame();
+        let rect = match target.request(2, first_pipeline_id, 3, half) {
+            Some(PersistentTargetOp::Store(rect, _)) => rect,
+            op => panic!("expected a store, got {:?}", op),
+        };
+        contents.lock().unwrap().push((rect, 3));
+        let other_rect = match target.request(3, second_pipeline_id, 4, half) {
+            Some(PersistentTargetOp::Store(rect, _)) => rect,
+            op => panic!("expected a store, got {:?}", op),
+        };
+        contents.lock().unwrap().push((other_rect, 4));
+        target.end_frame();
+
+        // Only the contents of a discarded pipeline are dropped.
+        target.discard_pipeline(second_pipeline_id);
+        assert_eq!(*contents.lock().unwrap(), vec![(rect, 3)]);
+        match target.request(2, first_pipeline_id, 3, half) {
+            Some(PersistentTargetOp::Reuse(stored_rect)) => assert_eq!(stored_rect, rect),
+            op => panic!("expected a reuse, got {:?}", op),
+        }
+    }
+}
@@ -0,0 +1,142 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use persistent_target::{PersistentTarget, PersistentTargetContents, PersistentTargetOp};
+use webrender_traits::{Epoch, PipelineId};
+
+/// The width and height of the render target that pictures are cached in.
+pub const PICTURE_CACHE_SIZE: u32 = 2048;
+
+/// A signature of everything that affects the rendered contents of a
+/// stacking context, relative to the origin of its bounding rect.
+#[derive(Debug, Copy, Clone, Eq, Hash, PartialEq)]
+pub struct PictureKey(pub u64);
+
+/// Identifies a cacheable stacking context across scene rebuilds. Stacking
+/// context indices change whenever any pipeline does, but a display list
+/// always flattens into the same stacking contexts, in the same order.
+#[derive(Debug, Copy, Clone, Eq, Hash, PartialEq)]
+pub struct PictureId {
+    pub pipeline_id: PipelineId,
+    pub epoch: Epoch,
+    /// The position of the stacking context among those of its pipeline.
+    pub index: usize,
+}
+
+pub type PictureCacheContents = PersistentTargetContents<PictureKey>;
+
+pub type PictureCacheOp = PersistentTargetOp<PictureKey>;
+
+/// Allocates space in the picture cache target for the stacking contexts
+/// whose contents don't change between frames.
+pub type PictureCache = PersistentTarget<PictureId, PictureKey>;
+
+pub fn new_picture_cache(contents: PictureCacheContents) -> PictureCache {
+    // Pictures are only stored once they have stayed the same for two frames
+    // in a row, so that pictures which change every frame aren't copied for
+    // nothing.
+    PictureCache::new(contents, PICTURE_CACHE_SIZE, false)
+}
+
+#[cfg(test)]
+mod tests {
+    use persistent_target::PersistentTargetOp;
+    use std::sync::{Arc, Mutex};
+    use super::{PICTURE_CACHE_SIZE, PictureCache, PictureId, PictureKey, new_picture_cache};
+    use webrender_traits::{DeviceIntRect, DeviceIntSize, Epoch, PipelineId};
+
+    fn picture_id(pipeline_id: PipelineId, index: usize) -> PictureId {
+        PictureId {
+            pipeline_id: pipeline_id,
+            epoch: Epoch(0),
+            index: index,
+        }
+    }
+
+    fn store_picture(cache: &mut PictureCache,
+                     contents: &Arc<Mutex<Vec<(DeviceIntRect, PictureKey)>>>,
+                     id: PictureId,
+                     key: PictureKey,
+                     size: DeviceIntSize)
+                     -> DeviceIntRect {
+        assert!(cache.request(id, id.pipeline_id, key, size).is_none());
+        cache.end_frame();
+
+        let rect = match cache.request(id, id.pipeline_id, key, size) {
+            Some(PersistentTargetOp::Store(rect, stored_key)) => {
+                assert_eq!(stored_key, key);
+                rect
+            }
+            op => panic!("expected a store, got {:?}", op),
+        };
+        cache.end_frame();
+        contents.lock().unwrap().push((rect, key));
+        rect
+    }
+
+    #[test]
+    fn pictures_are_stored_on_their_second_frame_and_then_reused() {
+        let contents = Arc::new(Mutex::new(Vec::new()));
+        let mut cache = new_picture_cache(contents.clone());
+        let id = picture_id(PipelineId(0, 0), 1);
+        let size = DeviceIntSize::new(100, 100);
+
+        let rect = store_picture(&mut cache, &contents, id, PictureKey(1), size);
+        match cache.request(id, id.pipeline_id, PictureKey(1), size) {
+            Some(PersistentTargetOp::Reuse(stored_rect)) => assert_eq!(stored_rect, rect),
+            op => panic!("expected a reuse, got {:?}", op),
+        }
+
+        // A picture that changes starts over.
+        cache.end_frame();
+        assert!(cache.request(id, id.pipeline_id, PictureKey(2), size).is_none());
+        assert!(contents.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn pictures_that_are_not_requested_are_freed() {
+        let contents = Arc::new(Mutex::new(Vec::new()));
+        let mut cache = new_picture_cache(contents.clone());
+        let pipeline_id = PipelineId(0, 0);
+        let size = DeviceIntSize::new(PICTURE_CACHE_SIZE as i32, PICTURE_CACHE_SIZE as i32);
+
+        store_picture(&mut cache, &contents, picture_id(pipeline_id, 1), PictureKey(1), size);
+        cache.end_frame();
+        assert!(contents.lock().unwrap().is_empty());
+
+        // The whole target is free for another picture.
+        store_picture(&mut cache, &contents, picture_id(pipeline_id, 2), PictureKey(2), size);
+    }
+
+    #[test]
+    fn only_the_pictures_of_a_discarded_pipeline_are_dropped() {
+        let contents = Arc::new(Mutex::new(Vec::new()));
+        let mut cache = new_picture_cache(contents.clone());
+        let size = DeviceIntSize::new(100, 100);
+        let first_id = picture_id(PipelineId(0, 0), 0);
+        let second_id = picture_id(PipelineId(0, 1), 0);
+
+        let first_rect = store_picture(&mut cache, &contents, first_id, PictureKey(1), size);
+        // Keep the first picture alive while the second is stored.
+        assert!(cache.request(first_id, first_id.pipeline_id, PictureKey(1), size).is_some());
+        assert!(cache.request(second_id, second_id.pipeline_id, PictureKey(2), size).is_none());
+        cache.end_frame();
+        assert!(cache.request(first_id, first_id.pipeline_id, PictureKey(1), size).is_some());
+        let second_rect = match cache.request(second_id, second_id.pipeline_id, PictureKey(2), size) {
+            Some(PersistentTargetOp::Store(rect, _)) => rect,
+            op => panic!("expected a store, got {:?}", op),
+        };
+        cache.end_frame();
+        contents.lock().unwrap().push((second_rect, PictureKey(2)));
+
+        cache.discard_pipeline(second_id.pipeline_id);
+        assert_eq!(*contents.lock().unwrap(), vec![(first_rect, PictureKey(1))]);
+
+        match cache.request(first_id, first_id.pipeline_id, PictureKey(1), size) {
+            Some(PersistentTargetOp::Reuse(rect)) => assert_eq!(rect, first_rect),
+            op => panic!("expected a reuse, got {:?}", op),
+        }
+        assert!(cache.request(second_id, second_id.pipeline_id, PictureKey(2), size).is_none());
+    }
+}
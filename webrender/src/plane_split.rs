@@ -0,0 +1,316 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Depth sorting for the planes of a 3D rendering context.
+//!
+//! The planes are inserted into a BSP tree, splitting any polygons that
+//! cross the plane of a node, so that the tree can be walked to produce
+//! a list of polygons that is correctly ordered from back to front.
+
+use euclid::Point3D;
+use webrender_traits::{LayerPoint4D, LayerRect, LayerToWorldTransform};
+
+/// Distances below this are treated as lying on a plane, to avoid
+/// producing slivers from floating point error.
+const PLANE_EPSILON: f32 = 0.01;
+
+fn dot(a: &Point3D<f32>, b: &Point3D<f32>) -> f32 {
+    a.x * b.x + a.y * b.y + a.z * b.z
+}
+
+fn lerp(a: &Point3D<f32>, b: &Point3D<f32>, t: f32) -> Point3D<f32> {
+    Point3D::new(a.x + (b.x - a.x) * t,
+                 a.y + (b.y - a.y) * t,
+                 a.z + (b.z - a.z) * t)
+}
+
+/// A convex, planar polygon in world space. Polygons are always stored as
+/// quads so they can be drawn with a single instance; a triangle repeats
+/// its last point.
+#[derive(Debug, Clone)]
+pub struct Polygon {
+    pub points: [Point3D<f32>; 4],
+    normal: Point3D<f32>,
+    offset: f32,
+    /// Index of the plane this polygon was (possibly) split from.
+    pub anchor: usize,
+}
+
+impl Polygon {
+    /// Transforms `rect` into world space. Returns `None` if the result
+    /// is degenerate (seen edge on) or passes behind the viewer.
+    pub fn from_transformed_rect(rect: &LayerRect,
+                                 transform: &LayerToWorldTransform,
+                                 anchor: usize) -> Option<Polygon> {
+        let corners = [
+            rect.origin,
+            rect.top_right(),
+            rect.bottom_right(),
+            rect.bottom_left(),
+        ];
+
+        let mut points = [Point3D::new(0.0, 0.0, 0.0); 4];
+        for (point, corner) in points.iter_mut().zip(corners.iter()) {
+            let p = transform.transform_point4d(&LayerPoint4D::new(corner.x, corner.y, 0.0, 1.0));
+            if p.w <= 0.0 {
+                return None;
+            }
+            *point = Point3D::new(p.x / p.w, p.y / p.w, p.z / p.w);
+        }
+
+        // Newell's method, which gives a stable normal even when some
+        // of the points are (nearly) coincident.
+        let mut normal = Point3D::new(0.0, 0.0, 0.0);
+        for i in 0..4 {
+            let a = &points[i];
+            let b = &points[(i + 1) % 4];
+            normal.x += (a.y - b.y) * (a.z + b.z);
+            normal.y += (a.z - b.z) * (a.x + b.x);
+            normal.z += (a.x - b.x) * (a.y + b.y);
+        }
+
+        let length = dot(&normal, &normal).sqrt();
+        if length < PLANE_EPSILON {
+            return None;
+        }
+        let normal = Point3D::new(normal.x / length, normal.y / length, normal.z / length);
+
+        Some(Polygon {
+            offset: -dot(&normal, &points[0]),
+            normal: normal,
+            points: points,
+            anchor: anchor,
+        })
+    }
+
+    fn signed_distance(&self, point: &Point3D<f32>) -> f32 {
+        dot(&self.normal, point) + self.offset
+    }
+
+    /// Builds polygons lying in the same plane as `self` from an arbitrary
+    /// convex point list, splitting it into quads as required.
+    fn with_points(&self, points: &[Point3D<f32>], output: &mut Vec<Polygon>) {
+        if points.len() < 3 {
+            return;
+        }
+
+        // Fan out from the first point, two triangles (one quad) at a time.
+        let mut i = 1;
+        while i + 1 < points.len() {
+            let last = if i + 2 < points.len() { i + 2 } else { i + 1 };
+            output.push(Polygon {
+                points: [points[0], points[i], points[i + 1], points[last]],
+                normal: self.normal,
+                offset: self.offset,
+                anchor: self.anchor,
+            });
+            i += 2;
+        }
+    }
+
+    /// Classifies `polygon` against the plane of `self`, splitting it in
+    /// two if it crosses the plane.
+    fn split(&self, polygon: Polygon) -> Split {
+        let distances = [
+            self.signed_distance(&polygon.points[0]),
+            self.signed_distance(&polygon.points[1]),
+            self.signed_distance(&polygon.points[2]),
+            self.signed_distance(&polygon.points[3]),
+        ];
+
+        if distances.iter().all(|d| d.abs() <= PLANE_EPSILON) {
+            return Split::Coplanar(polygon);
+        }
+        if distances.iter().all(|&d| d >= -PLANE_EPSILON) {
+            return Split::Front(polygon);
+        }
+        if distances.iter().all(|&d| d <= PLANE_EPSILON) {
+            return Split::Back(polygon);
+        }
+
+        let mut front_points = Vec::with_capacity(6);
+        let mut back_points = Vec::with_capacity(6);
+        for i in 0..4 {
+            let j = (i + 1) % 4;
+            let (a, b) = (&polygon.points[i], &polygon.points[j]);
+            let (da, db) = (distances[i], distances[j]);
+
+            if da >= -PLANE_EPSILON {
+                front_points.push(*a);
+            }
+            if da <= PLANE_EPSILON {
+                back_points.push(*a);
+            }
+            if (da > PLANE_EPSILON && db < -PLANE_EPSILON) ||
+               (da < -PLANE_EPSILON && db > PLANE_EPSILON) {
+                let point = lerp(a, b, da / (da - db));
+                front_points.push(point);
+                back_points.push(point);
+            }
+        }
+
+        let mut front = Vec::new();
+        let mut back = Vec::new();
+        polygon.with_points(&front_points, &mut front);
+        polygon.with_points(&back_points, &mut back);
+        Split::Both(front, back)
+    }
+}
+
+enum Split {
+    Coplanar(Polygon),
+    Front(Polygon),
+    Back(Polygon),
+    Both(Vec<Polygon>, Vec<Polygon>),
+}
+
+struct BspNode {
+    // The first polygon defines the plane of the node.
+    polygons: Vec<Polygon>,
+    front: Option<Box<BspNode>>,
+    back: Option<Box<BspNode>>,
+}
+
+impl BspNode {
+    fn new(polygon: Polygon) -> BspNode {
+        BspNode {
+            polygons: vec![polygon],
+            front: None,
+            back: None,
+        }
+    }
+
+    fn insert_into(child: &mut Option<Box<BspNode>>, polygon: Polygon) {
+        if let Some(ref mut node) = *child {
+            node.insert(polygon);
+            return;
+        }
+        *child = Some(Box::new(BspNode::new(polygon)));
+    }
+
+    fn insert(&mut self, polygon: Polygon) {
+        match self.polygons[0].split(polygon) {
+            Split::Coplanar(polygon) => self.polygons.push(polygon),
+            Split::Front(polygon) => BspNode::insert_into(&mut self.front, polygon),
+            Split::Back(polygon) => BspNode::insert_into(&mut self.back, polygon),
+            Split::Both(front, back) => {
+                for polygon in front {
+                    BspNode::insert_into(&mut self.front, polygon);
+                }
+                for polygon in back {
+                    BspNode::insert_into(&mut self.back, polygon);
+                }
+            }
+        }
+    }
+
+    fn order(&self, output: &mut Vec<Polygon>) {
+        // After projection the viewer looks down the negative z axis, so
+        // whichever side of the plane faces +z is closest.
+        let (first, last) = if self.polygons[0].normal.z >= 0.0 {
+            (&self.back, &self.front)
+        } else {
+            (&self.front, &self.back)
+        };
+
+        if let Some(ref node) = *first {
+            node.order(output);
+        }
+        // Coplanar polygons keep their painter's order.
+        output.extend(self.polygons.iter().cloned());
+        if let Some(ref node) = *last {
+            node.order(output);
+        }
+    }
+}
+
+/// Collects the planes of a 3D rendering context and sorts them.
+pub struct BspSplitter {
+    root: Option<BspNode>,
+}
+
+impl BspSplitter {
+    pub fn new() -> BspSplitter {
+        BspSplitter {
+            root: None,
+        }
+    }
+
+    /// Planes should be added in painter's order, which is used to order
+    /// coplanar polygons.
+    pub fn add(&mut self, polygon: Polygon) {
+        if let Some(ref mut root) = self.root {
+            root.insert(polygon);
+            return;
+        }
+        self.root = Some(BspNode::new(polygon));
+    }
+
+    /// Returns the (split) polygons ordered from back to front.
+    pub fn sort(&self) -> Vec<Polygon> {
+        let mut output = Vec::new();
+        if let Some(ref root) = self.root {
+            root.order(&mut output);
+        }
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BspSplitter, Polygon};
+    use euclid::Radians;
+    use std::f32::consts::FRAC_PI_4;
+    use webrender_traits::{LayerPoint, LayerRect, LayerSize, LayerToWorldTransform};
+
+    fn square() -> LayerRect {
+        LayerRect::new(LayerPoint::new(-50.0, 0.0), LayerSize::new(100.0, 100.0))
+    }
+
+    fn polygon_at_depth(z: f32, anchor: usize) -> Polygon {
+        let transform = LayerToWorldTransform::create_translation(0.0, 0.0, z);
+        Polygon::from_transformed_rect(&square(), &transform, anchor).unwrap()
+    }
+
+    fn anchors(polygons: &[Polygon]) -> Vec<usize> {
+        polygons.iter().map(|polygon| polygon.anchor).collect()
+    }
+
+    #[test]
+    fn parallel_planes_are_sorted_back_to_front() {
+        let mut splitter = BspSplitter::new();
+        splitter.add(polygon_at_depth(10.0, 0));
+        splitter.add(polygon_at_depth(-10.0, 1));
+        splitter.add(polygon_at_depth(0.0, 2));
+        assert_eq!(anchors(&splitter.sort()), vec![1, 2, 0]);
+    }
+
+    #[test]
+    fn coplanar_planes_keep_their_painter_order() {
+        let mut splitter = BspSplitter::new();
+        splitter.add(polygon_at_depth(0.0, 0));
+        splitter.add(polygon_at_depth(0.0, 1));
+        splitter.add(polygon_at_depth(0.0, 2));
+        assert_eq!(anchors(&splitter.sort()), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn intersecting_planes_are_split() {
+        // Tilting the square about the y axis makes it cross the plane
+        // z = 0 along x = 0, with its left half behind it.
+        let tilt = LayerToWorldTransform::create_rotation(0.0, 1.0, 0.0, Radians::new(FRAC_PI_4));
+        let tilted = Polygon::from_transformed_rect(&square(), &tilt, 1).unwrap();
+        assert!(tilted.points.iter().any(|point| point.z < -1.0));
+        assert!(tilted.points.iter().any(|point| point.z > 1.0));
+
+        let mut splitter = BspSplitter::new();
+        splitter.add(polygon_at_depth(0.0, 0));
+        splitter.add(tilted);
+        let sorted = splitter.sort();
+
+        assert_eq!(anchors(&sorted), vec![1, 0, 1]);
+        assert!(sorted[0].points.iter().all(|point| point.z <= 0.01));
+        assert!(sorted[2].points.iter().all(|point| point.z >= -0.01));
+    }
+}
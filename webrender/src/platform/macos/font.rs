@@ -111,21 +111,25 @@ fn get_glyph_metrics(ct_font: &CTFont,
     metrics
 }
 
+// Force CG to use sRGB color space to gamma correct.
+pub const DEFAULT_GAMMA: f32 = 0.0;
+pub const DEFAULT_CONTRAST: f32 = 0.0;
+
 impl FontContext {
     pub fn new() -> FontContext {
         debug!("Test for subpixel AA support: {}", supports_subpixel_aa());
 
-        // Force CG to use sRGB color space to gamma correct.
-        let contrast = 0.0;
-        let gamma = 0.0;
-
         FontContext {
             cg_fonts: HashMap::new(),
             ct_fonts: HashMap::new(),
-            gamma_lut: GammaLut::new(contrast, gamma, gamma),
+            gamma_lut: GammaLut::new(DEFAULT_CONTRAST, DEFAULT_GAMMA, DEFAULT_GAMMA),
         }
     }
 
+    pub fn set_gamma(&mut self, gamma: f32, contrast: f32) {
+        self.gamma_lut = GammaLut::new(contrast, gamma, gamma);
+    }
+
     pub fn add_raw_font(&mut self, font_key: &FontKey, bytes: &[u8]) {
         if self.cg_fonts.contains_key(font_key) {
             return
@@ -14,6 +14,7 @@ use freetype::freetype::{FT_Face, FT_Long, FT_UInt, FT_F26Dot6};
 use freetype::freetype::{FT_Init_FreeType, FT_Load_Glyph, FT_Render_Glyph};
 use freetype::freetype::{FT_New_Memory_Face, FT_GlyphSlot, FT_LcdFilter};
 use freetype::freetype::{FT_Done_Face};
+use gamma_lut::{GammaLut, Color as ColorLut};
 
 use std::{mem, ptr, slice};
 use std::collections::HashMap;
@@ -25,6 +26,7 @@ struct Face {
 pub struct FontContext {
     lib: FT_Library,
     faces: HashMap<FontKey, Face>,
+    gamma_lut: GammaLut,
 }
 
 pub struct RasterizedGlyph {
@@ -33,6 +35,12 @@ pub struct RasterizedGlyph {
     pub bytes: Vec<u8>,
 }
 
+// The default values used by Gecko for FreeType glyphs. FreeType
+// produces linear coverage, so without preblending text is rendered
+// noticeably thinner than on the other platforms.
+pub const DEFAULT_GAMMA: f32 = 1.8;
+pub const DEFAULT_CONTRAST: f32 = 1.0;
+
 fn float_to_fixed(before: usize, f: f64) -> i32 {
     ((1i32 << before) as f64 * f) as i32
 }
@@ -58,9 +66,14 @@ impl FontContext {
         FontContext {
             lib: lib,
             faces: HashMap::new(),
+            gamma_lut: GammaLut::new(DEFAULT_CONTRAST, DEFAULT_GAMMA, DEFAULT_GAMMA),
         }
     }
 
+    pub fn set_gamma(&mut self, gamma: f32, contrast: f32) {
+        self.gamma_lut = GammaLut::new(contrast, gamma, gamma);
+    }
+
     pub fn add_raw_font(&mut self, font_key: &FontKey, bytes: &[u8]) {
         if !self.faces.contains_key(&font_key) {
             let mut face: FT_Face = ptr::null_mut();
@@ -215,28 +228,54 @@ impl FontContext {
                         )
                     };
 
-                    // Convert to RGBA.
+                    let mut pixels = Vec::with_capacity(buffer.len() * 3);
                     for &byte in buffer.iter() {
-                        final_buffer.extend_from_slice(&[ 0xff, 0xff, 0xff, byte ]);
+                        pixels.extend_from_slice(&[ byte, byte, byte ]);
+                    }
+
+                    self.gamma_lut.preblend_rgb(&mut pixels,
+                                                bitmap.width as usize,
+                                                bitmap.rows as usize,
+                                                ColorLut::new(key.color.r,
+                                                              key.color.g,
+                                                              key.color.b,
+                                                              key.color.a));
+
+                    // Convert to RGBA, taking the G channel as the coverage.
+                    for rgb in pixels.chunks(3) {
+                        final_buffer.extend_from_slice(&[ 0xff, 0xff, 0xff, rgb[1] ]);
                     }
                 } else if bitmap.pixel_mode == FT_Pixel_Mode::FT_PIXEL_MODE_LCD as u8 {
                     // Extra subpixel on each side of the glyph.
                     glyph_width += 2;
 
+                    let pixel_width = bitmap.width / 3;
+                    let mut pixels = Vec::with_capacity((pixel_width * bitmap.rows * 3) as usize);
                     for y in 0..bitmap.rows {
-                        for x in 0..(bitmap.width / 3) {
+                        for x in 0..pixel_width {
                             let index = (y as i32 * bitmap.pitch) + (x as i32 * 3);
 
                             unsafe {
                                 let ptr = bitmap.buffer.offset(index as isize);
-                                let b = *ptr;
-                                let g = *(ptr.offset(1));
-                                let r = *(ptr.offset(2));
-
-                                final_buffer.extend_from_slice(&[r, g, b, 0xff]);
+                                pixels.extend_from_slice(&[*ptr,
+                                                           *(ptr.offset(1)),
+                                                           *(ptr.offset(2))]);
                             }
                         }
                     }
+
+                    self.gamma_lut.preblend_rgb(&mut pixels,
+                                                pixel_width as usize,
+                                                bitmap.rows as usize,
+                                                ColorLut::new(key.color.r,
+                                                              key.color.g,
+                                                              key.color.b,
+                                                              key.color.a));
+
+                    // FreeType produces RGB ordered subpixels, stored as BGRA.
+                    for rgb in pixels.chunks(3) {
+                        final_buffer.extend_from_slice(&[rgb[2], rgb[1], rgb[0], 0xff]);
+                    }
                 } else {
                     panic!("Unexpected render mode: {}!", bitmap.pixel_mode);
                 }
@@ -102,21 +102,28 @@ fn get_glyph_dimensions_with_analysis(analysis: dwrote::GlyphRunAnalysis,
     })
 }
 
+// These are the default values we use in Gecko.
+// TODO: Fetch this data from Gecko itself.
+pub const DEFAULT_GAMMA: f32 = 1.8;
+pub const DEFAULT_CONTRAST: f32 = 1.0;
+
+// We use a gamma value of 2.3 for gdi fonts
+const GDI_GAMMA: f32 = 2.3;
+
 impl FontContext {
     pub fn new() -> FontContext {
-        // These are the default values we use in Gecko.
-        // We use a gamma value of 2.3 for gdi fonts
-        // TODO: Fetch this data from Gecko itself.
-        let contrast = 1.0;
-        let gamma = 1.8;
-        let gdi_gamma = 2.3;
         FontContext {
             fonts: HashMap::new(),
-            gamma_lut: GammaLut::new(contrast, gamma, gamma),
-            gdi_gamma_lut: GammaLut::new(contrast, gdi_gamma, gdi_gamma),
+            gamma_lut: GammaLut::new(DEFAULT_CONTRAST, DEFAULT_GAMMA, DEFAULT_GAMMA),
+            gdi_gamma_lut: GammaLut::new(DEFAULT_CONTRAST, GDI_GAMMA, GDI_GAMMA),
         }
     }
 
+    pub fn set_gamma(&mut self, gamma: f32, contrast: f32) {
+        self.gamma_lut = GammaLut::new(contrast, gamma, gamma);
+        self.gdi_gamma_lut = GammaLut::new(contrast, GDI_GAMMA, GDI_GAMMA);
+    }
+
     pub fn add_raw_font(&mut self, font_key: &FontKey, data: &[u8]) {
         if self.fonts.contains_key(font_key) {
             return
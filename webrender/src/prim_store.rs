@@ -20,7 +20,7 @@ use webrender_traits::{device_length, DeviceIntRect, DeviceIntSize};
 use webrender_traits::{DeviceRect, DevicePoint, DeviceSize};
 use webrender_traits::{LayerRect, LayerSize, LayerPoint};
 use webrender_traits::{LayerToWorldTransform, GlyphInstance, GlyphOptions};
-use webrender_traits::{ExtendMode, GradientStop, TileOffset};
+use webrender_traits::{ExtendMode, GradientInterpolation, GradientStop, TileOffset};
 
 pub const CLIP_DATA_GPU_SIZE: usize = 5;
 pub const MASK_DATA_GPU_SIZE: usize = 1;
@@ -30,7 +30,7 @@ pub const MASK_DATA_GPU_SIZE: usize = 1;
 /// may grow. Storing them as texel coords and normalizing
 /// the UVs in the vertex shader means nothing needs to be
 /// updated on the CPU when the texture size changes.
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub struct TexelRect {
     pub uv0: DevicePoint,
     pub uv1: DevicePoint,
@@ -82,6 +82,7 @@ pub struct PrimitiveIndex(pub usize);
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub enum PrimitiveKind {
     Rectangle,
+    Line,
     TextRun,
     Image,
     YuvImage,
@@ -89,6 +90,7 @@ pub enum PrimitiveKind {
     AlignedGradient,
     AngleGradient,
     RadialGradient,
+    ConicGradient,
     BoxShadow,
 }
 
@@ -134,6 +136,23 @@ pub struct RectanglePrimitive {
     pub color: ColorF,
 }
 
+#[derive(Debug, Clone)]
+#[repr(C)]
+pub struct LinePrimitive {
+    pub color: ColorF,
+    pub style: f32,
+    pub orientation: f32,
+    pub padding: [f32; 2],
+}
+
+/// The device space corners of a polygon produced by depth sorting
+/// the planes of a 3D rendering context.
+#[derive(Debug, Clone)]
+#[repr(C)]
+pub struct SplitGeometry {
+    pub points: [DevicePoint; 4],
+}
+
 #[derive(Debug)]
 pub enum ImagePrimitiveKind {
     Image(ImageKey, ImageRendering, Option<TileOffset>, LayerSize),
@@ -146,6 +165,9 @@ pub struct ImagePrimitiveCpu {
     pub color_texture_id: SourceTexture,
     pub resource_address: GpuStoreAddress,
     pub sub_rect: Option<TexelRect>,
+    // The scale blob images are rasterized at, on top of their own size,
+    // so that zoomed blob images stay sharp.
+    pub raster_scale: f32,
 }
 
 #[derive(Debug, Clone)]
@@ -200,7 +222,7 @@ pub struct BorderPrimitiveGpu {
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
 pub struct BoxShadowPrimitiveCacheKey {
     pub shadow_rect_size: Size2D<Au>,
-    pub border_radius: Au,
+    pub border_radii: [Size2D<Au>; 4],
     pub blur_radius: Au,
     pub inverted: bool,
 }
@@ -211,10 +233,26 @@ pub struct BoxShadowPrimitiveGpu {
     pub src_rect: LayerRect,
     pub bs_rect: LayerRect,
     pub color: ColorF,
-    pub border_radius: f32,
+    pub border_radii: [LayerSize; 4],
     pub edge_size: f32,
     pub blur_radius: f32,
     pub inverted: f32,
+    pub mirror: f32,
+    pub padding: [f32; 8],
+}
+
+#[derive(Debug)]
+pub struct BoxShadowPrimitiveCpu {
+    // Cache tasks for the shadows. Shadows with identical cache
+    // keys share a single task.
+    pub render_tasks: Vec<RenderTask>,
+    pub shadows: Vec<BoxShadowCpu>,
+}
+
+#[derive(Debug)]
+pub struct BoxShadowCpu {
+    pub render_task_index: usize,
+    pub rect_count: i32,
 }
 
 #[derive(Debug, Clone)]
@@ -222,7 +260,8 @@ pub struct BoxShadowPrimitiveGpu {
 pub struct GradientStopGpu {
     color: ColorF,
     offset: f32,
-    padding: [f32; 3],
+    hint_exponent: f32,
+    padding: [f32; 2],
 }
 
 #[derive(Debug, Clone)]
@@ -238,7 +277,9 @@ pub struct GradientPrimitiveGpu {
 pub struct GradientPrimitiveCpu {
     pub stops_range: ItemRange,
     pub extend_mode: ExtendMode,
+    pub interpolation: GradientInterpolation,
     pub reverse_stops: bool,
+    pub entry_count: usize,
     pub cache_dirty: bool,
 }
 
@@ -257,12 +298,35 @@ pub struct RadialGradientPrimitiveGpu {
 pub struct RadialGradientPrimitiveCpu {
     pub stops_range: ItemRange,
     pub extend_mode: ExtendMode,
+    pub interpolation: GradientInterpolation,
+    pub entry_count: usize,
+    pub cache_dirty: bool,
+}
+
+#[derive(Debug, Clone)]
+#[repr(C)]
+pub struct ConicGradientPrimitiveGpu {
+    pub center: LayerPoint,
+    pub angle: f32,
+    pub extend_mode: f32,
+    pub padding: [f32; 4],
+}
+
+#[derive(Debug)]
+pub struct ConicGradientPrimitiveCpu {
+    pub stops_range: ItemRange,
+    pub extend_mode: ExtendMode,
+    pub interpolation: GradientInterpolation,
+    pub entry_count: usize,
     pub cache_dirty: bool,
 }
 
-// The number of entries in a gradient data table.
+// The number of entries in a gradient data block.
 pub const GRADIENT_DATA_RESOLUTION: usize = 128;
 
+// The largest number of entries a single gradient's table may use.
+const MAX_GRADIENT_DATA_ENTRIES: usize = 64 * GRADIENT_DATA_RESOLUTION;
+
 #[derive(Debug, Clone, Copy)]
 #[repr(C)]
 // An entry in a gradient data table representing a segment of the gradient color space.
@@ -272,13 +336,14 @@ pub struct GradientDataEntry {
 }
 
 #[repr(C)]
-// A table of gradient entries, with two colors per entry, that specify the start and end color
+// A block of gradient entries, with two colors per entry, that specify the start and end color
 // within the segment of the gradient space represented by that entry. To lookup a gradient result,
 // first the entry index is calculated to determine which two colors to interpolate between, then
 // the offset within that entry bucket is used to interpolate between the two colors in that entry.
 // This layout preserves hard stops, as the end color for a given entry can differ from the start
 // color for the following entry, despite them being adjacent. Colors are stored within in BGRA8
-// format for texture upload.
+// format for texture upload. A gradient with more entries than fit in one block spans several
+// consecutive blocks.
 pub struct GradientData {
     pub colors_high: [GradientDataEntry; GRADIENT_DATA_RESOLUTION],
     pub colors_low: [GradientDataEntry; GRADIENT_DATA_RESOLUTION],
@@ -303,38 +368,53 @@ impl Clone for GradientData {
 }
 
 impl GradientData {
+    // Pick the number of table entries for a set of stops. The table grows past the
+    // default resolution until every stop lands on an entry boundary, so that hard
+    // stops and the ends of each ramp aren't shifted however many stops there are.
+    fn entry_count(stops: &[GradientStop]) -> usize {
+        (GRADIENT_DATA_RESOLUTION..MAX_GRADIENT_DATA_ENTRIES).find(|&entry_count| {
+            stops.iter().all(|stop| {
+                let x = stop.offset.max(0.0).min(1.0) * entry_count as f32;
+                (x - x.round()).abs() < 0.01
+            })
+        }).unwrap_or(MAX_GRADIENT_DATA_ENTRIES)
+    }
+
     // Generate a color ramp between the start and end indexes from a start color to an end color.
-    fn fill_colors(&mut self, start_idx: usize, end_idx: usize, start_color: &ColorF, end_color: &ColorF) -> usize {
+    fn fill_colors(blocks: &mut [GradientData],
+                   start_idx: usize,
+                   end_idx: usize,
+                   start_color: &ColorF,
+                   end_color: &ColorF,
+                   hint_exponent: f32,
+                   interpolation: GradientInterpolation) -> usize {
         if start_idx >= end_idx {
             return start_idx;
         }
 
-        // Calculate the color difference for individual steps in the ramp.
         let inv_steps = 1.0 / (end_idx - start_idx) as f32;
-        let step_r = (end_color.r - start_color.r) * inv_steps;
-        let step_g = (end_color.g - start_color.g) * inv_steps;
-        let step_b = (end_color.b - start_color.b) * inv_steps;
-        let step_a = (end_color.a - start_color.a) * inv_steps;
-
         let mut cur_color = *start_color;
-        let mut cur_color_high = PackedTexel::high_bytes(&cur_color);
-        let mut cur_color_low = PackedTexel::low_bytes(&cur_color);
 
         // Walk the ramp writing start and end colors for each entry.
-        for index in start_idx..end_idx {
-            let high_byte_entry = &mut self.colors_high[index];
-            let low_byte_entry = &mut self.colors_low[index];
-
-            high_byte_entry.start_color = cur_color_high;
-            low_byte_entry.start_color = cur_color_low;
-            cur_color.r += step_r;
-            cur_color.g += step_g;
-            cur_color.b += step_b;
-            cur_color.a += step_a;
-            cur_color_high = PackedTexel::high_bytes(&cur_color);
-            cur_color_low = PackedTexel::low_bytes(&cur_color);
-            high_byte_entry.end_color = cur_color_high;
-            low_byte_entry.end_color = cur_color_low;
+        for (step, index) in (start_idx..end_idx).enumerate() {
+            let next_color = interpolate_color(start_color,
+                                               end_color,
+                                               (step + 1) as f32 * inv_steps,
+                                               hint_exponent,
+                                               interpolation);
+
+            let block = &mut blocks[index / GRADIENT_DATA_RESOLUTION];
+            let entry = index % GRADIENT_DATA_RESOLUTION;
+            block.colors_high[entry] = GradientDataEntry {
+                start_color: PackedTexel::high_bytes(&cur_color),
+                end_color: PackedTexel::high_bytes(&next_color),
+            };
+            block.colors_low[entry] = GradientDataEntry {
+                start_color: PackedTexel::low_bytes(&cur_color),
+                end_color: PackedTexel::low_bytes(&next_color),
+            };
+
+            cur_color = next_color;
         }
 
         end_idx
@@ -342,37 +422,137 @@ impl GradientData {
 
     // Compute an entry index based on a gradient stop offset.
     #[inline]
-    fn get_index(offset: f32) -> usize {
-        (offset.max(0.0).min(1.0) * GRADIENT_DATA_RESOLUTION as f32).round() as usize
+    fn get_index(offset: f32, entry_count: usize) -> usize {
+        (offset.max(0.0).min(1.0) * entry_count as f32).round() as usize
     }
 
     // Build the gradient data from the supplied stops, reversing them if necessary.
-    fn build(&mut self, src_stops: &[GradientStop], reverse_stops: bool) {
+    fn build(blocks: &mut [GradientData],
+             entry_count: usize,
+             src_stops: &[GradientStop],
+             reverse_stops: bool,
+             interpolation: GradientInterpolation) {
+        let stops = gradient_stops_for_interpolation(src_stops, reverse_stops, interpolation);
+
         let mut cur_idx = 0usize;
-        let mut cur_color = if let Some(src) = src_stops.first() {
+        let mut cur_color = if let Some(src) = stops.first() {
             src.color
         } else {
             ColorF::new(0.0, 0.0, 0.0, 0.0)
         };
+        let mut hint_exponent = 1.0;
 
-        if reverse_stops {
-            // If the gradient is reversed, then ensure the stops are processed in reverse order
-            // and that the offsets are inverted.
-            for src in src_stops.iter().rev() {
-                cur_idx = self.fill_colors(cur_idx, Self::get_index(1.0 - src.offset),
-                                           &cur_color, &src.color);
-                cur_color = src.color;
-            }
-        } else {
-            for src in src_stops {
-                cur_idx = self.fill_colors(cur_idx, Self::get_index(src.offset),
-                                           &cur_color, &src.color);
-                cur_color = src.color;
-            }
+        for (i, src) in stops.iter().enumerate() {
+            cur_idx = Self::fill_colors(blocks, cur_idx, Self::get_index(src.offset, entry_count),
+                                        &cur_color, &src.color, hint_exponent, interpolation);
+            cur_color = src.color;
+            hint_exponent = stops.get(i + 1).map_or(1.0, |next| stop_hint_exponent(src, next));
         }
 
         // Fill out any remaining entries in the gradient.
-        self.fill_colors(cur_idx, GRADIENT_DATA_RESOLUTION, &cur_color, &cur_color);
+        Self::fill_colors(blocks, cur_idx, entry_count, &cur_color, &cur_color, 1.0, interpolation);
+    }
+}
+
+// Allocate and build the gradient data table for a set of stops. Returns the address of its
+// first block, the number of blocks and the number of entries in the table.
+fn build_gradient_data(gpu_gradient_data: &mut GradientDataStore,
+                       src_stops: &[GradientStop],
+                       reverse_stops: bool,
+                       interpolation: GradientInterpolation) -> (GpuStoreAddress, usize, usize) {
+    let entry_count = GradientData::entry_count(src_stops);
+    let block_count = (entry_count + GRADIENT_DATA_RESOLUTION - 1) / GRADIENT_DATA_RESOLUTION;
+
+    let address = gpu_gradient_data.alloc(block_count);
+    let blocks = gpu_gradient_data.get_slice_mut(address, block_count);
+    GradientData::build(blocks, entry_count, src_stops, reverse_stops, interpolation);
+
+    (address, block_count, entry_count)
+}
+
+// Return the stops in the order they will be drawn, with their colors premultiplied if the
+// interpolation requires it. Reversed stops have their offsets inverted, and each hint is moved
+// to the stop that now starts its segment.
+fn gradient_stops_for_interpolation(src_stops: &[GradientStop],
+                                    reverse_stops: bool,
+                                    interpolation: GradientInterpolation) -> Vec<GradientStop> {
+    let mut stops: Vec<GradientStop> = if reverse_stops {
+        src_stops.iter().rev().enumerate().map(|(i, src)| {
+            let hint = src_stops.len().checked_sub(i + 2).and_then(|prev| src_stops[prev].hint);
+            GradientStop {
+                offset: 1.0 - src.offset,
+                color: src.color,
+                hint: hint.map(|hint| 1.0 - hint),
+            }
+        }).collect()
+    } else {
+        src_stops.to_vec()
+    };
+
+    if interpolation == GradientInterpolation::Premultiplied {
+        for stop in &mut stops {
+            stop.color = ColorF::new(stop.color.r * stop.color.a,
+                                     stop.color.g * stop.color.a,
+                                     stop.color.b * stop.color.a,
+                                     stop.color.a);
+        }
+    }
+
+    stops
+}
+
+// The exponent to raise a segment's normalized offset to, so that the color halfway between
+// two stops is reached at the first stop's hint.
+fn stop_hint_exponent(stop: &GradientStop, next: &GradientStop) -> f32 {
+    match stop.hint {
+        Some(hint) if next.offset > stop.offset => {
+            let hint = ((hint - stop.offset) / (next.offset - stop.offset)).max(0.001).min(0.999);
+            0.5f32.ln() / hint.ln()
+        }
+        _ => 1.0,
+    }
+}
+
+fn srgb_to_linear(c: f32) -> f32 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(c: f32) -> f32 {
+    if c <= 0.0031308 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+// Mix two stop colors at a normalized offset between them.
+fn interpolate_color(start: &ColorF,
+                     end: &ColorF,
+                     t: f32,
+                     hint_exponent: f32,
+                     interpolation: GradientInterpolation) -> ColorF {
+    let t = t.powf(hint_exponent);
+    let mix = |a: f32, b: f32| a + (b - a) * t;
+
+    match interpolation {
+        GradientInterpolation::Srgb |
+        GradientInterpolation::Premultiplied => {
+            ColorF::new(mix(start.r, end.r),
+                        mix(start.g, end.g),
+                        mix(start.b, end.b),
+                        mix(start.a, end.a))
+        }
+        GradientInterpolation::LinearLight => {
+            let mix_linear = |a: f32, b: f32| linear_to_srgb(mix(srgb_to_linear(a), srgb_to_linear(b)));
+            ColorF::new(mix_linear(start.r, end.r),
+                        mix_linear(start.g, end.g),
+                        mix_linear(start.b, end.b),
+                        mix(start.a, end.a))
+        }
     }
 }
 
@@ -394,21 +574,32 @@ pub struct TextRunPrimitiveCpu {
     pub logical_font_size: Au,
     pub blur_radius: Au,
     pub glyph_range: ItemRange,
+    // Offset applied to every glyph in the run. This is
+    // non-zero for runs that draw a text shadow.
+    pub offset: LayerPoint,
     pub cache_dirty: bool,
     // TODO(gw): Maybe make this an Arc for sharing with resource cache
     pub glyph_instances: Vec<GlyphInstance>,
     pub color_texture_id: SourceTexture,
     pub color: ColorF,
+    // The render mode requested for this run, and the mode actually
+    // used for the current frame. Subpixel AA falls back to grayscale
+    // when the run isn't drawn in a context that supports it.
+    pub normal_render_mode: FontRenderMode,
     pub render_mode: FontRenderMode,
     pub resource_address: GpuStoreAddress,
     pub glyph_options: Option<GlyphOptions>,
+    // The scale the glyphs are rasterized at, on top of the device pixel
+    // ratio, so that zoomed text stays sharp.
+    pub raster_scale: f32,
 }
 
 #[derive(Debug, Clone)]
 #[repr(C)]
 struct GlyphPrimitive {
     offset: LayerPoint,
-    padding: LayerPoint,
+    raster_scale: f32,
+    padding: f32,
 }
 
 #[derive(Debug, Clone)]
@@ -459,12 +650,16 @@ pub struct ClipData {
 
 impl ClipData {
     pub fn from_clip_region(clip: &ComplexClipRegion) -> ClipData {
+        // TODO(gw): Support other clip modes for regions?
+        ClipData::complex(clip, ClipMode::Clip)
+    }
+
+    pub fn complex(clip: &ComplexClipRegion, mode: ClipMode) -> ClipData {
         ClipData {
             rect: ClipRect {
                 rect: clip.rect,
                 padding: [0.0; 3],
-                // TODO(gw): Support other clip modes for regions?
-                mode: ClipMode::Clip as u32 as f32,
+                mode: mode as u32 as f32,
             },
             top_left: ClipCorner {
                 rect: LayerRect::new(
@@ -540,6 +735,7 @@ impl ClipData {
 #[derive(Debug)]
 pub enum PrimitiveContainer {
     Rectangle(RectanglePrimitive),
+    Line(LinePrimitive),
     TextRun(TextRunPrimitiveCpu, TextRunPrimitiveGpu),
     Image(ImagePrimitiveCpu, ImagePrimitiveGpu),
     YuvImage(YuvImagePrimitiveCpu, YuvImagePrimitiveGpu),
@@ -547,7 +743,8 @@ pub enum PrimitiveContainer {
     AlignedGradient(GradientPrimitiveCpu, GradientPrimitiveGpu),
     AngleGradient(GradientPrimitiveCpu, GradientPrimitiveGpu),
     RadialGradient(RadialGradientPrimitiveCpu, RadialGradientPrimitiveGpu),
-    BoxShadow(BoxShadowPrimitiveGpu, Vec<LayerRect>),
+    ConicGradient(ConicGradientPrimitiveCpu, ConicGradientPrimitiveGpu),
+    BoxShadow(Vec<(BoxShadowPrimitiveGpu, Vec<LayerRect>)>),
 }
 
 pub struct PrimitiveStore {
@@ -558,6 +755,8 @@ pub struct PrimitiveStore {
     pub cpu_yuv_images: Vec<YuvImagePrimitiveCpu>,
     pub cpu_gradients: Vec<GradientPrimitiveCpu>,
     pub cpu_radial_gradients: Vec<RadialGradientPrimitiveCpu>,
+    pub cpu_conic_gradients: Vec<ConicGradientPrimitiveCpu>,
+    pub cpu_box_shadows: Vec<BoxShadowPrimitiveCpu>,
     pub cpu_metadata: Vec<PrimitiveMetadata>,
     pub cpu_borders: Vec<BorderPrimitiveCpu>,
 
@@ -586,6 +785,8 @@ impl PrimitiveStore {
             cpu_yuv_images: Vec::new(),
             cpu_gradients: Vec::new(),
             cpu_radial_gradients: Vec::new(),
+            cpu_conic_gradients: Vec::new(),
+            cpu_box_shadows: Vec::new(),
             cpu_borders: Vec::new(),
             gpu_geometry: VertexDataStore::new(),
             gpu_data16: VertexDataStore::new(),
@@ -635,6 +836,24 @@ impl PrimitiveStore {
 
                 metadata
             }
+            PrimitiveContainer::Line(line) => {
+                let gpu_address = self.gpu_data32.push(line);
+
+                let metadata = PrimitiveMetadata {
+                    is_opaque: false,
+                    clips: clips,
+                    clip_cache_info: clip_info,
+                    prim_kind: PrimitiveKind::Line,
+                    cpu_prim_index: SpecificPrimitiveIndex::invalid(),
+                    gpu_prim_index: gpu_address,
+                    gpu_data_address: GpuStoreAddress(0),
+                    gpu_data_count: 0,
+                    render_task: None,
+                    clip_task: None,
+                };
+
+                metadata
+            }
             PrimitiveContainer::TextRun(mut text_cpu, text_gpu) => {
                 let gpu_address = self.gpu_data16.push(text_gpu);
                 let gpu_glyphs_address = self.gpu_data16.alloc(text_cpu.glyph_range.length);
@@ -740,8 +959,6 @@ impl PrimitiveStore {
             }
             PrimitiveContainer::AngleGradient(gradient_cpu, gradient_gpu) => {
                 let gpu_address = self.gpu_data32.push(gradient_gpu);
-                let gpu_gradient_address = self.gpu_gradient_data.alloc(1);
-
                 let metadata = PrimitiveMetadata {
                     // TODO: calculate if the gradient is actually opaque
                     is_opaque: false,
@@ -750,8 +967,9 @@ impl PrimitiveStore {
                     prim_kind: PrimitiveKind::AngleGradient,
                     cpu_prim_index: SpecificPrimitiveIndex(self.cpu_gradients.len()),
                     gpu_prim_index: gpu_address,
-                    gpu_data_address: gpu_gradient_address,
-                    gpu_data_count: 1,
+                    // The gradient table is allocated once the stops are known.
+                    gpu_data_address: GpuStoreAddress(0),
+                    gpu_data_count: 0,
                     render_task: None,
                     clip_task: None,
                 };
@@ -761,8 +979,6 @@ impl PrimitiveStore {
             }
             PrimitiveContainer::RadialGradient(radial_gradient_cpu, radial_gradient_gpu) => {
                 let gpu_address = self.gpu_data32.push(radial_gradient_gpu);
-                let gpu_gradient_address = self.gpu_gradient_data.alloc(1);
-
                 let metadata = PrimitiveMetadata {
                     // TODO: calculate if the gradient is actually opaque
                     is_opaque: false,
@@ -771,8 +987,9 @@ impl PrimitiveStore {
                     prim_kind: PrimitiveKind::RadialGradient,
                     cpu_prim_index: SpecificPrimitiveIndex(self.cpu_radial_gradients.len()),
                     gpu_prim_index: gpu_address,
-                    gpu_data_address: gpu_gradient_address,
-                    gpu_data_count: 1,
+                    // The gradient table is allocated once the stops are known.
+                    gpu_data_address: GpuStoreAddress(0),
+                    gpu_data_count: 0,
                     render_task: None,
                     clip_task: None,
                 };
@@ -780,54 +997,110 @@ impl PrimitiveStore {
                 self.cpu_radial_gradients.push(radial_gradient_cpu);
                 metadata
             }
-            PrimitiveContainer::BoxShadow(box_shadow_gpu, instance_rects) => {
-                let cache_key = PrimitiveCacheKey::BoxShadow(BoxShadowPrimitiveCacheKey {
-                    blur_radius: Au::from_f32_px(box_shadow_gpu.blur_radius),
-                    border_radius: Au::from_f32_px(box_shadow_gpu.border_radius),
-                    inverted: box_shadow_gpu.inverted != 0.0,
-                    shadow_rect_size: Size2D::new(Au::from_f32_px(box_shadow_gpu.bs_rect.size.width),
-                                                  Au::from_f32_px(box_shadow_gpu.bs_rect.size.height)),
-                });
+            PrimitiveContainer::ConicGradient(conic_gradient_cpu, conic_gradient_gpu) => {
+                let gpu_address = self.gpu_data32.push(conic_gradient_gpu);
+                let metadata = PrimitiveMetadata {
+                    // TODO: calculate if the gradient is actually opaque
+                    is_opaque: false,
+                    clips: clips,
+                    clip_cache_info: clip_info,
+                    prim_kind: PrimitiveKind::ConicGradient,
+                    cpu_prim_index: SpecificPrimitiveIndex(self.cpu_conic_gradients.len()),
+                    gpu_prim_index: gpu_address,
+                    // The gradient table is allocated once the stops are known.
+                    gpu_data_address: GpuStoreAddress(0),
+                    gpu_data_count: 0,
+                    render_task: None,
+                    clip_task: None,
+                };
 
-                // The actual cache size is calculated during prepare_prim_for_render().
-                // This is necessary since the size may change depending on the device
-                // pixel ratio (for example, during zoom or moving the window to a
-                // monitor with a different device pixel ratio).
-                let cache_size = DeviceIntSize::zero();
-
-                // Create a render task for this box shadow primitive. This renders a small
-                // portion of the box shadow to a render target. That portion is then
-                // stretched over the actual primitive rect by the box shadow primitive
-                // shader, to reduce the number of pixels that the expensive box
-                // shadow shader needs to run on.
-                // TODO(gw): In the future, we can probably merge the box shadow
-                // primitive (stretch) shader with the generic cached primitive shader.
-                let render_task = RenderTask::new_prim_cache(cache_key,
-                                                             cache_size,
-                                                             PrimitiveIndex(prim_index));
-
-                let gpu_prim_address = self.gpu_data64.push(box_shadow_gpu);
+                self.cpu_conic_gradients.push(conic_gradient_cpu);
+                metadata
+            }
+            PrimitiveContainer::BoxShadow(shadows) => {
+                let gpu_prim_address = self.gpu_data128.get_next_address();
                 let gpu_data_address = self.gpu_data16.get_next_address();
+                let mut box_shadow_cpu = BoxShadowPrimitiveCpu {
+                    render_tasks: Vec::new(),
+                    shadows: Vec::new(),
+                };
+                let mut cache_keys = Vec::new();
+                let mut rect_count = 0;
+
+                for (box_shadow_gpu, instance_rects) in shadows {
+                    let radius_key = |radius: &LayerSize| {
+                        Size2D::new(Au::from_f32_px(radius.width), Au::from_f32_px(radius.height))
+                    };
+                    let border_radii = [radius_key(&box_shadow_gpu.border_radii[0]),
+                                        radius_key(&box_shadow_gpu.border_radii[1]),
+                                        radius_key(&box_shadow_gpu.border_radii[2]),
+                                        radius_key(&box_shadow_gpu.border_radii[3])];
+
+                    let cache_key = PrimitiveCacheKey::BoxShadow(BoxShadowPrimitiveCacheKey {
+                        blur_radius: Au::from_f32_px(box_shadow_gpu.blur_radius),
+                        border_radii: border_radii,
+                        inverted: box_shadow_gpu.inverted != 0.0,
+                        shadow_rect_size: Size2D::new(Au::from_f32_px(box_shadow_gpu.bs_rect.size.width),
+                                                      Au::from_f32_px(box_shadow_gpu.bs_rect.size.height)),
+                    });
+
+                    let shadow_address = self.gpu_data128.push(box_shadow_gpu);
+
+                    // Shadows that only differ in offset and color produce the
+                    // same cached patch, so they share a single render task.
+                    let render_task_index = match cache_keys.iter().position(|key| *key == cache_key) {
+                        Some(index) => index,
+                        None => {
+                            // The actual cache size is calculated during prepare_prim_for_render().
+                            // This is necessary since the size may change depending on the device
+                            // pixel ratio (for example, during zoom or moving the window to a
+                            // monitor with a different device pixel ratio).
+                            let cache_size = DeviceIntSize::zero();
+
+                            // Create a render task for this box shadow. This renders a small
+                            // portion of the box shadow to a render target. That portion is then
+                            // stretched over the actual primitive rect by the box shadow primitive
+                            // shader, to reduce the number of pixels that the expensive box
+                            // shadow shader needs to run on.
+                            // TODO(gw): In the future, we can probably merge the box shadow
+                            // primitive (stretch) shader with the generic cached primitive shader.
+                            let render_task = RenderTask::new_box_shadow_cache(cache_key,
+                                                                               cache_size,
+                                                                               PrimitiveIndex(prim_index),
+                                                                               shadow_address);
+                            cache_keys.push(cache_key);
+                            box_shadow_cpu.render_tasks.push(render_task);
+                            box_shadow_cpu.render_tasks.len() - 1
+                        }
+                    };
+
+                    box_shadow_cpu.shadows.push(BoxShadowCpu {
+                        render_task_index: render_task_index,
+                        rect_count: instance_rects.len() as i32,
+                    });
+                    rect_count += instance_rects.len() as i32;
+
+                    for rect in instance_rects {
+                        self.gpu_data16.push(InstanceRect {
+                            rect: rect,
+                        });
+                    }
+                }
 
                 let metadata = PrimitiveMetadata {
                     is_opaque: false,
                     clips: clips,
                     clip_cache_info: clip_info,
                     prim_kind: PrimitiveKind::BoxShadow,
-                    cpu_prim_index: SpecificPrimitiveIndex::invalid(),
+                    cpu_prim_index: SpecificPrimitiveIndex(self.cpu_box_shadows.len()),
                     gpu_prim_index: gpu_prim_address,
                     gpu_data_address: gpu_data_address,
-                    gpu_data_count: instance_rects.len() as i32,
-                    render_task: Some(render_task),
+                    gpu_data_count: rect_count,
+                    render_task: None,
                     clip_task: None,
                 };
 
-                for rect in instance_rects {
-                    self.gpu_data16.push(InstanceRect {
-                        rect: rect,
-                    });
-                }
-
+                self.cpu_box_shadows.push(box_shadow_cpu);
                 metadata
             }
         };
@@ -841,7 +1114,7 @@ impl PrimitiveStore {
                                    clip_info: &MaskCacheInfo,
                                    resource_cache: &ResourceCache) {
         if let Some((ref mask, gpu_address)) = clip_info.image {
-            let cache_item = resource_cache.get_cached_image(mask.image, ImageRendering::Auto, None);
+            let cache_item = resource_cache.get_cached_image(mask.image, ImageRendering::Auto, None, 1.0);
             let mask_data = gpu_data32.get_slice_mut(gpu_address, MASK_DATA_GPU_SIZE);
             mask_data[0] = GpuBlock32::from(ImageMaskData {
                 uv_rect: DeviceRect::new(cache_item.uv0,
@@ -863,7 +1136,8 @@ impl PrimitiveStore {
                      image_key: ImageKey,
                      image_uv_address: GpuStoreAddress,
                      image_rendering: ImageRendering,
-                     tile_offset: Option<TileOffset>) -> (SourceTexture, Option<CacheItem>) {
+                     tile_offset: Option<TileOffset>,
+                     raster_scale: f32) -> (SourceTexture, Option<CacheItem>) {
         let image_properties = resource_cache.get_image_properties(image_key);
 
         // Check if an external image that needs to be resolved
@@ -881,7 +1155,10 @@ impl PrimitiveStore {
                 (SourceTexture::External(external_image), None)
             }
             None => {
-                let cache_item = resource_cache.get_cached_image(image_key, image_rendering, tile_offset);
+                let cache_item = resource_cache.get_cached_image(image_key,
+                                                                 image_rendering,
+                                                                 tile_offset,
+                                                                 raster_scale);
                 (cache_item.texture_id, Some(cache_item))
             }
         }
@@ -901,18 +1178,21 @@ impl PrimitiveStore {
 
             match metadata.prim_kind {
                 PrimitiveKind::Rectangle |
+                PrimitiveKind::Line |
                 PrimitiveKind::Border |
                 PrimitiveKind::BoxShadow |
                 PrimitiveKind::AlignedGradient |
                 PrimitiveKind::AngleGradient |
-                PrimitiveKind::RadialGradient=> {}
+                PrimitiveKind::RadialGradient |
+                PrimitiveKind::ConicGradient => {}
                 PrimitiveKind::TextRun => {
                     let text = &mut self.cpu_text_runs[metadata.cpu_prim_index.0];
 
-                    let font_size_dp = text.logical_font_size.scale_by(device_pixel_ratio);
+                    let font_size_dp =
+                        text.logical_font_size.scale_by(device_pixel_ratio * text.raster_scale);
 
-                    let dest_rects = self.gpu_resource_rects.get_slice_mut(text.resource_address,
-                                                                           text.glyph_range.length);
+                    let gpu_resource_rects = &mut self.gpu_resource_rects;
+                    let resource_address = text.resource_address;
 
                     let texture_id = resource_cache.get_glyphs(text.font_key,
                                                                font_size_dp,
@@ -920,9 +1200,10 @@ impl PrimitiveStore {
                                                                &text.glyph_instances,
                                                                text.render_mode,
                                                                text.glyph_options, |index, uv0, uv1| {
-                        let dest_rect = &mut dest_rects[index];
-                        dest_rect.uv0 = uv0;
-                        dest_rect.uv1 = uv1;
+                        gpu_resource_rects.set(resource_address + index, TexelRect {
+                            uv0: uv0,
+                            uv1: uv1,
+                        });
                     });
 
                     text.color_texture_id = texture_id;
@@ -939,7 +1220,8 @@ impl PrimitiveStore {
                                                           image_key,
                                                           image_cpu.resource_address,
                                                           image_rendering,
-                                                          tile_offset)
+                                                          tile_offset,
+                                                          image_cpu.raster_scale)
                         }
                         ImagePrimitiveKind::WebGL(context_id) => {
                             let cache_item = resource_cache.get_webgl_texture(&context_id);
@@ -948,19 +1230,25 @@ impl PrimitiveStore {
                     };
 
                     if let Some(cache_item) = cache_item {
-                        let resource_rect = self.gpu_resource_rects.get_mut(image_cpu.resource_address);
-                        match image_cpu.sub_rect {
+                        let resource_rect = match image_cpu.sub_rect {
                             Some(sub_rect) => {
-                                resource_rect.uv0.x = cache_item.uv0.x + sub_rect.uv0.x;
-                                resource_rect.uv0.y = cache_item.uv0.y + sub_rect.uv0.y;
-                                resource_rect.uv1.x = cache_item.uv0.x + sub_rect.uv1.x;
-                                resource_rect.uv1.y = cache_item.uv0.y + sub_rect.uv1.y;
+                                // The sub rect is in texels of the image at its own size.
+                                let scale = image_cpu.raster_scale;
+                                TexelRect {
+                                    uv0: DevicePoint::new(cache_item.uv0.x + sub_rect.uv0.x * scale,
+                                                          cache_item.uv0.y + sub_rect.uv0.y * scale),
+                                    uv1: DevicePoint::new(cache_item.uv0.x + sub_rect.uv1.x * scale,
+                                                          cache_item.uv0.y + sub_rect.uv1.y * scale),
+                                }
                             }
                             None => {
-                                resource_rect.uv0 = cache_item.uv0;
-                                resource_rect.uv1 = cache_item.uv1;
+                                TexelRect {
+                                    uv0: cache_item.uv0,
+                                    uv1: cache_item.uv1,
+                                }
                             }
-                        }
+                        };
+                        self.gpu_resource_rects.set(image_cpu.resource_address, resource_rect);
                     }
                     image_cpu.color_texture_id = texture_id;
                 }
@@ -980,14 +1268,16 @@ impl PrimitiveStore {
                                                               image_cpu.yuv_key[channel],
                                                               resource_address,
                                                               ImageRendering::Auto,
-                                                              None);
+                                                              None,
+                                                              1.0);
                             // texture_id
                             image_cpu.yuv_texture_id[channel] = texture_id;
                             // uv coordinates
                             if let Some(cache_item) = cache_item {
-                                let resource_rect = self.gpu_resource_rects.get_mut(image_cpu.yuv_resource_address + channel as i32);
-                                resource_rect.uv0 = cache_item.uv0;
-                                resource_rect.uv1 = cache_item.uv1;
+                                self.gpu_resource_rects.set(resource_address, TexelRect {
+                                    uv0: cache_item.uv0,
+                                    uv1: cache_item.uv1,
+                                });
                             }
                         }
                     }
@@ -1003,15 +1293,31 @@ impl PrimitiveStore {
         metadata.clips = match source {
             Some(source) => {
                 let (rect, is_complex) = match source {
-                    ClipSource::Complex(rect, radius, _) => (rect, radius > 0.0),
+                    ClipSource::Complex(rect, radii, _) => (rect, !radii.is_zero()),
                     ClipSource::Region(ref region, _) => (region.main, region.is_complex()),
                 };
                 self.gpu_geometry.get_mut(GpuStoreAddress(index.0 as i32))
                     .local_clip_rect = rect;
+                let clips = vec![source];
                 if is_complex {
-                    metadata.clip_cache_info = None; //CLIP TODO: re-use the existing GPU allocation
+                    // A single rounded rect keeps the same GPU allocation from frame
+                    // to frame, so only its bounds need to be recomputed.
+                    let can_reuse = match (&clips[0], &metadata.clip_cache_info) {
+                        (&ClipSource::Complex(..), &Some(ref info)) => {
+                            info.clip_range.item_count == 1 && info.image.is_none()
+                        }
+                        _ => false,
+                    };
+                    if can_reuse {
+                        metadata.clip_cache_info.as_mut().unwrap().invalidate();
+                    } else {
+                        if let Some(ref info) = metadata.clip_cache_info {
+                            info.free(&mut self.gpu_data32);
+                        }
+                        metadata.clip_cache_info = MaskCacheInfo::new(&clips, &mut self.gpu_data32);
+                    }
                 }
-                vec![source]
+                clips
             }
             None => {
                 vec![]
@@ -1019,6 +1325,14 @@ impl PrimitiveStore {
         }
     }
 
+    pub fn set_rectangle_color(&mut self, index: PrimitiveIndex, color: ColorF) {
+        let metadata = &mut self.cpu_metadata[index.0];
+        debug_assert!(metadata.prim_kind == PrimitiveKind::Rectangle);
+        metadata.is_opaque = color.a == 1.0;
+        *self.gpu_data16.get_mut(metadata.gpu_prim_index) =
+            GpuBlock16::from(RectanglePrimitive { color: color });
+    }
+
     pub fn get_metadata(&self, index: PrimitiveIndex) -> &PrimitiveMetadata {
         &self.cpu_metadata[index.0]
     }
@@ -1055,6 +1369,8 @@ impl PrimitiveStore {
                                    resource_cache: &mut ResourceCache,
                                    layer_transform: &LayerToWorldTransform,
                                    device_pixel_ratio: f32,
+                                   raster_scale: f32,
+                                   allow_subpixel_aa: bool,
                                    auxiliary_lists: &AuxiliaryLists) -> bool {
 
         let metadata = &mut self.cpu_metadata[prim_index.0];
@@ -1069,7 +1385,7 @@ impl PrimitiveStore {
                              auxiliary_lists);
             for clip in &metadata.clips {
                 if let &ClipSource::Region(ClipRegion{ image_mask: Some(ref mask), .. }, _) = clip {
-                    resource_cache.request_image(mask.image, ImageRendering::Auto, None);
+                    resource_cache.request_image(mask.image, ImageRendering::Auto, None, 1.0);
                     prim_needs_resolve = true;
                 }
             }
@@ -1077,6 +1393,7 @@ impl PrimitiveStore {
 
         match metadata.prim_kind {
             PrimitiveKind::Rectangle |
+            PrimitiveKind::Line |
             PrimitiveKind::Border  => {}
             PrimitiveKind::BoxShadow => {
                 // TODO(gw): Account for zoom factor!
@@ -1086,22 +1403,54 @@ impl PrimitiveStore {
                 // in device space. The shader adds a 1-pixel border around
                 // the patch, in order to prevent bilinear filter artifacts as
                 // the patch is clamped / mirrored across the box shadow rect.
-                let box_shadow_gpu: &BoxShadowPrimitiveGpu = unsafe {
-                    mem::transmute(self.gpu_data64.get(metadata.gpu_prim_index))
-                };
-                let edge_size = box_shadow_gpu.edge_size.ceil() * device_pixel_ratio;
-                let edge_size = edge_size as i32 + 2;   // Account for bilinear filtering
-                let cache_size = DeviceIntSize::new(edge_size, edge_size);
-                let location = RenderTaskLocation::Dynamic(None, cache_size);
-                metadata.render_task.as_mut().unwrap().location = location;
+                // If the corners differ, the patch holds all four corners,
+                // one per quadrant.
+                let box_shadow_cpu = &mut self.cpu_box_shadows[metadata.cpu_prim_index.0];
+                for (shadow_index, shadow) in box_shadow_cpu.shadows.iter().enumerate() {
+                    let box_shadow_gpu: &BoxShadowPrimitiveGpu = unsafe {
+                        mem::transmute(self.gpu_data128.get(metadata.gpu_prim_index + shadow_index))
+                    };
+                    let edge_size = box_shadow_gpu.edge_size.ceil() * device_pixel_ratio;
+                    let mut edge_size = edge_size as i32;
+                    if box_shadow_gpu.mirror == 0.0 {
+                        edge_size *= 2;
+                    }
+                    let edge_size = edge_size + 2;   // Account for bilinear filtering
+                    let cache_size = DeviceIntSize::new(edge_size, edge_size);
+                    let location = RenderTaskLocation::Dynamic(None, cache_size);
+                    box_shadow_cpu.render_tasks[shadow.render_task_index].location = location;
+                }
             }
             PrimitiveKind::TextRun => {
                 let text = &mut self.cpu_text_runs[metadata.cpu_prim_index.0];
 
-                let font_size_dp = text.logical_font_size.scale_by(device_pixel_ratio);
                 let src_glyphs = auxiliary_lists.glyph_instances(&text.glyph_range);
                 prim_needs_resolve = true;
 
+                let render_mode = match text.normal_render_mode {
+                    FontRenderMode::Subpixel if !allow_subpixel_aa => FontRenderMode::Alpha,
+                    render_mode => render_mode,
+                };
+
+                // Text shadows are blurred in a cache target at the device
+                // pixel ratio, so they aren't rasterized any larger.
+                let raster_scale = if text.blur_radius.0 == 0 { raster_scale } else { 1.0 };
+
+                // The glyphs are rasterized differently for each render
+                // mode and scale, so they must be fetched again when those change.
+                if render_mode != text.render_mode || raster_scale != text.raster_scale {
+                    text.render_mode = render_mode;
+                    text.raster_scale = raster_scale;
+                    if !text.cache_dirty {
+                        text.cache_dirty = true;
+                        text.glyph_instances.clear();
+                        metadata.gpu_data_count = text.glyph_range.length as i32;
+                    }
+                }
+
+                let glyph_pixel_ratio = device_pixel_ratio * text.raster_scale;
+                let font_size_dp = text.logical_font_size.scale_by(glyph_pixel_ratio);
+
                 if text.cache_dirty {
                     rebuild_bounding_rect = true;
                     text.cache_dirty = false;
@@ -1121,8 +1470,10 @@ impl PrimitiveStore {
                     let mut actual_glyph_count = 0;
 
                     for src in src_glyphs {
+                        let point = Point2D::new(src.point.x + text.offset.x,
+                                                 src.point.y + text.offset.y);
                         glyph_key.index = src.index;
-                        glyph_key.subpixel_point.set_offset(src.point, text.render_mode);
+                        glyph_key.subpixel_point.set_offset(point, text.render_mode);
 
                         let dimensions = match resource_cache.get_glyph_dimensions(&glyph_key) {
                             None => continue,
@@ -1132,24 +1483,25 @@ impl PrimitiveStore {
                         // TODO(gw): Check for this and ensure platforms return None in this case!!!
                         debug_assert!(dimensions.width > 0 && dimensions.height > 0);
 
-                        let x = src.point.x + dimensions.left as f32 / device_pixel_ratio;
-                        let y = src.point.y - dimensions.top as f32 / device_pixel_ratio;
+                        let x = point.x + dimensions.left as f32 / glyph_pixel_ratio;
+                        let y = point.y - dimensions.top as f32 / glyph_pixel_ratio;
 
-                        let width = dimensions.width as f32 / device_pixel_ratio;
-                        let height = dimensions.height as f32 / device_pixel_ratio;
+                        let width = dimensions.width as f32 / glyph_pixel_ratio;
+                        let height = dimensions.height as f32 / glyph_pixel_ratio;
 
                         let local_glyph_rect = LayerRect::new(LayerPoint::new(x, y),
                                                               LayerSize::new(width, height));
                         local_rect = local_rect.union(&local_glyph_rect);
 
                         dest_glyphs[actual_glyph_count] = GpuBlock16::from(GlyphPrimitive {
-                            padding: LayerPoint::zero(),
                             offset: local_glyph_rect.origin,
+                            raster_scale: text.raster_scale,
+                            padding: 0.0,
                         });
 
                         text.glyph_instances.push(GlyphInstance {
                             index: src.index,
-                            point: Point2D::new(src.point.x, src.point.y),
+                            point: point,
                         });
 
                         actual_glyph_count += 1;
@@ -1196,7 +1548,11 @@ impl PrimitiveStore {
                 prim_needs_resolve = true;
                 match image_cpu.kind {
                     ImagePrimitiveKind::Image(image_key, image_rendering, tile_offset, tile_spacing) => {
-                        resource_cache.request_image(image_key, image_rendering, tile_offset);
+                        image_cpu.raster_scale = raster_scale;
+                        resource_cache.request_image(image_key,
+                                                     image_rendering,
+                                                     tile_offset,
+                                                     raster_scale);
 
                         // TODO(gw): This doesn't actually need to be calculated each frame.
                         // It's cheap enough that it's not worth introducing a cache for images
@@ -1215,7 +1571,7 @@ impl PrimitiveStore {
                 prim_needs_resolve = true;
 
                 for channel in 0..3 {
-                    resource_cache.request_image(image_cpu.yuv_key[channel], ImageRendering::Auto, None);
+                    resource_cache.request_image(image_cpu.yuv_key[channel], ImageRendering::Auto, None, 1.0);
                 }
 
                 // TODO(nical): Currently assuming no tile_spacing for yuv images.
@@ -1230,11 +1586,13 @@ impl PrimitiveStore {
                     let dest_stops = self.gpu_data32.get_slice_mut(metadata.gpu_data_address,
                                                                    gradient.stops_range.length);
 
-                    for (src, dest) in src_stops.iter().zip(dest_stops.iter_mut()) {
+                    let stops = gradient_stops_for_interpolation(src_stops, false, gradient.interpolation);
+                    for (i, (src, dest)) in stops.iter().zip(dest_stops.iter_mut()).enumerate() {
                         *dest = GpuBlock32::from(GradientStopGpu {
                             offset: src.offset,
                             color: src.color,
-                            padding: [0.0; 3],
+                            hint_exponent: stops.get(i + 1).map_or(1.0, |next| stop_hint_exponent(src, next)),
+                            padding: [0.0; 2],
                         });
                     }
 
@@ -1245,8 +1603,14 @@ impl PrimitiveStore {
                 let gradient = &mut self.cpu_gradients[metadata.cpu_prim_index.0];
                 if gradient.cache_dirty {
                     let src_stops = auxiliary_lists.gradient_stops(&gradient.stops_range);
-                    let dest_gradient = self.gpu_gradient_data.get_mut(metadata.gpu_data_address);
-                    dest_gradient.build(src_stops, gradient.reverse_stops);
+                    let (address, block_count, entry_count) =
+                        build_gradient_data(&mut self.gpu_gradient_data,
+                                            src_stops,
+                                            gradient.reverse_stops,
+                                            gradient.interpolation);
+                    metadata.gpu_data_address = address;
+                    metadata.gpu_data_count = block_count as i32;
+                    gradient.entry_count = entry_count;
                     gradient.cache_dirty = false;
                 }
             }
@@ -1254,8 +1618,29 @@ impl PrimitiveStore {
                 let gradient = &mut self.cpu_radial_gradients[metadata.cpu_prim_index.0];
                 if gradient.cache_dirty {
                     let src_stops = auxiliary_lists.gradient_stops(&gradient.stops_range);
-                    let dest_gradient = self.gpu_gradient_data.get_mut(metadata.gpu_data_address);
-                    dest_gradient.build(src_stops, false);
+                    let (address, block_count, entry_count) =
+                        build_gradient_data(&mut self.gpu_gradient_data,
+                                            src_stops,
+                                            false,
+                                            gradient.interpolation);
+                    metadata.gpu_data_address = address;
+                    metadata.gpu_data_count = block_count as i32;
+                    gradient.entry_count = entry_count;
+                    gradient.cache_dirty = false;
+                }
+            }
+            PrimitiveKind::ConicGradient => {
+                let gradient = &mut self.cpu_conic_gradients[metadata.cpu_prim_index.0];
+                if gradient.cache_dirty {
+                    let src_stops = auxiliary_lists.gradient_stops(&gradient.stops_range);
+                    let (address, block_count, entry_count) =
+                        build_gradient_data(&mut self.gpu_gradient_data,
+                                            src_stops,
+                                            false,
+                                            gradient.interpolation);
+                    metadata.gpu_data_address = address;
+                    metadata.gpu_data_count = block_count as i32;
+                    gradient.entry_count = entry_count;
                     gradient.cache_dirty = false;
                 }
             }
@@ -1337,6 +1722,14 @@ impl Default for GpuBlock32 {
     }
 }
 
+impl From<[f32; 8]> for GpuBlock32 {
+    fn from(data: [f32; 8]) -> GpuBlock32 {
+        GpuBlock32 {
+            data: data,
+        }
+    }
+}
+
 impl From<GradientPrimitiveGpu> for GpuBlock32 {
     fn from(data: GradientPrimitiveGpu) -> GpuBlock32 {
         unsafe {
@@ -1345,6 +1738,22 @@ impl From<GradientPrimitiveGpu> for GpuBlock32 {
     }
 }
 
+impl From<LinePrimitive> for GpuBlock32 {
+    fn from(data: LinePrimitive) -> GpuBlock32 {
+        unsafe {
+            mem::transmute::<LinePrimitive, GpuBlock32>(data)
+        }
+    }
+}
+
+impl From<SplitGeometry> for GpuBlock32 {
+    fn from(data: SplitGeometry) -> GpuBlock32 {
+        unsafe {
+            mem::transmute::<SplitGeometry, GpuBlock32>(data)
+        }
+    }
+}
+
 impl From<GradientStopGpu> for GpuBlock32 {
     fn from(data: GradientStopGpu) -> GpuBlock32 {
         unsafe {
@@ -1361,6 +1770,14 @@ impl From<RadialGradientPrimitiveGpu> for GpuBlock32 {
     }
 }
 
+impl From<ConicGradientPrimitiveGpu> for GpuBlock32 {
+    fn from(data: ConicGradientPrimitiveGpu) -> GpuBlock32 {
+        unsafe {
+            mem::transmute::<ConicGradientPrimitiveGpu, GpuBlock32>(data)
+        }
+    }
+}
+
 impl From<YuvImagePrimitiveGpu> for GpuBlock16 {
     fn from(data: YuvImagePrimitiveGpu) -> GpuBlock16 {
         unsafe {
@@ -1407,14 +1824,6 @@ impl Default for GpuBlock64 {
     }
 }
 
-impl From<BoxShadowPrimitiveGpu> for GpuBlock64 {
-    fn from(data: BoxShadowPrimitiveGpu) -> GpuBlock64 {
-        unsafe {
-            mem::transmute::<BoxShadowPrimitiveGpu, GpuBlock64>(data)
-        }
-    }
-}
-
 #[derive(Clone)]
 #[repr(C)]
 pub struct GpuBlock128 {
@@ -1437,6 +1846,14 @@ impl From<BorderPrimitiveGpu> for GpuBlock128 {
     }
 }
 
+impl From<BoxShadowPrimitiveGpu> for GpuBlock128 {
+    fn from(data: BoxShadowPrimitiveGpu) -> GpuBlock128 {
+        unsafe {
+            mem::transmute::<BoxShadowPrimitiveGpu, GpuBlock128>(data)
+        }
+    }
+}
+
 //Test for one clip region contains another
 trait InsideTest<T> {
     fn might_contain(&self, clip: &T) -> bool;
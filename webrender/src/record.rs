@@ -69,11 +69,16 @@ pub fn should_record_msg(msg: &ApiMsg) -> bool {
         &ApiMsg::DeleteFont(..) |
         &ApiMsg::AddImage(..) |
         &ApiMsg::GenerateFrame(..) |
+        &ApiMsg::SetScrollLinkedProperties(..) |
         &ApiMsg::UpdateImage(..) |
         &ApiMsg::DeleteImage(..) |
         &ApiMsg::SetRootDisplayList(..) |
         &ApiMsg::SetRootPipeline(..) |
         &ApiMsg::Scroll(..) |
+        &ApiMsg::DragScrollbarThumb(..) |
+        &ApiMsg::ZoomScrollLayer(..) |
+        &ApiMsg::SetPipelineZoomLimits(..) |
+        &ApiMsg::ZoomPipeline(..) |
         &ApiMsg::TickScrollingBounce |
         &ApiMsg::WebGLCommand(..) =>
             true,
@@ -6,6 +6,8 @@ use byteorder::{LittleEndian, ReadBytesExt};
 use frame::Frame;
 use frame_builder::FrameBuilderConfig;
 use internal_types::{FontTemplate, SourceTexture, ResultMsg, RendererFrame};
+use mask_store::ClipMaskStoreContents;
+use picture_cache::PictureCacheContents;
 use profiler::{BackendProfileCounters, TextureCacheProfileCounters};
 use record::ApiRecordingReceiver;
 use resource_cache::ResourceCache;
@@ -17,10 +19,12 @@ use std::sync::mpsc::Sender;
 use texture_cache::TextureCache;
 use thread_profiler::register_thread_with_profiler;
 use threadpool::ThreadPool;
+use time::precise_time_ns;
 use webgl_types::{GLContextHandleWrapper, GLContextWrapper};
 use webrender_traits::{DeviceIntPoint, DeviceUintPoint, DeviceUintRect, DeviceUintSize, LayerPoint};
 use webrender_traits::{ApiMsg, AuxiliaryLists, BuiltDisplayList, IdNamespace, ImageData};
-use webrender_traits::{PipelineId, RenderNotifier, RenderDispatcher, WebGLCommand, WebGLContextId};
+use webrender_traits::{PipelineId, RenderNotifier, RenderDispatcher, ScrollChangePhase};
+use webrender_traits::{WebGLCommand, WebGLContextId};
 use webrender_traits::channel::{PayloadHelperMethods, PayloadReceiver, PayloadSender, MsgReceiver};
 use webrender_traits::{BlobImageRenderer, VRCompositorCommand, VRCompositorHandler};
 #[cfg(feature = "webgl")]
@@ -72,17 +76,26 @@ impl RenderBackend {
                hidpi_factor: f32,
                texture_cache: TextureCache,
                enable_aa: bool,
+               font_gamma: f32,
+               font_contrast: f32,
                workers: Arc<Mutex<ThreadPool>>,
                notifier: Arc<Mutex<Option<Box<RenderNotifier>>>>,
                webrender_context_handle: Option<GLContextHandleWrapper>,
                config: FrameBuilderConfig,
+               picture_cache_contents: PictureCacheContents,
+               mask_store_contents: ClipMaskStoreContents,
                recorder: Option<Box<ApiRecordingReceiver>>,
                main_thread_dispatcher: Arc<Mutex<Option<Box<RenderDispatcher>>>>,
                blob_image_renderer: Option<Box<BlobImageRenderer>>,
                vr_compositor_handler: Arc<Mutex<Option<Box<VRCompositorHandler>>>>,
                initial_window_size: DeviceUintSize) -> RenderBackend {
 
-        let resource_cache = ResourceCache::new(texture_cache, workers, blob_image_renderer, enable_aa);
+        let resource_cache = ResourceCache::new(texture_cache,
+                                                workers,
+                                                blob_image_renderer,
+                                                enable_aa,
+                                                font_gamma,
+                                                font_contrast);
 
         register_thread_with_profiler("Backend".to_string());
 
@@ -97,7 +110,7 @@ impl RenderBackend {
             pan: DeviceIntPoint::zero(),
             resource_cache: resource_cache,
             scene: Scene::new(),
-            frame: Frame::new(config),
+            frame: Frame::new(config, picture_cache_contents, mask_store_contents),
             next_namespace_id: IdNamespace(1),
             notifier: notifier,
             webrender_context_handle: webrender_context_handle,
@@ -136,6 +149,9 @@ impl RenderBackend {
                         ApiMsg::DeleteFont(id) => {
                             self.resource_cache.delete_font_template(id);
                         }
+                        ApiMsg::SetFontGamma(gamma, contrast) => {
+                            self.resource_cache.set_font_gamma(gamma, contrast);
+                        }
                         ApiMsg::GetGlyphDimensions(glyph_keys, tx) => {
                             let mut glyph_dimensions = Vec::with_capacity(glyph_keys.len());
                             for glyph_key in &glyph_keys {
@@ -223,6 +239,7 @@ impl RenderBackend {
                             if !preserve_frame_state {
                                 self.discard_frame_state_for_pipeline(pipeline_id);
                             }
+                            self.frame.discard_pictures_for_pipeline(pipeline_id);
                             profile_counters.total_time.profile(|| {
                                 self.scene.set_root_display_list(pipeline_id,
                                                                  epoch,
@@ -245,12 +262,30 @@ impl RenderBackend {
                                 self.build_scene();
                             })
                         }
-                        ApiMsg::Scroll(delta, cursor, move_phase) => {
+                        ApiMsg::SetScrollLinkedProperties(properties) => {
+                            profile_scope!("SetScrollLinkedProperties");
+                            self.scene.properties.set_scroll_linked_properties(properties);
+
+                            // The bindings are evaluated whenever a frame is built, so
+                            // the scene doesn't need to be flattened again.
+                            let frame = {
+                                let counters = &mut profile_counters.texture_cache;
+                                profile_counters.total_time.profile(|| {
+                                    self.render(counters)
+                                })
+                            };
+
+                            if self.scene.root_pipeline_id.is_some() {
+                                self.publish_frame_and_notify_compositor(frame, &mut profile_counters);
+                            }
+                        }
+                        ApiMsg::Scroll(delta, cursor, move_phase, event_time) => {
                             profile_scope!("Scroll");
+                            let event_time = event_time.unwrap_or_else(precise_time_ns);
                             let frame = {
                                 let counters = &mut profile_counters.texture_cache;
                                 profile_counters.total_time.profile(|| {
-                                    if self.frame.scroll(delta, cursor, move_phase) {
+                                    if self.frame.scroll(delta, cursor, move_phase, event_time) {
                                         Some(self.render(counters))
                                     } else {
                                         None
@@ -258,6 +293,8 @@ impl RenderBackend {
                                 })
                             };
 
+                            self.notify_scroll_offset_changes(ScrollChangePhase::Gesture(move_phase));
+
                             match frame {
                                 Some(frame) => {
                                     self.publish_frame(frame, &mut profile_counters);
@@ -266,12 +303,12 @@ impl RenderBackend {
                                 None => self.notify_compositor_of_new_scroll_frame(false),
                             }
                         }
-                        ApiMsg::ScrollLayerWithId(origin, id) => {
+                        ApiMsg::ScrollLayerWithId(origin, id, behavior) => {
                             profile_scope!("ScrollLayerWithScrollId");
                             let frame = {
                                 let counters = &mut profile_counters.texture_cache;
                                 profile_counters.total_time.profile(|| {
-                                    if self.frame.scroll_nodes(origin, id) {
+                                    if self.frame.scroll_nodes(origin, id, behavior) {
                                         Some(self.render(counters))
                                     } else {
                                         None
@@ -279,6 +316,8 @@ impl RenderBackend {
                                 })
                             };
 
+                            self.notify_scroll_offset_changes(ScrollChangePhase::Direct);
+
                             match frame {
                                 Some(frame) => {
                                     self.publish_frame(frame, &mut profile_counters);
@@ -298,8 +337,93 @@ impl RenderBackend {
                                 })
                             };
 
+                            self.notify_scroll_offset_changes(ScrollChangePhase::Animation);
+
                             self.publish_frame_and_notify_compositor(frame, &mut profile_counters);
                         }
+                        ApiMsg::HitTestScrollbar(cursor, tx) => {
+                            profile_scope!("HitTestScrollbar");
+                            tx.send(self.frame.hit_test_scrollbar(cursor)).unwrap();
+                        }
+                        ApiMsg::DragScrollbarThumb(id, axis, delta) => {
+                            profile_scope!("DragScrollbarThumb");
+                            let frame = {
+                                let counters = &mut profile_counters.texture_cache;
+                                profile_counters.total_time.profile(|| {
+                                    if self.frame.drag_scrollbar_thumb(id, axis, delta) {
+                                        Some(self.render(counters))
+                                    } else {
+                                        None
+                                    }
+                                })
+                            };
+
+                            self.notify_scroll_offset_changes(ScrollChangePhase::Direct);
+
+                            match frame {
+                                Some(frame) => {
+                                    self.publish_frame(frame, &mut profile_counters);
+                                    self.notify_compositor_of_new_scroll_frame(true)
+                                }
+                                None => self.notify_compositor_of_new_scroll_frame(false),
+                            }
+                        }
+                        ApiMsg::ZoomScrollLayer(id, zoom, focal_point, phase) => {
+                            profile_scope!("ZoomScrollLayer");
+                            let frame = {
+                                let counters = &mut profile_counters.texture_cache;
+                                profile_counters.total_time.profile(|| {
+                                    if self.frame.zoom_scroll_layer(id, zoom, focal_point, phase) {
+                                        Some(self.render(counters))
+                                    } else {
+                                        None
+                                    }
+                                })
+                            };
+
+                            self.notify_scroll_offset_changes(ScrollChangePhase::Gesture(phase));
+
+                            match frame {
+                                Some(frame) => {
+                                    self.publish_frame(frame, &mut profile_counters);
+                                    self.notify_compositor_of_new_scroll_frame(true)
+                                }
+                                None => self.notify_compositor_of_new_scroll_frame(false),
+                            }
+                        }
+                        ApiMsg::SetPipelineZoomLimits(pipeline_id, zoom_limits) => {
+                            profile_scope!("SetPipelineZoomLimits");
+                            self.scene.set_pipeline_zoom_limits(pipeline_id, zoom_limits);
+
+                            // The limits are given to the reference frame of the
+                            // pipeline when the scene is flattened.
+                            profile_counters.total_time.profile(|| {
+                                self.build_scene();
+                            })
+                        }
+                        ApiMsg::ZoomPipeline(pipeline_id, zoom, focal_point, phase) => {
+                            profile_scope!("ZoomPipeline");
+                            let frame = {
+                                let counters = &mut profile_counters.texture_cache;
+                                profile_counters.total_time.profile(|| {
+                                    if self.frame.zoom_pipeline(pipeline_id, zoom, focal_point, phase) {
+                                        Some(self.render(counters))
+                                    } else {
+                                        None
+                                    }
+                                })
+                            };
+
+                            self.notify_scroll_offset_changes(ScrollChangePhase::Gesture(phase));
+
+                            match frame {
+                                Some(frame) => {
+                                    self.publish_frame(frame, &mut profile_counters);
+                                    self.notify_compositor_of_new_scroll_frame(true)
+                                }
+                                None => self.notify_compositor_of_new_scroll_frame(false),
+                            }
+                        }
                         ApiMsg::TranslatePointToLayerSpace(..) => {
                             panic!("unused api - remove from webrender_traits");
                         }
@@ -371,7 +495,7 @@ impl RenderBackend {
                         ApiMsg::VRCompositorCommand(context_id, command) => {
                             self.handle_vr_compositor_command(context_id, command);
                         }
-                        ApiMsg::GenerateFrame(property_bindings) => {
+                        ApiMsg::GenerateFrame(property_bindings, frame_time) => {
                             profile_scope!("GenerateFrame");
 
                             // Ideally, when there are property bindings present,
@@ -391,12 +515,17 @@ impl RenderBackend {
                                 });
                             }
 
+                            let frame_time = frame_time.unwrap_or_else(precise_time_ns);
+                            let accumulated_scale_factor = self.accumulated_scale_factor();
                             let frame = {
                                 let counters = &mut profile_counters.texture_cache;
                                 profile_counters.total_time.profile(|| {
+                                    self.frame.tick_scroll_animations(frame_time, accumulated_scale_factor);
                                     self.render(counters)
                                 })
                             };
+
+                            self.notify_scroll_offset_changes(ScrollChangePhase::Animation);
                             if self.scene.root_pipeline_id.is_some() {
                                 self.publish_frame_and_notify_compositor(frame, &mut profile_counters);
                                 frame_counter += 1;
@@ -475,6 +604,7 @@ impl RenderBackend {
                                   self.pan.y as f32 / accumulated_scale_factor);
         let frame = self.frame.build(&mut self.resource_cache,
                                      &self.scene.pipeline_auxiliary_lists,
+                                     &self.scene.properties,
                                      accumulated_scale_factor,
                                      pan,
                                      texture_cache_profile);
@@ -513,6 +643,16 @@ impl RenderBackend {
         notifier.as_mut().unwrap().as_mut().unwrap().new_scroll_frame_ready(composite_needed);
     }
 
+    fn notify_scroll_offset_changes(&mut self, phase: ScrollChangePhase) {
+        let changes = self.frame.take_scroll_offset_changes(phase);
+        if changes.is_empty() {
+            return;
+        }
+
+        let mut notifier = self.notifier.lock();
+        notifier.as_mut().unwrap().as_mut().unwrap().scroll_offsets_changed(changes);
+    }
+
     fn handle_vr_compositor_command(&mut self, ctx_id: WebGLContextId, cmd: VRCompositorCommand) {
         let texture = match cmd {
             VRCompositorCommand::SubmitFrame(..) => {
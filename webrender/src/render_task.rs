@@ -2,8 +2,11 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
+use gpu_store::GpuStoreAddress;
 use internal_types::{HardwareCompositeOp, LowLevelFilterOp};
 use mask_cache::{MaskBounds, MaskCacheInfo};
+use mask_store::ClipMaskStoreOp;
+use picture_cache::PictureKey;
 use prim_store::{PrimitiveCacheKey, PrimitiveIndex};
 use std::{cmp, f32, i32, mem, usize};
 use tiling::{ClipScrollGroupIndex, PackedLayerIndex, RenderPass, RenderTargetIndex};
@@ -28,11 +31,15 @@ pub enum RenderTaskKey {
     HorizontalBlur(i32, PrimitiveIndex),
     /// Allocate a block of space in target for framebuffer copy.
     CopyFramebuffer(StackingContextIndex),
+    /// Allocate a block of space in target for the framebuffer copy read
+    /// by one of a stacking context's backdrop filters.
+    CopyBackdrop(StackingContextIndex, usize),
 }
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
 pub enum MaskCacheKey {
     Primitive(PrimitiveIndex),
+    /// The mask of a masking clip node together with its masking ancestors.
     ScrollLayer(ScrollLayerId),
 }
 
@@ -55,6 +62,9 @@ pub enum AlphaRenderItem {
     Blend(StackingContextIndex, RenderTaskId, LowLevelFilterOp, i32),
     Composite(StackingContextIndex, RenderTaskId, RenderTaskId, MixBlendMode, i32),
     HardwareComposite(StackingContextIndex, RenderTaskId, HardwareCompositeOp, i32),
+    SplitComposite(StackingContextIndex, RenderTaskId, GpuStoreAddress, i32),
+    BackdropFilter(StackingContextIndex, RenderTaskId, LowLevelFilterOp, i32),
+    Filter(StackingContextIndex, i32, GpuStoreAddress, [Option<RenderTaskId>; 2], i32),
 }
 
 #[derive(Debug, Clone)]
@@ -63,6 +73,9 @@ pub struct AlphaRenderTask {
     pub opaque_items: Vec<AlphaRenderItem>,
     pub alpha_items: Vec<AlphaRenderItem>,
     pub isolate_clear: bool,
+    /// Where to copy the result in the picture cache target, if it is the
+    /// rendered contents of a cached stacking context.
+    pub picture_store: Option<(DeviceIntRect, PictureKey)>,
 }
 
 #[derive(Debug, Copy, Clone)]
@@ -86,10 +99,25 @@ pub enum MaskGeometryKind {
 
 #[derive(Debug, Clone)]
 pub struct CacheMaskTask {
-    actual_rect: DeviceIntRect,
+    pub actual_rect: DeviceIntRect,
     inner_rect: DeviceIntRect,
     pub clips: Vec<(PackedLayerIndex, MaskCacheInfo)>,
     pub geometry_kind: MaskGeometryKind,
+    /// Whether this mask is copied into or out of the clip mask store.
+    pub store_op: Option<ClipMaskStoreOp>,
+}
+
+#[derive(Debug, Clone)]
+pub struct BlurTask {
+    pub blur_radius: DeviceIntLength,
+    pub src_task_id: RenderTaskId,
+    screen_origin: DeviceIntPoint,
+}
+
+#[derive(Debug, Clone)]
+pub struct CachedPictureTask {
+    screen_origin: DeviceIntPoint,
+    pub cache_rect: DeviceIntRect,
 }
 
 #[derive(Debug)]
@@ -125,10 +153,15 @@ impl Default for RenderTaskData {
 pub enum RenderTaskKind {
     Alpha(AlphaRenderTask),
     CachePrimitive(PrimitiveIndex),
+    /// Draw the corner patch for one of a box shadow primitive's shadows,
+    /// described by the GPU data at the given address.
+    CacheBoxShadow(PrimitiveIndex, GpuStoreAddress),
     CacheMask(CacheMaskTask),
-    VerticalBlur(DeviceIntLength, PrimitiveIndex),
-    HorizontalBlur(DeviceIntLength, PrimitiveIndex),
+    VerticalBlur(BlurTask),
+    HorizontalBlur(BlurTask),
     Readback(DeviceIntRect),
+    /// Copy the contents of a stacking context from the picture cache target.
+    CachedPicture(CachedPictureTask),
 }
 
 // TODO(gw): Consider storing these in a separate array and having
@@ -156,10 +189,31 @@ impl RenderTask {
                 alpha_items: Vec::new(),
                 opaque_items: Vec::new(),
                 isolate_clear: isolate_clear,
+                picture_store: None,
             }),
         }
     }
 
+    // Construct an alpha batch task that copies the output of another alpha
+    // batch task covering the same screen rect. Tasks can only read from the
+    // pass directly before them, so this carries a result forward a pass.
+    pub fn new_alpha_batch_copy(task_index: RenderTaskIndex,
+                                stacking_context_index: StackingContextIndex,
+                                src_task_id: RenderTaskId,
+                                screen_rect: DeviceIntRect,
+                                z: i32) -> RenderTask {
+        let mut task = RenderTask::new_alpha_batch(task_index,
+                                                   screen_rect.origin,
+                                                   true,
+                                                   RenderTaskLocation::Dynamic(None, screen_rect.size));
+        let item = AlphaRenderItem::HardwareComposite(stacking_context_index,
+                                                      src_task_id,
+                                                      HardwareCompositeOp::PremultipliedAlpha,
+                                                      z);
+        task.as_alpha_batch().alpha_items.push(item);
+        task
+    }
+
     pub fn new_prim_cache(key: PrimitiveCacheKey,
                           size: DeviceIntSize,
                           prim_index: PrimitiveIndex) -> RenderTask {
@@ -171,6 +225,18 @@ impl RenderTask {
         }
     }
 
+    pub fn new_box_shadow_cache(key: PrimitiveCacheKey,
+                                size: DeviceIntSize,
+                                prim_index: PrimitiveIndex,
+                                prim_address: GpuStoreAddress) -> RenderTask {
+        RenderTask {
+            id: RenderTaskId::Dynamic(RenderTaskKey::CachePrimitive(key)),
+            children: Vec::new(),
+            location: RenderTaskLocation::Dynamic(None, size),
+            kind: RenderTaskKind::CacheBoxShadow(prim_index, prim_address),
+        }
+    }
+
     pub fn new_readback(key: StackingContextIndex,
                     screen_rect: DeviceIntRect) -> RenderTask {
         RenderTask {
@@ -181,6 +247,34 @@ impl RenderTask {
         }
     }
 
+    pub fn new_backdrop_readback(key: StackingContextIndex,
+                                 filter_index: usize,
+                                 screen_rect: DeviceIntRect) -> RenderTask {
+        RenderTask {
+            id: RenderTaskId::Dynamic(RenderTaskKey::CopyBackdrop(key, filter_index)),
+            children: Vec::new(),
+            location: RenderTaskLocation::Dynamic(None, screen_rect.size),
+            kind: RenderTaskKind::Readback(screen_rect),
+        }
+    }
+
+    // Construct a task that copies the stored contents of a stacking context
+    // out of the picture cache target. It is laid out like an alpha batch
+    // task, so it can be composited in its place.
+    pub fn new_cached_picture(task_index: RenderTaskIndex,
+                              screen_rect: DeviceIntRect,
+                              cache_rect: DeviceIntRect) -> RenderTask {
+        RenderTask {
+            id: RenderTaskId::Static(task_index),
+            children: Vec::new(),
+            location: RenderTaskLocation::Dynamic(None, screen_rect.size),
+            kind: RenderTaskKind::CachedPicture(CachedPictureTask {
+                screen_origin: screen_rect.origin,
+                cache_rect: cache_rect,
+            }),
+        }
+    }
+
     pub fn new_mask(actual_rect: DeviceIntRect,
                     mask_key: MaskCacheKey,
                     clips: &[(PackedLayerIndex, MaskCacheInfo)])
@@ -258,6 +352,7 @@ impl RenderTask {
                 inner_rect: inner_rect,
                 clips: clips.to_vec(),
                 geometry_kind: geometry_kind,
+                store_op: None,
             }),
         })
     }
@@ -290,28 +385,80 @@ impl RenderTask {
 
         let blur_task_v = RenderTask {
             id: RenderTaskId::Dynamic(RenderTaskKey::VerticalBlur(blur_radius.0, prim_index)),
+            kind: RenderTaskKind::VerticalBlur(BlurTask {
+                blur_radius: blur_radius,
+                src_task_id: prim_cache_task.id,
+                screen_origin: DeviceIntPoint::zero(),
+            }),
             children: vec![prim_cache_task],
             location: RenderTaskLocation::Dynamic(None, blur_target_size),
-            kind: RenderTaskKind::VerticalBlur(blur_radius, prim_index),
         };
 
         let blur_task_h = RenderTask {
             id: RenderTaskId::Dynamic(RenderTaskKey::HorizontalBlur(blur_radius.0, prim_index)),
+            kind: RenderTaskKind::HorizontalBlur(BlurTask {
+                blur_radius: blur_radius,
+                src_task_id: blur_task_v.id,
+                screen_origin: DeviceIntPoint::zero(),
+            }),
             children: vec![blur_task_v],
             location: RenderTaskLocation::Dynamic(None, blur_target_size),
-            kind: RenderTaskKind::HorizontalBlur(blur_radius, prim_index),
         };
 
         blur_task_h
     }
 
+    // Construct a render task to blur the output of an alpha batch task,
+    // such as the contents of a stacking context that casts a drop shadow.
+    // The blur tasks are the same size as the source, so the source must
+    // leave enough room around its contents for the blur to spread into.
+    // The result can be composited like an alpha batch task, positioned at
+    // `screen_origin`.
+    pub fn new_alpha_batch_blur(src_task: RenderTask,
+                                blur_radius: DeviceIntLength,
+                                screen_origin: DeviceIntPoint,
+                                next_task_index: &mut RenderTaskIndex) -> RenderTask {
+        let size = match src_task.location {
+            RenderTaskLocation::Dynamic(_, size) => size,
+            RenderTaskLocation::Fixed => panic!("Can't blur the framebuffer!"),
+        };
+
+        let blur_task_v = RenderTask {
+            id: RenderTaskId::Static(*next_task_index),
+            kind: RenderTaskKind::VerticalBlur(BlurTask {
+                blur_radius: blur_radius,
+                src_task_id: src_task.id,
+                screen_origin: screen_origin,
+            }),
+            children: vec![src_task],
+            location: RenderTaskLocation::Dynamic(None, size),
+        };
+        next_task_index.0 += 1;
+
+        let blur_task_h = RenderTask {
+            id: RenderTaskId::Static(*next_task_index),
+            kind: RenderTaskKind::HorizontalBlur(BlurTask {
+                blur_radius: blur_radius,
+                src_task_id: blur_task_v.id,
+                screen_origin: screen_origin,
+            }),
+            children: vec![blur_task_v],
+            location: RenderTaskLocation::Dynamic(None, size),
+        };
+        next_task_index.0 += 1;
+
+        blur_task_h
+    }
+
     pub fn as_alpha_batch<'a>(&'a mut self) -> &'a mut AlphaRenderTask {
         match self.kind {
             RenderTaskKind::Alpha(ref mut task) => task,
             RenderTaskKind::CachePrimitive(..) |
+            RenderTaskKind::CacheBoxShadow(..) |
             RenderTaskKind::CacheMask(..) |
             RenderTaskKind::VerticalBlur(..) |
             RenderTaskKind::Readback(..) |
+            RenderTaskKind::CachedPicture(..) |
             RenderTaskKind::HorizontalBlur(..) => unreachable!(),
         }
     }
@@ -349,16 +496,17 @@ impl RenderTask {
                     ],
                 }
             }
-            RenderTaskKind::CachePrimitive(..) => {
+            RenderTaskKind::CachePrimitive(..) |
+            RenderTaskKind::CacheBoxShadow(..) => {
                 RenderTaskData {
                     data: [
                         target_rect.origin.x as f32,
                         target_rect.origin.y as f32,
                         target_rect.size.width as f32,
                         target_rect.size.height as f32,
-                        target_index.0 as f32,
                         0.0,
                         0.0,
+                        target_index.0 as f32,
                         0.0,
                         0.0,
                         0.0,
@@ -385,18 +533,20 @@ impl RenderTask {
                     ],
                 }
             }
-            RenderTaskKind::VerticalBlur(blur_radius, _) |
-            RenderTaskKind::HorizontalBlur(blur_radius, _) => {
+            RenderTaskKind::VerticalBlur(ref task) |
+            RenderTaskKind::HorizontalBlur(ref task) => {
+                // The layout matches alpha batch tasks, so the result of a
+                // blur can be composited in the same way.
                 RenderTaskData {
                     data: [
                         target_rect.origin.x as f32,
                         target_rect.origin.y as f32,
                         target_rect.size.width as f32,
                         target_rect.size.height as f32,
+                        task.screen_origin.x as f32,
+                        task.screen_origin.y as f32,
                         target_index.0 as f32,
-                        blur_radius.0 as f32,
-                        0.0,
-                        0.0,
+                        task.blur_radius.0 as f32,
                         0.0,
                         0.0,
                         0.0,
@@ -404,19 +554,39 @@ impl RenderTask {
                     ]
                 }
             }
-            RenderTaskKind::Readback(..) => {
+            RenderTaskKind::Readback(screen_rect) => {
+                // Readbacks are laid out like alpha batch tasks, so that
+                // shaders can sample them the same way.
                 RenderTaskData {
                     data: [
                         target_rect.origin.x as f32,
                         target_rect.origin.y as f32,
                         target_rect.size.width as f32,
                         target_rect.size.height as f32,
+                        screen_rect.origin.x as f32,
+                        screen_rect.origin.y as f32,
                         target_index.0 as f32,
                         0.0,
                         0.0,
                         0.0,
                         0.0,
                         0.0,
+                    ]
+                }
+            }
+            RenderTaskKind::CachedPicture(ref task) => {
+                RenderTaskData {
+                    data: [
+                        target_rect.origin.x as f32,
+                        target_rect.origin.y as f32,
+                        target_rect.size.width as f32,
+                        target_rect.size.height as f32,
+                        task.screen_origin.x as f32,
+                        task.screen_origin.y as f32,
+                        target_index.0 as f32,
+                        0.0,
+                        0.0,
+                        0.0,
                         0.0,
                         0.0,
                     ]
@@ -469,8 +639,10 @@ impl RenderTask {
         match self.kind {
             RenderTaskKind::Alpha(..) |
             RenderTaskKind::CachePrimitive(..) |
+            RenderTaskKind::CacheBoxShadow(..) |
             RenderTaskKind::VerticalBlur(..) |
             RenderTaskKind::Readback(..) |
+            RenderTaskKind::CachedPicture(..) |
             RenderTaskKind::HorizontalBlur(..) => RenderTargetKind::Color,
             RenderTaskKind::CacheMask(..) => RenderTargetKind::Alpha,
         }
@@ -15,14 +15,18 @@ use device::{DepthFunction, Device, FrameId, ProgramId, TextureId, VertexFormat,
 use device::{GpuSample, TextureFilter, VAOId, VertexUsageHint, FileWatcherHandler, TextureTarget, ShaderError};
 use euclid::Matrix4D;
 use fnv::FnvHasher;
-use frame_builder::FrameBuilderConfig;
+use frame_builder::{FrameBuilderConfig, ScrollbarStyle};
 use gleam::gl;
-use gpu_store::{GpuStore, GpuStoreLayout};
+use gpu_store::{GpuStore, GpuStoreAddress, GpuStoreLayout, GpuStoreUpdates, merge_dirty_ranges};
 use internal_types::{CacheTextureId, RendererFrame, ResultMsg, TextureUpdateOp};
 use internal_types::{ExternalImageUpdateList, TextureUpdateList, PackedVertex, RenderTargetMode};
 use internal_types::{ORTHO_NEAR_PLANE, ORTHO_FAR_PLANE, SourceTexture};
 use internal_types::{BatchTextures, TextureSampler};
-use prim_store::GradientData;
+use mask_store::{CLIP_MASK_STORE_SIZE, ClipMaskStoreContents};
+use picture_cache::{PICTURE_CACHE_SIZE, PictureCacheContents};
+use platform::font;
+use prim_store::{GpuBlock16, GpuBlock32, GpuBlock64, GpuBlock128, GradientData};
+use prim_store::{PrimitiveGeometry, TexelRect};
 use profiler::{Profiler, BackendProfileCounters};
 use profiler::{GpuProfileTag, RendererProfileTimers, RendererProfileCounters};
 use record::ApiRecordingReceiver;
@@ -35,8 +39,10 @@ use std::f32;
 use std::hash::BuildHasherDefault;
 use std::marker::PhantomData;
 use std::mem;
+use std::ops::Range;
 use std::path::PathBuf;
 use std::rc::Rc;
+use std::slice;
 use std::sync::{Arc, Mutex};
 use std::sync::mpsc::{channel, Receiver, Sender};
 use std::thread;
@@ -64,16 +70,21 @@ const GPU_TAG_CACHE_TEXT_RUN: GpuProfileTag = GpuProfileTag { label: "C_TextRun"
 const GPU_TAG_INIT: GpuProfileTag = GpuProfileTag { label: "Init", color: debug_colors::WHITE };
 const GPU_TAG_SETUP_TARGET: GpuProfileTag = GpuProfileTag { label: "Target", color: debug_colors::SLATEGREY };
 const GPU_TAG_PRIM_RECT: GpuProfileTag = GpuProfileTag { label: "Rect", color: debug_colors::RED };
+const GPU_TAG_PRIM_LINE: GpuProfileTag = GpuProfileTag { label: "Line", color: debug_colors::DARKRED };
 const GPU_TAG_PRIM_IMAGE: GpuProfileTag = GpuProfileTag { label: "Image", color: debug_colors::GREEN };
 const GPU_TAG_PRIM_IMAGE_RECT: GpuProfileTag = GpuProfileTag { label: "ImageRect", color: debug_colors::GREENYELLOW };
 const GPU_TAG_PRIM_YUV_IMAGE: GpuProfileTag = GpuProfileTag { label: "YuvImage", color: debug_colors::DARKGREEN };
 const GPU_TAG_PRIM_BLEND: GpuProfileTag = GpuProfileTag { label: "Blend", color: debug_colors::LIGHTBLUE };
 const GPU_TAG_PRIM_HW_COMPOSITE: GpuProfileTag = GpuProfileTag { label: "HwComposite", color: debug_colors::DODGERBLUE };
+const GPU_TAG_PRIM_SPLIT_COMPOSITE: GpuProfileTag = GpuProfileTag { label: "SplitComposite", color: debug_colors::DARKBLUE };
+const GPU_TAG_PRIM_FILTER: GpuProfileTag = GpuProfileTag { label: "Filter", color: debug_colors::TEAL };
+const GPU_TAG_PRIM_BACKDROP_FILTER: GpuProfileTag = GpuProfileTag { label: "BackdropFilter", color: debug_colors::SLATEBLUE };
 const GPU_TAG_PRIM_COMPOSITE: GpuProfileTag = GpuProfileTag { label: "Composite", color: debug_colors::MAGENTA };
 const GPU_TAG_PRIM_TEXT_RUN: GpuProfileTag = GpuProfileTag { label: "TextRun", color: debug_colors::BLUE };
 const GPU_TAG_PRIM_GRADIENT: GpuProfileTag = GpuProfileTag { label: "Gradient", color: debug_colors::YELLOW };
 const GPU_TAG_PRIM_ANGLE_GRADIENT: GpuProfileTag = GpuProfileTag { label: "AngleGradient", color: debug_colors::POWDERBLUE };
 const GPU_TAG_PRIM_RADIAL_GRADIENT: GpuProfileTag = GpuProfileTag { label: "RadialGradient", color: debug_colors::LIGHTPINK };
+const GPU_TAG_PRIM_CONIC_GRADIENT: GpuProfileTag = GpuProfileTag { label: "ConicGradient", color: debug_colors::THISTLE };
 const GPU_TAG_PRIM_BOX_SHADOW: GpuProfileTag = GpuProfileTag { label: "BoxShadow", color: debug_colors::CYAN };
 const GPU_TAG_PRIM_BORDER: GpuProfileTag = GpuProfileTag { label: "Border", color: debug_colors::ORANGE };
 const GPU_TAG_PRIM_CACHE_IMAGE: GpuProfileTag = GpuProfileTag { label: "CacheImage", color: debug_colors::SILVER };
@@ -183,6 +194,114 @@ impl<L: GpuStoreLayout> GpuDataTexture<L> {
     }
 }
 
+/// A data texture holding the renderer's copy of a `GpuStore`. The updates
+/// sent with each frame are applied to a CPU-side copy as soon as the frame
+/// arrives, since a frame can be replaced before it is drawn. Only the rows
+/// that changed are uploaded when a frame is drawn.
+///
+/// Like the per-frame data textures, each store cycles through a pool of
+/// `GPU_DATA_TEXTURE_POOL` textures, so that uploading a frame's changes
+/// doesn't have to wait for the GPU to finish drawing the previous frames
+/// from the same texture. Each texture keeps its own list of the rows that
+/// changed since it was last used.
+struct GpuStoreTexture<T, L> {
+    ids: Vec<TextureId>,
+    /// The item count each texture was last allocated with. A texture is
+    /// reallocated when the store changes size.
+    item_counts: Vec<usize>,
+    /// The item ranges written since each texture was last flushed.
+    dirty_ranges: Vec<Vec<Range<usize>>>,
+    index: usize,
+    data: Vec<T>,
+    layout: PhantomData<L>,
+}
+
+impl<T: Clone + Default, L: GpuStoreLayout> GpuStoreTexture<T, L> {
+    fn new(device: &mut Device) -> GpuStoreTexture<T, L> {
+        let ids = device.create_texture_ids(GPU_DATA_TEXTURE_POOL as i32, TextureTarget::Default);
+
+        GpuStoreTexture {
+            ids: ids,
+            item_counts: vec![0; GPU_DATA_TEXTURE_POOL],
+            dirty_ranges: vec![Vec::new(); GPU_DATA_TEXTURE_POOL],
+            index: 0,
+            data: Vec::new(),
+            layout: PhantomData,
+        }
+    }
+
+    fn id(&self) -> TextureId {
+        self.ids[self.index]
+    }
+
+    fn apply_updates(&mut self, updates: &mut GpuStoreUpdates<T>) {
+        if updates.item_count != self.data.len() {
+            self.data.resize(updates.item_count, T::default());
+        }
+
+        for block in updates.blocks.drain(..) {
+            let start = block.address.0 as usize;
+            let end = start + block.data.len();
+            self.data[start..end].clone_from_slice(&block.data);
+            self.mark_dirty(start..end);
+        }
+    }
+
+    /// Patches a single item on the render thread.
+    fn get_mut(&mut self, address: GpuStoreAddress) -> &mut T {
+        let index = address.0 as usize;
+        self.mark_dirty(index..index + 1);
+        &mut self.data[index]
+    }
+
+    fn mark_dirty(&mut self, range: Range<usize>) {
+        for dirty_ranges in &mut self.dirty_ranges {
+            dirty_ranges.push(range.clone());
+        }
+    }
+
+    /// Moves on to the next texture of the pool and brings it up to date.
+    fn flush(&mut self, device: &mut Device) {
+        self.index = (self.index + 1) % GPU_DATA_TEXTURE_POOL;
+        let dirty_ranges = mem::replace(&mut self.dirty_ranges[self.index], Vec::new());
+        if self.data.is_empty() {
+            return;
+        }
+
+        let id = self.ids[self.index];
+        let width = L::texture_width::<T>() as u32;
+
+        if self.item_counts[self.index] != self.data.len() {
+            let height = L::rows_for_items::<T>(self.data.len());
+            device.init_texture(id,
+                                width,
+                                height as u32,
+                                L::image_format(),
+                                L::texture_filter(),
+                                RenderTargetMode::None,
+                                Some(as_bytes(&self.data)));
+            self.item_counts[self.index] = self.data.len();
+            return;
+        }
+
+        for range in merge_dirty_ranges::<T, L>(dirty_ranges, self.data.len()) {
+            device.update_texture(id,
+                                  0,
+                                  L::rows_for_items::<T>(range.start) as u32,
+                                  width,
+                                  L::rows_for_items::<T>(range.end - range.start) as u32,
+                                  None,
+                                  as_bytes(&self.data[range]));
+        }
+    }
+}
+
+fn as_bytes<T>(data: &[T]) -> &[u8] {
+    unsafe {
+        slice::from_raw_parts(data.as_ptr() as *const u8, data.len() * mem::size_of::<T>())
+    }
+}
+
 pub struct VertexDataTextureLayout {}
 
 impl GpuStoreLayout for VertexDataTextureLayout {
@@ -200,6 +319,7 @@ impl GpuStoreLayout for VertexDataTextureLayout {
 }
 
 type VertexDataTexture = GpuDataTexture<VertexDataTextureLayout>;
+type VertexStoreTexture<T> = GpuStoreTexture<T, VertexDataTextureLayout>;
 pub type VertexDataStore<T> = GpuStore<T, VertexDataTextureLayout>;
 
 pub struct GradientDataTextureLayout {}
@@ -218,7 +338,7 @@ impl GpuStoreLayout for GradientDataTextureLayout {
     }
 }
 
-type GradientDataTexture = GpuDataTexture<GradientDataTextureLayout>;
+type GradientStoreTexture = GpuStoreTexture<GradientData, GradientDataTextureLayout>;
 pub type GradientDataStore = GpuStore<GradientData, GradientDataTextureLayout>;
 
 const TRANSFORM_FEATURE: &'static str = "TRANSFORM";
@@ -387,13 +507,6 @@ fn create_clip_shader(name: &'static str, device: &mut Device) -> Result<Program
 struct GpuDataTextures {
     layer_texture: VertexDataTexture,
     render_task_texture: VertexDataTexture,
-    prim_geom_texture: VertexDataTexture,
-    data16_texture: VertexDataTexture,
-    data32_texture: VertexDataTexture,
-    data64_texture: VertexDataTexture,
-    data128_texture: VertexDataTexture,
-    resource_rects_texture: VertexDataTexture,
-    gradient_data_texture: GradientDataTexture,
 }
 
 impl GpuDataTextures {
@@ -401,36 +514,69 @@ impl GpuDataTextures {
         GpuDataTextures {
             layer_texture: VertexDataTexture::new(device),
             render_task_texture: VertexDataTexture::new(device),
-            prim_geom_texture: VertexDataTexture::new(device),
-            data16_texture: VertexDataTexture::new(device),
-            data32_texture: VertexDataTexture::new(device),
-            data64_texture: VertexDataTexture::new(device),
-            data128_texture: VertexDataTexture::new(device),
-            resource_rects_texture: VertexDataTexture::new(device),
-            gradient_data_texture: GradientDataTexture::new(device),
         }
     }
 
     fn init_frame(&mut self, device: &mut Device, frame: &mut Frame) {
-        self.data16_texture.init(device, &mut frame.gpu_data16);
-        self.data32_texture.init(device, &mut frame.gpu_data32);
-        self.data64_texture.init(device, &mut frame.gpu_data64);
-        self.data128_texture.init(device, &mut frame.gpu_data128);
-        self.prim_geom_texture.init(device, &mut frame.gpu_geometry);
-        self.resource_rects_texture.init(device, &mut frame.gpu_resource_rects);
         self.layer_texture.init(device, &mut frame.layer_texture_data);
         self.render_task_texture.init(device, &mut frame.render_task_data);
-        self.gradient_data_texture.init(device, &mut frame.gpu_gradient_data);
 
         device.bind_texture(TextureSampler::Layers, self.layer_texture.id);
         device.bind_texture(TextureSampler::RenderTasks, self.render_task_texture.id);
-        device.bind_texture(TextureSampler::Geometry, self.prim_geom_texture.id);
-        device.bind_texture(TextureSampler::Data16, self.data16_texture.id);
-        device.bind_texture(TextureSampler::Data32, self.data32_texture.id);
-        device.bind_texture(TextureSampler::Data64, self.data64_texture.id);
-        device.bind_texture(TextureSampler::Data128, self.data128_texture.id);
-        device.bind_texture(TextureSampler::ResourceRects, self.resource_rects_texture.id);
-        device.bind_texture(TextureSampler::Gradients, self.gradient_data_texture.id);
+    }
+}
+
+/// The textures holding the primitive data stores, which persist between
+/// frames and are updated incrementally.
+struct GpuStoreTextures {
+    prim_geom_texture: VertexStoreTexture<PrimitiveGeometry>,
+    data16_texture: VertexStoreTexture<GpuBlock16>,
+    data32_texture: VertexStoreTexture<GpuBlock32>,
+    data64_texture: VertexStoreTexture<GpuBlock64>,
+    data128_texture: VertexStoreTexture<GpuBlock128>,
+    resource_rects_texture: VertexStoreTexture<TexelRect>,
+    gradient_data_texture: GradientStoreTexture,
+}
+
+impl GpuStoreTextures {
+    fn new(device: &mut Device) -> GpuStoreTextures {
+        GpuStoreTextures {
+            prim_geom_texture: VertexStoreTexture::new(device),
+            data16_texture: VertexStoreTexture::new(device),
+            data32_texture: VertexStoreTexture::new(device),
+            data64_texture: VertexStoreTexture::new(device),
+            data128_texture: VertexStoreTexture::new(device),
+            resource_rects_texture: VertexStoreTexture::new(device),
+            gradient_data_texture: GradientStoreTexture::new(device),
+        }
+    }
+
+    fn apply_updates(&mut self, frame: &mut Frame) {
+        self.prim_geom_texture.apply_updates(&mut frame.gpu_geometry);
+        self.data16_texture.apply_updates(&mut frame.gpu_data16);
+        self.data32_texture.apply_updates(&mut frame.gpu_data32);
+        self.data64_texture.apply_updates(&mut frame.gpu_data64);
+        self.data128_texture.apply_updates(&mut frame.gpu_data128);
+        self.resource_rects_texture.apply_updates(&mut frame.gpu_resource_rects);
+        self.gradient_data_texture.apply_updates(&mut frame.gpu_gradient_data);
+    }
+
+    fn flush(&mut self, device: &mut Device) {
+        self.prim_geom_texture.flush(device);
+        self.data16_texture.flush(device);
+        self.data32_texture.flush(device);
+        self.data64_texture.flush(device);
+        self.data128_texture.flush(device);
+        self.resource_rects_texture.flush(device);
+        self.gradient_data_texture.flush(device);
+
+        device.bind_texture(TextureSampler::Geometry, self.prim_geom_texture.id());
+        device.bind_texture(TextureSampler::Data16, self.data16_texture.id());
+        device.bind_texture(TextureSampler::Data32, self.data32_texture.id());
+        device.bind_texture(TextureSampler::Data64, self.data64_texture.id());
+        device.bind_texture(TextureSampler::Data128, self.data128_texture.id());
+        device.bind_texture(TextureSampler::ResourceRects, self.resource_rects_texture.id());
+        device.bind_texture(TextureSampler::Gradients, self.gradient_data_texture.id());
     }
 }
 
@@ -464,6 +610,7 @@ pub struct Renderer {
     // a cache shader (e.g. blur) to the screen.
     ps_rectangle: PrimitiveShader,
     ps_rectangle_clip: PrimitiveShader,
+    ps_line: PrimitiveShader,
     ps_text_run: PrimitiveShader,
     ps_text_run_subpixel: PrimitiveShader,
     ps_image: PrimitiveShader,
@@ -473,11 +620,14 @@ pub struct Renderer {
     ps_gradient: PrimitiveShader,
     ps_angle_gradient: PrimitiveShader,
     ps_radial_gradient: PrimitiveShader,
+    ps_conic_gradient: PrimitiveShader,
     ps_box_shadow: PrimitiveShader,
     ps_cache_image: PrimitiveShader,
 
     ps_blend: LazilyCompiledShader,
     ps_hw_composite: LazilyCompiledShader,
+    ps_split_composite: LazilyCompiledShader,
+    ps_filter: LazilyCompiledShader,
     ps_composite: LazilyCompiledShader,
 
     notifier: Arc<Mutex<Option<Box<RenderNotifier>>>>,
@@ -503,6 +653,7 @@ pub struct Renderer {
 
     gdt_index: usize,
     gpu_data_textures: [GpuDataTextures; GPU_DATA_TEXTURE_POOL],
+    gpu_store_textures: GpuStoreTextures,
 
     pipeline_epoch_map: HashMap<PipelineId, Epoch, BuildHasherDefault<FnvHasher>>,
     /// Used to dispatch functions to the main thread's event loop.
@@ -526,6 +677,22 @@ pub struct Renderer {
 
     dither_matrix_texture_id: TextureId,
 
+    /// The render target that the contents of static stacking contexts are
+    /// kept in between frames. Created when the first picture is stored.
+    picture_cache_texture_id: Option<TextureId>,
+
+    /// The pictures stored in the picture cache target, which the backend
+    /// checks before drawing a stacking context from it.
+    picture_cache_contents: PictureCacheContents,
+
+    /// The render target that shared clip masks are kept in between frames.
+    /// Created when the first mask is stored.
+    clip_mask_store_texture_id: Option<TextureId>,
+
+    /// The masks stored in the clip mask store, which the backend checks
+    /// before copying a mask from it.
+    clip_mask_store_contents: ClipMaskStoreContents,
+
     /// Optional trait object that allows the client
     /// application to provide external buffers for image data.
     external_image_handler: Option<Box<ExternalImageHandler>>,
@@ -652,6 +819,13 @@ impl Renderer {
                                  options.precache_shaders)
         };
 
+        let ps_line = try!{
+            PrimitiveShader::new("ps_line",
+                                 &mut device,
+                                 &[],
+                                 options.precache_shaders)
+        };
+
         let ps_text_run = try!{
             PrimitiveShader::new("ps_text_run",
                                  &mut device,
@@ -722,6 +896,13 @@ impl Renderer {
                                  options.precache_shaders)
         };
 
+        let ps_conic_gradient = try!{
+            PrimitiveShader::new("ps_conic_gradient",
+                                 &mut device,
+                                 &[],
+                                 options.precache_shaders)
+        };
+
         let ps_cache_image = try!{
             PrimitiveShader::new("ps_cache_image",
                                  &mut device,
@@ -753,6 +934,22 @@ impl Renderer {
                                      options.precache_shaders)
         };
 
+        let ps_split_composite = try!{
+            LazilyCompiledShader::new(ShaderKind::Primitive,
+                                     "ps_split_composite",
+                                     &[],
+                                     &mut device,
+                                     options.precache_shaders)
+        };
+
+        let ps_filter = try!{
+            LazilyCompiledShader::new(ShaderKind::Primitive,
+                                     "ps_filter",
+                                     &[],
+                                     &mut device,
+                                     options.precache_shaders)
+        };
+
         let device_max_size = device.max_texture_size();
         let max_texture_size = cmp::min(device_max_size, options.max_texture_size.unwrap_or(device_max_size));
 
@@ -823,6 +1020,7 @@ impl Renderer {
             GpuDataTextures::new(&mut device),
             GpuDataTextures::new(&mut device),
         ];
+        let gpu_store_textures = GpuStoreTextures::new(&mut device);
 
         let x0 = 0.0;
         let y0 = 0.0;
@@ -863,6 +1061,11 @@ impl Renderer {
         let vr_compositor = Arc::new(Mutex::new(None));
         let backend_vr_compositor = vr_compositor.clone();
 
+        let picture_cache_contents = Arc::new(Mutex::new(Vec::new()));
+        let backend_picture_cache_contents = picture_cache_contents.clone();
+        let clip_mask_store_contents = Arc::new(Mutex::new(Vec::new()));
+        let backend_clip_mask_store_contents = clip_mask_store_contents.clone();
+
         // We need a reference to the webrender context from the render backend in order to share
         // texture ids
         let context_handle = match options.renderer_kind {
@@ -871,10 +1074,12 @@ impl Renderer {
         };
 
         let config = FrameBuilderConfig::new(options.enable_scrollbars,
+                                             options.scrollbar_style,
                                              options.enable_subpixel_aa,
                                              options.debug);
 
         let (device_pixel_ratio, enable_aa) = (options.device_pixel_ratio, options.enable_aa);
+        let (font_gamma, font_contrast) = (options.font_gamma, options.font_contrast);
         let render_target_debug = options.render_target_debug;
         let payload_tx_for_backend = payload_tx.clone();
         let recorder = options.recorder;
@@ -892,10 +1097,14 @@ impl Renderer {
                                                  device_pixel_ratio,
                                                  texture_cache,
                                                  enable_aa,
+                                                 font_gamma,
+                                                 font_contrast,
                                                  workers,
                                                  backend_notifier,
                                                  context_handle,
                                                  config,
+                                                 backend_picture_cache_contents,
+                                                 backend_clip_mask_store_contents,
                                                  recorder,
                                                  backend_main_thread_dispatcher,
                                                  blob_image_renderer,
@@ -919,6 +1128,7 @@ impl Renderer {
             cs_clip_image: cs_clip_image,
             ps_rectangle: ps_rectangle,
             ps_rectangle_clip: ps_rectangle_clip,
+            ps_line: ps_line,
             ps_text_run: ps_text_run,
             ps_text_run_subpixel: ps_text_run_subpixel,
             ps_image: ps_image,
@@ -929,9 +1139,12 @@ impl Renderer {
             ps_gradient: ps_gradient,
             ps_angle_gradient: ps_angle_gradient,
             ps_radial_gradient: ps_radial_gradient,
+            ps_conic_gradient: ps_conic_gradient,
             ps_cache_image: ps_cache_image,
             ps_blend: ps_blend,
             ps_hw_composite: ps_hw_composite,
+            ps_split_composite: ps_split_composite,
+            ps_filter: ps_filter,
             ps_composite: ps_composite,
             notifier: notifier,
             debug: debug_renderer,
@@ -952,11 +1165,16 @@ impl Renderer {
             clip_vao_id: clip_vao_id,
             gdt_index: 0,
             gpu_data_textures: gpu_data_textures,
+            gpu_store_textures: gpu_store_textures,
             pipeline_epoch_map: HashMap::with_hasher(Default::default()),
             main_thread_dispatcher: main_thread_dispatcher,
             cache_texture_id_map: Vec::new(),
             dummy_cache_texture_id: dummy_cache_texture_id,
             dither_matrix_texture_id: dither_matrix_texture_id,
+            picture_cache_texture_id: None,
+            picture_cache_contents: picture_cache_contents,
+            clip_mask_store_texture_id: None,
+            clip_mask_store_contents: clip_mask_store_contents,
             external_image_handler: None,
             external_images: HashMap::with_hasher(Default::default()),
             vr_compositor_handler: vr_compositor,
@@ -1018,9 +1236,15 @@ impl Renderer {
         // Pull any pending results and return the most recent.
         while let Ok(msg) = self.result_rx.try_recv() {
             match msg {
-                ResultMsg::NewFrame(frame, texture_update_list, external_image_update_list, profile_counters) => {
+                ResultMsg::NewFrame(mut frame, texture_update_list, external_image_update_list, profile_counters) => {
                     self.pending_texture_updates.push(texture_update_list);
 
+                    // Frames can be replaced before they are drawn, so apply
+                    // their GPU store updates straight away.
+                    if let Some(ref mut tiling_frame) = frame.frame {
+                        self.gpu_store_textures.apply_updates(tiling_frame);
+                    }
+
                     // When a new frame is ready, we could start to update all pending external image requests here.
                     self.release_external_images(external_image_update_list);
 
@@ -1162,6 +1386,16 @@ impl Renderer {
         }
     }
 
+    /// Returns true if a smooth scroll or fling is in progress, or if scrollbars
+    /// are visible and still have to fade out. The embedder should keep
+    /// generating frames, with a frame time, until this returns false.
+    pub fn layers_are_animating_scroll(&self) -> bool {
+        match self.current_frame {
+            None => false,
+            Some(ref current_frame) => !current_frame.layers_animating_scroll.is_empty(),
+        }
+    }
+
 /*
     fn update_shaders(&mut self) {
         let update_uniforms = !self.pending_shader_updates.is_empty();
@@ -1329,11 +1563,20 @@ impl Renderer {
         match batch.data {
             PrimitiveBatchData::Instances(ref data) => {
                 let (marker, shader) = match batch.key.kind {
-                    AlphaBatchKind::Composite => unreachable!(),
+                    AlphaBatchKind::Composite |
+                    AlphaBatchKind::BackdropFilter => unreachable!(),
                     AlphaBatchKind::HardwareComposite => {
                         let shader = self.ps_hw_composite.get(&mut self.device);
                         (GPU_TAG_PRIM_HW_COMPOSITE, shader)
                     }
+                    AlphaBatchKind::SplitComposite => {
+                        let shader = self.ps_split_composite.get(&mut self.device);
+                        (GPU_TAG_PRIM_SPLIT_COMPOSITE, shader)
+                    }
+                    AlphaBatchKind::Filter => {
+                        let shader = self.ps_filter.get(&mut self.device);
+                        (GPU_TAG_PRIM_FILTER, shader)
+                    }
                     AlphaBatchKind::Blend => {
                         let shader = self.ps_blend.get(&mut self.device);
                         (GPU_TAG_PRIM_BLEND, shader)
@@ -1346,6 +1589,10 @@ impl Renderer {
                         };
                         (GPU_TAG_PRIM_RECT, shader)
                     }
+                    AlphaBatchKind::Line => {
+                        let shader = self.ps_line.get(&mut self.device, transform_kind);
+                        (GPU_TAG_PRIM_LINE, shader)
+                    }
                     AlphaBatchKind::TextRun => {
                         let shader = match batch.key.blend_mode {
                             BlendMode::Subpixel(..) => self.ps_text_run_subpixel.get(&mut self.device, transform_kind),
@@ -1381,6 +1628,10 @@ impl Renderer {
                         let shader = self.ps_radial_gradient.get(&mut self.device, transform_kind);
                         (GPU_TAG_PRIM_RADIAL_GRADIENT, shader)
                     }
+                    AlphaBatchKind::ConicGradient => {
+                        let shader = self.ps_conic_gradient.get(&mut self.device, transform_kind);
+                        (GPU_TAG_PRIM_CONIC_GRADIENT, shader)
+                    }
                     AlphaBatchKind::BoxShadow => {
                         let shader = self.ps_box_shadow.get(&mut self.device, transform_kind);
                         (GPU_TAG_PRIM_BOX_SHADOW, shader)
@@ -1402,9 +1653,15 @@ impl Renderer {
                                           projection);
             }
             PrimitiveBatchData::Composite(ref instance) => {
-                let _gm = self.gpu_profile.add_marker(GPU_TAG_PRIM_COMPOSITE);
+                let (marker, shader) = match batch.key.kind {
+                    AlphaBatchKind::BackdropFilter => {
+                        (GPU_TAG_PRIM_BACKDROP_FILTER, self.ps_blend.get(&mut self.device))
+                    }
+                    _ => (GPU_TAG_PRIM_COMPOSITE, self.ps_composite.get(&mut self.device)),
+                };
+                let _gm = self.gpu_profile.add_marker(marker);
                 let vao = self.prim_vao_id;
-                let shader = self.ps_composite.get(&mut self.device).unwrap();
+                let shader = shader.unwrap();
 
                 // TODO(gw): This code branch is all a bit hacky. We rely
                 // on pulling specific values from the render target data
@@ -1419,17 +1676,16 @@ impl Renderer {
 
                 let backdrop = &render_task_data[instance.task_index as usize];
                 let readback = &render_task_data[instance.user_data[0] as usize];
-                let source = &render_task_data[instance.user_data[1] as usize];
 
                 // Bind the FBO to blit the backdrop to.
                 // Called per-instance in case the layer (and therefore FBO)
                 // changes. The device will skip the GL call if the requested
                 // target is already bound.
-                let cache_draw_target = (cache_texture, readback.data[4] as i32);
+                let cache_draw_target = (cache_texture, readback.data[6] as i32);
                 self.device.bind_draw_target(Some(cache_draw_target), Some(cache_texture_dimensions));
 
-                let src_x = backdrop.data[0] - backdrop.data[4] + source.data[4];
-                let src_y = backdrop.data[1] - backdrop.data[5] + source.data[5];
+                let src_x = backdrop.data[0] - backdrop.data[4] + readback.data[4];
+                let src_y = backdrop.data[1] - backdrop.data[5] + readback.data[5];
 
                 let dest_x = readback.data[0];
                 let dest_y = readback.data[1];
@@ -1504,6 +1760,14 @@ impl Renderer {
                                               *isolate_clear);
             }
 
+            for &(cache_rect, target_rect) in &target.picture_loads {
+                let picture_cache_texture_id = self.picture_cache_texture_id
+                                                   .expect("No pictures have been stored!");
+                self.device.blit_render_target(Some((picture_cache_texture_id, 0)),
+                                               Some(cache_rect),
+                                               target_rect);
+            }
+
             self.device.disable_depth_write();
         }
 
@@ -1619,6 +1883,85 @@ impl Renderer {
         self.device.set_blend(false);
     }
 
+    /// Copy the stacking contexts that a color target rendered for the
+    /// picture cache into the picture cache target, and let the backend know
+    /// they can be reused.
+    fn store_pictures(&mut self,
+                      render_target: (TextureId, i32),
+                      target: &ColorRenderTarget) {
+        if target.picture_stores.is_empty() {
+            return;
+        }
+
+        let texture_id = match self.picture_cache_texture_id {
+            Some(texture_id) => texture_id,
+            None => {
+                let texture_id = self.device.create_texture_ids(1, TextureTarget::Array)[0];
+                self.device.init_texture(texture_id,
+                                         PICTURE_CACHE_SIZE,
+                                         PICTURE_CACHE_SIZE,
+                                         ImageFormat::RGBA8,
+                                         TextureFilter::Linear,
+                                         RenderTargetMode::LayerRenderTarget(1),
+                                         None);
+                self.picture_cache_texture_id = Some(texture_id);
+                texture_id
+            }
+        };
+
+        let _gm = GpuMarker::new(self.device.rc_gl(), "store pictures");
+        self.device.bind_draw_target(Some((texture_id, 0)),
+                                     Some(DeviceUintSize::new(PICTURE_CACHE_SIZE, PICTURE_CACHE_SIZE)));
+
+        let mut contents = self.picture_cache_contents.lock().unwrap();
+        for &(target_rect, cache_rect, key) in &target.picture_stores {
+            self.device.blit_render_target(Some(render_target),
+                                           Some(target_rect),
+                                           cache_rect);
+            contents.retain(|&(stored_rect, _)| !stored_rect.intersects(&cache_rect));
+            contents.push((cache_rect, key));
+        }
+    }
+
+    /// Copy the shared clip masks that an alpha target rendered for the clip
+    /// mask store into the store, and let the backend know they can be reused.
+    fn store_clip_masks(&mut self,
+                        render_target: (TextureId, i32),
+                        target: &AlphaRenderTarget) {
+        if target.mask_stores.is_empty() {
+            return;
+        }
+
+        let texture_id = match self.clip_mask_store_texture_id {
+            Some(texture_id) => texture_id,
+            None => {
+                let texture_id = self.device.create_texture_ids(1, TextureTarget::Array)[0];
+                self.device.init_texture(texture_id,
+                                         CLIP_MASK_STORE_SIZE,
+                                         CLIP_MASK_STORE_SIZE,
+                                         ImageFormat::A8,
+                                         TextureFilter::Nearest,
+                                         RenderTargetMode::LayerRenderTarget(1),
+                                         None);
+                self.clip_mask_store_texture_id = Some(texture_id);
+                texture_id
+            }
+        };
+
+        let _gm = GpuMarker::new(self.device.rc_gl(), "store clip masks");
+        self.device.bind_draw_target(Some((texture_id, 0)),
+                                     Some(DeviceUintSize::new(CLIP_MASK_STORE_SIZE, CLIP_MASK_STORE_SIZE)));
+
+        let mut contents = self.clip_mask_store_contents.lock().unwrap();
+        for &(target_rect, store_rect, key) in &target.mask_stores {
+            self.device.blit_render_target(Some(render_target),
+                                           Some(target_rect),
+                                           store_rect);
+            contents.retain(|&(stored_rect, _)| !stored_rect.intersects(&store_rect));
+            contents.push((store_rect, key));
+        }
+    }
+
     fn draw_alpha_target(&mut self,
                          render_target: (TextureId, i32),
                          target: &AlphaRenderTarget,
@@ -1639,6 +1982,14 @@ impl Renderer {
             self.device.clear_target_rect(Some(clear_color),
                                           None,
                                           target.used_rect());
+
+            for &(store_rect, target_rect) in &target.mask_loads {
+                let clip_mask_store_texture_id = self.clip_mask_store_texture_id
+                                                     .expect("No clip masks have been stored!");
+                self.device.blit_render_target(Some((clip_mask_store_texture_id, 0)),
+                                               Some(store_rect),
+                                               target_rect);
+            }
         }
 
         // Draw the clip items into the tiled alpha mask.
@@ -1705,8 +2056,9 @@ impl Renderer {
                 };
 
                 self.external_images.insert(ext_image.id, texture_id);
-                let resource_rect_index = deferred_resolve.resource_address.0 as usize;
-                let resource_rect = &mut frame.gpu_resource_rects[resource_rect_index];
+                let resource_rect = self.gpu_store_textures
+                                        .resource_rects_texture
+                                        .get_mut(deferred_resolve.resource_address);
                 resource_rect.uv0 = DevicePoint::new(image.u0, image.v0);
                 resource_rect.uv1 = DevicePoint::new(image.u1, image.v1);
             }
@@ -1810,6 +2162,7 @@ impl Renderer {
             // number of driver stalls.
             self.gpu_data_textures[self.gdt_index].init_frame(&mut self.device, frame);
             self.gdt_index = (self.gdt_index + 1) % GPU_DATA_TEXTURE_POOL;
+            self.gpu_store_textures.flush(&mut self.device);
 
             let mut src_color_id = self.dummy_cache_texture_id;
             let mut src_alpha_id = self.dummy_cache_texture_id;
@@ -1849,10 +2202,12 @@ impl Renderer {
                 self.device.bind_texture(TextureSampler::CacheRGBA8, src_color_id);
 
                 for (target_index, target) in pass.alpha_targets.targets.iter().enumerate() {
-                    self.draw_alpha_target((pass.alpha_texture_id.unwrap(), target_index as i32),
+                    let render_target = (pass.alpha_texture_id.unwrap(), target_index as i32);
+                    self.draw_alpha_target(render_target,
                                            target,
                                            *size,
                                            &projection);
+                    self.store_clip_masks(render_target, target);
                 }
 
                 for (target_index, target) in pass.color_targets.targets.iter().enumerate() {
@@ -1867,6 +2222,9 @@ impl Renderer {
                                            &frame.render_task_data,
                                            &projection);
 
+                    if let Some(render_target) = render_target {
+                        self.store_pictures(render_target, target);
+                    }
                 }
 
                 src_color_id = pass.color_texture_id.unwrap_or(self.dummy_cache_texture_id);
@@ -1945,6 +2303,12 @@ impl Renderer {
         //Note: this is a fake frame, only needed because texture deletion is require to happen inside a frame
         self.device.begin_frame(1.0);
         self.device.deinit_texture(self.dummy_cache_texture_id);
+        if let Some(texture_id) = self.picture_cache_texture_id {
+            self.device.deinit_texture(texture_id);
+        }
+        if let Some(texture_id) = self.clip_mask_store_texture_id {
+            self.device.deinit_texture(texture_id);
+        }
         self.device.end_frame();
     }
 }
@@ -1991,10 +2355,14 @@ pub struct RendererOptions {
     pub device_pixel_ratio: f32,
     pub resource_override_path: Option<PathBuf>,
     pub enable_aa: bool,
+    /// The gamma and contrast used to preblend glyph coverage.
+    pub font_gamma: f32,
+    pub font_contrast: f32,
     pub enable_profiler: bool,
     pub max_recorded_profiles: usize,
     pub debug: bool,
     pub enable_scrollbars: bool,
+    pub scrollbar_style: ScrollbarStyle,
     pub precache_shaders: bool,
     pub renderer_kind: RendererKind,
     pub enable_subpixel_aa: bool,
@@ -2013,10 +2381,13 @@ impl Default for RendererOptions {
             device_pixel_ratio: 1.0,
             resource_override_path: None,
             enable_aa: true,
+            font_gamma: font::DEFAULT_GAMMA,
+            font_contrast: font::DEFAULT_CONTRAST,
             enable_profiler: false,
             max_recorded_profiles: 0,
             debug: false,
             enable_scrollbars: false,
+            scrollbar_style: ScrollbarStyle::default(),
             precache_shaders: false,
             renderer_kind: RendererKind::Native,
             enable_subpixel_aa: false,
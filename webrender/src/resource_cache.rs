@@ -25,7 +25,7 @@ use webrender_traits::{Epoch, FontKey, GlyphKey, ImageKey, ImageFormat, ImageRen
 use webrender_traits::{FontRenderMode, ImageData, GlyphDimensions, WebGLContextId};
 use webrender_traits::{DevicePoint, DeviceIntSize, DeviceUintRect, ImageDescriptor, ColorF};
 use webrender_traits::{GlyphOptions, GlyphInstance, TileOffset, TileSize};
-use webrender_traits::{BlobImageRenderer, BlobImageDescriptor, BlobImageError};
+use webrender_traits::{BlobImageRenderer, BlobImageDescriptor, BlobImageError, RasterizedBlobImage};
 use webrender_traits::{ExternalImageData, ExternalImageType};
 use threadpool::ThreadPool;
 use euclid::Point2D;
@@ -46,6 +46,8 @@ enum GlyphCacheMsg {
     RequestGlyphs(FontKey, Au, ColorF, Vec<GlyphInstance>, FontRenderMode, Option<GlyphOptions>),
     // Remove an existing font.
     DeleteFont(FontKey),
+    /// Change the gamma and contrast used to preblend glyphs.
+    SetGamma(f32, f32),
     /// Finished requesting glyphs. Reply with new glyphs.
     EndFrame,
 }
@@ -99,6 +101,7 @@ pub struct ImageProperties {
     pub descriptor: ImageDescriptor,
     pub external_image: Option<ExternalImageData>,
     pub tiling: Option<TileSize>,
+    pub epoch: Epoch,
 }
 
 #[derive(Debug, Copy, Clone, PartialEq)]
@@ -162,6 +165,15 @@ impl<K,V> ResourceClassCache<K,V> where K: Clone + Hash + Eq + Debug, V: Resourc
         self.last_access_times.insert((*key).clone(), frame);
     }
 
+    fn clear(&mut self, texture_cache: &mut TextureCache) {
+        for (_, resource) in self.resources.drain() {
+            if let Some(texture_cache_item_id) = resource.texture_cache_item_id() {
+                texture_cache.free(texture_cache_item_id)
+            }
+        }
+        self.last_access_times.clear();
+    }
+
     fn expire_old_resources(&mut self, texture_cache: &mut TextureCache, frame_id: FrameId) {
         let mut resources_to_destroy = vec![];
         for (key, this_frame_id) in &self.last_access_times {
@@ -187,6 +199,9 @@ struct ImageRequest {
     key: ImageKey,
     rendering: ImageRendering,
     tile: Option<TileOffset>,
+    /// The scale a blob image is rasterized at, so that zoomed blob images
+    /// stay sharp. Always one for other images, which have a fixed size.
+    scale: Au,
 }
 
 struct GlyphRasterJob {
@@ -229,8 +244,12 @@ impl ResourceCache {
     pub fn new(texture_cache: TextureCache,
                workers: Arc<Mutex<ThreadPool>>,
                blob_image_renderer: Option<Box<BlobImageRenderer>>,
-               enable_aa: bool) -> ResourceCache {
-        let (glyph_cache_tx, glyph_cache_result_queue) = spawn_glyph_cache_thread(workers);
+               enable_aa: bool,
+               font_gamma: f32,
+               font_contrast: f32) -> ResourceCache {
+        let (glyph_cache_tx, glyph_cache_result_queue) = spawn_glyph_cache_thread(workers,
+                                                                                  font_gamma,
+                                                                                  font_contrast);
 
         ResourceCache {
             cached_glyphs: Some(ResourceClassCache::new()),
@@ -287,6 +306,16 @@ impl ResourceCache {
         self.font_templates.remove(&font_key);
     }
 
+    pub fn set_font_gamma(&mut self, gamma: f32, contrast: f32) {
+        self.glyph_cache_tx
+            .send(GlyphCacheMsg::SetGamma(gamma, contrast))
+            .unwrap();
+
+        // The glyph keys don't include the gamma, so every glyph has to be
+        // rasterized again.
+        self.cached_glyphs.as_mut().unwrap().clear(&mut self.texture_cache);
+    }
+
     pub fn add_image_template(&mut self,
                               image_key: ImageKey,
                               descriptor: ImageDescriptor,
@@ -383,17 +412,33 @@ impl ResourceCache {
         webgl_texture.size = size;
     }
 
+    fn image_request(&self,
+                     key: ImageKey,
+                     rendering: ImageRendering,
+                     tile: Option<TileOffset>,
+                     scale: f32) -> ImageRequest {
+        // Only whole blob images can be rasterized again at another size.
+        let scale = match (&self.image_templates[&key].data, tile) {
+            (&ImageData::Blob(..), None) => scale,
+            _ => 1.0,
+        };
+
+        ImageRequest {
+            key: key,
+            rendering: rendering,
+            tile: tile,
+            scale: Au::from_f32_px(scale),
+        }
+    }
+
     pub fn request_image(&mut self,
                          key: ImageKey,
                          rendering: ImageRendering,
-                         tile: Option<TileOffset>) {
+                         tile: Option<TileOffset>,
+                         scale: f32) {
 
         debug_assert!(self.state == State::AddResources);
-        let request = ImageRequest {
-            key: key,
-            rendering: rendering,
-            tile: tile,
-        };
+        let request = self.image_request(key, rendering, tile, scale);
 
         let template = self.image_templates.get(&key).unwrap();
         if let ImageData::Blob(ref data) = template.data {
@@ -403,16 +448,16 @@ impl ResourceCache {
                     None => false,
                 };
 
+                let scale = request.scale.to_f32_px();
                 if !same_epoch && self.blob_image_requests.insert(request) {
                     renderer.request_blob_image(
                         key,
                         data.clone(),
                         &BlobImageDescriptor {
-                            width: template.descriptor.width,
-                            height: template.descriptor.height,
+                            width: (template.descriptor.width as f32 * scale).ceil() as u32,
+                            height: (template.descriptor.height as f32 * scale).ceil() as u32,
                             format: template.descriptor.format,
-                            // TODO(nical): figure out the scale factor (should change with zoom).
-                            scale_factor: 1.0,
+                            scale_factor: scale,
                         },
                         template.dirty_rect,
                     );
@@ -523,13 +568,10 @@ impl ResourceCache {
     pub fn get_cached_image(&self,
                             image_key: ImageKey,
                             image_rendering: ImageRendering,
-                            tile: Option<TileOffset>) -> CacheItem {
+                            tile: Option<TileOffset>,
+                            scale: f32) -> CacheItem {
         debug_assert!(self.state == State::QueryResources);
-        let key = ImageRequest {
-            key: image_key,
-            rendering: image_rendering,
-            tile: tile,
-        };
+        let key = self.image_request(image_key, image_rendering, tile, scale);
         let image_info = &self.cached_images.get(&key, self.current_frame_id);
         let item = self.texture_cache.get(image_info.texture_cache_id);
         CacheItem {
@@ -563,6 +605,7 @@ impl ResourceCache {
             descriptor: image_template.descriptor,
             external_image: external_image,
             tiling: image_template.tiling,
+            epoch: image_template.epoch,
         }
     }
 
@@ -658,7 +701,7 @@ impl ResourceCache {
                                                 .resolve_blob_image(request.key) {
                     Ok(image) => {
                         self.finalize_image_request(request,
-                                                    Some(ImageData::new(image.data)),
+                                                    Some(image),
                                                     texture_cache_profile);
                     }
                     // TODO(nical): I think that we should handle these somewhat gracefully,
@@ -684,14 +727,27 @@ impl ResourceCache {
 
     fn update_texture_cache(&mut self,
                             request: &ImageRequest,
-                            image_data: Option<ImageData>,
+                            blob_image: Option<RasterizedBlobImage>,
                             texture_cache_profile: &mut TextureCacheProfileCounters) {
         let image_template = self.image_templates.get_mut(&request.key).unwrap();
-        let image_data = image_data.unwrap_or_else(||{
-            image_template.data.clone()
-        });
 
-        let descriptor = if let Some(tile) = request.tile {
+        // Blob images may have been rasterized at a different size than the
+        // template describes, so the texture is sized to fit the result.
+        let (image_data, blob_size) = match blob_image {
+            Some(image) => (ImageData::new(image.data), Some((image.width, image.height))),
+            None => (image_template.data.clone(), None),
+        };
+
+        let descriptor = if let Some((width, height)) = blob_size {
+            ImageDescriptor {
+                width: width,
+                height: height,
+                stride: None,
+                offset: 0,
+                format: image_template.descriptor.format,
+                is_opaque: image_template.descriptor.is_opaque,
+            }
+        } else if let Some(tile) = request.tile {
             let tile_size = image_template.tiling.unwrap() as u32;
             let image_descriptor = &image_template.descriptor;
             let stride = image_descriptor.compute_stride();
@@ -767,7 +823,7 @@ impl ResourceCache {
     }
     fn finalize_image_request(&mut self,
                               request: ImageRequest,
-                              image_data: Option<ImageData>,
+                              blob_image: Option<RasterizedBlobImage>,
                               texture_cache_profile: &mut TextureCacheProfileCounters) {
         match self.image_templates.get(&request.key).unwrap().data {
             ImageData::External(ext_image) => {
@@ -778,14 +834,14 @@ impl ResourceCache {
                     }
                     ExternalImageType::ExternalBuffer => {
                         self.update_texture_cache(&request,
-                                                  image_data,
+                                                  blob_image,
                                                   texture_cache_profile);
                     }
                 }
             }
             ImageData::Raw(..) | ImageData::Blob(..) => {
                 self.update_texture_cache(&request,
-                                           image_data,
+                                           blob_image,
                                            texture_cache_profile);
             }
         }
@@ -827,7 +883,9 @@ impl Resource for CachedImageInfo {
     }
 }
 
-fn spawn_glyph_cache_thread(workers: Arc<Mutex<ThreadPool>>) -> (Sender<GlyphCacheMsg>, Receiver<GlyphCacheResultMsg>) {
+fn spawn_glyph_cache_thread(workers: Arc<Mutex<ThreadPool>>,
+                            font_gamma: f32,
+                            font_contrast: f32) -> (Sender<GlyphCacheMsg>, Receiver<GlyphCacheResultMsg>) {
     let worker_count = {
         workers.lock().unwrap().max_count()
     };
@@ -849,6 +907,9 @@ fn spawn_glyph_cache_thread(workers: Arc<Mutex<ThreadPool>>) -> (Sender<GlyphCac
             let barrier = barrier.clone();
             workers.lock().unwrap().execute(move || {
                 register_thread_with_profiler(format!("Glyph Worker {}", i));
+                FONT_CONTEXT.with(|font_context| {
+                    font_context.borrow_mut().set_gamma(font_gamma, font_contrast);
+                });
                 barrier.wait();
             });
         }
@@ -919,6 +980,21 @@ fn spawn_glyph_cache_thread(workers: Arc<Mutex<ThreadPool>>) -> (Sender<GlyphCac
                     }
 
                 }
+                GlyphCacheMsg::SetGamma(gamma, contrast) => {
+                    profile_scope!("SetGamma");
+
+                    // Change the gamma of the font context in each worker thread.
+                    let barrier = Arc::new(Barrier::new(worker_count));
+                    for _ in 0..worker_count {
+                        let barrier = barrier.clone();
+                        workers.lock().unwrap().execute(move || {
+                            FONT_CONTEXT.with(|font_context| {
+                                font_context.borrow_mut().set_gamma(gamma, contrast);
+                            });
+                            barrier.wait();
+                        });
+                    }
+                }
                 GlyphCacheMsg::RequestGlyphs(key, size, color, glyph_instances, render_mode, glyph_options) => {
                     profile_scope!("RequestGlyphs");
 
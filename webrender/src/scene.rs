@@ -8,7 +8,8 @@ use std::hash::BuildHasherDefault;
 use tiling::AuxiliaryListsMap;
 use webrender_traits::{AuxiliaryLists, BuiltDisplayList, PipelineId, Epoch, ColorF};
 use webrender_traits::{DisplayItem, DynamicProperties, LayerSize, LayoutTransform};
-use webrender_traits::{PropertyBinding, PropertyBindingId};
+use webrender_traits::{PropertyBinding, PropertyBindingId, ScrollLinkedProperties};
+use webrender_traits::{ScrollLinkedValue, ZoomLimits};
 
 /// Stores a map of the animated property bindings for the current display list. These
 /// can be used to animate the transform and/or opacity of a display list without
@@ -16,6 +17,8 @@ use webrender_traits::{PropertyBinding, PropertyBindingId};
 pub struct SceneProperties {
     transform_properties: HashMap<PropertyBindingId, LayoutTransform>,
     float_properties: HashMap<PropertyBindingId, f32>,
+    scroll_linked_transforms: HashMap<PropertyBindingId, ScrollLinkedValue<LayoutTransform>>,
+    scroll_linked_floats: HashMap<PropertyBindingId, ScrollLinkedValue<f32>>,
 }
 
 impl SceneProperties {
@@ -23,9 +26,39 @@ impl SceneProperties {
         SceneProperties {
             transform_properties: HashMap::with_hasher(Default::default()),
             float_properties: HashMap::with_hasher(Default::default()),
+            scroll_linked_transforms: HashMap::with_hasher(Default::default()),
+            scroll_linked_floats: HashMap::with_hasher(Default::default()),
         }
     }
 
+    /// Set the bindings whose values follow scroll offsets. Unlike the values
+    /// from `set_properties`, these are evaluated every frame, with the scroll
+    /// offsets of that frame.
+    pub fn set_scroll_linked_properties(&mut self, properties: ScrollLinkedProperties) {
+        self.scroll_linked_transforms.clear();
+        self.scroll_linked_floats.clear();
+
+        for property in properties.transforms {
+            self.scroll_linked_transforms.insert(property.key.id, property);
+        }
+
+        for property in properties.floats {
+            self.scroll_linked_floats.insert(property.key.id, property);
+        }
+    }
+
+    /// Get the scroll-linked value bound to a transform property, if there is one.
+    pub fn scroll_linked_transform(&self,
+                                   id: PropertyBindingId)
+                                   -> Option<&ScrollLinkedValue<LayoutTransform>> {
+        self.scroll_linked_transforms.get(&id)
+    }
+
+    /// Get the scroll-linked value bound to a float property, if there is one.
+    pub fn scroll_linked_float(&self, id: PropertyBindingId) -> Option<&ScrollLinkedValue<f32>> {
+        self.scroll_linked_floats.get(&id)
+    }
+
     /// Set the current property list for this display list.
     pub fn set_properties(&mut self, properties: DynamicProperties) {
         self.transform_properties.clear();
@@ -40,7 +73,9 @@ impl SceneProperties {
         }
     }
 
-    /// Get the current value for a transform property.
+    /// Get the current value for a transform property. Scroll-linked properties
+    /// resolve to the identity here, and are evaluated whenever the transforms
+    /// of the clip scroll tree are updated.
     pub fn resolve_layout_transform(&self,
                                     property: Option<&PropertyBinding<LayoutTransform>>)
                                     -> LayoutTransform {
@@ -51,6 +86,9 @@ impl SceneProperties {
 
         match *property {
             PropertyBinding::Value(matrix) => matrix,
+            PropertyBinding::Binding(ref key) if self.scroll_linked_transforms.contains_key(&key.id) => {
+                LayoutTransform::identity()
+            }
             PropertyBinding::Binding(ref key) => {
                 self.transform_properties
                     .get(&key.id)
@@ -63,10 +101,14 @@ impl SceneProperties {
         }
     }
 
-    /// Get the current value for a float property.
+    /// Get the current value for a float property. Scroll-linked properties
+    /// resolve to the default here, and are evaluated when the frame is built.
     pub fn resolve_float(&self, property: &PropertyBinding<f32>, default_value: f32) -> f32 {
         match *property {
             PropertyBinding::Value(value) => value,
+            PropertyBinding::Binding(ref key) if self.scroll_linked_floats.contains_key(&key.id) => {
+                default_value
+            }
             PropertyBinding::Binding(ref key) => {
                 self.float_properties
                     .get(&key.id)
@@ -96,6 +138,8 @@ pub struct Scene {
     pub pipeline_auxiliary_lists: AuxiliaryListsMap,
     pub display_lists: HashMap<PipelineId, Vec<DisplayItem>, BuildHasherDefault<FnvHasher>>,
     pub properties: SceneProperties,
+    /// The pipelines which can be zoomed as a whole, and how far.
+    pub pipeline_zoom_limits: HashMap<PipelineId, ZoomLimits, BuildHasherDefault<FnvHasher>>,
 }
 
 impl Scene {
@@ -106,9 +150,19 @@ impl Scene {
             pipeline_auxiliary_lists: HashMap::with_hasher(Default::default()),
             display_lists: HashMap::with_hasher(Default::default()),
             properties: SceneProperties::new(),
+            pipeline_zoom_limits: HashMap::with_hasher(Default::default()),
         }
     }
 
+    pub fn set_pipeline_zoom_limits(&mut self,
+                                    pipeline_id: PipelineId,
+                                    zoom_limits: Option<ZoomLimits>) {
+        match zoom_limits {
+            Some(zoom_limits) => self.pipeline_zoom_limits.insert(pipeline_id, zoom_limits),
+            None => self.pipeline_zoom_limits.remove(&pipeline_id),
+        };
+    }
+
     pub fn set_root_pipeline_id(&mut self, pipeline_id: PipelineId) {
         self.root_pipeline_id = Some(pipeline_id);
     }
@@ -0,0 +1,191 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use webrender_traits::LayerPoint;
+
+/// How long a smooth scroll takes to reach its target, in nanoseconds.
+const SMOOTH_SCROLL_DURATION: u64 = 250 * 1000 * 1000;
+
+/// The time constant of the exponential decay of a fling, in seconds. After
+/// this long, a fling has slowed to about a third of its release velocity.
+const FLING_TIME_CONSTANT: f32 = 0.325;
+
+/// Flings slower than this, in layer pixels per second, come to a stop.
+pub const MIN_FLING_VELOCITY: f32 = 50.0;
+
+/// A time-based scroll animation, ticked with the frame time supplied when
+/// generating a frame. Offsets are in the same space as `ScrollingState::offset`,
+/// but aren't snapped to pixels, so that the node can snap every frame without
+/// losing the fractional progress of the animation.
+#[derive(Copy, Clone, Debug)]
+pub enum ScrollAnimation {
+    /// Eases from one offset to a target offset.
+    Smooth {
+        from: LayerPoint,
+        to: LayerPoint,
+        /// The frame time of the first tick, or None if it hasn't ticked yet.
+        start_time: Option<u64>,
+    },
+    /// Kinetic scrolling which decelerates from the velocity at release.
+    Fling {
+        /// The current offset.
+        offset: LayerPoint,
+        /// The current velocity, in layer pixels per second.
+        velocity: LayerPoint,
+        /// The frame time of the previous tick, or None if it hasn't ticked yet.
+        last_time: Option<u64>,
+    },
+}
+
+impl ScrollAnimation {
+    pub fn smooth(from: LayerPoint, to: LayerPoint) -> ScrollAnimation {
+        ScrollAnimation::Smooth {
+            from: from,
+            to: to,
+            start_time: None,
+        }
+    }
+
+    pub fn fling(from: LayerPoint, velocity: LayerPoint) -> ScrollAnimation {
+        ScrollAnimation::Fling {
+            offset: from,
+            velocity: velocity,
+            last_time: None,
+        }
+    }
+
+    /// The offset this animation comes to rest at, if it is known up front.
+    pub fn target(&self) -> Option<LayerPoint> {
+        match *self {
+            ScrollAnimation::Smooth { to, .. } => Some(to),
+            ScrollAnimation::Fling { .. } => None,
+        }
+    }
+
+    /// Advance the animation to `time`. Returns the new offset and whether the
+    /// animation is complete.
+    pub fn tick(&mut self, time: u64) -> (LayerPoint, bool) {
+        match *self {
+            ScrollAnimation::Smooth { from, to, ref mut start_time } => {
+                let start = match *start_time {
+                    Some(start) => start,
+                    None => {
+                        *start_time = Some(time);
+                        return (from, false);
+                    }
+                };
+
+                let elapsed = time.saturating_sub(start);
+                if elapsed >= SMOOTH_SCROLL_DURATION {
+                    return (to, true);
+                }
+
+                let progress = ease_out_cubic(elapsed as f32 / SMOOTH_SCROLL_DURATION as f32);
+                (LayerPoint::new(from.x + (to.x - from.x) * progress,
+                                 from.y + (to.y - from.y) * progress),
+                 false)
+            }
+            ScrollAnimation::Fling { ref mut offset, ref mut velocity, ref mut last_time } => {
+                let previous = match *last_time {
+                    Some(previous) => previous,
+                    None => {
+                        *last_time = Some(time);
+                        return (*offset, false);
+                    }
+                };
+                *last_time = Some(time);
+
+                // Integrate the exponentially decaying velocity over the elapsed
+                // time, so the distance travelled doesn't depend on frame rate.
+                let dt = time.saturating_sub(previous) as f32 / 1.0e9;
+                let decay = (-dt / FLING_TIME_CONSTANT).exp();
+                let distance = FLING_TIME_CONSTANT * (1.0 - decay);
+                *offset = LayerPoint::new(offset.x + velocity.x * distance,
+                                          offset.y + velocity.y * distance);
+                *velocity = LayerPoint::new(velocity.x * decay, velocity.y * decay);
+
+                let finished = velocity.x.abs() < MIN_FLING_VELOCITY &&
+                               velocity.y.abs() < MIN_FLING_VELOCITY;
+                (*offset, finished)
+            }
+        }
+    }
+}
+
+fn ease_out_cubic(t: f32) -> f32 {
+    let t = 1.0 - t;
+    1.0 - t * t * t
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MIN_FLING_VELOCITY, SMOOTH_SCROLL_DURATION, ScrollAnimation};
+    use webrender_traits::LayerPoint;
+
+    const FRAME: u64 = 16 * 1000 * 1000;
+
+    #[test]
+    fn smooth_scrolls_start_on_the_first_tick() {
+        let from = LayerPoint::new(0.0, -10.0);
+        let to = LayerPoint::new(0.0, -110.0);
+        let mut animation = ScrollAnimation::smooth(from, to);
+        assert_eq!(animation.target(), Some(to));
+
+        // However late the first frame comes, the animation runs its full length.
+        assert_eq!(animation.tick(5000 * FRAME), (from, false));
+
+        let (offset, finished) = animation.tick(5000 * FRAME + SMOOTH_SCROLL_DURATION / 2);
+        assert!(!finished);
+        // Easing out covers more than half of the distance in the first half.
+        assert!(offset.y < -60.0 && offset.y > -110.0);
+
+        let (offset, finished) = animation.tick(5000 * FRAME + SMOOTH_SCROLL_DURATION);
+        assert_eq!(offset, to);
+        assert!(finished);
+    }
+
+    #[test]
+    fn flings_decelerate_to_a_stop() {
+        let mut offset = LayerPoint::zero();
+        let mut animation = ScrollAnimation::fling(offset, LayerPoint::new(0.0, -2000.0));
+        assert_eq!(animation.target(), None);
+
+        let mut time = 1000 * FRAME;
+        assert_eq!(animation.tick(time), (offset, false));
+
+        let mut last_step = ::std::f32::MAX;
+        let mut finished = false;
+        let mut frames = 0;
+        while !finished {
+            time += FRAME;
+            let (new_offset, done) = animation.tick(time);
+            let step = offset.y - new_offset.y;
+            assert!(step > 0.0 && step < last_step);
+            last_step = step;
+            offset = new_offset;
+            finished = done;
+            frames += 1;
+            assert!(frames < 1000);
+        }
+
+        match animation {
+            ScrollAnimation::Fling { velocity, .. } => assert!(velocity.y.abs() < MIN_FLING_VELOCITY),
+            ScrollAnimation::Smooth { .. } => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn flings_travel_the_same_distance_at_any_frame_rate() {
+        let velocity = LayerPoint::new(1000.0, 0.0);
+        let mut slow = ScrollAnimation::fling(LayerPoint::zero(), velocity);
+        let mut fast = ScrollAnimation::fling(LayerPoint::zero(), velocity);
+        slow.tick(0);
+        fast.tick(0);
+
+        let (slow_offset, _) = slow.tick(2 * FRAME);
+        fast.tick(FRAME);
+        let (fast_offset, _) = fast.tick(2 * FRAME);
+        assert!((slow_offset.x - fast_offset.x).abs() < 0.01);
+    }
+}
@@ -39,6 +39,13 @@ impl Spring {
         }
     }
 
+    /// Change how hard the spring pulls and how much it bounces, keeping its
+    /// current motion.
+    pub fn set_parameters(&mut self, stiffness: f32, damping: f32) {
+        self.stiffness = stiffness;
+        self.damping = damping;
+    }
+
     /// Set coords on a spring, mutating spring
     pub fn coords(&mut self, cur: LayerPoint, prev: LayerPoint, dest: LayerPoint) {
         self.cur = cur;
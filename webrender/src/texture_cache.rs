@@ -272,7 +272,7 @@ impl TexturePage {
         self.dirty = false;
     }
 
-    fn free(&mut self, rect: &DeviceUintRect) {
+    pub fn free(&mut self, rect: &DeviceUintRect) {
         debug_assert!(self.allocations > 0);
         self.allocations -= 1;
         if self.allocations == 0 {
@@ -4,13 +4,17 @@
 
 use app_units::Au;
 use device::TextureId;
+use filter_graph::FilterGraph;
 use fnv::FnvHasher;
-use gpu_store::GpuStoreAddress;
-use internal_types::{ANGLE_FLOAT_TO_FIXED, BatchTextures, CacheTextureId, LowLevelFilterOp};
-use internal_types::SourceTexture;
+use gpu_store::{GpuStoreAddress, GpuStoreUpdates};
+use internal_types::{ANGLE_FLOAT_TO_FIXED, AxisDirection, BatchTextures, CacheTextureId};
+use internal_types::{LowLevelFilterOp, SourceTexture};
 use mask_cache::MaskCacheInfo;
+use mask_store::ClipMaskKey;
+use persistent_target::PersistentTargetOp;
+use picture_cache::PictureKey;
 use prim_store::{CLIP_DATA_GPU_SIZE, DeferredResolve, GpuBlock128, GpuBlock16, GpuBlock32};
-use prim_store::{GpuBlock64, GradientData, PrimitiveCacheKey, PrimitiveGeometry, PrimitiveIndex};
+use prim_store::{GpuBlock64, GradientData, PrimitiveGeometry, PrimitiveIndex};
 use prim_store::{PrimitiveKind, PrimitiveMetadata, PrimitiveStore, TexelRect};
 use profiler::FrameProfileCounters;
 use render_task::{AlphaRenderItem, MaskGeometryKind, MaskSegment, RenderTask, RenderTaskData};
@@ -24,10 +28,11 @@ use std::hash::BuildHasherDefault;
 use texture_cache::TexturePage;
 use util::{TransformedRect, TransformedRectKind};
 use webrender_traits::{AuxiliaryLists, ColorF, DeviceIntPoint, DeviceIntRect};
-use webrender_traits::{DeviceIntSize, DeviceUintPoint};
+use webrender_traits::{DeviceIntSize, DeviceUintPoint, Epoch};
 use webrender_traits::{DeviceUintSize, FontRenderMode, ImageRendering, LayerPoint, LayerRect};
-use webrender_traits::{LayerToWorldTransform, MixBlendMode, PipelineId, ScrollLayerId};
-use webrender_traits::{WorldPoint4D, WorldToLayerTransform};
+use webrender_traits::{LayerToWorldTransform, MixBlendMode, PipelineId, ScrollLayerId, ScrollbarAxis};
+use webrender_traits::PropertyBindingId;
+use webrender_traits::{TransformStyle, WorldPoint4D, WorldToLayerTransform};
 use webrender_traits::{ExternalImageType};
 
 // Special sentinel value recognized by the shader. It is considered to be
@@ -39,6 +44,38 @@ pub type AuxiliaryListsMap = HashMap<PipelineId,
                                      AuxiliaryLists,
                                      BuildHasherDefault<FnvHasher>>;
 
+pub type PipelineEpochMap = HashMap<PipelineId, Epoch, BuildHasherDefault<FnvHasher>>;
+
+// Returns the ps_blend op for a filter, and the amount passed with it.
+fn blend_filter_params(filter: LowLevelFilterOp) -> (i32, i32) {
+    let (filter_mode, amount) = match filter {
+        LowLevelFilterOp::Blur(radius, AxisDirection::Horizontal) => (0, radius.to_f32_px()),
+        LowLevelFilterOp::Contrast(amount) => (1, amount.to_f32_px()),
+        LowLevelFilterOp::Grayscale(amount) => (2, amount.to_f32_px()),
+        LowLevelFilterOp::HueRotate(angle) => (3, (angle as f32) / ANGLE_FLOAT_TO_FIXED),
+        LowLevelFilterOp::Invert(amount) => (4, amount.to_f32_px()),
+        LowLevelFilterOp::Saturate(amount) => (5, amount.to_f32_px()),
+        LowLevelFilterOp::Sepia(amount) => (6, amount.to_f32_px()),
+        LowLevelFilterOp::Brightness(amount) => (7, amount.to_f32_px()),
+        LowLevelFilterOp::Opacity(amount) => (8, amount.to_f32_px()),
+        LowLevelFilterOp::DropShadow(..) => (9, 0.0),
+        LowLevelFilterOp::Blur(radius, AxisDirection::Vertical) => (10, radius.to_f32_px()),
+        // Filter graphs are drawn by their own tasks.
+        LowLevelFilterOp::Graph(..) => unreachable!(),
+    };
+
+    // Drop shadows pass their color in place of an amount.
+    let amount = match filter {
+        LowLevelFilterOp::DropShadow(_, _, _, color) => {
+            ((color.r as u32) << 24 | (color.g as u32) << 16 |
+             (color.b as u32) << 8 | color.a as u32) as i32
+        }
+        _ => (amount * 65535.0).round() as i32,
+    };
+
+    (filter_mode, amount)
+}
+
 trait AlphaBatchHelpers {
     fn get_batch_kind(&self, metadata: &PrimitiveMetadata) -> AlphaBatchKind;
     fn get_color_textures(&self, metadata: &PrimitiveMetadata) -> [SourceTexture; 3];
@@ -64,6 +101,21 @@ trait AlphaBatchHelpers {
                                        task_index: RenderTaskIndex,
                                        src_task_index: RenderTaskIndex,
                                        z_sort_index: i32);
+    fn add_split_composite_to_batch(&self,
+                                    stacking_context_index: StackingContextIndex,
+                                    batch: &mut PrimitiveBatch,
+                                    task_index: RenderTaskIndex,
+                                    src_task_index: RenderTaskIndex,
+                                    geometry_address: GpuStoreAddress,
+                                    z_sort_index: i32);
+    fn add_filter_to_batch(&self,
+                           stacking_context_index: StackingContextIndex,
+                           batch: &mut PrimitiveBatch,
+                           task_index: RenderTaskIndex,
+                           op: i32,
+                           params_address: GpuStoreAddress,
+                           input_task_indices: [Option<RenderTaskIndex>; 2],
+                           z_sort_index: i32);
 }
 
 impl AlphaBatchHelpers for PrimitiveStore {
@@ -91,9 +143,11 @@ impl AlphaBatchHelpers for PrimitiveStore {
             }
             PrimitiveKind::YuvImage => AlphaBatchKind::YuvImage,
             PrimitiveKind::Rectangle => AlphaBatchKind::Rectangle,
+            PrimitiveKind::Line => AlphaBatchKind::Line,
             PrimitiveKind::AlignedGradient => AlphaBatchKind::AlignedGradient,
             PrimitiveKind::AngleGradient => AlphaBatchKind::AngleGradient,
             PrimitiveKind::RadialGradient => AlphaBatchKind::RadialGradient,
+            PrimitiveKind::ConicGradient => AlphaBatchKind::ConicGradient,
             PrimitiveKind::TextRun => {
                 let text_run_cpu = &self.cpu_text_runs[metadata.cpu_prim_index.0];
                 if text_run_cpu.blur_radius.0 == 0 {
@@ -116,9 +170,11 @@ impl AlphaBatchHelpers for PrimitiveStore {
             PrimitiveKind::Border |
             PrimitiveKind::BoxShadow |
             PrimitiveKind::Rectangle |
+            PrimitiveKind::Line |
             PrimitiveKind::AlignedGradient |
             PrimitiveKind::AngleGradient |
-            PrimitiveKind::RadialGradient => [invalid; 3],
+            PrimitiveKind::RadialGradient |
+            PrimitiveKind::ConicGradient => [invalid; 3],
             PrimitiveKind::Image => {
                 let image_cpu = &self.cpu_images[metadata.cpu_prim_index.0];
                 [image_cpu.color_texture_id, invalid, invalid]
@@ -151,7 +207,8 @@ impl AlphaBatchHelpers for PrimitiveStore {
             PrimitiveKind::Image |
             PrimitiveKind::AlignedGradient |
             PrimitiveKind::AngleGradient |
-            PrimitiveKind::RadialGradient => {
+            PrimitiveKind::RadialGradient |
+            PrimitiveKind::ConicGradient => {
                 if needs_blending {
                     BlendMode::PremultipliedAlpha
                 } else {
@@ -175,19 +232,7 @@ impl AlphaBatchHelpers for PrimitiveStore {
                           src_task_index: RenderTaskIndex,
                           filter: LowLevelFilterOp,
                           z_sort_index: i32) {
-        let (filter_mode, amount) = match filter {
-            LowLevelFilterOp::Blur(..) => (0, 0.0),
-            LowLevelFilterOp::Contrast(amount) => (1, amount.to_f32_px()),
-            LowLevelFilterOp::Grayscale(amount) => (2, amount.to_f32_px()),
-            LowLevelFilterOp::HueRotate(angle) => (3, (angle as f32) / ANGLE_FLOAT_TO_FIXED),
-            LowLevelFilterOp::Invert(amount) => (4, amount.to_f32_px()),
-            LowLevelFilterOp::Saturate(amount) => (5, amount.to_f32_px()),
-            LowLevelFilterOp::Sepia(amount) => (6, amount.to_f32_px()),
-            LowLevelFilterOp::Brightness(amount) => (7, amount.to_f32_px()),
-            LowLevelFilterOp::Opacity(amount) => (8, amount.to_f32_px()),
-        };
-
-        let amount = (amount * 65535.0).round() as i32;
+        let (filter_mode, amount) = blend_filter_params(filter);
 
         batch.items.push(PrimitiveBatchItem::StackingContext(stacking_context_index));
 
@@ -233,6 +278,64 @@ impl AlphaBatchHelpers for PrimitiveStore {
         }
     }
 
+    fn add_split_composite_to_batch(&self,
+                                    stacking_context_index: StackingContextIndex,
+                                    batch: &mut PrimitiveBatch,
+                                    task_index: RenderTaskIndex,
+                                    src_task_index: RenderTaskIndex,
+                                    geometry_address: GpuStoreAddress,
+                                    z_sort_index: i32) {
+        batch.items.push(PrimitiveBatchItem::StackingContext(stacking_context_index));
+
+        match batch.data {
+            PrimitiveBatchData::Instances(ref mut data) => {
+                data.push(PrimitiveInstance {
+                    global_prim_id: -1,
+                    prim_address: geometry_address,
+                    task_index: task_index.0 as i32,
+                    clip_task_index: -1,
+                    layer_index: -1,
+                    sub_index: -1,
+                    user_data: [src_task_index.0 as i32, 0],
+                    z_sort_index: z_sort_index,
+                });
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    fn add_filter_to_batch(&self,
+                           stacking_context_index: StackingContextIndex,
+                           batch: &mut PrimitiveBatch,
+                           task_index: RenderTaskIndex,
+                           op: i32,
+                           params_address: GpuStoreAddress,
+                           input_task_indices: [Option<RenderTaskIndex>; 2],
+                           z_sort_index: i32) {
+        batch.items.push(PrimitiveBatchItem::StackingContext(stacking_context_index));
+
+        // Inputs that the filter doesn't read are passed as -1.
+        let input_task_index = |index: usize| {
+            input_task_indices[index].map_or(-1, |task_index| task_index.0 as i32)
+        };
+
+        match batch.data {
+            PrimitiveBatchData::Instances(ref mut data) => {
+                data.push(PrimitiveInstance {
+                    global_prim_id: -1,
+                    prim_address: params_address,
+                    task_index: task_index.0 as i32,
+                    clip_task_index: -1,
+                    layer_index: -1,
+                    sub_index: op,
+                    user_data: [input_task_index(0), input_task_index(1)],
+                    z_sort_index: z_sort_index,
+                });
+            }
+            _ => unreachable!(),
+        }
+    }
+
     fn add_prim_to_batch(&self,
                          prim_index: PrimitiveIndex,
                          batch: &mut PrimitiveBatch,
@@ -262,9 +365,13 @@ impl AlphaBatchHelpers for PrimitiveStore {
             &mut PrimitiveBatchData::Instances(ref mut data) => {
                 match batch.key.kind {
                     AlphaBatchKind::Composite => unreachable!(),
+                    AlphaBatchKind::BackdropFilter => unreachable!(),
                     AlphaBatchKind::HardwareComposite => unreachable!(),
+                    AlphaBatchKind::SplitComposite => unreachable!(),
+                    AlphaBatchKind::Filter => unreachable!(),
                     AlphaBatchKind::Blend => unreachable!(),
-                    AlphaBatchKind::Rectangle => {
+                    AlphaBatchKind::Rectangle |
+                    AlphaBatchKind::Line => {
                         data.push(PrimitiveInstance {
                             task_index: task_index,
                             clip_task_index: clip_task_index,
@@ -350,6 +457,7 @@ impl AlphaBatchHelpers for PrimitiveStore {
                         }
                     }
                     AlphaBatchKind::AngleGradient => {
+                        let gradient_cpu = &self.cpu_gradients[metadata.cpu_prim_index.0];
                         data.push(PrimitiveInstance {
                             task_index: task_index,
                             clip_task_index: clip_task_index,
@@ -357,11 +465,25 @@ impl AlphaBatchHelpers for PrimitiveStore {
                             global_prim_id: global_prim_id,
                             prim_address: prim_address,
                             sub_index: metadata.gpu_data_address.0,
-                            user_data: [ metadata.gpu_data_count, 0 ],
+                            user_data: [ metadata.gpu_data_count, gradient_cpu.entry_count as i32 ],
                             z_sort_index: z_sort_index,
                         });
                     }
                     AlphaBatchKind::RadialGradient => {
+                        let gradient_cpu = &self.cpu_radial_gradients[metadata.cpu_prim_index.0];
+                        data.push(PrimitiveInstance {
+                            task_index: task_index,
+                            clip_task_index: clip_task_index,
+                            layer_index: packed_layer_index,
+                            global_prim_id: global_prim_id,
+                            prim_address: prim_address,
+                            sub_index: metadata.gpu_data_address.0,
+                            user_data: [ metadata.gpu_data_count, gradient_cpu.entry_count as i32 ],
+                            z_sort_index: z_sort_index,
+                        });
+                    }
+                    AlphaBatchKind::ConicGradient => {
+                        let gradient_cpu = &self.cpu_conic_gradients[metadata.cpu_prim_index.0];
                         data.push(PrimitiveInstance {
                             task_index: task_index,
                             clip_task_index: clip_task_index,
@@ -369,26 +491,36 @@ impl AlphaBatchHelpers for PrimitiveStore {
                             global_prim_id: global_prim_id,
                             prim_address: prim_address,
                             sub_index: metadata.gpu_data_address.0,
-                            user_data: [ metadata.gpu_data_count, 0 ],
+                            user_data: [ metadata.gpu_data_count, gradient_cpu.entry_count as i32 ],
                             z_sort_index: z_sort_index,
                         });
                     }
                     AlphaBatchKind::BoxShadow => {
-                        let cache_task_id = &metadata.render_task.as_ref().unwrap().id;
-                        let cache_task_index = render_tasks.get_task_index(cache_task_id,
-                                                                           child_pass_index);
+                        let box_shadow_cpu = &self.cpu_box_shadows[metadata.cpu_prim_index.0];
+                        let mut rect_address = metadata.gpu_data_address;
+
+                        // Every shadow of the primitive goes into this batch, in
+                        // paint order, each sampling from its (possibly shared)
+                        // cache task.
+                        for (shadow_index, shadow) in box_shadow_cpu.shadows.iter().enumerate() {
+                            let cache_task_id = &box_shadow_cpu.render_tasks[shadow.render_task_index].id;
+                            let cache_task_index = render_tasks.get_task_index(cache_task_id,
+                                                                               child_pass_index);
+
+                            for rect_index in 0..shadow.rect_count {
+                                data.push(PrimitiveInstance {
+                                    task_index: task_index,
+                                    clip_task_index: clip_task_index,
+                                    layer_index: packed_layer_index,
+                                    global_prim_id: global_prim_id,
+                                    prim_address: prim_address + shadow_index,
+                                    sub_index: rect_address.0 + rect_index,
+                                    user_data: [ cache_task_index.0 as i32, 0 ],
+                                    z_sort_index: z_sort_index,
+                                });
+                            }
 
-                        for rect_index in 0..metadata.gpu_data_count {
-                            data.push(PrimitiveInstance {
-                                task_index: task_index,
-                                clip_task_index: clip_task_index,
-                                layer_index: packed_layer_index,
-                                global_prim_id: global_prim_id,
-                                prim_address: prim_address,
-                                sub_index: metadata.gpu_data_address.0 + rect_index,
-                                user_data: [ cache_task_index.0 as i32, 0 ],
-                                z_sort_index: z_sort_index,
-                            });
+                            rect_address = rect_address + shadow.rect_count;
                         }
                     }
                     AlphaBatchKind::CacheImage => {
@@ -421,7 +553,7 @@ impl AlphaBatchHelpers for PrimitiveStore {
 pub struct ScrollbarPrimitive {
     pub scroll_layer_id: ScrollLayerId,
     pub prim_index: PrimitiveIndex,
-    pub border_radius: f32,
+    pub axis: ScrollbarAxis,
 }
 
 #[derive(Debug)]
@@ -434,7 +566,7 @@ pub enum PrimitiveRunCmd {
 #[derive(Debug, Copy, Clone)]
 pub enum PrimitiveFlags {
     None,
-    Scrollbar(ScrollLayerId, f32)
+    Scrollbar(ScrollLayerId, ScrollbarAxis)
 }
 
 #[derive(Debug, Copy, Clone)]
@@ -573,6 +705,22 @@ impl AlphaBatcher {
                                             BatchTextures::no_texture()),
                          &stacking_context.bounding_rect)
                     }
+                    &AlphaRenderItem::SplitComposite(stacking_context_index, ..) => {
+                        let stacking_context = &ctx.stacking_context_store[stacking_context_index.0];
+                        (AlphaBatchKey::new(AlphaBatchKind::SplitComposite,
+                                            AlphaBatchKeyFlags::empty(),
+                                            BlendMode::PremultipliedAlpha,
+                                            BatchTextures::no_texture()),
+                         &stacking_context.bounding_rect)
+                    }
+                    &AlphaRenderItem::Filter(stacking_context_index, ..) => {
+                        let stacking_context = &ctx.stacking_context_store[stacking_context_index.0];
+                        (AlphaBatchKey::new(AlphaBatchKind::Filter,
+                                            AlphaBatchKeyFlags::empty(),
+                                            BlendMode::None,
+                                            BatchTextures::no_texture()),
+                         &stacking_context.bounding_rect)
+                    }
                     &AlphaRenderItem::Composite(stacking_context_index,
                                                 backdrop_id,
                                                 src_id,
@@ -591,6 +739,17 @@ impl AlphaBatcher {
                         alpha_batches.push(batch);
                         continue;
                     }
+                    &AlphaRenderItem::BackdropFilter(stacking_context_index, readback_id, filter, z) => {
+                        // Backdrop filters read back what has been drawn so
+                        // far, so like composites they get their own batch.
+                        let batch = PrimitiveBatch::new_backdrop_filter(stacking_context_index,
+                                                                        task_index,
+                                                                        render_tasks.get_task_index(&readback_id, child_pass_index),
+                                                                        filter,
+                                                                        z);
+                        alpha_batches.push(batch);
+                        continue;
+                    }
                     &AlphaRenderItem::Primitive(clip_scroll_group_index, prim_index, _) => {
                         let group = &ctx.clip_scroll_group_store[clip_scroll_group_index.0];
                         let prim_metadata = ctx.prim_store.get_metadata(prim_index);
@@ -656,9 +815,16 @@ impl AlphaBatcher {
                 if alpha_batch_index.is_none() {
                     let new_batch = match item {
                         &AlphaRenderItem::Composite(..) => unreachable!(),
+                        &AlphaRenderItem::BackdropFilter(..) => unreachable!(),
                         &AlphaRenderItem::HardwareComposite(..) => {
                             PrimitiveBatch::new_instances(AlphaBatchKind::HardwareComposite, batch_key)
                         }
+                        &AlphaRenderItem::SplitComposite(..) => {
+                            PrimitiveBatch::new_instances(AlphaBatchKind::SplitComposite, batch_key)
+                        }
+                        &AlphaRenderItem::Filter(..) => {
+                            PrimitiveBatch::new_instances(AlphaBatchKind::Filter, batch_key)
+                        }
                         &AlphaRenderItem::Blend(..) => {
                             PrimitiveBatch::new_instances(AlphaBatchKind::Blend, batch_key)
                         }
@@ -675,6 +841,7 @@ impl AlphaBatcher {
                 let batch = &mut alpha_batches[alpha_batch_index.unwrap()];
                 match item {
                     &AlphaRenderItem::Composite(..) => unreachable!(),
+                    &AlphaRenderItem::BackdropFilter(..) => unreachable!(),
                     &AlphaRenderItem::Blend(stacking_context_index, src_id, info, z) => {
                         ctx.prim_store.add_blend_to_batch(stacking_context_index,
                                                           batch,
@@ -691,6 +858,28 @@ impl AlphaBatcher {
                             render_tasks.get_static_task_index(&src_id),
                             z);
                     }
+                    &AlphaRenderItem::SplitComposite(stacking_context_index, src_id, geometry_address, z) => {
+                        ctx.prim_store.add_split_composite_to_batch(
+                            stacking_context_index,
+                            batch,
+                            task_index,
+                            render_tasks.get_static_task_index(&src_id),
+                            geometry_address,
+                            z);
+                    }
+                    &AlphaRenderItem::Filter(stacking_context_index, op, params_address, input_ids, z) => {
+                        let input_task_indices = [
+                            input_ids[0].map(|id| render_tasks.get_static_task_index(&id)),
+                            input_ids[1].map(|id| render_tasks.get_static_task_index(&id)),
+                        ];
+                        ctx.prim_store.add_filter_to_batch(stacking_context_index,
+                                                           batch,
+                                                           task_index,
+                                                           op,
+                                                           params_address,
+                                                           input_task_indices,
+                                                           z);
+                    }
                     &AlphaRenderItem::Primitive(clip_scroll_group_index, prim_index, z) => {
                         let packed_layer = ctx.clip_scroll_group_store[clip_scroll_group_index.0]
                                               .packed_layer_index;
@@ -708,8 +897,11 @@ impl AlphaBatcher {
             for item in task.opaque_items.iter().rev() {
                 let batch_key = match item {
                     &AlphaRenderItem::Composite(..) => unreachable!(),
+                    &AlphaRenderItem::BackdropFilter(..) => unreachable!(),
                     &AlphaRenderItem::Blend(..) => unreachable!(),
                     &AlphaRenderItem::HardwareComposite(..) => unreachable!(),
+                    &AlphaRenderItem::SplitComposite(..) => unreachable!(),
+                    &AlphaRenderItem::Filter(..) => unreachable!(),
                     &AlphaRenderItem::Primitive(clip_scroll_group_index, prim_index, _) => {
                         let group = &ctx.clip_scroll_group_store[clip_scroll_group_index.0];
                         let transform_kind = group.xf_rect.as_ref().unwrap().kind;
@@ -749,8 +941,11 @@ impl AlphaBatcher {
                 if existing_opaque_batch_index == opaque_batches.len() {
                     let new_batch = match item {
                         &AlphaRenderItem::Composite(..) => unreachable!(),
+                        &AlphaRenderItem::BackdropFilter(..) => unreachable!(),
                         &AlphaRenderItem::Blend(..) => unreachable!(),
                         &AlphaRenderItem::HardwareComposite(..) => unreachable!(),
+                        &AlphaRenderItem::SplitComposite(..) => unreachable!(),
+                        &AlphaRenderItem::Filter(..) => unreachable!(),
                         &AlphaRenderItem::Primitive(_, prim_index, _) => {
                             let prim_metadata = ctx.prim_store.get_metadata(prim_index);
                             let batch_kind = ctx.prim_store.get_batch_kind(prim_metadata);
@@ -763,8 +958,11 @@ impl AlphaBatcher {
                 let batch = &mut opaque_batches[existing_opaque_batch_index];
                 match item {
                     &AlphaRenderItem::Composite(..) => unreachable!(),
+                    &AlphaRenderItem::BackdropFilter(..) => unreachable!(),
                     &AlphaRenderItem::Blend(..) => unreachable!(),
                     &AlphaRenderItem::HardwareComposite(..) => unreachable!(),
+                    &AlphaRenderItem::SplitComposite(..) => unreachable!(),
+                    &AlphaRenderItem::Filter(..) => unreachable!(),
                     &AlphaRenderItem::Primitive(clip_scroll_group_index, prim_index, z) => {
                         let packed_layer_index =
                             ctx.clip_scroll_group_store[clip_scroll_group_index.0]
@@ -855,7 +1053,7 @@ impl ClipBatcher {
             }
 
             if let Some((ref mask, address)) = info.image {
-                let cache_item = resource_cache.get_cached_image(mask.image, ImageRendering::Auto, None);
+                let cache_item = resource_cache.get_cached_image(mask.image, ImageRendering::Auto, None, 1.0);
                 self.images.entry(cache_item.texture_id)
                            .or_insert(Vec::new())
                            .push(CacheClipInstance {
@@ -1014,6 +1212,12 @@ pub struct ColorRenderTarget {
     pub horizontal_blurs: Vec<BlurCommand>,
     pub readbacks: Vec<DeviceIntRect>,
     pub isolate_clears: Vec<DeviceIntRect>,
+    // Stacking contexts to copy out of the picture cache target, as
+    // (cache rect, target rect). Drawn right after the clears.
+    pub picture_loads: Vec<(DeviceIntRect, DeviceIntRect)>,
+    // Stacking contexts to copy into the picture cache target once this
+    // target has been drawn, as (target rect, cache rect, key).
+    pub picture_stores: Vec<(DeviceIntRect, DeviceIntRect, PictureKey)>,
     allocator: TextureAllocator,
 }
 
@@ -1032,6 +1236,8 @@ impl RenderTarget for ColorRenderTarget {
             horizontal_blurs: Vec::new(),
             readbacks: Vec::new(),
             isolate_clears: Vec::new(),
+            picture_loads: Vec::new(),
+            picture_stores: Vec::new(),
             allocator: TextureAllocator::new(size),
         }
     }
@@ -1071,51 +1277,51 @@ impl RenderTarget for ColorRenderTarget {
                     };
                     self.isolate_clears.push(location);
                 }
+
+                if let Some((cache_rect, key)) = info.picture_store {
+                    let location = match task.location {
+                        RenderTaskLocation::Dynamic(origin, size) => {
+                            DeviceIntRect::new(origin.unwrap().0, size)
+                        }
+                        RenderTaskLocation::Fixed => panic!("Can't cache the framebuffer!")
+                    };
+                    self.picture_stores.push((location, cache_rect, key));
+                }
             }
-            RenderTaskKind::VerticalBlur(_, prim_index) => {
-                // Find the child render task that we are applying
-                // a vertical blur on.
-                // TODO(gw): Consider a simpler way for render tasks to find
-                //           their child tasks than having to construct the
-                //           correct id here.
+            RenderTaskKind::VerticalBlur(ref info) => {
                 let child_pass_index = RenderPassIndex(pass_index.0 - 1);
-                let task_key = RenderTaskKey::CachePrimitive(PrimitiveCacheKey::TextShadow(prim_index));
-                let src_id = RenderTaskId::Dynamic(task_key);
                 self.vertical_blurs.push(BlurCommand {
                     task_id: render_tasks.get_task_index(&task.id, pass_index).0 as i32,
-                    src_task_id: render_tasks.get_task_index(&src_id, child_pass_index).0 as i32,
+                    src_task_id: render_tasks.get_task_index(&info.src_task_id, child_pass_index).0 as i32,
                     blur_direction: BlurDirection::Vertical as i32,
                     padding: 0,
                 });
             }
-            RenderTaskKind::HorizontalBlur(blur_radius, prim_index) => {
-                // Find the child render task that we are applying
-                // a horizontal blur on.
+            RenderTaskKind::HorizontalBlur(ref info) => {
                 let child_pass_index = RenderPassIndex(pass_index.0 - 1);
-                let src_id = RenderTaskId::Dynamic(RenderTaskKey::VerticalBlur(blur_radius.0, prim_index));
                 self.horizontal_blurs.push(BlurCommand {
                     task_id: render_tasks.get_task_index(&task.id, pass_index).0 as i32,
-                    src_task_id: render_tasks.get_task_index(&src_id, child_pass_index).0 as i32,
+                    src_task_id: render_tasks.get_task_index(&info.src_task_id, child_pass_index).0 as i32,
                     blur_direction: BlurDirection::Horizontal as i32,
                     padding: 0,
                 });
             }
+            RenderTaskKind::CacheBoxShadow(prim_index, prim_address) => {
+                self.box_shadow_cache_prims.push(PrimitiveInstance {
+                    global_prim_id: prim_index.0 as i32,
+                    prim_address: prim_address,
+                    task_index: render_tasks.get_task_index(&task.id, pass_index).0 as i32,
+                    clip_task_index: 0,
+                    layer_index: 0,
+                    sub_index: 0,
+                    user_data: [0; 2],
+                    z_sort_index: 0,        // z is disabled for rendering cache primitives
+                });
+            }
             RenderTaskKind::CachePrimitive(prim_index) => {
                 let prim_metadata = ctx.prim_store.get_metadata(prim_index);
 
                 match prim_metadata.prim_kind {
-                    PrimitiveKind::BoxShadow => {
-                        self.box_shadow_cache_prims.push(PrimitiveInstance {
-                            global_prim_id: prim_index.0 as i32,
-                            prim_address: prim_metadata.gpu_prim_index,
-                            task_index: render_tasks.get_task_index(&task.id, pass_index).0 as i32,
-                            clip_task_index: 0,
-                            layer_index: 0,
-                            sub_index: 0,
-                            user_data: [0; 2],
-                            z_sort_index: 0,        // z is disabled for rendering cache primitives
-                        });
-                    }
                     PrimitiveKind::TextRun => {
                         let text = &ctx.prim_store.cpu_text_runs[prim_metadata.cpu_prim_index.0];
                         // We only cache text runs with a text-shadow (for now).
@@ -1158,12 +1364,27 @@ impl RenderTarget for ColorRenderTarget {
             RenderTaskKind::Readback(device_rect) => {
                 self.readbacks.push(device_rect);
             }
+            RenderTaskKind::CachedPicture(ref info) => {
+                let location = match task.location {
+                    RenderTaskLocation::Dynamic(origin, size) => {
+                        DeviceIntRect::new(origin.unwrap().0, size)
+                    }
+                    RenderTaskLocation::Fixed => panic!()
+                };
+                self.picture_loads.push((info.cache_rect, location));
+            }
         }
     }
 }
 
 pub struct AlphaRenderTarget {
     pub clip_batcher: ClipBatcher,
+    // Clip masks to copy out of the clip mask store, as (store rect,
+    // target rect). Drawn right after the clear.
+    pub mask_loads: Vec<(DeviceIntRect, DeviceIntRect)>,
+    // Clip masks to copy into the clip mask store once this target has
+    // been drawn, as (target rect, store rect, key).
+    pub mask_stores: Vec<(DeviceIntRect, DeviceIntRect, ClipMaskKey)>,
     allocator: TextureAllocator,
 }
 
@@ -1175,6 +1396,8 @@ impl RenderTarget for AlphaRenderTarget {
     fn new(size: DeviceUintSize) -> AlphaRenderTarget {
         AlphaRenderTarget {
             clip_batcher: ClipBatcher::new(),
+            mask_loads: Vec::new(),
+            mask_stores: Vec::new(),
             allocator: TextureAllocator::new(size),
         }
     }
@@ -1193,15 +1416,33 @@ impl RenderTarget for AlphaRenderTarget {
             RenderTaskKind::VerticalBlur(..) |
             RenderTaskKind::HorizontalBlur(..) |
             RenderTaskKind::CachePrimitive(..) |
-            RenderTaskKind::Readback(..) => {
+            RenderTaskKind::CacheBoxShadow(..) |
+            RenderTaskKind::Readback(..) |
+            RenderTaskKind::CachedPicture(..) => {
                 panic!("Should not be added to alpha target!");
             }
             RenderTaskKind::CacheMask(ref task_info) => {
+                let location = match task.location {
+                    RenderTaskLocation::Dynamic(origin, size) => {
+                        DeviceIntRect::new(origin.unwrap().0, size)
+                    }
+                    RenderTaskLocation::Fixed => panic!()
+                };
+
+                if let Some(PersistentTargetOp::Reuse(store_rect)) = task_info.store_op {
+                    self.mask_loads.push((store_rect, location));
+                    return;
+                }
+
                 let task_index = render_tasks.get_task_index(&task.id, pass_index);
                 self.clip_batcher.add(task_index,
                                       &task_info.clips,
                                       &ctx.resource_cache,
                                       task_info.geometry_kind);
+
+                if let Some(PersistentTargetOp::Store(store_rect, key)) = task_info.store_op {
+                    self.mask_stores.push((location, store_rect, key));
+                }
             }
         }
     }
@@ -1325,9 +1566,13 @@ impl RenderPass {
 #[repr(u8)]
 pub enum AlphaBatchKind {
     Composite = 0,
+    BackdropFilter,
     HardwareComposite,
+    SplitComposite,
+    Filter,
     Blend,
     Rectangle,
+    Line,
     TextRun,
     Image,
     ImageRect,
@@ -1336,6 +1581,7 @@ pub enum AlphaBatchKind {
     AlignedGradient,
     AngleGradient,
     RadialGradient,
+    ConicGradient,
     BoxShadow,
     CacheImage,
 }
@@ -1459,6 +1705,7 @@ impl PrimitiveBatch {
     fn new_instances(batch_kind: AlphaBatchKind, key: AlphaBatchKey) -> PrimitiveBatch {
         let data = match batch_kind {
             AlphaBatchKind::Rectangle |
+            AlphaBatchKind::Line |
             AlphaBatchKind::TextRun |
             AlphaBatchKind::Image |
             AlphaBatchKind::ImageRect |
@@ -1467,13 +1714,17 @@ impl PrimitiveBatch {
             AlphaBatchKind::AlignedGradient |
             AlphaBatchKind::AngleGradient |
             AlphaBatchKind::RadialGradient |
+            AlphaBatchKind::ConicGradient |
             AlphaBatchKind::BoxShadow |
             AlphaBatchKind::Blend |
             AlphaBatchKind::HardwareComposite |
+            AlphaBatchKind::SplitComposite |
+            AlphaBatchKind::Filter |
             AlphaBatchKind::CacheImage => {
                 PrimitiveBatchData::Instances(Vec::new())
             }
-            AlphaBatchKind::Composite => unreachable!(),
+            AlphaBatchKind::Composite |
+            AlphaBatchKind::BackdropFilter => unreachable!(),
         };
 
         PrimitiveBatch {
@@ -1511,6 +1762,36 @@ impl PrimitiveBatch {
             items: vec![PrimitiveBatchItem::StackingContext(stacking_context_index)],
         }
     }
+
+    fn new_backdrop_filter(stacking_context_index: StackingContextIndex,
+                           task_index: RenderTaskIndex,
+                           readback_task: RenderTaskIndex,
+                           filter: LowLevelFilterOp,
+                           z_sort_index: i32) -> PrimitiveBatch {
+        let (filter_mode, amount) = blend_filter_params(filter);
+        let data = PrimitiveBatchData::Composite(PrimitiveInstance {
+            global_prim_id: -1,
+            prim_address: GpuStoreAddress(0),
+            task_index: task_index.0 as i32,
+            clip_task_index: -1,
+            layer_index: -1,
+            sub_index: filter_mode,
+            user_data: [ readback_task.0 as i32,
+                         amount ],
+            z_sort_index: z_sort_index,
+        });
+        // The filtered backdrop replaces the one it was read from.
+        let key = AlphaBatchKey::new(AlphaBatchKind::BackdropFilter,
+                                     AlphaBatchKeyFlags::empty(),
+                                     BlendMode::None,
+                                     BatchTextures::no_texture());
+
+        PrimitiveBatch {
+            key: key,
+            data: data,
+            items: vec![PrimitiveBatchItem::StackingContext(stacking_context_index)],
+        }
+    }
 }
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Ord, PartialOrd)]
@@ -1527,6 +1808,12 @@ pub struct StackingContext {
     // context's coordinate system.
     pub reference_frame_offset: LayerPoint,
 
+    // The reference frame this stacking context is positioned in, and its
+    // bounds within that reference frame. These define the plane of the
+    // stacking context when it is part of a 3D rendering context.
+    pub reference_frame_id: ScrollLayerId,
+    pub local_bounds: LayerRect,
+
     // Bounding rectangle for this stacking context calculated based on the size
     // and position of all its children.
     pub bounding_rect: DeviceIntRect,
@@ -1546,23 +1833,58 @@ pub struct StackingContext {
     // Wehther or not this stacking context has any visible components, calculated
     // based on the size and position of all children and how they are clipped.
     pub is_visible: bool,
+
+    pub transform_style: TransformStyle,
+
+    // When false, this stacking context (and everything in it) is not drawn
+    // if its transform makes it face away from the viewer.
+    pub backface_visible: bool,
+
+    // Set during culling when the back face of this stacking context, or of
+    // one of its ancestors, is turned towards the viewer and is not visible.
+    pub is_backface_hidden: bool,
+
+    // Set for the children of a preserve-3d stacking context. Each one is drawn
+    // into its own surface, which is then composited as part of the depth
+    // sorted planes of the parent.
+    pub is_3d_plane: bool,
+
+    // The stacking contexts that make up the 3D rendering context established
+    // by this one, in painter's order.
+    pub planes: Vec<StackingContextIndex>,
+
+    // The (possibly split) polygons of the planes above, sorted back to front,
+    // and the location of their geometry in the GPU store. Rebuilt every frame.
+    pub split_polygons: Vec<(StackingContextIndex, GpuStoreAddress)>,
 }
 
 impl StackingContext {
     pub fn new(pipeline_id: PipelineId,
                reference_frame_offset: LayerPoint,
+               reference_frame_id: ScrollLayerId,
+               local_bounds: LayerRect,
                is_page_root: bool,
+               transform_style: TransformStyle,
+               backface_visible: bool,
                composite_ops: CompositeOps)
                -> StackingContext {
         StackingContext {
             pipeline_id: pipeline_id,
             reference_frame_offset: reference_frame_offset,
+            reference_frame_id: reference_frame_id,
+            local_bounds: local_bounds,
             bounding_rect: DeviceIntRect::zero(),
             composite_ops: composite_ops,
             clip_scroll_groups: Vec::new(),
             should_isolate: false,
             is_page_root: is_page_root,
             is_visible: false,
+            transform_style: transform_style,
+            backface_visible: backface_visible,
+            is_backface_hidden: false,
+            is_3d_plane: false,
+            planes: Vec::new(),
+            split_polygons: Vec::new(),
         }
     }
 
@@ -1650,19 +1972,41 @@ impl PackedLayer {
     }
 }
 
+/// The opacity filters whose amount is a property binding, by index into the
+/// filters, with the binding and the amount they were flattened with.
+pub type ScrollLinkedOpacities = Vec<(usize, PropertyBindingId, f32)>;
+
 #[derive(Debug, Clone)]
 pub struct CompositeOps {
     // Requires only a single texture as input (e.g. most filters)
     pub filters: Vec<LowLevelFilterOp>,
 
+    // The graphs referenced by LowLevelFilterOp::Graph filters.
+    pub filter_graphs: Vec<FilterGraph>,
+
+    // The opacity filters whose amount may follow a scroll offset. They are
+    // updated every time the frame is built.
+    pub scroll_linked_opacities: ScrollLinkedOpacities,
+
+    // Applied in place to the content beneath the stacking context,
+    // using a framebuffer readback for each filter.
+    pub backdrop_filters: Vec<LowLevelFilterOp>,
+
     // Requires two source textures (e.g. mix-blend-mode)
     pub mix_blend_mode: Option<MixBlendMode>,
 }
 
 impl CompositeOps {
-    pub fn new(filters: Vec<LowLevelFilterOp>, mix_blend_mode: Option<MixBlendMode>) -> CompositeOps {
+    pub fn new(filters: Vec<LowLevelFilterOp>,
+               filter_graphs: Vec<FilterGraph>,
+               scroll_linked_opacities: ScrollLinkedOpacities,
+               backdrop_filters: Vec<LowLevelFilterOp>,
+               mix_blend_mode: Option<MixBlendMode>) -> CompositeOps {
         CompositeOps {
             filters: filters,
+            filter_graphs: filter_graphs,
+            scroll_linked_opacities: scroll_linked_opacities,
+            backdrop_filters: backdrop_filters,
             mix_blend_mode: mix_blend_mode
         }
     }
@@ -1671,8 +2015,26 @@ impl CompositeOps {
         self.filters.len() + if self.mix_blend_mode.is_some() { 1 } else { 0 }
     }
 
+    /// The number of leading filters whose output is kept when the stacking
+    /// context is cached. Any opacity filters after them are applied when the
+    /// cached picture is composited, so they can be animated without
+    /// invalidating it.
+    pub fn picture_filter_count(&self) -> usize {
+        self.filters.iter().rposition(|filter| {
+            match *filter {
+                LowLevelFilterOp::Opacity(..) => false,
+                _ => true,
+            }
+        }).map_or(0, |index| index + 1)
+    }
+
     pub fn will_make_invisible(&self) -> bool {
-        for op in &self.filters {
+        for (index, op) in self.filters.iter().enumerate() {
+            // Bound opacities may become visible without the scene being rebuilt.
+            if self.scroll_linked_opacities.iter().any(|&(filter_index, _, _)| filter_index == index) {
+                continue;
+            }
+
             match op {
                 &LowLevelFilterOp::Opacity(Au(0)) => return true,
                 _ => {}
@@ -1694,13 +2056,17 @@ pub struct Frame {
 
     pub layer_texture_data: Vec<PackedLayer>,
     pub render_task_data: Vec<RenderTaskData>,
-    pub gpu_data16: Vec<GpuBlock16>,
-    pub gpu_data32: Vec<GpuBlock32>,
-    pub gpu_data64: Vec<GpuBlock64>,
-    pub gpu_data128: Vec<GpuBlock128>,
-    pub gpu_geometry: Vec<PrimitiveGeometry>,
-    pub gpu_gradient_data: Vec<GradientData>,
-    pub gpu_resource_rects: Vec<TexelRect>,
+
+    // The primitive data stores persist between frames, so
+    // only the parts that changed since the previous frame
+    // are sent to the renderer.
+    pub gpu_data16: GpuStoreUpdates<GpuBlock16>,
+    pub gpu_data32: GpuStoreUpdates<GpuBlock32>,
+    pub gpu_data64: GpuStoreUpdates<GpuBlock64>,
+    pub gpu_data128: GpuStoreUpdates<GpuBlock128>,
+    pub gpu_geometry: GpuStoreUpdates<PrimitiveGeometry>,
+    pub gpu_gradient_data: GpuStoreUpdates<GradientData>,
+    pub gpu_resource_rects: GpuStoreUpdates<TexelRect>,
 
     // List of textures that we don't know about yet
     // from the backend thread. The render thread
@@ -26,6 +26,15 @@ pub trait MatrixHelpers<Src, Dst> {
     fn reset_after_transforming_rect(&self) -> TypedMatrix4D<f32, Src, Dst>;
 
     fn is_identity(&self) -> bool;
+
+    /// Returns true if this matrix is a 2D translation that moves points by a whole number of
+    /// device pixels.
+    fn is_2d_integer_translation(&self, device_pixel_ratio: f32) -> bool;
+
+    /// Returns true if the front face of a plane transformed by this matrix is turned
+    /// towards the viewer. Matrices that flatten the plane into a line are treated as
+    /// showing their back face.
+    fn is_front_face_visible(&self) -> bool;
 }
 
 impl<Src, Dst> MatrixHelpers<Src, Dst> for TypedMatrix4D<f32, Src, Dst> {
@@ -62,6 +71,25 @@ impl<Src, Dst> MatrixHelpers<Src, Dst> for TypedMatrix4D<f32, Src, Dst> {
     fn is_identity(&self) -> bool {
         *self == TypedMatrix4D::identity()
     }
+
+    fn is_2d_integer_translation(&self, device_pixel_ratio: f32) -> bool {
+        self.m11 == 1.0 && self.m12 == 0.0 && self.m13 == 0.0 && self.m14 == 0.0 &&
+        self.m21 == 0.0 && self.m22 == 1.0 && self.m23 == 0.0 && self.m24 == 0.0 &&
+        self.m31 == 0.0 && self.m32 == 0.0 && self.m33 == 1.0 && self.m34 == 0.0 &&
+        self.m44 == 1.0 &&
+        (self.m41 * device_pixel_ratio).fract() == 0.0 &&
+        (self.m42 * device_pixel_ratio).fract() == 0.0
+    }
+
+    fn is_front_face_visible(&self) -> bool {
+        // The plane faces away from the viewer when the z axis of the local
+        // coordinate system is flipped, which shows up as a negative m33 in
+        // the inverse matrix.
+        match self.inverse() {
+            Some(inverse) => inverse.m33 >= 0.0,
+            None => false,
+        }
+    }
 }
 
 pub trait RectHelpers<U> where Self: Sized {
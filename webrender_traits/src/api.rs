@@ -12,7 +12,7 @@ use std::marker::PhantomData;
 use {AuxiliaryLists, AuxiliaryListsDescriptor, BuiltDisplayList, BuiltDisplayListDescriptor};
 use {ColorF, DeviceIntPoint, DeviceIntSize, DeviceUintRect, DeviceUintSize, FontKey};
 use {GlyphDimensions, GlyphKey, ImageData, ImageDescriptor, ImageKey, LayoutPoint, LayoutSize};
-use {LayoutTransform, NativeFontHandle, ScrollLayerId, WorldPoint};
+use {LayoutTransform, NativeFontHandle, ScrollLayerId, WorldPoint, ZoomLimits};
 #[cfg(feature = "webgl")]
 use {WebGLCommand, WebGLContextId};
 
@@ -23,6 +23,9 @@ pub enum ApiMsg {
     AddRawFont(FontKey, Vec<u8>),
     AddNativeFont(FontKey, NativeFontHandle),
     DeleteFont(FontKey),
+    /// Changes the gamma and contrast used to preblend glyph coverage. Glyphs
+    /// rasterized with the previous values are discarded.
+    SetFontGamma(f32, f32),
     /// Gets the glyph dimensions
     GetGlyphDimensions(Vec<GlyphKey>, MsgSender<Vec<Option<GlyphDimensions>>>),
     /// Adds an image from the resource cache.
@@ -48,15 +51,26 @@ pub enum ApiMsg {
     SetPan(DeviceIntPoint),
     SetRootPipeline(PipelineId),
     SetWindowParameters(DeviceUintSize, DeviceUintRect),
-    Scroll(ScrollLocation, WorldPoint, ScrollEventPhase),
-    ScrollLayerWithId(LayoutPoint, ScrollLayerId),
+    Scroll(ScrollLocation, WorldPoint, ScrollEventPhase, Option<u64>),
+    ScrollLayerWithId(LayoutPoint, ScrollLayerId, ScrollBehavior),
+    HitTestScrollbar(WorldPoint, MsgSender<Option<ScrollbarHitTestResult>>),
+    DragScrollbarThumb(ScrollLayerId, ScrollbarAxis, f32),
+    ZoomScrollLayer(ScrollLayerId, f32, WorldPoint, ScrollEventPhase),
+    /// Makes a pipeline zoomable within the given limits, or not at all.
+    SetPipelineZoomLimits(PipelineId, Option<ZoomLimits>),
+    ZoomPipeline(PipelineId, f32, WorldPoint, ScrollEventPhase),
     TickScrollingBounce,
     TranslatePointToLayerSpace(WorldPoint, MsgSender<(LayoutPoint, PipelineId)>),
     GetScrollLayerState(MsgSender<Vec<ScrollLayerState>>),
     RequestWebGLContext(DeviceIntSize, GLContextAttributes, MsgSender<Result<(WebGLContextId, GLLimits), String>>),
     ResizeWebGLContext(WebGLContextId, DeviceIntSize),
     WebGLCommand(WebGLContextId, WebGLCommand),
-    GenerateFrame(Option<DynamicProperties>),
+    /// Generates a frame, optionally with new property bindings. The frame time, in
+    /// nanoseconds, drives scroll animations. When it isn't supplied, the render
+    /// backend samples its own clock.
+    GenerateFrame(Option<DynamicProperties>, Option<u64>),
+    /// Replaces the property bindings which follow scroll offsets.
+    SetScrollLinkedProperties(ScrollLinkedProperties),
     // WebVR commands that must be called in the WebGL render thread.
     VRCompositorCommand(WebGLContextId, VRCompositorCommand),
     /// An opaque handle that must be passed to the render notifier. It is used by Gecko
@@ -72,6 +86,7 @@ impl fmt::Debug for ApiMsg {
             &ApiMsg::AddRawFont(..) => { write!(f, "ApiMsg::AddRawFont") }
             &ApiMsg::AddNativeFont(..) => { write!(f, "ApiMsg::AddNativeFont") }
             &ApiMsg::DeleteFont(..) => { write!(f, "ApiMsg::DeleteFont") }
+            &ApiMsg::SetFontGamma(..) => { write!(f, "ApiMsg::SetFontGamma") }
             &ApiMsg::GetGlyphDimensions(..) => { write!(f, "ApiMsg::GetGlyphDimensions") }
             &ApiMsg::AddImage(..) => { write!(f, "ApiMsg::AddImage") }
             &ApiMsg::UpdateImage(..) => { write!(f, "ApiMsg::UpdateImage") }
@@ -81,6 +96,11 @@ impl fmt::Debug for ApiMsg {
             &ApiMsg::SetRootPipeline(..) => { write!(f, "ApiMsg::SetRootPipeline") }
             &ApiMsg::Scroll(..) => { write!(f, "ApiMsg::Scroll") }
             &ApiMsg::ScrollLayerWithId(..) => { write!(f, "ApiMsg::ScrollLayerWithId") }
+            &ApiMsg::HitTestScrollbar(..) => { write!(f, "ApiMsg::HitTestScrollbar") }
+            &ApiMsg::DragScrollbarThumb(..) => { write!(f, "ApiMsg::DragScrollbarThumb") }
+            &ApiMsg::ZoomScrollLayer(..) => { write!(f, "ApiMsg::ZoomScrollLayer") }
+            &ApiMsg::SetPipelineZoomLimits(..) => { write!(f, "ApiMsg::SetPipelineZoomLimits") }
+            &ApiMsg::ZoomPipeline(..) => { write!(f, "ApiMsg::ZoomPipeline") }
             &ApiMsg::TickScrollingBounce => { write!(f, "ApiMsg::TickScrollingBounce") }
             &ApiMsg::TranslatePointToLayerSpace(..) => { write!(f, "ApiMsg::TranslatePointToLayerSpace") }
             &ApiMsg::GetScrollLayerState(..) => { write!(f, "ApiMsg::GetScrollLayerState") }
@@ -88,6 +108,7 @@ impl fmt::Debug for ApiMsg {
             &ApiMsg::ResizeWebGLContext(..) => { write!(f, "ApiMsg::ResizeWebGLContext") }
             &ApiMsg::WebGLCommand(..) => { write!(f, "ApiMsg::WebGLCommand") }
             &ApiMsg::GenerateFrame(..) => { write!(f, "ApiMsg::GenerateFrame") }
+            &ApiMsg::SetScrollLinkedProperties(..) => { write!(f, "ApiMsg::SetScrollLinkedProperties") }
             &ApiMsg::VRCompositorCommand(..) => { write!(f, "ApiMsg::VRCompositorCommand") }
             &ApiMsg::ExternalEvent(..) => { write!(f, "ApiMsg::ExternalEvent") }
             &ApiMsg::ShutDown => { write!(f, "ApiMsg::ShutDown") }
@@ -214,6 +235,13 @@ impl RenderApi {
         self.api_sender.send(msg).unwrap();
     }
 
+    /// Changes the gamma and contrast used to preblend glyph coverage,
+    /// overriding `RendererOptions::font_gamma` and `font_contrast`.
+    pub fn set_font_gamma(&self, gamma: f32, contrast: f32) {
+        let msg = ApiMsg::SetFontGamma(gamma, contrast);
+        self.api_sender.send(msg).unwrap();
+    }
+
     /// Gets the dimensions for the supplied glyph keys
     ///
     /// Note: Internally, the internal texture cache doesn't store
@@ -326,12 +354,82 @@ impl RenderApi {
     /// Webrender looks for the layer closest to the user
     /// which has `ScrollPolicy::Scrollable` set.
     pub fn scroll(&self, scroll_location: ScrollLocation, cursor: WorldPoint, phase: ScrollEventPhase) {
-        let msg = ApiMsg::Scroll(scroll_location, cursor, phase);
+        let msg = ApiMsg::Scroll(scroll_location, cursor, phase, None);
+        self.api_sender.send(msg).unwrap();
+    }
+
+    /// Like `scroll`, for an input event that happened at `event_time`. The
+    /// velocity of a fling is measured from these timestamps, so they should
+    /// come from the same monotonic clock as the frame times, in nanoseconds.
+    pub fn scroll_at(&self,
+                     scroll_location: ScrollLocation,
+                     cursor: WorldPoint,
+                     phase: ScrollEventPhase,
+                     event_time: u64) {
+        let msg = ApiMsg::Scroll(scroll_location, cursor, phase, Some(event_time));
         self.api_sender.send(msg).unwrap();
     }
 
     pub fn scroll_layer_with_id(&self, new_scroll_origin: LayoutPoint, id: ScrollLayerId) {
-        let msg = ApiMsg::ScrollLayerWithId(new_scroll_origin, id);
+        let msg = ApiMsg::ScrollLayerWithId(new_scroll_origin, id, ScrollBehavior::Instant);
+        self.api_sender.send(msg).unwrap();
+    }
+
+    /// Like `scroll_layer_with_id`, but animates to the new scroll origin
+    /// over the next few generated frames.
+    pub fn smooth_scroll_layer_with_id(&self, new_scroll_origin: LayoutPoint, id: ScrollLayerId) {
+        let msg = ApiMsg::ScrollLayerWithId(new_scroll_origin, id, ScrollBehavior::Smooth);
+        self.api_sender.send(msg).unwrap();
+    }
+
+    /// Finds the scrollbar thumb under `point`, if any. Scrollbars are only
+    /// drawn when they are enabled in the `RendererOptions`.
+    pub fn hit_test_scrollbar(&self, point: WorldPoint) -> Option<ScrollbarHitTestResult> {
+        let (tx, rx) = channel::msg_channel().unwrap();
+        let msg = ApiMsg::HitTestScrollbar(point, tx);
+        self.api_sender.send(msg).unwrap();
+        rx.recv().unwrap()
+    }
+
+    /// Moves a scrollbar thumb by `delta` along its track, scrolling the
+    /// content of the layer in proportion.
+    pub fn drag_scrollbar_thumb(&self, id: ScrollLayerId, axis: ScrollbarAxis, delta: f32) {
+        let msg = ApiMsg::DragScrollbarThumb(id, axis, delta);
+        self.api_sender.send(msg).unwrap();
+    }
+
+    /// Zooms the content of a scroll layer to `zoom`, keeping the content under
+    /// `focal_point` in place. The zoom is clamped to the layer's `ZoomLimits`,
+    /// and layers without any can't be zoomed. Text and blob images are scaled
+    /// while the gesture is in progress and rasterized again at the new size
+    /// once it ends.
+    pub fn zoom_scroll_layer(&self,
+                             id: ScrollLayerId,
+                             zoom: f32,
+                             focal_point: WorldPoint,
+                             phase: ScrollEventPhase) {
+        let msg = ApiMsg::ZoomScrollLayer(id, zoom, focal_point, phase);
+        self.api_sender.send(msg).unwrap();
+    }
+
+    /// Allows the whole of a pipeline to be zoomed with `zoom_pipeline`, within
+    /// `zoom_limits`. Pipelines can't be zoomed until this is called, and
+    /// passing None makes them unzoomable again.
+    pub fn set_pipeline_zoom_limits(&self,
+                                    pipeline_id: PipelineId,
+                                    zoom_limits: Option<ZoomLimits>) {
+        let msg = ApiMsg::SetPipelineZoomLimits(pipeline_id, zoom_limits);
+        self.api_sender.send(msg).unwrap();
+    }
+
+    /// Zooms the whole of a pipeline, like `zoom_scroll_layer`, without
+    /// affecting the pipelines around it.
+    pub fn zoom_pipeline(&self,
+                         pipeline_id: PipelineId,
+                         zoom: f32,
+                         focal_point: WorldPoint,
+                         phase: ScrollEventPhase) {
+        let msg = ApiMsg::ZoomPipeline(pipeline_id, zoom, focal_point, phase);
         self.api_sender.send(msg).unwrap();
     }
 
@@ -400,7 +498,25 @@ impl RenderApi {
     /// property bindings that should be used to resolve bindings
     /// in the current display list.
     pub fn generate_frame(&self, property_bindings: Option<DynamicProperties>) {
-        let msg = ApiMsg::GenerateFrame(property_bindings);
+        let msg = ApiMsg::GenerateFrame(property_bindings, None);
+        self.api_sender.send(msg).unwrap();
+    }
+
+    /// Generate a new frame, advancing scroll animations to `frame_time`.
+    /// The frame time is in nanoseconds and should come from a monotonic
+    /// clock, such as the embedder's vsync timestamp.
+    pub fn generate_frame_at(&self,
+                             property_bindings: Option<DynamicProperties>,
+                             frame_time: u64) {
+        let msg = ApiMsg::GenerateFrame(property_bindings, Some(frame_time));
+        self.api_sender.send(msg).unwrap();
+    }
+
+    /// Supply the property bindings whose values follow scroll offsets. These
+    /// stay in effect, replacing any supplied before, and are evaluated on the
+    /// render backend so that they keep up with asynchronous scrolling.
+    pub fn set_scroll_linked_properties(&self, properties: ScrollLinkedProperties) {
+        let msg = ApiMsg::SetScrollLinkedProperties(properties);
         self.api_sender.send(msg).unwrap();
     }
 
@@ -451,12 +567,34 @@ pub enum ScrollEventPhase {
     End,
 }
 
-#[derive(Clone, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct ScrollLayerState {
     pub id: ScrollLayerId,
     pub scroll_offset: LayoutPoint,
 }
 
+/// What moved a scroll layer, as reported in a `ScrollOffsetChange`.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub enum ScrollChangePhase {
+    /// A scroll gesture sent with `RenderApi::scroll`, in the given phase.
+    Gesture(ScrollEventPhase),
+    /// Generating a frame: smooth scrolls, flings, snapping and overscroll
+    /// bounces, as well as scroll offsets restored with a new display list.
+    Animation,
+    /// `RenderApi::scroll_layer_with_id` or a scrollbar drag.
+    Direct,
+}
+
+/// A scroll layer which moved, as reported to
+/// `RenderNotifier::scroll_offsets_changed`.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ScrollOffsetChange {
+    pub state: ScrollLayerState,
+    /// The id given to the layer by the embedder, if it has one.
+    pub external_id: Option<u64>,
+    pub phase: ScrollChangePhase,
+}
+
 #[derive(Clone, Copy, Debug, Deserialize, Serialize)]
 pub enum ScrollLocation {
     /// Scroll by a certain amount.
@@ -464,7 +602,48 @@ pub enum ScrollLocation {
     /// Scroll to very top of element.
     Start,
     /// Scroll to very bottom of element.
-    End
+    End,
+    /// Scroll to the very left of element.
+    HorizontalStart,
+    /// Scroll to the very right of element.
+    HorizontalEnd,
+    /// Scroll up by most of the height of the element's viewport.
+    PageUp,
+    /// Scroll down by most of the height of the element's viewport.
+    PageDown,
+    /// Scroll left by most of the width of the element's viewport.
+    PageLeft,
+    /// Scroll right by most of the width of the element's viewport.
+    PageRight,
+    /// Scroll by a number of pages. Negative values scroll up.
+    Page(f32),
+    /// Scroll by a number of lines. Negative values scroll up.
+    Line(f32),
+    /// Scroll to a scroll origin, in the coordinates of the layer's content.
+    Position(LayoutPoint),
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub enum ScrollbarAxis {
+    Horizontal,
+    Vertical,
+}
+
+/// A scrollbar thumb found by a hit test, which can then be dragged with
+/// `RenderApi::drag_scrollbar_thumb`.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub struct ScrollbarHitTestResult {
+    pub scroll_layer_id: ScrollLayerId,
+    pub axis: ScrollbarAxis,
+}
+
+/// How a layer moves to a new scroll position.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub enum ScrollBehavior {
+    /// Jump to the new position immediately.
+    Instant,
+    /// Animate to the new position with an easing curve.
+    Smooth,
 }
 
 /// Represents a zoom factor.
@@ -564,6 +743,92 @@ pub struct DynamicProperties {
     pub floats: Vec<PropertyValue<f32>>,
 }
 
+/// Values which can be interpolated between, so that they can follow a scroll offset.
+pub trait Interpolate {
+    fn interpolate(&self, other: &Self, t: f32) -> Self;
+}
+
+impl Interpolate for f32 {
+    fn interpolate(&self, other: &f32, t: f32) -> f32 {
+        *self + (*other - *self) * t
+    }
+}
+
+/// Transforms are interpolated component-wise, which works well for
+/// translations and scales but not for rotations.
+impl Interpolate for LayoutTransform {
+    fn interpolate(&self, other: &LayoutTransform, t: f32) -> LayoutTransform {
+        let a = self.to_row_major_array();
+        let b = other.to_row_major_array();
+        let m = |i: usize| a[i].interpolate(&b[i], t);
+        LayoutTransform::row_major(m(0), m(1), m(2), m(3),
+                                   m(4), m(5), m(6), m(7),
+                                   m(8), m(9), m(10), m(11),
+                                   m(12), m(13), m(14), m(15))
+    }
+}
+
+/// The value of an animated property as a function of how far a scroll layer
+/// is scrolled along one axis. The function is given by stops of scroll position
+/// and value, in increasing order of position. Between stops the value is
+/// interpolated linearly, and beyond the first and last stops it is held, so
+/// two stops give a clamped linear mapping.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ScrollLinkedValue<T> {
+    pub key: PropertyBindingKey<T>,
+    pub scroll_layer_id: ScrollLayerId,
+    pub axis: ScrollbarAxis,
+    pub stops: Vec<(f32, T)>,
+}
+
+impl<T: Copy + Interpolate> ScrollLinkedValue<T> {
+    pub fn linear(key: PropertyBindingKey<T>,
+                  scroll_layer_id: ScrollLayerId,
+                  axis: ScrollbarAxis,
+                  start: (f32, T),
+                  end: (f32, T))
+                  -> ScrollLinkedValue<T> {
+        ScrollLinkedValue {
+            key: key,
+            scroll_layer_id: scroll_layer_id,
+            axis: axis,
+            stops: vec![start, end],
+        }
+    }
+
+    /// The value when the scroll layer is scrolled to `position` along the
+    /// axis, or None if there are no stops.
+    pub fn value_at(&self, position: f32) -> Option<T> {
+        let (first_position, first_value) = match self.stops.first() {
+            Some(&stop) => stop,
+            None => return None,
+        };
+        if position <= first_position {
+            return Some(first_value);
+        }
+
+        for stops in self.stops.windows(2) {
+            let (start, start_value) = stops[0];
+            let (end, end_value) = stops[1];
+            if position <= end {
+                let t = if end > start { (position - start) / (end - start) } else { 1.0 };
+                return Some(start_value.interpolate(&end_value, t));
+            }
+        }
+
+        self.stops.last().map(|&(_, value)| value)
+    }
+}
+
+/// The property bindings whose values follow scroll offsets, supplied with
+/// `RenderApi::set_scroll_linked_properties`. Their keys are used in the
+/// display list just like those of `DynamicProperties`.
+#[derive(Clone, Deserialize, Serialize, Debug)]
+pub struct ScrollLinkedProperties {
+    pub transforms: Vec<ScrollLinkedValue<LayoutTransform>>,
+    pub floats: Vec<ScrollLinkedValue<f32>>,
+}
+
 pub type VRCompositorId = u64;
 
 // WebVR commands that must be called in the WebGL render thread.
@@ -584,6 +849,9 @@ pub trait VRCompositorHandler: Send {
 pub trait RenderNotifier: Send {
     fn new_frame_ready(&mut self);
     fn new_scroll_frame_ready(&mut self, composite_needed: bool);
+    /// Called with the scroll layers that moved, whenever any have, so their
+    /// offsets don't have to be polled with `get_scroll_layer_state`.
+    fn scroll_offsets_changed(&mut self, _changes: Vec<ScrollOffsetChange>) {}
     fn external_event(&mut self, _evt: ExternalEvent) { unimplemented!() }
     fn shut_down(&mut self) {}
 }
@@ -592,3 +860,61 @@ pub trait RenderNotifier: Send {
 pub trait RenderDispatcher: Send {
     fn dispatch(&self, Box<Fn() + Send>);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{PropertyBindingKey, ScrollLinkedValue, ScrollbarAxis};
+    use {LayoutTransform, PipelineId, ScrollLayerId};
+
+    fn linked<T: Copy>(stops: Vec<(f32, T)>) -> ScrollLinkedValue<T> {
+        ScrollLinkedValue {
+            key: PropertyBindingKey::new(1),
+            scroll_layer_id: ScrollLayerId::root_scroll_layer(PipelineId(0, 0)),
+            axis: ScrollbarAxis::Vertical,
+            stops: stops,
+        }
+    }
+
+    #[test]
+    fn two_stops_map_linearly_and_clamp_at_the_ends() {
+        let value = ScrollLinkedValue::linear(PropertyBindingKey::new(1),
+                                              ScrollLayerId::root_scroll_layer(PipelineId(0, 0)),
+                                              ScrollbarAxis::Vertical,
+                                              (100.0, 0.0),
+                                              (200.0, 1.0));
+        assert_eq!(value.value_at(50.0), Some(0.0));
+        assert_eq!(value.value_at(100.0), Some(0.0));
+        assert_eq!(value.value_at(150.0), Some(0.5));
+        assert_eq!(value.value_at(200.0), Some(1.0));
+        assert_eq!(value.value_at(300.0), Some(1.0));
+    }
+
+    #[test]
+    fn values_are_interpolated_between_the_surrounding_stops() {
+        let value = linked(vec![(0.0, 0.0), (100.0, 1.0), (300.0, 0.0)]);
+        assert_eq!(value.value_at(50.0), Some(0.5));
+        assert_eq!(value.value_at(100.0), Some(1.0));
+        assert_eq!(value.value_at(200.0), Some(0.5));
+        assert_eq!(value.value_at(400.0), Some(0.0));
+    }
+
+    #[test]
+    fn stops_at_the_same_position_make_the_value_jump() {
+        let value = linked(vec![(0.0, 0.0), (100.0, 0.0), (100.0, 1.0)]);
+        assert_eq!(value.value_at(100.0), Some(0.0));
+        assert_eq!(value.value_at(101.0), Some(1.0));
+    }
+
+    #[test]
+    fn values_without_stops_are_undefined() {
+        let value: ScrollLinkedValue<f32> = linked(vec![]);
+        assert_eq!(value.value_at(0.0), None);
+    }
+
+    #[test]
+    fn transforms_are_interpolated_component_wise() {
+        let value = linked(vec![(0.0, LayoutTransform::create_translation(0.0, 0.0, 0.0)),
+                                (100.0, LayoutTransform::create_translation(0.0, 100.0, 0.0))]);
+        assert_eq!(value.value_at(25.0), Some(LayoutTransform::create_translation(0.0, 25.0, 0.0)));
+    }
+}
@@ -21,17 +21,22 @@ pub struct DisplayItem {
 pub enum SpecificDisplayItem {
     Clip(ClipDisplayItem),
     Rectangle(RectangleDisplayItem),
+    Line(LineDisplayItem),
     Text(TextDisplayItem),
     Image(ImageDisplayItem),
     YuvImage(YuvImageDisplayItem),
     WebGL(WebGLDisplayItem),
     Border(BorderDisplayItem),
     BoxShadow(BoxShadowDisplayItem),
+    BoxShadowList(BoxShadowListDisplayItem),
     Gradient(GradientDisplayItem),
     RadialGradient(RadialGradientDisplayItem),
+    ConicGradient(ConicGradientDisplayItem),
     Iframe(IframeDisplayItem),
     PushStackingContext(PushStackingContextDisplayItem),
     PopStackingContext,
+    PushTextShadow(PushTextShadowDisplayItem),
+    PopTextShadow,
 }
 
 #[repr(C)]
@@ -46,6 +51,96 @@ pub struct ClipDisplayItem {
     pub content_size: LayoutSize,
     pub id: ScrollLayerId,
     pub parent_id: ScrollLayerId,
+    pub scroll_snap: Option<ScrollSnapInfo>,
+    pub overscroll: OverscrollBehavior,
+    /// How far the content of this scroll frame can be zoomed with
+    /// `RenderApi::zoom_scroll_layer`, or None if it can't be zoomed.
+    pub zoom_limits: Option<ZoomLimits>,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub struct ZoomLimits {
+    pub min: f32,
+    pub max: f32,
+}
+
+impl ZoomLimits {
+    pub fn clamp(&self, zoom: f32) -> f32 {
+        zoom.max(self.min).min(self.max)
+    }
+}
+
+impl Default for ZoomLimits {
+    /// Zooming in up to ten times, and not out.
+    fn default() -> ZoomLimits {
+        ZoomLimits {
+            min: 1.0,
+            max: 10.0,
+        }
+    }
+}
+
+/// How a scroll frame reacts to scrolls that run past its edges.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub struct OverscrollBehavior {
+    /// Whether the content can be dragged past the edges, springing back once
+    /// released. None uses the platform default, which only bounces on macOS.
+    pub bounce: Option<bool>,
+    /// Whether the part of a scroll this frame can't take is handed on to the
+    /// nearest scroll frame around it that can scroll. None hands it on to the
+    /// topmost scroll frame of the page instead.
+    pub scroll_chaining: Option<bool>,
+    /// The spring that pulls overscrolled content back into place, or None
+    /// for the default spring.
+    pub spring: Option<SpringParameters>,
+}
+
+impl Default for OverscrollBehavior {
+    fn default() -> OverscrollBehavior {
+        OverscrollBehavior {
+            bounce: None,
+            scroll_chaining: None,
+            spring: None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub struct SpringParameters {
+    /// How hard the spring pulls back.
+    pub stiffness: f32,
+    /// Friction. 1.0 means no bounce.
+    pub damping: f32,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub struct ScrollSnapInfo {
+    pub snap_type: ScrollSnapType,
+    pub areas: ItemRange,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub enum ScrollSnapType {
+    /// The scroll frame always comes to rest on a snap point.
+    Mandatory,
+    /// The scroll frame only snaps when it comes to rest near a snap point.
+    Proximity,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub enum ScrollSnapAlign {
+    Start,
+    Center,
+    End,
+}
+
+/// An area of a scroll frame's content which the viewport can snap to. The rect
+/// is in the same coordinate system as the items inside the scroll frame.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub struct ScrollSnapArea {
+    pub rect: LayoutRect,
+    /// Which edges, or the center, of the area and the viewport line up.
+    pub align: ScrollSnapAlign,
 }
 
 #[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
@@ -53,6 +148,33 @@ pub struct RectangleDisplayItem {
     pub color: ColorF,
 }
 
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub struct LineDisplayItem {
+    pub baseline: f32,
+    pub start: f32,
+    pub end: f32,
+    pub orientation: LineOrientation, // toggles whether above values are interpreted as x/y values
+    pub width: f32,
+    pub color: ColorF,
+    pub style: LineStyle,
+}
+
+#[repr(u32)]
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub enum LineOrientation {
+    Vertical    = 0,
+    Horizontal  = 1,
+}
+
+#[repr(u32)]
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub enum LineStyle {
+    Solid   = 0,
+    Dotted  = 1,
+    Dashed  = 2,
+    Wavy    = 3,
+}
+
 #[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
 pub struct TextDisplayItem {
     pub glyphs: ItemRange,
@@ -63,6 +185,20 @@ pub struct TextDisplayItem {
     pub glyph_options: Option<GlyphOptions>,
 }
 
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub struct PushTextShadowDisplayItem {
+    pub shadow: TextShadow,
+}
+
+/// A shadow drawn beneath every text item between a `PushTextShadow`
+/// and the matching `PopTextShadow`.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub struct TextShadow {
+    pub offset: LayoutPoint,
+    pub color: ColorF,
+    pub blur_radius: f32,
+}
+
 #[derive(Clone, Copy, Debug, Deserialize, Hash, Eq, PartialEq, PartialOrd, Ord, Serialize)]
 pub struct GlyphOptions {
     // These are currently only used on windows for dwrite fonts.
@@ -122,12 +258,19 @@ pub struct RadialGradientBorder {
     pub outset: SideOffsets2D<f32>,
 }
 
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub struct ConicGradientBorder {
+    pub gradient: ConicGradient,
+    pub outset: SideOffsets2D<f32>,
+}
+
 #[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
 pub enum BorderDetails {
     Normal(NormalBorder),
     Image(ImageBorder),
     Gradient(GradientBorder),
     RadialGradient(RadialGradientBorder),
+    ConicGradient(ConicGradientBorder),
 }
 
 #[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
@@ -189,15 +332,48 @@ pub struct BoxShadowDisplayItem {
     pub color: ColorF,
     pub blur_radius: f32,
     pub spread_radius: f32,
-    pub border_radius: f32,
+    pub border_radius: BorderRadius,
     pub clip_mode: BoxShadowClipMode,
 }
 
+/// A single shadow in a `BoxShadowListDisplayItem`.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub struct BoxShadow {
+    pub offset: LayoutPoint,
+    pub color: ColorF,
+    pub blur_radius: f32,
+    pub spread_radius: f32,
+    pub clip_mode: BoxShadowClipMode,
+}
+
+/// All of the shadows cast by one box. As with CSS `box-shadow`, shadows
+/// earlier in the list are drawn on top of later ones.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub struct BoxShadowListDisplayItem {
+    pub box_bounds: LayoutRect,
+    pub border_radius: BorderRadius,
+    pub shadows: ItemRange,
+}
+
 #[repr(u32)]
 #[derive(Debug, Copy, Clone, Hash, Eq, PartialEq, Serialize, Deserialize, Ord, PartialOrd)]
 pub enum ExtendMode {
     Clamp,
     Repeat,
+    Reflect,
+}
+
+/// How the colors between two gradient stops are computed.
+#[repr(u32)]
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq, Serialize, Deserialize)]
+pub enum GradientInterpolation {
+    /// Mix the stop colors exactly as given.
+    Srgb,
+    /// Premultiply each stop color by its alpha before mixing, so that fading
+    /// towards a transparent stop doesn't bleed that stop's color in.
+    Premultiplied,
+    /// Mix the stop colors in linear light rather than in sRGB.
+    LinearLight,
 }
 
 #[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
@@ -206,6 +382,7 @@ pub struct Gradient {
     pub end_point: LayoutPoint,
     pub stops: ItemRange,
     pub extend_mode: ExtendMode,
+    pub interpolation: GradientInterpolation,
 }
 
 #[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
@@ -218,6 +395,9 @@ pub struct GradientDisplayItem {
 pub struct GradientStop {
     pub offset: f32,
     pub color: ColorF,
+    /// The offset, between this stop and the next one, at which the color
+    /// halfway between the two is reached. `None` puts it at the midpoint.
+    pub hint: Option<f32>,
 }
 known_heap_size!(0, GradientStop);
 
@@ -230,6 +410,7 @@ pub struct RadialGradient {
     pub ratio_xy: f32,
     pub stops: ItemRange,
     pub extend_mode: ExtendMode,
+    pub interpolation: GradientInterpolation,
 }
 
 #[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
@@ -237,6 +418,22 @@ pub struct RadialGradientDisplayItem {
     pub gradient: RadialGradient,
 }
 
+/// A gradient whose stops are laid out around `center` rather than along a
+/// line. Offset 0 sits at `angle` radians, measured clockwise from the top.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub struct ConicGradient {
+    pub center: LayoutPoint,
+    pub angle: f32,
+    pub stops: ItemRange,
+    pub extend_mode: ExtendMode,
+    pub interpolation: GradientInterpolation,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub struct ConicGradientDisplayItem {
+    pub gradient: ConicGradient,
+}
+
 #[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
 pub struct PushStackingContextDisplayItem {
     pub stacking_context: StackingContext,
@@ -247,9 +444,14 @@ pub struct StackingContext {
     pub scroll_policy: ScrollPolicy,
     pub z_index: i32,
     pub transform: Option<PropertyBinding<LayoutTransform>>,
+    pub transform_style: TransformStyle,
+    pub backface_visible: bool,
     pub perspective: Option<LayoutTransform>,
     pub mix_blend_mode: MixBlendMode,
     pub filters: ItemRange,
+    /// Filters applied to what is already drawn beneath the stacking
+    /// context's bounds, before its content is drawn over the result.
+    pub backdrop_filters: ItemRange,
 }
 
 #[repr(u32)]
@@ -261,6 +463,13 @@ pub enum ScrollPolicy {
 
 known_heap_size!(0, ScrollPolicy);
 
+#[repr(u32)]
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum TransformStyle {
+    Flat        = 0,
+    Preserve3D  = 1,
+}
+
 #[repr(u32)]
 #[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
 pub enum MixBlendMode {
@@ -293,6 +502,85 @@ pub enum FilterOp {
     Opacity(PropertyBinding<f32>),
     Saturate(f32),
     Sepia(f32),
+    /// A blurred, tinted copy of the stacking context's alpha, drawn
+    /// beneath it at the given offset.
+    DropShadow(LayoutPoint, f32, ColorF),
+    /// Multiplies the unpremultiplied `[r, g, b, a, 1]` of each pixel by this
+    /// row-major 4x5 matrix, like SVG's `feColorMatrix`.
+    ColorMatrix([f32; 20]),
+    /// Runs a graph of filter primitives, created with
+    /// `DisplayListBuilder::create_filter_graph`.
+    Graph(ItemRange),
+}
+
+/// The name of the result of a filter primitive, like the `result`
+/// attribute of an SVG filter primitive. Names are chosen by the client.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub struct FilterResultName(pub u32);
+
+/// Where a filter primitive reads one of its inputs from.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub enum FilterInput {
+    /// The contents of the stacking context.
+    SourceGraphic,
+    /// The result of the previous primitive, or the source graphic for the
+    /// first primitive of the graph.
+    Previous,
+    /// The result of the closest earlier primitive with the given name. A
+    /// graph that reads a name no earlier primitive has is invalid, and the
+    /// stacking context it applies to isn't drawn.
+    Result(FilterResultName),
+}
+
+/// A function applied to a single color channel by a component transfer.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub enum TransferFunction {
+    Identity,
+    /// `slope * C + intercept`
+    Linear(f32, f32),
+    /// `amplitude * pow(C, exponent) + offset`
+    Gamma(f32, f32, f32),
+}
+
+/// The Porter-Duff operators of SVG's `feComposite`, where `in1` is the
+/// source and `in2` the destination.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub enum CompositeOperator {
+    Over,
+    In,
+    Out,
+    Atop,
+    Xor,
+    /// `k1 * in1 * in2 + k2 * in1 + k3 * in2 + k4`
+    Arithmetic(f32, f32, f32, f32),
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+pub enum FilterPrimitiveKind {
+    /// Applies a color matrix to `in1`, as `FilterOp::ColorMatrix` does.
+    ColorMatrix([f32; 20]),
+    /// Remaps the red, green, blue and alpha channels of `in1`.
+    ComponentTransfer([TransferFunction; 4]),
+    /// Fills the stacking context bounds with a color. Takes no inputs.
+    Flood(ColorF),
+    /// Moves `in1` by the given offset.
+    Offset(LayoutPoint),
+    /// Combines `in1` with `in2`.
+    Composite(CompositeOperator),
+    /// Draws `in2` on top of `in1`. Merges can be chained to stack more
+    /// inputs, like the nodes of SVG's `feMerge`.
+    Merge,
+}
+
+/// A single node of a filter graph. Inputs that a primitive doesn't use
+/// are ignored.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+pub struct FilterPrimitive {
+    pub kind: FilterPrimitiveKind,
+    pub in1: FilterInput,
+    pub in2: FilterInput,
+    /// The name later primitives can read the result of this one by.
+    pub result: Option<FilterResultName>,
 }
 
 #[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
@@ -358,18 +646,24 @@ impl StackingContext {
     pub fn new(scroll_policy: ScrollPolicy,
                z_index: i32,
                transform: Option<PropertyBinding<LayoutTransform>>,
+               transform_style: TransformStyle,
+               backface_visible: bool,
                perspective: Option<LayoutTransform>,
                mix_blend_mode: MixBlendMode,
                filters: Vec<FilterOp>,
+               backdrop_filters: Vec<FilterOp>,
                auxiliary_lists_builder: &mut AuxiliaryListsBuilder)
                -> StackingContext {
         StackingContext {
             scroll_policy: scroll_policy,
             z_index: z_index,
             transform: transform,
+            transform_style: transform_style,
+            backface_visible: backface_visible,
             perspective: perspective,
             mix_blend_mode: mix_blend_mode,
             filters: auxiliary_lists_builder.add_filters(&filters),
+            backdrop_filters: auxiliary_lists_builder.add_filters(&backdrop_filters),
         }
     }
 }
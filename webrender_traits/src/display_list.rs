@@ -5,34 +5,44 @@
 use app_units::Au;
 use std::mem;
 use std::slice;
-use {BorderDetails, BorderDisplayItem, BorderWidths, BoxShadowClipMode, BoxShadowDisplayItem};
-use {ClipDisplayItem, ClipRegion, ColorF, ComplexClipRegion, DisplayItem, ExtendMode, FilterOp};
-use {FontKey, GlyphInstance, GlyphOptions, Gradient, GradientDisplayItem, GradientStop};
+use {BorderDetails, BorderDisplayItem, BorderRadius, BorderWidths, BoxShadow, BoxShadowClipMode};
+use {BoxShadowDisplayItem, BoxShadowListDisplayItem};
+use {ClipDisplayItem, ClipRegion, ColorF, ComplexClipRegion, ConicGradient};
+use {ConicGradientDisplayItem, DisplayItem, ExtendMode, FilterOp};
+use FilterPrimitive;
+use {FontKey, GlyphInstance, GlyphOptions, Gradient, GradientDisplayItem, GradientInterpolation};
+use GradientStop;
 use {IframeDisplayItem, ImageDisplayItem, ImageKey, ImageMask, ImageRendering, ItemRange};
-use {LayoutPoint, LayoutRect, LayoutSize, LayoutTransform, MixBlendMode, PipelineId};
-use {PropertyBinding, PushStackingContextDisplayItem, RadialGradient, RadialGradientDisplayItem};
-use {RectangleDisplayItem, ScrollLayerId, ScrollPolicy, SpecificDisplayItem, StackingContext};
-use {TextDisplayItem, WebGLContextId, WebGLDisplayItem, YuvColorSpace};
+use {LayoutPoint, LayoutRect, LayoutSize, LayoutTransform, LineDisplayItem, LineOrientation};
+use {LineStyle, MixBlendMode, OverscrollBehavior, PipelineId};
+use {PropertyBinding, PushStackingContextDisplayItem, PushTextShadowDisplayItem, RadialGradient};
+use {RadialGradientDisplayItem, RectangleDisplayItem, ScrollLayerId, ScrollPolicy};
+use {ScrollSnapArea, ScrollSnapInfo, ScrollSnapType};
+use {SpecificDisplayItem, StackingContext, TextDisplayItem, TextShadow, WebGLContextId};
+use {WebGLDisplayItem, YuvColorSpace, ZoomLimits};
 use YuvImageDisplayItem;
 
 #[derive(Clone, Deserialize, Serialize)]
 pub struct AuxiliaryLists {
-    /// The concatenation of: gradient stops, complex clip regions, filters, and glyph instances,
-    /// in that order.
+    /// The concatenation of: gradient stops, complex clip regions, filters, filter primitives, box
+    /// shadows, scroll snap areas, and glyph instances, in that order.
     data: Vec<u8>,
     descriptor: AuxiliaryListsDescriptor,
 }
 
 /// Describes the memory layout of the auxiliary lists.
 ///
-/// Auxiliary lists consist of some number of gradient stops, complex clip regions, filters, and
-/// glyph instances, in that order.
+/// Auxiliary lists consist of some number of gradient stops, complex clip regions, filters, filter
+/// primitives, box shadows, scroll snap areas, and glyph instances, in that order.
 #[repr(C)]
 #[derive(Clone, Copy, Debug, Deserialize, Serialize)]
 pub struct AuxiliaryListsDescriptor {
     gradient_stops_size: usize,
     complex_clip_regions_size: usize,
     filters_size: usize,
+    filter_primitives_size: usize,
+    box_shadows_size: usize,
+    scroll_snap_areas_size: usize,
     glyph_instances_size: usize,
 }
 
@@ -151,6 +161,39 @@ impl DisplayListBuilder {
         self.push_item(item, rect, clip);
     }
 
+    pub fn push_line(&mut self,
+                     clip: ClipRegion,
+                     baseline: f32,
+                     start: f32,
+                     end: f32,
+                     orientation: LineOrientation,
+                     width: f32,
+                     color: ColorF,
+                     style: LineStyle) {
+        let rect = match orientation {
+            LineOrientation::Horizontal => {
+                LayoutRect::new(LayoutPoint::new(start, baseline),
+                                LayoutSize::new(end - start, width))
+            }
+            LineOrientation::Vertical => {
+                LayoutRect::new(LayoutPoint::new(baseline, start),
+                                LayoutSize::new(width, end - start))
+            }
+        };
+
+        let item = SpecificDisplayItem::Line(LineDisplayItem {
+            baseline: baseline,
+            start: start,
+            end: end,
+            orientation: orientation,
+            width: width,
+            color: color,
+            style: style,
+        });
+
+        self.push_item(item, rect, clip);
+    }
+
     pub fn push_image(&mut self,
                       rect: LayoutRect,
                       clip: ClipRegion,
@@ -227,12 +270,14 @@ impl DisplayListBuilder {
                            start_point: LayoutPoint,
                            end_point: LayoutPoint,
                            stops: Vec<GradientStop>,
-                           extend_mode: ExtendMode) -> Gradient {
+                           extend_mode: ExtendMode,
+                           interpolation: GradientInterpolation) -> Gradient {
         Gradient {
             start_point: start_point,
             end_point: end_point,
             stops: self.auxiliary_lists_builder.add_gradient_stops(&stops),
             extend_mode: extend_mode,
+            interpolation: interpolation,
         }
     }
 
@@ -243,7 +288,8 @@ impl DisplayListBuilder {
                                   end_radius: f32,
                                   ratio_xy: f32,
                                   stops: Vec<GradientStop>,
-                                  extend_mode: ExtendMode) -> RadialGradient {
+                                  extend_mode: ExtendMode,
+                                  interpolation: GradientInterpolation) -> RadialGradient {
         RadialGradient {
             start_center: start_center,
             start_radius: start_radius,
@@ -252,6 +298,22 @@ impl DisplayListBuilder {
             ratio_xy: ratio_xy,
             stops: self.auxiliary_lists_builder.add_gradient_stops(&stops),
             extend_mode: extend_mode,
+            interpolation: interpolation,
+        }
+    }
+
+    pub fn create_conic_gradient(&mut self,
+                                 center: LayoutPoint,
+                                 angle: f32,
+                                 stops: Vec<GradientStop>,
+                                 extend_mode: ExtendMode,
+                                 interpolation: GradientInterpolation) -> ConicGradient {
+        ConicGradient {
+            center: center,
+            angle: angle,
+            stops: self.auxiliary_lists_builder.add_gradient_stops(&stops),
+            extend_mode: extend_mode,
+            interpolation: interpolation,
         }
     }
 
@@ -276,7 +338,7 @@ impl DisplayListBuilder {
                            color: ColorF,
                            blur_radius: f32,
                            spread_radius: f32,
-                           border_radius: f32,
+                           border_radius: BorderRadius,
                            clip_mode: BoxShadowClipMode) {
         let item = SpecificDisplayItem::BoxShadow(BoxShadowDisplayItem {
             box_bounds: box_bounds,
@@ -291,15 +353,31 @@ impl DisplayListBuilder {
         self.push_item(item, rect, clip);
     }
 
+    pub fn push_box_shadows(&mut self,
+                            rect: LayoutRect,
+                            clip: ClipRegion,
+                            box_bounds: LayoutRect,
+                            border_radius: BorderRadius,
+                            shadows: Vec<BoxShadow>) {
+        let item = SpecificDisplayItem::BoxShadowList(BoxShadowListDisplayItem {
+            box_bounds: box_bounds,
+            border_radius: border_radius,
+            shadows: self.auxiliary_lists_builder.add_box_shadows(&shadows),
+        });
+
+        self.push_item(item, rect, clip);
+    }
+
     pub fn push_gradient(&mut self,
                          rect: LayoutRect,
                          clip: ClipRegion,
                          start_point: LayoutPoint,
                          end_point: LayoutPoint,
                          stops: Vec<GradientStop>,
-                         extend_mode: ExtendMode) {
+                         extend_mode: ExtendMode,
+                         interpolation: GradientInterpolation) {
         let item = SpecificDisplayItem::Gradient(GradientDisplayItem {
-            gradient: self.create_gradient(start_point, end_point, stops, extend_mode),
+            gradient: self.create_gradient(start_point, end_point, stops, extend_mode, interpolation),
         });
 
         self.push_item(item, rect, clip);
@@ -314,11 +392,27 @@ impl DisplayListBuilder {
                                 end_radius: f32,
                                 ratio_xy: f32,
                                 stops: Vec<GradientStop>,
-                                extend_mode: ExtendMode) {
+                                extend_mode: ExtendMode,
+                                interpolation: GradientInterpolation) {
         let item = SpecificDisplayItem::RadialGradient(RadialGradientDisplayItem {
             gradient: self.create_radial_gradient(start_center, start_radius,
                                                   end_center, end_radius, ratio_xy,
-                                                  stops, extend_mode),
+                                                  stops, extend_mode, interpolation),
+        });
+
+        self.push_item(item, rect, clip);
+    }
+
+    pub fn push_conic_gradient(&mut self,
+                               rect: LayoutRect,
+                               clip: ClipRegion,
+                               center: LayoutPoint,
+                               angle: f32,
+                               stops: Vec<GradientStop>,
+                               extend_mode: ExtendMode,
+                               interpolation: GradientInterpolation) {
+        let item = SpecificDisplayItem::ConicGradient(ConicGradientDisplayItem {
+            gradient: self.create_conic_gradient(center, angle, stops, extend_mode, interpolation),
         });
 
         self.push_item(item, rect, clip);
@@ -329,17 +423,23 @@ impl DisplayListBuilder {
                                  bounds: LayoutRect,
                                  z_index: i32,
                                  transform: Option<PropertyBinding<LayoutTransform>>,
+                                 transform_style: TransformStyle,
+                                 backface_visible: bool,
                                  perspective: Option<LayoutTransform>,
                                  mix_blend_mode: MixBlendMode,
-                                 filters: Vec<FilterOp>) {
+                                 filters: Vec<FilterOp>,
+                                 backdrop_filters: Vec<FilterOp>) {
         let item = SpecificDisplayItem::PushStackingContext(PushStackingContextDisplayItem {
             stacking_context: StackingContext {
                 scroll_policy: scroll_policy,
                 z_index: z_index,
                 transform: transform,
+                transform_style: transform_style,
+                backface_visible: backface_visible,
                 perspective: perspective,
                 mix_blend_mode: mix_blend_mode,
                 filters: self.auxiliary_lists_builder.add_filters(&filters),
+                backdrop_filters: self.auxiliary_lists_builder.add_filters(&backdrop_filters),
             }
         });
 
@@ -350,11 +450,65 @@ impl DisplayListBuilder {
         self.push_new_empty_item(SpecificDisplayItem::PopStackingContext);
     }
 
+    /// Applies `shadow` to all text items pushed before the matching
+    /// `pop_text_shadow`. The shadows are drawn underneath all the items
+    /// pushed in between in the same stacking context, text or not, which
+    /// keep their order. Text shadows may be nested, in which case the text
+    /// gets every shadow in the stack, with the outermost on top.
+    pub fn push_text_shadow(&mut self, shadow: TextShadow) {
+        let item = SpecificDisplayItem::PushTextShadow(PushTextShadowDisplayItem {
+            shadow: shadow,
+        });
+        self.push_new_empty_item(item);
+    }
+
+    pub fn pop_text_shadow(&mut self) {
+        self.push_new_empty_item(SpecificDisplayItem::PopTextShadow);
+    }
+
     pub fn define_clip(&mut self,
                        clip: ClipRegion,
                        content_size: LayoutSize,
                        id: Option<ScrollLayerId>)
                        -> ScrollLayerId {
+        self.define_scroll_frame(clip, content_size, id, None, OverscrollBehavior::default(), None)
+    }
+
+    /// Defines a scroll frame which settles onto one of `snap_areas` when a
+    /// scroll gesture or fling ends.
+    pub fn define_clip_with_scroll_snap(&mut self,
+                                        clip: ClipRegion,
+                                        content_size: LayoutSize,
+                                        id: Option<ScrollLayerId>,
+                                        snap_type: ScrollSnapType,
+                                        snap_areas: &[ScrollSnapArea])
+                                        -> ScrollLayerId {
+        self.define_scroll_frame(clip,
+                                 content_size,
+                                 id,
+                                 Some((snap_type, snap_areas)),
+                                 OverscrollBehavior::default(),
+                                 None)
+    }
+
+    /// Defines a clip with all of the scrolling behaviour a scroll frame can
+    /// have: snapping, if `scroll_snap` is given, what happens when a scroll
+    /// runs past its edges, and zooming, if `zoom_limits` is given.
+    pub fn define_scroll_frame(&mut self,
+                               clip: ClipRegion,
+                               content_size: LayoutSize,
+                               id: Option<ScrollLayerId>,
+                               scroll_snap: Option<(ScrollSnapType, &[ScrollSnapArea])>,
+                               overscroll: OverscrollBehavior,
+                               zoom_limits: Option<ZoomLimits>)
+                               -> ScrollLayerId {
+        let scroll_snap = scroll_snap.map(|(snap_type, snap_areas)| {
+            ScrollSnapInfo {
+                snap_type: snap_type,
+                areas: self.auxiliary_lists_builder.add_scroll_snap_areas(snap_areas),
+            }
+        });
+
         let id = match id {
             Some(id) => id,
             None => {
@@ -367,6 +521,9 @@ impl DisplayListBuilder {
             content_size: content_size,
             id: id,
             parent_id: *self.clip_stack.last().unwrap(),
+            scroll_snap: scroll_snap,
+            overscroll: overscroll,
+            zoom_limits: zoom_limits,
         });
 
         self.push_item(item, clip.main, clip);
@@ -419,8 +576,15 @@ impl DisplayListBuilder {
                 RadialGradient(ref mut item) => {
                     item.gradient.stops = self.auxiliary_lists_builder.add_gradient_stops(aux.gradient_stops(&item.gradient.stops));
                 }
+                ConicGradient(ref mut item) => {
+                    item.gradient.stops = self.auxiliary_lists_builder.add_gradient_stops(aux.gradient_stops(&item.gradient.stops));
+                }
+                BoxShadowList(ref mut item) => {
+                    item.shadows = self.auxiliary_lists_builder.add_box_shadows(aux.box_shadows(&item.shadows));
+                }
                 PushStackingContext(ref mut item) => {
-                    item.stacking_context.filters = self.auxiliary_lists_builder.add_filters(aux.filters(&item.stacking_context.filters));
+                    item.stacking_context.filters = self.relocate_filters(&aux, &item.stacking_context.filters);
+                    item.stacking_context.backdrop_filters = self.relocate_filters(&aux, &item.stacking_context.backdrop_filters);
                 }
                 Iframe(_) | Clip(_) => {
                     // We don't support relocating these
@@ -434,6 +598,25 @@ impl DisplayListBuilder {
         }
     }
 
+    fn relocate_filters(&mut self, aux: &AuxiliaryLists, filters: &ItemRange) -> ItemRange {
+        let filters: Vec<FilterOp> = aux.filters(filters).iter().map(|filter| {
+            match *filter {
+                FilterOp::Graph(ref primitives) => {
+                    FilterOp::Graph(self.auxiliary_lists_builder.add_filter_primitives(aux.filter_primitives(primitives)))
+                }
+                filter => filter,
+            }
+        }).collect();
+        self.auxiliary_lists_builder.add_filters(&filters)
+    }
+
+    /// Creates a filter op that runs `primitives` over the contents of the
+    /// stacking context it's applied to. The result of the last primitive
+    /// is the output of the filter.
+    pub fn create_filter_graph(&mut self, primitives: Vec<FilterPrimitive>) -> FilterOp {
+        FilterOp::Graph(self.auxiliary_lists_builder.add_filter_primitives(&primitives))
+    }
+
     pub fn new_clip_region(&mut self,
                            rect: &LayoutRect,
                            complex: Vec<ComplexClipRegion>,
@@ -490,6 +673,9 @@ pub struct AuxiliaryListsBuilder {
     gradient_stops: Vec<GradientStop>,
     complex_clip_regions: Vec<ComplexClipRegion>,
     filters: Vec<FilterOp>,
+    filter_primitives: Vec<FilterPrimitive>,
+    box_shadows: Vec<BoxShadow>,
+    scroll_snap_areas: Vec<ScrollSnapArea>,
     glyph_instances: Vec<GlyphInstance>,
 }
 
@@ -499,6 +685,9 @@ impl AuxiliaryListsBuilder {
             gradient_stops: Vec::new(),
             complex_clip_regions: Vec::new(),
             filters: Vec::new(),
+            filter_primitives: Vec::new(),
+            box_shadows: Vec::new(),
+            scroll_snap_areas: Vec::new(),
             glyph_instances: Vec::new(),
         }
     }
@@ -529,6 +718,30 @@ impl AuxiliaryListsBuilder {
         filters_range.get(&self.filters[..])
     }
 
+    pub fn add_filter_primitives(&mut self, filter_primitives: &[FilterPrimitive]) -> ItemRange {
+        ItemRange::new(&mut self.filter_primitives, filter_primitives)
+    }
+
+    pub fn filter_primitives(&self, filter_primitives_range: &ItemRange) -> &[FilterPrimitive] {
+        filter_primitives_range.get(&self.filter_primitives[..])
+    }
+
+    pub fn add_box_shadows(&mut self, box_shadows: &[BoxShadow]) -> ItemRange {
+        ItemRange::new(&mut self.box_shadows, box_shadows)
+    }
+
+    pub fn box_shadows(&self, box_shadows_range: &ItemRange) -> &[BoxShadow] {
+        box_shadows_range.get(&self.box_shadows[..])
+    }
+
+    pub fn add_scroll_snap_areas(&mut self, scroll_snap_areas: &[ScrollSnapArea]) -> ItemRange {
+        ItemRange::new(&mut self.scroll_snap_areas, scroll_snap_areas)
+    }
+
+    pub fn scroll_snap_areas(&self, scroll_snap_areas_range: &ItemRange) -> &[ScrollSnapArea] {
+        scroll_snap_areas_range.get(&self.scroll_snap_areas[..])
+    }
+
     pub fn add_glyph_instances(&mut self, glyph_instances: &[GlyphInstance]) -> ItemRange {
         ItemRange::new(&mut self.glyph_instances, glyph_instances)
     }
@@ -545,9 +758,21 @@ impl AuxiliaryListsBuilder {
             let complex_clip_regions_size = blob.len() - gradient_stops_size;
             blob.extend_from_slice(convert_pod_to_blob(&self.filters));
             let filters_size = blob.len() - (complex_clip_regions_size + gradient_stops_size);
+            blob.extend_from_slice(convert_pod_to_blob(&self.filter_primitives));
+            let filter_primitives_size = blob.len() -
+                (complex_clip_regions_size + gradient_stops_size + filters_size);
+            blob.extend_from_slice(convert_pod_to_blob(&self.box_shadows));
+            let box_shadows_size = blob.len() -
+                (complex_clip_regions_size + gradient_stops_size + filters_size +
+                 filter_primitives_size);
+            blob.extend_from_slice(convert_pod_to_blob(&self.scroll_snap_areas));
+            let scroll_snap_areas_size = blob.len() -
+                (complex_clip_regions_size + gradient_stops_size + filters_size +
+                 filter_primitives_size + box_shadows_size);
             blob.extend_from_slice(convert_pod_to_blob(&self.glyph_instances));
             let glyph_instances_size = blob.len() -
-                (complex_clip_regions_size + gradient_stops_size + filters_size);
+                (complex_clip_regions_size + gradient_stops_size + filters_size +
+                 filter_primitives_size + box_shadows_size + scroll_snap_areas_size);
 
             AuxiliaryLists {
                 data: blob,
@@ -555,6 +780,9 @@ impl AuxiliaryListsBuilder {
                     gradient_stops_size: gradient_stops_size,
                     complex_clip_regions_size: complex_clip_regions_size,
                     filters_size: filters_size,
+                    filter_primitives_size: filter_primitives_size,
+                    box_shadows_size: box_shadows_size,
+                    scroll_snap_areas_size: scroll_snap_areas_size,
                     glyph_instances_size: glyph_instances_size,
                 },
             }
@@ -565,6 +793,7 @@ impl AuxiliaryListsBuilder {
 impl AuxiliaryListsDescriptor {
     pub fn size(&self) -> usize {
         self.gradient_stops_size + self.complex_clip_regions_size + self.filters_size +
+            self.filter_primitives_size + self.box_shadows_size + self.scroll_snap_areas_size +
             self.glyph_instances_size
     }
 }
@@ -618,10 +847,44 @@ impl AuxiliaryLists {
         }
     }
 
+    /// Returns the filter primitives described by `filter_primitives_range`.
+    pub fn filter_primitives(&self, filter_primitives_range: &ItemRange) -> &[FilterPrimitive] {
+        let start = self.descriptor.gradient_stops_size +
+            self.descriptor.complex_clip_regions_size + self.descriptor.filters_size;
+        let end = start + self.descriptor.filter_primitives_size;
+        unsafe {
+            filter_primitives_range.get(convert_blob_to_pod(&self.data[start..end]))
+        }
+    }
+
+    /// Returns the box shadows described by `box_shadows_range`.
+    pub fn box_shadows(&self, box_shadows_range: &ItemRange) -> &[BoxShadow] {
+        let start = self.descriptor.gradient_stops_size +
+            self.descriptor.complex_clip_regions_size + self.descriptor.filters_size +
+            self.descriptor.filter_primitives_size;
+        let end = start + self.descriptor.box_shadows_size;
+        unsafe {
+            box_shadows_range.get(convert_blob_to_pod(&self.data[start..end]))
+        }
+    }
+
+    /// Returns the scroll snap areas described by `scroll_snap_areas_range`.
+    pub fn scroll_snap_areas(&self, scroll_snap_areas_range: &ItemRange) -> &[ScrollSnapArea] {
+        let start = self.descriptor.gradient_stops_size +
+            self.descriptor.complex_clip_regions_size + self.descriptor.filters_size +
+            self.descriptor.filter_primitives_size + self.descriptor.box_shadows_size;
+        let end = start + self.descriptor.scroll_snap_areas_size;
+        unsafe {
+            scroll_snap_areas_range.get(convert_blob_to_pod(&self.data[start..end]))
+        }
+    }
+
     /// Returns the glyph instances described by `glyph_instances_range`.
     pub fn glyph_instances(&self, glyph_instances_range: &ItemRange) -> &[GlyphInstance] {
         let start = self.descriptor.gradient_stops_size +
-            self.descriptor.complex_clip_regions_size + self.descriptor.filters_size;
+            self.descriptor.complex_clip_regions_size + self.descriptor.filters_size +
+            self.descriptor.filter_primitives_size + self.descriptor.box_shadows_size +
+            self.descriptor.scroll_snap_areas_size;
         unsafe {
             glyph_instances_range.get(convert_blob_to_pod(&self.data[start..]))
         }
@@ -285,6 +285,8 @@ fn main() {
     // handle some global arguments
     let res_path = args.value_of("shaders").map(|s| PathBuf::from(s));
     let dp_ratio = args.value_of("dp_ratio").map(|v| v.parse::<f32>().unwrap());
+    let font_gamma = args.value_of("font_gamma").map(|v| v.parse::<f32>().unwrap());
+    let font_contrast = args.value_of("font_contrast").map(|v| v.parse::<f32>().unwrap());
     let limit_seconds = args.value_of("time").map(|s| time::Duration::seconds(s.parse::<i64>().unwrap()));
     let save_type = args.value_of("save").map(|s| {
         if s == "yaml" {
@@ -325,6 +327,8 @@ fn main() {
                                  size,
                                  args.is_present("rebuild"),
                                  args.is_present("subpixel-aa"),
+                                 font_gamma,
+                                 font_contrast,
                                  args.is_present("debug"),
                                  args.is_present("verbose"));
 
@@ -4,9 +4,9 @@
 
 use std::str::CharIndices;
 
-// support arguments like '4', 'ab', '4.0'
+// support arguments like '4', 'ab', '4.0', '-4'
 fn acceptable_arg_character(c: char) -> bool {
-    c.is_alphanumeric() || c == '.'
+    c.is_alphanumeric() || c == '.' || c == '-'
 }
 
 // A crapy parser for parsing strings like "translate(1, 3)"
@@ -92,4 +92,5 @@ fn test() {
     assert!(parse_function("  rotate(40)").0 == "rotate");
     assert!(parse_function("  rotate  (40)").0 == "rotate");
     assert!(parse_function("  rotate  (  40 )").1[0] == "40");
+    assert!(parse_function("drop-shadow(-4, 4, 2, red)").1[0] == "-4");
 }
@@ -134,6 +134,11 @@ pub struct Wrench {
     pub rebuild_display_lists: bool,
     pub verbose: bool,
 
+    /// The gamma and contrast glyphs are currently rasterized with, and the
+    /// ones wrench was started with.
+    font_gamma: (f32, f32),
+    default_font_gamma: (f32, f32),
+
     pub frame_start_sender: chase_lev::Worker<time::SteadyTime>,
 }
 
@@ -145,6 +150,8 @@ impl Wrench {
                size: DeviceUintSize,
                do_rebuild: bool,
                subpixel_aa: bool,
+               font_gamma: Option<f32>,
+               font_contrast: Option<f32>,
                debug: bool,
                verbose: bool)
            -> Wrench
@@ -165,7 +172,7 @@ impl Wrench {
             }
         });
 
-        let opts = webrender::RendererOptions {
+        let mut opts = webrender::RendererOptions {
             device_pixel_ratio: dp_ratio,
             resource_override_path: shader_override_path,
             recorder: recorder,
@@ -174,6 +181,14 @@ impl Wrench {
             max_recorded_profiles: 16,
             .. Default::default()
         };
+        if let Some(font_gamma) = font_gamma {
+            opts.font_gamma = font_gamma;
+        }
+        if let Some(font_contrast) = font_contrast {
+            opts.font_contrast = font_contrast;
+        }
+
+        let font_gamma = (opts.font_gamma, opts.font_contrast);
 
         let (renderer, sender) = webrender::renderer::Renderer::new(window.clone_gl(), opts, size).unwrap();
         let api = sender.create_api();
@@ -203,6 +218,9 @@ impl Wrench {
             verbose: verbose,
             device_pixel_ratio: dp_ratio,
 
+            font_gamma: font_gamma,
+            default_font_gamma: font_gamma,
+
             image_map: HashMap::new(),
 
             root_pipeline_id: PipelineId(0, 0),
@@ -357,6 +375,17 @@ impl Wrench {
         }
     }
 
+    /// Rasterizes glyphs with the given gamma and contrast from now on, or
+    /// with the ones wrench was started with where they aren't given.
+    pub fn set_font_gamma(&mut self, gamma: Option<f32>, contrast: Option<f32>) {
+        let font_gamma = (gamma.unwrap_or(self.default_font_gamma.0),
+                          contrast.unwrap_or(self.default_font_gamma.1));
+        if font_gamma != self.font_gamma {
+            self.api.set_font_gamma(font_gamma.0, font_gamma.1);
+            self.font_gamma = font_gamma;
+        }
+    }
+
     pub fn begin_frame(&mut self) {
         self.frame_start_sender.push(time::SteadyTime::now());
     }
@@ -364,7 +393,9 @@ impl Wrench {
     pub fn send_lists(&mut self,
                       frame_number: u32,
                       display_list: DisplayListBuilder,
-                      scroll_offsets: &HashMap<ScrollLayerId, LayerPoint>) {
+                      scroll_offsets: &HashMap<ScrollLayerId, LayerPoint>,
+                      zooms: &HashMap<ScrollLayerId, (f32, WorldPoint)>,
+                      pipeline_zooms: &HashMap<PipelineId, f32>) {
         let root_background_color = Some(ColorF::new(1.0, 1.0, 1.0, 1.0));
         self.api.set_root_display_list(root_background_color,
                                        Epoch(frame_number),
@@ -372,6 +403,15 @@ impl Wrench {
                                        display_list.finalize(),
                                        false);
 
+        // Zooms go first, since they move the content the offsets scroll over.
+        for (pipeline_id, zoom) in pipeline_zooms {
+            self.api.zoom_pipeline(*pipeline_id, *zoom, WorldPoint::zero(), ScrollEventPhase::End);
+        }
+
+        for (id, &(zoom, focal_point)) in zooms {
+            self.api.zoom_scroll_layer(*id, zoom, focal_point, ScrollEventPhase::End);
+        }
+
         for (id, offset) in scroll_offsets {
             self.api.scroll_layer_with_id(*offset, *id);
         }
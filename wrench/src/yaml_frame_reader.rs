@@ -4,6 +4,7 @@
 
 use app_units::Au;
 use clap;
+use parse_function::parse_function;
 use euclid::{Point2D, TypedPoint2D, SideOffsets2D};
 use std::collections::HashMap;
 use std::fs::File;
@@ -33,6 +34,204 @@ fn broadcast<T: Clone>(base_vals: &[T], num_items: usize) -> Vec<T> {
     vals
 }
 
+// The extend mode of a gradient item, from `extend-mode`, or the older
+// `repeat` flag.
+fn gradient_extend_mode(item: &Yaml) -> ExtendMode {
+    item["extend-mode"].as_extend_mode().unwrap_or_else(|| {
+        if item["repeat"].as_bool().unwrap_or(false) {
+            ExtendMode::Repeat
+        } else {
+            ExtendMode::Clamp
+        }
+    })
+}
+
+fn gradient_interpolation(item: &Yaml) -> GradientInterpolation {
+    item["interpolation"].as_gradient_interpolation().unwrap_or(GradientInterpolation::Srgb)
+}
+
+// A single shadow, either from a box-shadow item itself or from an
+// entry in its `shadows` list.
+fn box_shadow(item: &Yaml) -> BoxShadow {
+    let clip_mode = if let Some(mode) = item["clip-mode"].as_str() {
+        match mode {
+            "none" => BoxShadowClipMode::None,
+            "outset" => BoxShadowClipMode::Outset,
+            "inset" => BoxShadowClipMode::Inset,
+            s => panic!("Unknown box shadow clip mode {}", s),
+        }
+    } else {
+        BoxShadowClipMode::None
+    };
+
+    BoxShadow {
+        offset: item["offset"].as_point().unwrap_or(TypedPoint2D::zero()),
+        color: item["color"].as_colorf().unwrap_or(ColorF::new(0.0, 0.0, 0.0, 1.0)),
+        blur_radius: item["blur-radius"].as_force_f32().unwrap_or(0.0),
+        spread_radius: item["spread-radius"].as_force_f32().unwrap_or(0.0),
+        clip_mode: clip_mode,
+    }
+}
+
+// What a scroll frame does when scrolled past its edges, from its `overscroll` table.
+fn overscroll_behavior(item: &Yaml) -> OverscrollBehavior {
+    if item.is_badvalue() {
+        return OverscrollBehavior::default();
+    }
+
+    let spring = &item["spring"];
+    OverscrollBehavior {
+        bounce: item["bounce"].as_bool(),
+        scroll_chaining: item["scroll-chaining"].as_bool(),
+        spring: if spring.is_badvalue() {
+            None
+        } else {
+            Some(SpringParameters {
+                stiffness: spring["stiffness"].as_force_f32().expect("spring must have a stiffness"),
+                damping: spring["damping"].as_force_f32().expect("spring must have a damping"),
+            })
+        },
+    }
+}
+
+// How far a scroll frame can be zoomed, from its `zoom-limits: [min, max]` entry.
+fn zoom_limits(item: &Yaml) -> Option<ZoomLimits> {
+    if item.is_badvalue() {
+        return None;
+    }
+
+    let limits = item.as_vec_f32().expect("zoom limits must be [min, max]");
+    assert!(limits.len() == 2, "zoom limits must be [min, max]");
+    Some(ZoomLimits {
+        min: limits[0],
+        max: limits[1],
+    })
+}
+
+// The snap type and areas of a scroll frame, from its `scroll-snap` table.
+fn scroll_snap(item: &Yaml) -> Option<(ScrollSnapType, Vec<ScrollSnapArea>)> {
+    if item.is_badvalue() {
+        return None;
+    }
+
+    let snap_type = match item["type"].as_str().unwrap_or("mandatory") {
+        "mandatory" => ScrollSnapType::Mandatory,
+        "proximity" => ScrollSnapType::Proximity,
+        s => panic!("Unknown scroll snap type {}", s),
+    };
+
+    let areas = item["areas"].as_vec().expect("scroll snap must have areas").iter().map(|area| {
+        let align = match area["align"].as_str().unwrap_or("start") {
+            "start" => ScrollSnapAlign::Start,
+            "center" => ScrollSnapAlign::Center,
+            "end" => ScrollSnapAlign::End,
+            s => panic!("Unknown scroll snap align {}", s),
+        };
+
+        ScrollSnapArea {
+            rect: area["rect"].as_rect().expect("scroll snap areas must have a rect"),
+            align: align,
+        }
+    }).collect();
+
+    Some((snap_type, areas))
+}
+
+// The name of a filter result. Names are numbered in the order they first
+// appear in the graph.
+fn filter_result_name(names: &mut HashMap<String, FilterResultName>, name: &str) -> FilterResultName {
+    let next = FilterResultName(names.len() as u32);
+    *names.entry(name.to_owned()).or_insert(next)
+}
+
+// An input of a filter primitive, from its `in` or `in2` entry. Anything
+// other than `source-graphic` or `previous` names the result of another
+// primitive.
+fn filter_input(item: &Yaml, names: &mut HashMap<String, FilterResultName>) -> FilterInput {
+    match item.as_str() {
+        None | Some("previous") => FilterInput::Previous,
+        Some("source-graphic") => FilterInput::SourceGraphic,
+        Some(name) => FilterInput::Result(filter_result_name(names, name)),
+    }
+}
+
+// A component transfer function, like `linear(2, 0)`.
+fn transfer_function(item: &Yaml) -> TransferFunction {
+    let function = match item.as_str() {
+        Some(function) => function,
+        None => return TransferFunction::Identity,
+    };
+
+    match parse_function(function) {
+        ("identity", _) => TransferFunction::Identity,
+        ("linear", ref args) if args.len() == 2 => {
+            TransferFunction::Linear(args[0].parse().unwrap(), args[1].parse().unwrap())
+        }
+        ("gamma", ref args) if args.len() == 3 => {
+            TransferFunction::Gamma(args[0].parse().unwrap(),
+                                    args[1].parse().unwrap(),
+                                    args[2].parse().unwrap())
+        }
+        _ => panic!("Unknown transfer function {}", function),
+    }
+}
+
+// The primitives of a `graph` filter.
+fn filter_primitives(item: &Yaml) -> Vec<FilterPrimitive> {
+    let mut names = HashMap::new();
+
+    item.as_vec().expect("a filter graph must be a list of primitives").iter().map(|primitive| {
+        let kind = match primitive["type"].as_str().expect("filter primitives must have a type") {
+            "color-matrix" => {
+                let values = primitive["matrix"].as_vec_f32().expect("color-matrix needs a matrix");
+                assert!(values.len() == 20, "color-matrix needs 20 values");
+                let mut matrix = [0.0; 20];
+                matrix.copy_from_slice(&values);
+                FilterPrimitiveKind::ColorMatrix(matrix)
+            }
+            "component-transfer" => {
+                FilterPrimitiveKind::ComponentTransfer([transfer_function(&primitive["red"]),
+                                                        transfer_function(&primitive["green"]),
+                                                        transfer_function(&primitive["blue"]),
+                                                        transfer_function(&primitive["alpha"])])
+            }
+            "flood" => {
+                FilterPrimitiveKind::Flood(primitive["color"].as_colorf().unwrap_or(*BLACK_COLOR))
+            }
+            "offset" => {
+                FilterPrimitiveKind::Offset(primitive["offset"].as_point().unwrap_or(LayoutPoint::zero()))
+            }
+            "composite" => {
+                let operator = primitive["operator"].as_str().unwrap_or("over");
+                let operator = match parse_function(operator) {
+                    ("over", _) => CompositeOperator::Over,
+                    ("in", _) => CompositeOperator::In,
+                    ("out", _) => CompositeOperator::Out,
+                    ("atop", _) => CompositeOperator::Atop,
+                    ("xor", _) => CompositeOperator::Xor,
+                    ("arithmetic", ref args) if args.len() == 4 => {
+                        CompositeOperator::Arithmetic(args[0].parse().unwrap(),
+                                                      args[1].parse().unwrap(),
+                                                      args[2].parse().unwrap(),
+                                                      args[3].parse().unwrap())
+                    }
+                    _ => panic!("Unknown composite operator {}", operator),
+                };
+                FilterPrimitiveKind::Composite(operator)
+            }
+            "merge" => FilterPrimitiveKind::Merge,
+            s => panic!("Unknown filter primitive {}", s),
+        };
+
+        FilterPrimitive {
+            kind: kind,
+            in1: filter_input(&primitive["in"], &mut names),
+            in2: filter_input(&primitive["in2"], &mut names),
+            result: primitive["result"].as_str().map(|name| filter_result_name(&mut names, name)),
+        }
+    }).collect()
+}
+
 pub struct YamlFrameReader {
     frame_built: bool,
     yaml_path: PathBuf,
@@ -49,6 +248,12 @@ pub struct YamlFrameReader {
     /// A HashMap of offsets which specify what scroll offsets particular
     /// scroll layers should be initialized with.
     scroll_offsets: HashMap<ScrollLayerId, LayerPoint>,
+
+    /// The zooms of the scroll layers, and the points they are zoomed about.
+    zooms: HashMap<ScrollLayerId, (f32, WorldPoint)>,
+
+    /// The zooms of the pipelines which can be zoomed as a whole.
+    pipeline_zooms: HashMap<PipelineId, f32>,
 }
 
 impl YamlFrameReader {
@@ -63,6 +268,8 @@ impl YamlFrameReader {
             queue_depth: 1,
             include_only: vec![],
             scroll_offsets: HashMap::new(),
+            zooms: HashMap::new(),
+            pipeline_zooms: HashMap::new(),
         }
     }
 
@@ -82,6 +289,8 @@ impl YamlFrameReader {
 
     pub fn reset(&mut self) {
         self.scroll_offsets.clear();
+        self.zooms.clear();
+        self.pipeline_zooms.clear();
     }
 
     pub fn build(&mut self, wrench: &mut Wrench) {
@@ -93,6 +302,9 @@ impl YamlFrameReader {
         assert_eq!(yaml_doc.len(), 1);
 
         let yaml = yaml_doc.pop().unwrap();
+        wrench.set_font_gamma(yaml["font-gamma"].as_force_f32(),
+                              yaml["font-contrast"].as_force_f32());
+
         if !yaml["pipelines"].is_badvalue() {
             let pipelines = yaml["pipelines"].as_vec().unwrap();
             for pipeline in pipelines {
@@ -103,7 +315,9 @@ impl YamlFrameReader {
                 self.add_stacking_context_from_yaml(wrench, pipeline, true);
                 wrench.send_lists(self.frame_count,
                                   self.builder.as_ref().unwrap().clone(),
-                                  &self.scroll_offsets);
+                                  &self.scroll_offsets,
+                                  &self.zooms,
+                                  &self.pipeline_zooms);
             }
 
         }
@@ -197,24 +411,51 @@ impl YamlFrameReader {
         self.builder().push_rect(rect, clip, color);
     }
 
+    fn handle_line(&mut self, wrench: &mut Wrench, clip_region: &ClipRegion, item: &Yaml) {
+        let color = item["color"].as_colorf().unwrap_or(*BLACK_COLOR);
+        let baseline = item["baseline"].as_force_f32().expect("line must have baseline");
+        let start = item["start"].as_force_f32().expect("line must have start");
+        let end = item["end"].as_force_f32().expect("line must have end");
+        let width = item["width"].as_force_f32().expect("line must have width");
+        let orientation = match item["orientation"].as_str() {
+            Some("horizontal") | None => LineOrientation::Horizontal,
+            Some("vertical") => LineOrientation::Vertical,
+            Some(s) => panic!("Unknown line orientation {}", s),
+        };
+        let style = match item["style"].as_str() {
+            Some("solid") | None => LineStyle::Solid,
+            Some("dotted") => LineStyle::Dotted,
+            Some("dashed") => LineStyle::Dashed,
+            Some("wavy") => LineStyle::Wavy,
+            Some(s) => panic!("Unknown line style {}", s),
+        };
+
+        let bounds = match orientation {
+            LineOrientation::Horizontal => {
+                LayoutRect::new(LayoutPoint::new(start, baseline),
+                                LayoutSize::new(end - start, width))
+            }
+            LineOrientation::Vertical => {
+                LayoutRect::new(LayoutPoint::new(baseline, start),
+                                LayoutSize::new(width, end - start))
+            }
+        };
+
+        let clip = self.to_clip_region(&item["clip"], &bounds, wrench).unwrap_or(*clip_region);
+        self.builder().push_line(clip, baseline, start, end, orientation, width, color, style);
+    }
+
     fn handle_gradient(&mut self, wrench: &mut Wrench, clip_region: &ClipRegion, item: &Yaml) {
         let bounds_key = if item["type"].is_badvalue() { "gradient" } else { "bounds" };
         let bounds = item[bounds_key].as_rect().expect("gradient must have bounds");
         let start = item["start"].as_point().expect("gradient must have start");
         let end = item["end"].as_point().expect("gradient must have end");
-        let stops = item["stops"].as_vec().expect("gradient must have stops")
-            .chunks(2).map(|chunk| GradientStop {
-                offset: chunk[0].as_force_f32().expect("gradient stop offset is not f32"),
-                color: chunk[1].as_colorf().expect("gradient stop color is not color"),
-            }).collect::<Vec<_>>();
-        let extend_mode = if item["repeat"].as_bool().unwrap_or(false) {
-            ExtendMode::Repeat
-        } else {
-            ExtendMode::Clamp
-        };
+        let stops = item["stops"].as_vec_gradient_stop().expect("gradient must have stops");
+        let extend_mode = gradient_extend_mode(item);
+        let interpolation = gradient_interpolation(item);
 
         let clip = self.to_clip_region(&item["clip"], &bounds, wrench).unwrap_or(*clip_region);
-        self.builder().push_gradient(bounds, clip, start, end, stops, extend_mode);
+        self.builder().push_gradient(bounds, clip, start, end, stops, extend_mode, interpolation);
     }
 
     fn handle_radial_gradient(&mut self, wrench: &mut Wrench, clip_region: &ClipRegion, item: &Yaml) {
@@ -225,20 +466,29 @@ impl YamlFrameReader {
         let end_center = item["end-center"].as_point().expect("radial gradient must have end center");
         let end_radius = item["end-radius"].as_force_f32().expect("radial gradient must have end radius");
         let ratio_xy = item["ratio-xy"].as_force_f32().unwrap_or(1.0);
-        let stops = item["stops"].as_vec().expect("radial gradient must have stops")
-            .chunks(2).map(|chunk| GradientStop {
-                offset: chunk[0].as_force_f32().expect("gradient stop offset is not f32"),
-                color: chunk[1].as_colorf().expect("gradient stop color is not color"),
-            }).collect::<Vec<_>>();
-        let extend_mode = if item["repeat"].as_bool().unwrap_or(false) {
-            ExtendMode::Repeat
-        } else {
-            ExtendMode::Clamp
-        };
+        let stops = item["stops"].as_vec_gradient_stop().expect("radial gradient must have stops");
+        let extend_mode = gradient_extend_mode(item);
+        let interpolation = gradient_interpolation(item);
 
         let clip = self.to_clip_region(&item["clip"], &bounds, wrench).unwrap_or(*clip_region);
         self.builder().push_radial_gradient(bounds, clip, start_center, start_radius,
-                                            end_center, end_radius, ratio_xy, stops, extend_mode);
+                                            end_center, end_radius, ratio_xy, stops, extend_mode,
+                                            interpolation);
+    }
+
+    fn handle_conic_gradient(&mut self, wrench: &mut Wrench, clip_region: &ClipRegion, item: &Yaml) {
+        let bounds_key = if item["type"].is_badvalue() { "conic-gradient" } else { "bounds" };
+        let bounds = item[bounds_key].as_rect().expect("conic gradient must have bounds");
+        let center = item["center"].as_point().expect("conic gradient must have center");
+        // The start angle is given in degrees, like rotate() transforms.
+        let angle = item["angle"].as_force_f32().unwrap_or(0.0).to_radians();
+        let stops = item["stops"].as_vec_gradient_stop().expect("conic gradient must have stops");
+        let extend_mode = gradient_extend_mode(item);
+        let interpolation = gradient_interpolation(item);
+
+        let clip = self.to_clip_region(&item["clip"], &bounds, wrench).unwrap_or(*clip_region);
+        self.builder().push_conic_gradient(bounds, clip, center, angle, stops, extend_mode,
+                                           interpolation);
     }
 
     fn handle_border(&mut self, wrench: &mut Wrench, clip_region: &ClipRegion, item: &Yaml) {
@@ -325,20 +575,14 @@ impl YamlFrameReader {
                 "gradient" => {
                     let start = item["start"].as_point().expect("gradient must have start");
                     let end = item["end"].as_point().expect("gradient must have end");
-                    let stops = item["stops"].as_vec().expect("gradient must have stops")
-                        .chunks(2).map(|chunk| GradientStop {
-                            offset: chunk[0].as_force_f32().expect("gradient stop offset is not f32"),
-                            color: chunk[1].as_colorf().expect("gradient stop color is not color"),
-                        }).collect::<Vec<_>>();
-                    let extend_mode = if item["repeat"].as_bool().unwrap_or(false) {
-                        ExtendMode::Repeat
-                    } else {
-                        ExtendMode::Clamp
-                    };
+                    let stops = item["stops"].as_vec_gradient_stop().expect("gradient must have stops");
+                    let extend_mode = gradient_extend_mode(item);
+                    let interpolation = gradient_interpolation(item);
                     let outset = item["outset"].as_vec_f32().expect("borders must have outset");
                     let outset = broadcast(&outset, 4);
                     Some(BorderDetails::Gradient(GradientBorder {
-                        gradient: self.builder().create_gradient(start, end, stops, extend_mode),
+                        gradient: self.builder().create_gradient(start, end, stops, extend_mode,
+                                                                 interpolation),
                         outset: SideOffsets2D::new(outset[0], outset[1], outset[2], outset[3]),
                     }))
                 },
@@ -349,22 +593,29 @@ impl YamlFrameReader {
                     let end_center = item["end-center"].as_point().expect("radial gradient must have end center");
                     let end_radius = item["end-radius"].as_force_f32().expect("radial gradient must have end radius");
                     let ratio_xy = item["ratio-xy"].as_force_f32().unwrap_or(1.0);
-                    let stops = item["stops"].as_vec().expect("radial gradient must have stops")
-                        .chunks(2).map(|chunk| GradientStop {
-                            offset: chunk[0].as_force_f32().expect("gradient stop offset is not f32"),
-                            color: chunk[1].as_colorf().expect("gradient stop color is not color"),
-                        }).collect::<Vec<_>>();
-                    let extend_mode = if item["repeat"].as_bool().unwrap_or(false) {
-                        ExtendMode::Repeat
-                    } else {
-                        ExtendMode::Clamp
-                    };
+                    let stops = item["stops"].as_vec_gradient_stop().expect("radial gradient must have stops");
+                    let extend_mode = gradient_extend_mode(item);
+                    let interpolation = gradient_interpolation(item);
                     let outset = item["outset"].as_vec_f32().expect("borders must have outset");
                     let outset = broadcast(&outset, 4);
                     Some(BorderDetails::RadialGradient(RadialGradientBorder {
                         gradient: self.builder().create_radial_gradient(start_center, start_radius,
                                                                         end_center, end_radius, ratio_xy,
-                                                                        stops, extend_mode),
+                                                                        stops, extend_mode, interpolation),
+                        outset: SideOffsets2D::new(outset[0], outset[1], outset[2], outset[3]),
+                    }))
+                },
+                "conic-gradient" => {
+                    let center = item["center"].as_point().expect("conic gradient must have center");
+                    let angle = item["angle"].as_force_f32().unwrap_or(0.0).to_radians();
+                    let stops = item["stops"].as_vec_gradient_stop().expect("conic gradient must have stops");
+                    let extend_mode = gradient_extend_mode(item);
+                    let interpolation = gradient_interpolation(item);
+                    let outset = item["outset"].as_vec_f32().expect("borders must have outset");
+                    let outset = broadcast(&outset, 4);
+                    Some(BorderDetails::ConicGradient(ConicGradientBorder {
+                        gradient: self.builder().create_conic_gradient(center, angle, stops,
+                                                                       extend_mode, interpolation),
                         outset: SideOffsets2D::new(outset[0], outset[1], outset[2], outset[3]),
                     }))
                 },
@@ -390,25 +641,18 @@ impl YamlFrameReader {
         let bounds_key = if item["type"].is_badvalue() { "box-shadow" } else { "bounds" };
         let bounds = item[bounds_key].as_rect().expect("box shadow must have bounds");
         let box_bounds = item["box-bounds"].as_rect().unwrap_or(bounds);
-        let offset = item["offset"].as_point().unwrap_or(TypedPoint2D::zero());
-        let color = item["color"].as_colorf().unwrap_or(ColorF::new(0.0, 0.0, 0.0, 1.0));
-        let blur_radius = item["blur-radius"].as_force_f32().unwrap_or(0.0);
-        let spread_radius = item["spread-radius"].as_force_f32().unwrap_or(0.0);
-        let border_radius = item["border-radius"].as_force_f32().unwrap_or(0.0);
-        let clip_mode = if let Some(mode) = item["clip-mode"].as_str() {
-            match mode {
-                "none" => BoxShadowClipMode::None,
-                "outset" => BoxShadowClipMode::Outset,
-                "inset" => BoxShadowClipMode::Inset,
-                s => panic!("Unknown box shadow clip mode {}", s),
-            }
-        } else {
-            BoxShadowClipMode::None
-        };
+        let border_radius = item["border-radius"].as_border_radius().unwrap_or(BorderRadius::zero());
 
         let clip = self.to_clip_region(&item["clip"], &bounds, wrench).unwrap_or(*clip_region);
-        self.builder().push_box_shadow(bounds, clip, box_bounds, offset, color, blur_radius, spread_radius,
-                                       border_radius, clip_mode);
+        if let Some(shadows) = item["shadows"].as_vec() {
+            let shadows = shadows.iter().map(box_shadow).collect();
+            self.builder().push_box_shadows(bounds, clip, box_bounds, border_radius, shadows);
+        } else {
+            let shadow = box_shadow(item);
+            self.builder().push_box_shadow(bounds, clip, box_bounds, shadow.offset, shadow.color,
+                                           shadow.blur_radius, shadow.spread_radius,
+                                           border_radius, shadow.clip_mode);
+        }
     }
 
     fn rsrc_path(&self, item: &Yaml) -> PathBuf {
@@ -552,6 +796,8 @@ impl YamlFrameReader {
                     "gradient"
                 } else if !item["radial-gradient"].is_badvalue() {
                     "radial-gradient"
+                } else if !item["conic-gradient"].is_badvalue() {
+                    "conic-gradient"
                 } else {
                     item["type"].as_str().unwrap_or("unknown")
                 };
@@ -574,6 +820,7 @@ impl YamlFrameReader {
 
             match item_type {
                 "rect" => self.handle_rect(wrench, &full_clip_region, &item),
+                "line" => self.handle_line(wrench, &full_clip_region, &item),
                 "image" => self.handle_image(wrench, &full_clip_region, &item),
                 "text" | "glyphs" => self.handle_text(wrench, &full_clip_region, &item),
                 "scroll-layer" => self.add_scroll_layer_from_yaml(wrench, &item),
@@ -581,8 +828,10 @@ impl YamlFrameReader {
                 "border" => self.handle_border(wrench, &full_clip_region, &item),
                 "gradient" => self.handle_gradient(wrench, &full_clip_region, &item),
                 "radial-gradient" => self.handle_radial_gradient(wrench, &full_clip_region, &item),
+                "conic-gradient" => self.handle_conic_gradient(wrench, &full_clip_region, &item),
                 "box-shadow" => self.handle_box_shadow(wrench, &full_clip_region, &item),
                 "iframe" => self.handle_iframe(wrench, &full_clip_region, &item),
+                "text-shadow" => self.handle_text_shadow(wrench, &item),
                 "stacking-context" => { },
                 _ => println!("Skipping unknown item type: {:?}", item),
             }
@@ -593,6 +842,44 @@ impl YamlFrameReader {
         }
     }
 
+    pub fn handle_text_shadow(&mut self, wrench: &mut Wrench, yaml: &Yaml) {
+        let offset = yaml["offset"].as_point().unwrap_or(TypedPoint2D::zero());
+        let color = yaml["color"].as_colorf().unwrap_or(*BLACK_COLOR);
+        let blur_radius = yaml["blur-radius"].as_force_f32().unwrap_or(0.0);
+
+        self.builder().push_text_shadow(TextShadow {
+            offset: offset,
+            color: color,
+            blur_radius: blur_radius,
+        });
+        if !yaml["items"].is_badvalue() {
+            self.add_display_list_items_from_yaml(wrench, &yaml["items"]);
+        }
+        self.builder().pop_text_shadow();
+    }
+
+    // The filters of a stacking context. Besides filter functions, the list
+    // can hold `graph` tables of filter primitives.
+    fn filters_from_yaml(&mut self, yaml: &Yaml) -> Vec<FilterOp> {
+        if yaml.is_badvalue() {
+            return vec![];
+        }
+
+        let filters = match yaml.as_vec() {
+            Some(filters) => filters.clone(),
+            None => vec![yaml.clone()],
+        };
+
+        filters.iter().map(|filter| {
+            if !filter["graph"].is_badvalue() {
+                let primitives = filter_primitives(&filter["graph"]);
+                self.builder().create_filter_graph(primitives)
+            } else {
+                filter.as_filter_op().expect("Unknown filter")
+            }
+        }).collect()
+    }
+
     pub fn add_scroll_layer_from_yaml(&mut self, wrench: &mut Wrench, yaml: &Yaml) {
         let id = self.handle_clip_from_yaml(wrench, yaml);
 
@@ -612,12 +899,26 @@ impl YamlFrameReader {
         let id = yaml["id"].as_i64().map(|id|
             ScrollLayerId::new(id as u64, self.builder().pipeline_id));
 
-        let id = self.builder().define_clip(clip, content_size, id);
+        let snap = scroll_snap(&yaml["scroll-snap"]);
+        let snap = snap.as_ref().map(|&(snap_type, ref areas)| (snap_type, &areas[..]));
+        let overscroll = overscroll_behavior(&yaml["overscroll"]);
+        let zoom_limits = zoom_limits(&yaml["zoom-limits"]);
+        let id = self.builder().define_scroll_frame(clip,
+                                                    content_size,
+                                                    id,
+                                                    snap,
+                                                    overscroll,
+                                                    zoom_limits);
 
         if let Some(size) = yaml["scroll-offset"].as_point() {
             self.scroll_offsets.insert(id, LayerPoint::new(size.x, size.y));
         }
 
+        // Scroll layers are zoomed about the top left of their bounds.
+        if let Some(zoom) = yaml["zoom"].as_force_f32() {
+            self.zooms.insert(id, (zoom, WorldPoint::new(bounds.origin.x, bounds.origin.y)));
+        }
+
         id
     }
 
@@ -647,23 +948,39 @@ impl YamlFrameReader {
                                                    .unwrap_or(MixBlendMode::Normal);
         let scroll_policy = yaml["scroll-policy"].as_scroll_policy()
                                                  .unwrap_or(ScrollPolicy::Scrollable);
+        let transform_style = yaml["transform-style"].as_transform_style()
+                                                     .unwrap_or(TransformStyle::Flat);
+        let backface_visible = yaml["backface-visible"].as_bool().unwrap_or(true);
 
         if is_root {
             if let Some(size) = yaml["scroll-offset"].as_point() {
                 let id = ScrollLayerId::root_scroll_layer(self.builder().pipeline_id);
                 self.scroll_offsets.insert(id, LayerPoint::new(size.x, size.y));
             }
+
+            // The whole pipeline can be zoomed about its top left, if it has limits.
+            if let Some(zoom_limits) = zoom_limits(&yaml["zoom-limits"]) {
+                let pipeline_id = self.builder().pipeline_id;
+                wrench.api.set_pipeline_zoom_limits(pipeline_id, Some(zoom_limits));
+                if let Some(zoom) = yaml["zoom"].as_force_f32() {
+                    self.pipeline_zooms.insert(pipeline_id, zoom);
+                }
+            }
         }
 
-        let filters = yaml["filters"].as_vec_filter_op().unwrap_or(vec![]);
+        let filters = self.filters_from_yaml(&yaml["filters"]);
+        let backdrop_filters = yaml["backdrop-filters"].as_vec_filter_op().unwrap_or(vec![]);
 
         self.builder().push_stacking_context(scroll_policy,
                                              bounds,
                                              z_index as i32,
                                              transform.into(),
+                                             transform_style,
+                                             backface_visible,
                                              perspective,
                                              mix_blend_mode,
-                                             filters);
+                                             filters,
+                                             backdrop_filters);
 
         if !yaml["items"].is_badvalue() {
             self.add_display_list_items_from_yaml(wrench, &yaml["items"]);
@@ -686,7 +1003,9 @@ impl WrenchThing for YamlFrameReader {
             wrench.begin_frame();
             wrench.send_lists(self.frame_count,
                               self.builder.as_ref().unwrap().clone(),
-                              &self.scroll_offsets);
+                              &self.scroll_offsets,
+                              &self.zooms,
+                              &self.pipeline_zooms);
         } else {
             wrench.refresh();
         }
@@ -19,7 +19,8 @@ use time;
 use webrender;
 use webrender_traits::*;
 use webrender_traits::SpecificDisplayItem::*;
-use yaml_helper::{mix_blend_mode_to_string, scroll_policy_to_string};
+use yaml_helper::{extend_mode_to_string, gradient_interpolation_to_string, mix_blend_mode_to_string};
+use yaml_helper::{scroll_policy_to_string, transform_style_to_string};
 use yaml_rust::{Yaml, YamlEmitter};
 
 type Table = yaml_rust::yaml::Hash;
@@ -155,6 +156,33 @@ fn scroll_policy_node(parent: &mut Table, key: &str, value: ScrollPolicy) {
     yaml_node(parent, key, Yaml::String(scroll_policy_to_string(value).to_owned()));
 }
 
+fn transform_style_node(parent: &mut Table, key: &str, value: TransformStyle) {
+    yaml_node(parent, key, Yaml::String(transform_style_to_string(value).to_owned()));
+}
+
+fn box_shadow_clip_mode_to_string(clip_mode: BoxShadowClipMode) -> &'static str {
+    match clip_mode {
+        BoxShadowClipMode::None => "none",
+        BoxShadowClipMode::Outset => "outset",
+        BoxShadowClipMode::Inset => "inset",
+    }
+}
+
+fn scroll_snap_type_to_string(snap_type: ScrollSnapType) -> &'static str {
+    match snap_type {
+        ScrollSnapType::Mandatory => "mandatory",
+        ScrollSnapType::Proximity => "proximity",
+    }
+}
+
+fn scroll_snap_align_to_string(align: ScrollSnapAlign) -> &'static str {
+    match align {
+        ScrollSnapAlign::Start => "start",
+        ScrollSnapAlign::Center => "center",
+        ScrollSnapAlign::End => "end",
+    }
+}
+
 fn maybe_radius_yaml(radius: &BorderRadius) -> Option<Yaml> {
     if let Some(radius) = radius.is_uniform_size() {
         if radius == LayoutSize::zero() {
@@ -182,6 +210,14 @@ fn write_sc(parent: &mut Table, sc: &StackingContext) {
         None => {}
     };
 
+    if sc.transform_style != TransformStyle::Flat {
+        transform_style_node(parent, "transform-style", sc.transform_style);
+    }
+
+    if !sc.backface_visible {
+        bool_node(parent, "backface-visible", false);
+    }
+
     if let Some(perspective) = sc.perspective {
         matrix4d_node(parent, "perspective", &perspective);
     }
@@ -503,6 +539,26 @@ impl YamlFrameWriter {
                     str_node(&mut v, "type", "rect");
                     color_node(&mut v, "color", item.color);
                 },
+                Line(item) => {
+                    str_node(&mut v, "type", "line");
+                    f32_node(&mut v, "baseline", item.baseline);
+                    f32_node(&mut v, "start", item.start);
+                    f32_node(&mut v, "end", item.end);
+                    f32_node(&mut v, "width", item.width);
+                    color_node(&mut v, "color", item.color);
+                    let orientation = match item.orientation {
+                        LineOrientation::Horizontal => "horizontal",
+                        LineOrientation::Vertical => "vertical",
+                    };
+                    str_node(&mut v, "orientation", orientation);
+                    let style = match item.style {
+                        LineStyle::Solid => "solid",
+                        LineStyle::Dotted => "dotted",
+                        LineStyle::Dashed => "dashed",
+                        LineStyle::Wavy => "wavy",
+                    };
+                    str_node(&mut v, "style", style);
+                },
                 Text(item) => {
                     let gi = aux.glyph_instances(&item.glyphs);
                     let mut indices: Vec<u32> = vec![];
@@ -652,9 +708,13 @@ impl YamlFrameWriter {
                             for stop in aux.gradient_stops(&details.gradient.stops) {
                                 stops.push(Yaml::Real(stop.offset.to_string()));
                                 stops.push(Yaml::String(color_to_string(stop.color)));
+                                if let Some(hint) = stop.hint {
+                                    stops.push(Yaml::Real(hint.to_string()));
+                                }
                             }
                             yaml_node(&mut v, "stops", Yaml::Array(stops));
-                            bool_node(&mut v, "repeat", details.gradient.extend_mode == ExtendMode::Repeat);
+                            str_node(&mut v, "extend-mode", extend_mode_to_string(details.gradient.extend_mode));
+                            str_node(&mut v, "interpolation", gradient_interpolation_to_string(details.gradient.interpolation));
                             yaml_node(&mut v, "outset", f32_vec_yaml(&outset, true));
                         }
                         BorderDetails::RadialGradient(ref details) => {
@@ -677,9 +737,39 @@ impl YamlFrameWriter {
                             for stop in aux.gradient_stops(&details.gradient.stops) {
                                 stops.push(Yaml::Real(stop.offset.to_string()));
                                 stops.push(Yaml::String(color_to_string(stop.color)));
+                                if let Some(hint) = stop.hint {
+                                    stops.push(Yaml::Real(hint.to_string()));
+                                }
+                            }
+                            yaml_node(&mut v, "stops", Yaml::Array(stops));
+                            str_node(&mut v, "extend-mode", extend_mode_to_string(details.gradient.extend_mode));
+                            str_node(&mut v, "interpolation", gradient_interpolation_to_string(details.gradient.interpolation));
+                            yaml_node(&mut v, "outset", f32_vec_yaml(&outset, true));
+                        }
+                        BorderDetails::ConicGradient(ref details) => {
+                            let widths: Vec<f32> = vec![ item.widths.top,
+                                                         item.widths.right,
+                                                         item.widths.bottom,
+                                                         item.widths.left ];
+                            let outset: Vec<f32> = vec![ details.outset.top,
+                                                         details.outset.right,
+                                                         details.outset.bottom,
+                                                         details.outset.left];
+                            yaml_node(&mut v, "width", f32_vec_yaml(&widths, true));
+                            str_node(&mut v, "border-type", "conic-gradient");
+                            point_node(&mut v, "center", &details.gradient.center);
+                            f32_node(&mut v, "angle", details.gradient.angle.to_degrees());
+                            let mut stops = vec![];
+                            for stop in aux.gradient_stops(&details.gradient.stops) {
+                                stops.push(Yaml::Real(stop.offset.to_string()));
+                                stops.push(Yaml::String(color_to_string(stop.color)));
+                                if let Some(hint) = stop.hint {
+                                    stops.push(Yaml::Real(hint.to_string()));
+                                }
                             }
                             yaml_node(&mut v, "stops", Yaml::Array(stops));
-                            bool_node(&mut v, "repeat", details.gradient.extend_mode == ExtendMode::Repeat);
+                            str_node(&mut v, "extend-mode", extend_mode_to_string(details.gradient.extend_mode));
+                            str_node(&mut v, "interpolation", gradient_interpolation_to_string(details.gradient.interpolation));
                             yaml_node(&mut v, "outset", f32_vec_yaml(&outset, true));
                         }
                     }
@@ -691,13 +781,28 @@ impl YamlFrameWriter {
                     color_node(&mut v, "color", item.color);
                     f32_node(&mut v, "blur-radius", item.blur_radius);
                     f32_node(&mut v, "spread-radius", item.spread_radius);
-                    f32_node(&mut v, "border-radius", item.border_radius);
-                    let clip_mode = match item.clip_mode {
-                        BoxShadowClipMode::None => "none",
-                        BoxShadowClipMode::Outset => "outset",
-                        BoxShadowClipMode::Inset => "inset"
-                    };
-                    str_node(&mut v, "clip-mode", clip_mode);
+                    if let Some(radius_node) = maybe_radius_yaml(&item.border_radius) {
+                        yaml_node(&mut v, "border-radius", radius_node);
+                    }
+                    str_node(&mut v, "clip-mode", box_shadow_clip_mode_to_string(item.clip_mode));
+                },
+                BoxShadowList(item) => {
+                    str_node(&mut v, "type", "box-shadow");
+                    rect_node(&mut v, "box-bounds", &item.box_bounds);
+                    if let Some(radius_node) = maybe_radius_yaml(&item.border_radius) {
+                        yaml_node(&mut v, "border-radius", radius_node);
+                    }
+                    let mut shadows = vec![];
+                    for shadow in aux.box_shadows(&item.shadows) {
+                        let mut s = new_table();
+                        point_node(&mut s, "offset", &shadow.offset);
+                        color_node(&mut s, "color", shadow.color);
+                        f32_node(&mut s, "blur-radius", shadow.blur_radius);
+                        f32_node(&mut s, "spread-radius", shadow.spread_radius);
+                        str_node(&mut s, "clip-mode", box_shadow_clip_mode_to_string(shadow.clip_mode));
+                        shadows.push(Yaml::Hash(s));
+                    }
+                    vec_node(&mut v, "shadows", shadows);
                 },
                 Gradient(item) => {
                     str_node(&mut v, "type", "gradient");
@@ -707,9 +812,13 @@ impl YamlFrameWriter {
                     for stop in aux.gradient_stops(&item.gradient.stops) {
                         stops.push(Yaml::Real(stop.offset.to_string()));
                         stops.push(Yaml::String(color_to_string(stop.color)));
+                        if let Some(hint) = stop.hint {
+                            stops.push(Yaml::Real(hint.to_string()));
+                        }
                     }
                     yaml_node(&mut v, "stops", Yaml::Array(stops));
-                    bool_node(&mut v, "repeat", item.gradient.extend_mode == ExtendMode::Repeat);
+                    str_node(&mut v, "extend-mode", extend_mode_to_string(item.gradient.extend_mode));
+                    str_node(&mut v, "interpolation", gradient_interpolation_to_string(item.gradient.interpolation));
                 },
                 RadialGradient(item) => {
                     str_node(&mut v, "type", "radial-gradient");
@@ -722,9 +831,29 @@ impl YamlFrameWriter {
                     for stop in aux.gradient_stops(&item.gradient.stops) {
                         stops.push(Yaml::Real(stop.offset.to_string()));
                         stops.push(Yaml::String(color_to_string(stop.color)));
+                        if let Some(hint) = stop.hint {
+                            stops.push(Yaml::Real(hint.to_string()));
+                        }
                     }
                     yaml_node(&mut v, "stops", Yaml::Array(stops));
-                    bool_node(&mut v, "repeat", item.gradient.extend_mode == ExtendMode::Repeat);
+                    str_node(&mut v, "extend-mode", extend_mode_to_string(item.gradient.extend_mode));
+                    str_node(&mut v, "interpolation", gradient_interpolation_to_string(item.gradient.interpolation));
+                },
+                ConicGradient(item) => {
+                    str_node(&mut v, "type", "conic-gradient");
+                    point_node(&mut v, "center", &item.gradient.center);
+                    f32_node(&mut v, "angle", item.gradient.angle.to_degrees());
+                    let mut stops = vec![];
+                    for stop in aux.gradient_stops(&item.gradient.stops) {
+                        stops.push(Yaml::Real(stop.offset.to_string()));
+                        stops.push(Yaml::String(color_to_string(stop.color)));
+                        if let Some(hint) = stop.hint {
+                            stops.push(Yaml::Real(hint.to_string()));
+                        }
+                    }
+                    yaml_node(&mut v, "stops", Yaml::Array(stops));
+                    str_node(&mut v, "extend-mode", extend_mode_to_string(item.gradient.extend_mode));
+                    str_node(&mut v, "interpolation", gradient_interpolation_to_string(item.gradient.interpolation));
                 },
                 Iframe(item) => {
                     str_node(&mut v, "type", "iframe");
@@ -739,8 +868,47 @@ impl YamlFrameWriter {
                     str_node(&mut v, "type", "clip");
                     size_node(&mut v, "content-size", &item.content_size);
                     usize_node(&mut v, "id", clip_id_mapper.add_id(item.id));
+                    if let Some(scroll_snap) = item.scroll_snap {
+                        let mut snap = new_table();
+                        str_node(&mut snap, "type", scroll_snap_type_to_string(scroll_snap.snap_type));
+                        let mut areas = vec![];
+                        for area in aux.scroll_snap_areas(&scroll_snap.areas) {
+                            let mut a = new_table();
+                            rect_node(&mut a, "rect", &area.rect);
+                            str_node(&mut a, "align", scroll_snap_align_to_string(area.align));
+                            areas.push(Yaml::Hash(a));
+                        }
+                        vec_node(&mut snap, "areas", areas);
+                        table_node(&mut v, "scroll-snap", snap);
+                    }
+                    if item.overscroll != OverscrollBehavior::default() {
+                        let mut overscroll = new_table();
+                        if let Some(bounce) = item.overscroll.bounce {
+                            bool_node(&mut overscroll, "bounce", bounce);
+                        }
+                        if let Some(scroll_chaining) = item.overscroll.scroll_chaining {
+                            bool_node(&mut overscroll, "scroll-chaining", scroll_chaining);
+                        }
+                        if let Some(spring_parameters) = item.overscroll.spring {
+                            let mut spring = new_table();
+                            f32_node(&mut spring, "stiffness", spring_parameters.stiffness);
+                            f32_node(&mut spring, "damping", spring_parameters.damping);
+                            table_node(&mut overscroll, "spring", spring);
+                        }
+                        table_node(&mut v, "overscroll", overscroll);
+                    }
+                    if let Some(zoom_limits) = item.zoom_limits {
+                        f32_vec_node(&mut v, "zoom-limits", &[zoom_limits.min, zoom_limits.max]);
+                    }
                 }
-                PopStackingContext => return,
+                PushTextShadow(item) => {
+                    str_node(&mut v, "type", "text-shadow");
+                    point_node(&mut v, "offset", &item.shadow.offset);
+                    color_node(&mut v, "color", item.shadow.color);
+                    f32_node(&mut v, "blur-radius", item.shadow.blur_radius);
+                    self.write_display_list(&mut v, list_iterator, aux, clip_id_mapper);
+                },
+                PopStackingContext | PopTextShadow => return,
             }
             if !v.is_empty() {
                 list.push(Yaml::Hash(v));
@@ -27,6 +27,10 @@ pub trait YamlHelper {
     fn as_border_radius(&self) -> Option<BorderRadius>;
     fn as_mix_blend_mode(&self) -> Option<MixBlendMode>;
     fn as_scroll_policy(&self) -> Option<ScrollPolicy>;
+    fn as_transform_style(&self) -> Option<TransformStyle>;
+    fn as_extend_mode(&self) -> Option<ExtendMode>;
+    fn as_gradient_interpolation(&self) -> Option<GradientInterpolation>;
+    fn as_vec_gradient_stop(&self) -> Option<Vec<GradientStop>>;
     fn as_filter_op(&self) -> Option<FilterOp>;
     fn as_vec_filter_op(&self) -> Option<Vec<FilterOp>>;
 }
@@ -91,6 +95,23 @@ define_enum_conversion!(string_to_scroll_policy, scroll_policy_to_string, Scroll
     ("fixed", ScrollPolicy::Fixed)
 ]);
 
+define_enum_conversion!(string_to_transform_style, transform_style_to_string, TransformStyle, [
+    ("flat", TransformStyle::Flat),
+    ("preserve-3d", TransformStyle::Preserve3D)
+]);
+
+define_enum_conversion!(string_to_extend_mode, extend_mode_to_string, ExtendMode, [
+    ("clamp", ExtendMode::Clamp),
+    ("repeat", ExtendMode::Repeat),
+    ("reflect", ExtendMode::Reflect)
+]);
+
+define_enum_conversion!(string_to_gradient_interpolation, gradient_interpolation_to_string, GradientInterpolation, [
+    ("srgb", GradientInterpolation::Srgb),
+    ("premultiplied", GradientInterpolation::Premultiplied),
+    ("linear-light", GradientInterpolation::LinearLight)
+]);
+
 impl YamlHelper for Yaml {
     fn as_force_f32(&self) -> Option<f32> {
         match *self {
@@ -332,6 +353,50 @@ impl YamlHelper for Yaml {
         return self.as_str().and_then(|string| string_to_scroll_policy(string))
     }
 
+    fn as_transform_style(&self) -> Option<TransformStyle> {
+        return self.as_str().and_then(|string| string_to_transform_style(string))
+    }
+
+    fn as_extend_mode(&self) -> Option<ExtendMode> {
+        return self.as_str().and_then(|string| string_to_extend_mode(string))
+    }
+
+    fn as_gradient_interpolation(&self) -> Option<GradientInterpolation> {
+        return self.as_str().and_then(|string| string_to_gradient_interpolation(string))
+    }
+
+    // Stops are written as offset, color pairs. A lone offset between two
+    // stops is a color hint for the segment between them.
+    fn as_vec_gradient_stop(&self) -> Option<Vec<GradientStop>> {
+        let items = match self.as_vec() {
+            Some(items) => items,
+            None => return None,
+        };
+
+        let mut stops: Vec<GradientStop> = Vec::new();
+        let mut i = 0;
+        while i < items.len() {
+            let offset = items[i].as_force_f32().expect("gradient stop offset is not f32");
+            match items.get(i + 1) {
+                Some(color) if color.as_force_f32().is_none() => {
+                    stops.push(GradientStop {
+                        offset: offset,
+                        color: color.as_colorf().expect("gradient stop color is not color"),
+                        hint: None,
+                    });
+                    i += 2;
+                }
+                _ => {
+                    let stop = stops.last_mut().expect("gradient hint must follow a stop");
+                    stop.hint = Some(offset);
+                    i += 1;
+                }
+            }
+        }
+
+        Some(stops)
+    }
+
     fn as_filter_op(&self) -> Option<FilterOp> {
         if let Some(s) = self.as_str() {
             match parse_function(s) {
@@ -363,6 +428,19 @@ impl YamlHelper for Yaml {
                 ("sepia", ref args) if args.len() == 1 => {
                     Some(FilterOp::Sepia(args[0].parse().unwrap()))
                 }
+                ("drop-shadow", ref args) if args.len() == 4 => {
+                    let offset = LayoutPoint::new(args[0].parse().unwrap(),
+                                                  args[1].parse().unwrap());
+                    let color = string_to_color(args[3]).expect("expected a color name");
+                    Some(FilterOp::DropShadow(offset, args[2].parse().unwrap(), color))
+                }
+                ("color-matrix", ref args) if args.len() == 20 => {
+                    let mut matrix = [0.0; 20];
+                    for (value, arg) in matrix.iter_mut().zip(args.iter()) {
+                        *value = arg.parse().unwrap();
+                    }
+                    Some(FilterOp::ColorMatrix(matrix))
+                }
                 (_, _) => { None }
             }
         } else {